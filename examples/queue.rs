@@ -0,0 +1,36 @@
+use std::{pin::pin, time::Duration};
+
+use burger::{dispatch, service_fn, ServiceExt};
+use futures_util::StreamExt;
+use tokio::{sync::mpsc, time::sleep};
+use tokio_stream::wrappers::ReceiverStream;
+
+const N_PRODUCERS: usize = 3;
+const JOBS_PER_PRODUCER: u64 = 5;
+
+#[tokio::main]
+async fn main() {
+    // The worker may only process two jobs at once.
+    let svc = service_fn(|(producer, job): (usize, u64)| async move {
+        sleep(Duration::from_millis(10 * job)).await;
+        format!("producer {producer} job {job} done")
+    })
+    .concurrency_limit(2);
+
+    let (sender, receiver) = mpsc::channel(4);
+    for producer in 0..N_PRODUCERS {
+        let sender = sender.clone();
+        tokio::spawn(async move {
+            for job in 0..JOBS_PER_PRODUCER {
+                // Waits while the queue is full, the queue is only drained as permits are acquired.
+                sender.send((producer, job)).await.unwrap();
+            }
+        });
+    }
+    drop(sender);
+
+    let mut responses = pin!(dispatch(&svc, ReceiverStream::new(receiver), 4));
+    while let Some(response) = responses.next().await {
+        println!("{response}");
+    }
+}
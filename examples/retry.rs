@@ -22,7 +22,7 @@ where
 {
     type RequestState<'a> = Attempts<'a>;
 
-    fn create(&self, request: &Request) -> Attempts {
+    fn create(&self, request: &Request) -> Attempts<'_> {
         Attempts {
             max: &self.0,
             request: request.clone(),
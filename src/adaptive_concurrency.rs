@@ -0,0 +1,329 @@
+//! The [`ServiceExt::adaptive_concurrency`](crate::ServiceExt::adaptive_concurrency) combinator
+//! returns [`AdaptiveConcurrency`], which restricts the number of inflight
+//! [calls](Service::call) to a limit which is automatically tuned.
+//!
+//! The limit is tuned using the additive-increase/multiplicative-decrease (AIMD) algorithm,
+//! configured by [`Aimd`]. Each call with a latency below the threshold contributes towards
+//! increasing the limit by one, once a full limit's worth of such calls have completed. Each call
+//! with a latency exceeding the threshold multiplies the limit by the backoff ratio, at most once
+//! per threshold period.
+//!
+//! Permits are drawn from an [`AdjustableLimit`], one of the permit sources described in the
+//! [`limit`](crate::limit#permit-sources) module, so waiting [`Service::acquire`]s are served in
+//! first-in, first-out order and lowering the limit doesn't interrupt inflight calls.
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::{adaptive_concurrency::Aimd, *};
+//! # use tokio::time::sleep;
+//! # use std::time::Duration;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let config = Aimd::new(Duration::from_millis(100))
+//!     .initial_limit(4)
+//!     .min_limit(1)
+//!     .max_limit(64);
+//! let svc = service_fn(|x: u32| async move {
+//!     sleep(Duration::from_millis(10)).await;
+//!     2 * x
+//! })
+//! .adaptive_concurrency(config);
+//! let response = svc.oneshot(4).await;
+//! assert_eq!(response, 8);
+//! assert_eq!(svc.limit(), 4);
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`AdaptiveConcurrency`] defers to the inner service.
+
+use std::{
+    fmt,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    describe::{Describe, StackNode},
+    limit::{AdjustableLimit, AdjustableLimitPermit, Permits},
+    load::Load,
+    Middleware, Service,
+};
+
+/// The configuration of the AIMD algorithm used by [`AdaptiveConcurrency`].
+///
+/// See the [module](crate::adaptive_concurrency) for more information.
+#[derive(Debug, Clone)]
+pub struct Aimd {
+    latency_threshold: Duration,
+    initial_limit: usize,
+    min_limit: usize,
+    max_limit: usize,
+    backoff: f64,
+}
+
+impl Aimd {
+    /// Constructs a configuration, where calls exceeding the latency threshold cause the limit to
+    /// decrease.
+    ///
+    /// Defaults to an initial limit of 16, a minimum limit of 1, a maximum limit of 1024, and a
+    /// backoff ratio of 0.9.
+    pub fn new(latency_threshold: Duration) -> Self {
+        Self {
+            latency_threshold,
+            initial_limit: 16,
+            min_limit: 1,
+            max_limit: 1024,
+            backoff: 0.9,
+        }
+    }
+
+    /// Sets the initial limit.
+    pub fn initial_limit(mut self, limit: usize) -> Self {
+        self.initial_limit = limit;
+        self
+    }
+
+    /// Sets the minimum limit, this must be at least 1.
+    pub fn min_limit(mut self, limit: usize) -> Self {
+        self.min_limit = limit.max(1);
+        self
+    }
+
+    /// Sets the maximum limit.
+    pub fn max_limit(mut self, limit: usize) -> Self {
+        self.max_limit = limit;
+        self
+    }
+
+    /// Sets the ratio, between 0 and 1, which the limit is multiplied by when decreasing.
+    pub fn backoff(mut self, ratio: f64) -> Self {
+        self.backoff = ratio.clamp(0.0, 1.0);
+        self
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    limit: usize,
+    /// Calls beneath the latency threshold since the limit last increased.
+    successes: usize,
+    last_decrease: Option<Instant>,
+}
+
+/// A wrapper for the
+/// [`ServiceExt::adaptive_concurrency`](crate::ServiceExt::adaptive_concurrency) combinator.
+///
+/// See the [module](crate::adaptive_concurrency) for more information.
+#[derive(Debug)]
+pub struct AdaptiveConcurrency<S> {
+    inner: S,
+    permits: AdjustableLimit,
+    state: Mutex<State>,
+    config: Aimd,
+}
+
+impl<S> AdaptiveConcurrency<S> {
+    pub(crate) fn new(inner: S, config: Aimd) -> Self {
+        let limit = config
+            .initial_limit
+            .clamp(config.min_limit, config.max_limit.max(config.min_limit));
+        Self {
+            inner,
+            permits: AdjustableLimit::new(limit),
+            state: Mutex::new(State {
+                limit,
+                successes: 0,
+                last_decrease: None,
+            }),
+            config,
+        }
+    }
+
+    /// Returns the current concurrency limit.
+    pub fn limit(&self) -> usize {
+        self.state.lock().unwrap().limit
+    }
+
+    /// Updates the limit using the latency of a completed call.
+    fn record(&self, latency: Duration, now: Instant) {
+        let mut state = self.state.lock().unwrap();
+        if latency > self.config.latency_threshold {
+            let cooled_down = state.last_decrease.is_none_or(|last| {
+                now.saturating_duration_since(last) >= self.config.latency_threshold
+            });
+            if !cooled_down {
+                return;
+            }
+            let target = ((state.limit as f64) * self.config.backoff) as usize;
+            let target = target.max(self.config.min_limit);
+            self.permits.set_limit(target);
+            state.limit = target;
+            state.successes = 0;
+            state.last_decrease = Some(now);
+            tracing::trace!(limit = state.limit, "decreased limit");
+        } else {
+            state.successes += 1;
+            if state.successes >= state.limit && state.limit < self.config.max_limit {
+                state.limit += 1;
+                state.successes = 0;
+                self.permits.set_limit(state.limit);
+                tracing::trace!(limit = state.limit, "increased limit");
+            }
+        }
+    }
+}
+
+/// The [`Service::Permit`] type for [`AdaptiveConcurrency`].
+pub struct AdaptiveConcurrencyPermit<'a, S, Request>
+where
+    S: Service<Request> + 'a,
+{
+    inner: S::Permit<'a>,
+    service: &'a AdaptiveConcurrency<S>,
+    _slot: AdjustableLimitPermit<'a>,
+}
+
+impl<'a, S, Request> fmt::Debug for AdaptiveConcurrencyPermit<'a, S, Request>
+where
+    S: Service<Request> + fmt::Debug,
+    S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AdaptiveConcurrencyPermit")
+            .field("inner", &self.inner)
+            .field("service", &self.service)
+            .field("_slot", &self._slot)
+            .finish()
+    }
+}
+
+impl<Request, S> Service<Request> for AdaptiveConcurrency<S>
+where
+    S: Service<Request>,
+{
+    type Response = S::Response;
+    type Permit<'a> = AdaptiveConcurrencyPermit<'a, S, Request>
+    where
+        S: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        AdaptiveConcurrencyPermit {
+            _slot: self.permits.acquire().await,
+            inner: self.inner.acquire().await,
+            service: self,
+        }
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        let AdaptiveConcurrencyPermit {
+            inner,
+            service,
+            _slot,
+        } = permit;
+        let start = Instant::now();
+        let response = S::call(inner, request).await;
+        let now = Instant::now();
+        service.record(now.saturating_duration_since(start), now);
+        drop(_slot);
+        response
+    }
+}
+
+impl<S> Load for AdaptiveConcurrency<S>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S> Describe for AdaptiveConcurrency<S>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("AdaptiveConcurrency")
+            .with_config("initial_limit", self.config.initial_limit)
+            .with_config("min_limit", self.config.min_limit)
+            .with_config("max_limit", self.config.max_limit)
+            .with_config(
+                "latency_threshold",
+                format_args!("{:?}", self.config.latency_threshold),
+            )
+            .with_config("backoff", self.config.backoff)
+            .with_child(self.inner.describe())
+    }
+}
+
+impl<S, T> Middleware<S> for AdaptiveConcurrency<T>
+where
+    T: Middleware<S>,
+{
+    type Service = AdaptiveConcurrency<T::Service>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self {
+            inner,
+            permits,
+            state,
+            config,
+        } = self;
+        AdaptiveConcurrency {
+            inner: inner.apply(svc),
+            permits,
+            state,
+            config,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{AdaptiveConcurrency, Aimd};
+
+    #[test]
+    fn aimd() {
+        let config = Aimd::new(Duration::from_millis(100))
+            .initial_limit(10)
+            .min_limit(2)
+            .max_limit(11)
+            .backoff(0.5);
+        let svc = AdaptiveConcurrency::new((), config);
+        let now = Instant::now();
+        let fast = Duration::from_millis(1);
+        let slow = Duration::from_millis(200);
+
+        // A full limit's worth of fast calls increases the limit by one.
+        for _ in 0..10 {
+            svc.record(fast, now);
+        }
+        assert_eq!(svc.limit(), 11);
+        assert_eq!(svc.permits.available_permits(), 11);
+
+        // Bounded by the maximum.
+        for _ in 0..11 {
+            svc.record(fast, now);
+        }
+        assert_eq!(svc.limit(), 11);
+
+        // Slow calls decrease the limit, at most once per threshold period.
+        svc.record(slow, now);
+        svc.record(slow, now);
+        assert_eq!(svc.limit(), 5);
+        assert_eq!(svc.permits.available_permits(), 5);
+
+        // Bounded by the minimum.
+        svc.record(slow, now + Duration::from_secs(1));
+        svc.record(slow, now + Duration::from_secs(2));
+        assert_eq!(svc.limit(), 2);
+    }
+}
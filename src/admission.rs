@@ -0,0 +1,549 @@
+//! An [`Admission`] decides whether to admit each attempt at a request, and is informed of the
+//! [`Outcome`] of every attempt it admitted. A single [`Admission`] may be consulted by several
+//! layers, so that work rejected by one cannot be reintroduced by another.
+//!
+//! The [`ServiceExt::admit`](crate::ServiceExt::admit) combinator returns [`Admit`], which consults
+//! the [`Admission`] before acquiring the inner [`Service::Permit`]. If admitted, the inner permit
+//! is acquired as usual and [`Service::call`] responds with [`Ok`]. Otherwise,
+//! [`Service::acquire`] returns immediately and [`Service::call`] returns the request as [`Err`],
+//! similar to [`ServiceExt::load_shed`](crate::ServiceExt::load_shed). A closure classifies each
+//! response as a success or a failure.
+//!
+//! The same [`Admission`] is attached to a [`Retry`](crate::retry::Retry) using
+//! [`Retry::with_admission`](crate::retry::Retry::with_admission). Each retry is then an
+//! [`Attempt::Retry`], which must be admitted, and its [`Outcome`] is given by the
+//! [`Policy`](crate::retry::Policy).
+//!
+//! The [`Adaptive`] admission admits attempts while the number of admitted attempts inflight is
+//! below a maximum. Retries are additionally only admitted while the recent success rate is above a
+//! minimum, so that retries stop once they're unlikely to help.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::sync::Arc;
+//!
+//! use burger::{admission::Adaptive, retry::CloneRequest, *};
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let admission = Arc::new(Adaptive::new(100, 0.5));
+//! let svc = service_fn(|x: u32| async move { if x % 2 == 0 { Ok(x) } else { Err(x) } })
+//!     .retry(CloneRequest::new(3, |response: &Result<u32, u32>| response.is_err()))
+//!     .with_admission(admission.clone())
+//!     .admit(admission.clone(), |response: &Result<u32, u32>| response.is_ok());
+//! assert_eq!(svc.oneshot(2).await, Ok(Ok(2)));
+//! assert_eq!(admission.inflight(), 0);
+//! # }
+//! ```
+//!
+//! # Adaptive throttling
+//!
+//! The [`Throttle`] admission implements client-side adaptive throttling, as described in the
+//! [Google SRE book](https://sre.google/sre-book/handling-overload/). It tracks the number of
+//! requests and the number which succeeded, decaying both over a window of time. Once requests
+//! exceed `multiplier` times the successes, each attempt is rejected with probability
+//!
+//! ```text
+//! max(0, (requests - multiplier * successes) / (requests + 1))
+//! ```
+//!
+//! Rejected attempts count as requests, so shedding intensifies while the inner service keeps
+//! failing and eases as successes return. Unlike
+//! [`ServiceExt::load_shed`](crate::ServiceExt::load_shed), this reacts to the outcome of calls
+//! rather than backpressure, protecting an upstream which is browning out while still accepting
+//! work. A lower multiplier sheds more aggressively, while a multiplier of 2 tolerates a failure
+//! rate of up to a half before shedding.
+//!
+//! The [`ServiceExt::auto_shed`](crate::ServiceExt::auto_shed) combinator is shorthand for
+//! [`ServiceExt::admit`](crate::ServiceExt::admit) with a [`Throttle`]. The closure should classify
+//! responses indicating overload, such as `503 Service Unavailable`, as failures, while responses
+//! which are unrelated to the upstream's health, such as `404 Not Found`, may be successes.
+//!
+//! ```rust
+//! use std::time::Duration;
+//!
+//! use burger::*;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc = service_fn(|x: u32| async move { if x < 100 { Ok(x) } else { Err("overloaded") } })
+//!     .auto_shed(2.0, Duration::from_secs(60), |response: &Result<u32, _>| {
+//!         response.is_ok()
+//!     });
+//! assert_eq!(svc.oneshot(1).await, Ok(Ok(1)));
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`Admit`] defers to the inner service.
+
+use std::{
+    any, fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    random, Middleware, Service,
+};
+
+/// The kind of attempt being admitted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Attempt {
+    /// The first attempt at a request.
+    Initial,
+    /// A subsequent attempt at a request, following a failure.
+    Retry,
+}
+
+/// The outcome of an admitted attempt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// The attempt succeeded.
+    Success,
+    /// The attempt failed.
+    Failure,
+    /// The attempt ended without being classified, for example it was cancelled.
+    Abandoned,
+}
+
+/// Admission control, shared between layers.
+///
+/// See the [module](crate::admission) for more information.
+pub trait Admission: fmt::Debug + Send + Sync {
+    /// Decides whether to admit the attempt. If admitted, the attempt is committed to and
+    /// [`Admission::release`] will be called exactly once with its outcome.
+    fn admit(&self, attempt: Attempt) -> bool;
+
+    /// Releases an admitted attempt with its [`Outcome`].
+    fn release(&self, outcome: Outcome);
+}
+
+impl<A> Admission for Arc<A>
+where
+    A: Admission + ?Sized,
+{
+    fn admit(&self, attempt: Attempt) -> bool {
+        A::admit(self, attempt)
+    }
+
+    fn release(&self, outcome: Outcome) {
+        A::release(self, outcome)
+    }
+}
+
+/// An admitted attempt, released as [`Outcome::Abandoned`] if dropped before being finished.
+pub(crate) struct Admitted<'a, A>
+where
+    A: Admission + ?Sized,
+{
+    admission: Option<&'a A>,
+}
+
+impl<'a, A> Admitted<'a, A>
+where
+    A: Admission + ?Sized,
+{
+    pub(crate) fn new(admission: &'a A, attempt: Attempt) -> Option<Self> {
+        admission.admit(attempt).then(|| Self {
+            admission: Some(admission),
+        })
+    }
+
+    pub(crate) fn finish(mut self, outcome: Outcome) {
+        if let Some(admission) = self.admission.take() {
+            admission.release(outcome);
+        }
+    }
+}
+
+impl<A> fmt::Debug for Admitted<'_, A>
+where
+    A: Admission + ?Sized,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Admitted")
+            .field("admission", &self.admission)
+            .finish()
+    }
+}
+
+impl<A> Drop for Admitted<'_, A>
+where
+    A: Admission + ?Sized,
+{
+    fn drop(&mut self) {
+        if let Some(admission) = self.admission.take() {
+            admission.release(Outcome::Abandoned);
+        }
+    }
+}
+
+/// The weight of each new outcome in the success rate of [`Adaptive`].
+const SUCCESS_RATE_WEIGHT: f64 = 0.1;
+
+#[derive(Debug)]
+struct AdaptiveState {
+    inflight: usize,
+    success_rate: f64,
+}
+
+/// An [`Admission`] limiting the attempts inflight, and the retries by the recent success rate.
+///
+/// See the [module](crate::admission) for more information.
+#[derive(Debug)]
+pub struct Adaptive {
+    max_inflight: usize,
+    min_success_rate: f64,
+    state: Mutex<AdaptiveState>,
+}
+
+impl Adaptive {
+    /// Constructs an [`Adaptive`] admitting up to `max_inflight` attempts at once, and admitting
+    /// retries only while the success rate is at least `min_success_rate`, between zero and one.
+    pub fn new(max_inflight: usize, min_success_rate: f64) -> Self {
+        Self {
+            max_inflight,
+            min_success_rate: min_success_rate.clamp(0.0, 1.0),
+            state: Mutex::new(AdaptiveState {
+                inflight: 0,
+                success_rate: 1.0,
+            }),
+        }
+    }
+
+    /// Returns the number of admitted attempts inflight.
+    pub fn inflight(&self) -> usize {
+        self.state.lock().unwrap().inflight
+    }
+
+    /// Returns the exponentially weighted moving average of the success rate.
+    pub fn success_rate(&self) -> f64 {
+        self.state.lock().unwrap().success_rate
+    }
+}
+
+impl Admission for Adaptive {
+    fn admit(&self, attempt: Attempt) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let admitted = state.inflight < self.max_inflight
+            && (attempt == Attempt::Initial || state.success_rate >= self.min_success_rate);
+        if admitted {
+            state.inflight += 1;
+        } else {
+            tracing::trace!(?attempt, inflight = state.inflight, "attempt not admitted");
+        }
+        admitted
+    }
+
+    fn release(&self, outcome: Outcome) {
+        let mut state = self.state.lock().unwrap();
+        state.inflight -= 1;
+        let observation = match outcome {
+            Outcome::Success => 1.0,
+            Outcome::Failure => 0.0,
+            Outcome::Abandoned => return,
+        };
+        state.success_rate += SUCCESS_RATE_WEIGHT * (observation - state.success_rate);
+    }
+}
+
+#[derive(Debug)]
+struct ThrottleState {
+    requests: f64,
+    successes: f64,
+    updated: Instant,
+}
+
+impl ThrottleState {
+    /// Decays the counts by the time elapsed since they were last updated.
+    fn decay(&mut self, window: Duration, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated);
+        let decay = (-elapsed.as_secs_f64() / window.as_secs_f64()).exp();
+        self.requests *= decay;
+        self.successes *= decay;
+        self.updated = now;
+    }
+
+    fn rejection_probability(&self, multiplier: f64) -> f64 {
+        ((self.requests - multiplier * self.successes) / (self.requests + 1.0)).max(0.0)
+    }
+}
+
+/// An [`Admission`] rejecting attempts with a probability which rises as the recent ratio of
+/// successes to requests falls.
+///
+/// See the [module](crate::admission#adaptive-throttling) for more information.
+#[derive(Debug)]
+pub struct Throttle {
+    multiplier: f64,
+    window: Duration,
+    state: Mutex<ThrottleState>,
+}
+
+impl Throttle {
+    /// Constructs a [`Throttle`] which sheds once requests exceed `multiplier` times the successes,
+    /// with both decaying over the `window`.
+    pub fn new(multiplier: f64, window: Duration) -> Self {
+        Self {
+            multiplier: multiplier.max(0.0),
+            window,
+            state: Mutex::new(ThrottleState {
+                requests: 0.0,
+                successes: 0.0,
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    /// Returns the probability with which the next attempt will be rejected.
+    pub fn rejection_probability(&self) -> f64 {
+        let mut state = self.state.lock().unwrap();
+        state.decay(self.window, Instant::now());
+        state.rejection_probability(self.multiplier)
+    }
+}
+
+impl Admission for Throttle {
+    fn admit(&self, attempt: Attempt) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.decay(self.window, Instant::now());
+        let probability = state.rejection_probability(self.multiplier);
+        state.requests += 1.0;
+        let admitted = random::next_f64() >= probability;
+        if !admitted {
+            tracing::trace!(?attempt, probability, "attempt throttled");
+        }
+        admitted
+    }
+
+    fn release(&self, outcome: Outcome) {
+        let mut state = self.state.lock().unwrap();
+        state.decay(self.window, Instant::now());
+        match outcome {
+            Outcome::Success => state.successes += 1.0,
+            Outcome::Failure => {}
+            // An abandoned attempt says nothing of the service's health.
+            Outcome::Abandoned => state.requests = (state.requests - 1.0).max(0.0),
+        }
+    }
+}
+
+/// A wrapper [`Service`] for the [`ServiceExt::admit`](crate::ServiceExt::admit) combinator.
+///
+/// See the [module](crate::admission) for more information.
+#[derive(Clone)]
+pub struct Admit<S, A, F> {
+    inner: S,
+    admission: A,
+    classify: F,
+}
+
+impl<S, A, F> fmt::Debug for Admit<S, A, F>
+where
+    S: fmt::Debug,
+    A: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Admit")
+            .field("inner", &self.inner)
+            .field("admission", &self.admission)
+            .field("classify", &format_args!("{}", any::type_name::<F>()))
+            .finish()
+    }
+}
+
+impl<S, A, F> Admit<S, A, F> {
+    pub(crate) fn new(inner: S, admission: A, classify: F) -> Self {
+        Self {
+            inner,
+            admission,
+            classify,
+        }
+    }
+}
+
+/// The [`Service::Permit`] type for [`Admit`].
+pub struct AdmitPermit<'a, S, A, F, Request>
+where
+    S: Service<Request> + 'a,
+    A: Admission,
+{
+    inner: Option<(S::Permit<'a>, Admitted<'a, A>)>,
+    classify: &'a F,
+}
+
+impl<'a, S, A, F, Request> fmt::Debug for AdmitPermit<'a, S, A, F, Request>
+where
+    S: Service<Request>,
+    S::Permit<'a>: fmt::Debug,
+    A: Admission,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AdmitPermit")
+            .field("inner", &self.inner)
+            .field("classify", &format_args!("{}", any::type_name::<F>()))
+            .finish()
+    }
+}
+
+impl<Request, S, A, F> Service<Request> for Admit<S, A, F>
+where
+    S: Service<Request>,
+    A: Admission,
+    F: Fn(&S::Response) -> bool,
+{
+    type Response = Result<S::Response, Request>;
+    type Permit<'a> = AdmitPermit<'a, S, A, F, Request>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        let inner = match Admitted::new(&self.admission, Attempt::Initial) {
+            Some(admitted) => Some((self.inner.acquire().await, admitted)),
+            None => None,
+        };
+        AdmitPermit {
+            inner,
+            classify: &self.classify,
+        }
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        let AdmitPermit { inner, classify } = permit;
+        let Some((inner, admitted)) = inner else {
+            return Err(request);
+        };
+        let response = S::call(inner, request).await;
+        let outcome = if classify(&response) {
+            Outcome::Success
+        } else {
+            Outcome::Failure
+        };
+        admitted.finish(outcome);
+        Ok(response)
+    }
+}
+
+impl<S, A, F> Load for Admit<S, A, F>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S, A, F> Describe for Admit<S, A, F>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("Admit").with_child(self.inner.describe())
+    }
+}
+
+impl<S, T, A, F> Middleware<S> for Admit<T, A, F>
+where
+    T: Middleware<S>,
+{
+    type Service = Admit<T::Service, A, F>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self {
+            inner,
+            admission,
+            classify,
+        } = self;
+        Admit {
+            inner: inner.apply(svc),
+            admission,
+            classify,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Adaptive, Admission, Attempt, Outcome, Throttle};
+
+    #[test]
+    fn retries_follow_success_rate() {
+        let admission = Adaptive::new(2, 0.5);
+        for _ in 0..10 {
+            assert!(admission.admit(Attempt::Initial));
+            admission.release(Outcome::Failure);
+        }
+        assert!(admission.success_rate() < 0.5);
+        assert!(!admission.admit(Attempt::Retry));
+
+        // Initial attempts are still admitted, up to the maximum inflight.
+        assert!(admission.admit(Attempt::Initial));
+        assert!(admission.admit(Attempt::Initial));
+        assert!(!admission.admit(Attempt::Initial));
+        admission.release(Outcome::Abandoned);
+        admission.release(Outcome::Abandoned);
+        assert_eq!(admission.inflight(), 0);
+    }
+
+    #[test]
+    fn throttle_follows_success_ratio() {
+        let throttle = Throttle::new(2.0, Duration::from_secs(60));
+        for _ in 0..100 {
+            assert!(throttle.admit(Attempt::Initial));
+            throttle.release(Outcome::Success);
+        }
+        assert_eq!(throttle.rejection_probability(), 0.0);
+
+        // Failures accumulate until requests exceed twice the successes.
+        let mut rejected = 0;
+        for _ in 0..1000 {
+            if throttle.admit(Attempt::Initial) {
+                throttle.release(Outcome::Failure);
+            } else {
+                rejected += 1;
+            }
+        }
+        assert!(throttle.rejection_probability() > 0.7);
+        assert!(rejected > 300);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn shared_with_retry() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        use crate::{retry::CloneRequest, service_fn, ServiceExt};
+
+        let calls = AtomicUsize::new(0);
+        let admission = Arc::new(Adaptive::new(1, 0.0));
+        let svc = service_fn(|()| async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err::<(), ()>(())
+        })
+        .retry(CloneRequest::new(3, |response: &Result<(), ()>| {
+            response.is_err()
+        }))
+        .with_admission(admission.clone())
+        .admit(admission.clone(), |response: &Result<(), ()>| {
+            response.is_ok()
+        });
+
+        // The initial attempt occupies the only slot, so retries are not admitted.
+        assert_eq!(svc.oneshot(()).await, Ok(Err(())));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(admission.inflight(), 0);
+        assert!(admission.success_rate() < 1.0);
+    }
+}
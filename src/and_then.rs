@@ -0,0 +1,131 @@
+//! The [`ServiceExt::and_then`](crate::ServiceExt::and_then) combinator returns [`AndThen`], which
+//! extends a [fallible service](crate::TryService) with a closure accepting the [`Ok`] variant of
+//! the [`Service::Response`] and returning a [`Future`]. The [`Err`] variant is passed through
+//! unchanged.
+//!
+//! For a synchronous version see the [map_ok](mod@crate::map_ok) module.
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::*;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc = service_fn(|x: u32| async move { x.checked_sub(3).ok_or("underflow") })
+//!     .and_then(|x: u32| async move { Ok(x.to_string()) });
+//! assert_eq!(svc.oneshot(5).await, Ok("2".to_string()));
+//! assert_eq!(svc.oneshot(2).await, Err("underflow"));
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`AndThen`] defers to the inner service.
+
+use core::{any, fmt, future::Future};
+
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Middleware, Service, TryService,
+};
+
+/// A wrapper [`Service`] for the [`ServiceExt::and_then`](crate::ServiceExt::and_then) combinator.
+///
+/// See the [module](crate::and_then) for more information.
+#[derive(Clone, Debug)]
+pub struct AndThen<S, F> {
+    inner: S,
+    closure: F,
+}
+
+impl<S, F> AndThen<S, F> {
+    pub(crate) fn new(inner: S, closure: F) -> Self {
+        Self { inner, closure }
+    }
+}
+
+/// The [`Service::Permit`] type for [`AndThen`].
+pub struct AndThenPermit<'a, S, F, Request>
+where
+    S: Service<Request> + 'a,
+{
+    inner: S::Permit<'a>,
+    closure: &'a F,
+}
+
+impl<'a, S, F, Request> fmt::Debug for AndThenPermit<'a, S, F, Request>
+where
+    S: Service<Request>,
+    S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AndThenPermit")
+            .field("inner", &self.inner)
+            .field("closure", &format_args!("{}", any::type_name::<F>()))
+            .finish()
+    }
+}
+
+impl<Request, S, F, Fut, Output> Service<Request> for AndThen<S, F>
+where
+    S: TryService<Request>,
+    F: Fn(S::Ok) -> Fut,
+    Fut: Future<Output = Result<Output, S::Error>>,
+{
+    type Response = Result<Output, S::Error>;
+    type Permit<'a> = AndThenPermit<'a, S, F, Request>
+    where
+        S: 'a,
+        F: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        AndThenPermit {
+            inner: self.inner.acquire().await,
+            closure: &self.closure,
+        }
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        match S::call(permit.inner, request).await {
+            Ok(ok) => (permit.closure)(ok).await,
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl<S, F> Load for AndThen<S, F>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S, F> Describe for AndThen<S, F>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("AndThen").with_child(self.inner.describe())
+    }
+}
+
+impl<S, T, F> Middleware<S> for AndThen<T, F>
+where
+    T: Middleware<S>,
+{
+    type Service = AndThen<T::Service, F>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self { inner, closure } = self;
+        AndThen {
+            inner: inner.apply(svc),
+            closure,
+        }
+    }
+}
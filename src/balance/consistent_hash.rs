@@ -0,0 +1,314 @@
+//! The [`consistent_hash`] function returns [`ConsistentHash`], which routes each request to a
+//! service chosen by the hash of a key extracted from the request.
+//!
+//! Services are placed on a hash ring at a number of pseudo-random positions, determined by their
+//! key. A request is routed to the first service found clockwise of the request's hash. This
+//! ensures that inserting or removing a service only remaps a small fraction of requests, which is
+//! desirable for cache affinity.
+//!
+//! As the request is unknown until [`Service::call`], [`Service::acquire`] on [`ConsistentHash`]
+//! only waits until a service is present. The chosen service's permit is acquired during
+//! [`Service::call`].
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::*;
+//! # use futures::stream::{iter, StreamExt};
+//! # use std::future::ready;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let a: fn(_) -> _ = |x: (String, u32)| ready(2 * x.1);
+//! let b: fn(_) -> _ = |x: (String, u32)| ready(x.1 + 3);
+//! let svc_stream = iter([service_fn(a), service_fn(b)])
+//!     .enumerate()
+//!     .map(|(index, svc)| balance::Change::Insert(index, svc));
+//! let (svc, worker) = balance::consistent_hash(svc_stream, |(user, _): &(String, u32)| {
+//!     user.clone()
+//! });
+//! tokio::spawn(worker);
+//! let first = svc.oneshot(("alice".to_string(), 5)).await;
+//! let second = svc.oneshot(("alice".to_string(), 5)).await;
+//! assert_eq!(first, second);
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! This has _no_ [`Load`](crate::load::Load) implementation.
+
+use std::{
+    any,
+    collections::{btree_map::Entry, BTreeMap, HashMap},
+    convert::Infallible,
+    fmt,
+    future::Future,
+    hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash},
+    sync::Arc,
+};
+
+use futures_util::Stream;
+use tokio::sync::RwLock;
+
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Service, ServiceExt,
+};
+
+use super::{worker, Change, Members, Parked, Terminated};
+
+/// The number of positions each service occupies on the ring.
+const VIRTUAL_NODES: u32 = 64;
+
+/// A deterministic hasher, so that routing is consistent across instances.
+type RingHasher = BuildHasherDefault<DefaultHasher>;
+
+#[derive(Debug)]
+struct Ring<S, Key> {
+    services: HashMap<Key, Arc<S>>,
+    /// The keys at each position, in order of insertion, as positions of distinct keys may collide.
+    ring: BTreeMap<u64, Vec<Key>>,
+}
+
+impl<S, Key> Ring<S, Key>
+where
+    Key: Eq + Hash,
+{
+    /// Panics if empty.
+    fn route(&self, hash: u64) -> &Arc<S> {
+        let (_, keys) = self
+            .ring
+            .range(hash..)
+            .next()
+            .or_else(|| self.ring.iter().next())
+            .expect("ring is not empty");
+        &self.services[&keys[0]]
+    }
+
+    fn positions(key: &Key) -> impl Iterator<Item = u64> + '_ {
+        (0..VIRTUAL_NODES).map(move |replica| RingHasher::default().hash_one((key, replica)))
+    }
+}
+
+impl<S, Key> Members<Key, S> for Ring<S, Key>
+where
+    Key: Eq + Hash + Clone,
+{
+    fn insert(&mut self, key: Key, service: S) -> bool {
+        for position in Self::positions(&key) {
+            let keys = self.ring.entry(position).or_default();
+            if !keys.contains(&key) {
+                keys.push(key.clone());
+            }
+        }
+        self.services.insert(key, Arc::new(service)).is_some()
+    }
+
+    fn remove(&mut self, key: &Key) -> bool {
+        for position in Self::positions(key) {
+            if let Entry::Occupied(mut entry) = self.ring.entry(position) {
+                entry.get_mut().retain(|other| other != key);
+                if entry.get().is_empty() {
+                    entry.remove();
+                }
+            }
+        }
+        self.services.remove(key).is_some()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.services.is_empty()
+    }
+
+    fn len(&self) -> usize {
+        self.services.len()
+    }
+}
+
+/// A [`Service`] for the [`consistent_hash`] constructor.
+///
+/// See the [module](mod@crate::balance::consistent_hash) for more information.
+pub struct ConsistentHash<S, Key, F> {
+    inner: Arc<RwLock<Ring<S, Key>>>,
+    key_fn: F,
+    /// Holds the write lock if the worker ends while the ring is empty.
+    _parked: Parked<Ring<S, Key>>,
+}
+
+impl<S, Key, F> fmt::Debug for ConsistentHash<S, Key, F>
+where
+    S: fmt::Debug,
+    Key: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConsistentHash")
+            .field("inner", &self.inner)
+            .field("key_fn", &format_args!("{}", any::type_name::<F>()))
+            .finish()
+    }
+}
+
+impl<S, Key, F> ConsistentHash<S, Key, F>
+where
+    S: Load,
+{
+    /// Returns [`Load::load`] for all current services.
+    pub async fn load_profile(&self) -> Vec<S::Metric> {
+        self.inner
+            .read()
+            .await
+            .services
+            .values()
+            .map(|svc| svc.load())
+            .collect()
+    }
+}
+
+/// The [`Service::Permit`] type for [`ConsistentHash`].
+pub struct ConsistentHashPermit<'a, S, Key, F> {
+    service: &'a ConsistentHash<S, Key, F>,
+}
+
+impl<'a, S, Key, F> fmt::Debug for ConsistentHashPermit<'a, S, Key, F>
+where
+    S: fmt::Debug,
+    Key: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConsistentHashPermit")
+            .field("service", &self.service)
+            .finish()
+    }
+}
+
+impl<Request, S, Key, F, H> Service<Request> for ConsistentHash<S, Key, F>
+where
+    S: Service<Request>,
+    Key: Eq + Hash,
+    F: Fn(&Request) -> H,
+    H: Hash,
+{
+    type Response = S::Response;
+    type Permit<'a> = ConsistentHashPermit<'a, S, Key, F>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        // The worker holds the write lock while the ring is empty.
+        drop(self.inner.read().await);
+        ConsistentHashPermit { service: self }
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        let ConsistentHashPermit { service } = permit;
+        let hash = RingHasher::default().hash_one((service.key_fn)(&request));
+        let chosen = service.inner.read().await.route(hash).clone();
+        chosen.oneshot(request).await
+    }
+}
+
+impl<S, Key, F> Describe for ConsistentHash<S, Key, F>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        let node = StackNode::new("ConsistentHash");
+        // The worker holds the lock while applying changes, or while there are no services.
+        match self.inner.try_read() {
+            Ok(ring) => {
+                node.with_children(ring.services.values().map(|service| service.describe()))
+            }
+            Err(_) => node,
+        }
+    }
+}
+
+/// Constructs a consistent hashing load balancer, [`ConsistentHash`], and a worker [`Future`],
+/// from a [`Stream`] of [`Change`] and a closure extracting a hashable key from each request.
+///
+/// See [module](mod@crate::balance::consistent_hash) for more information.
+pub fn consistent_hash<St, Key, S, F>(
+    changes: St,
+    key_fn: F,
+) -> (
+    ConsistentHash<S, Key, F>,
+    impl Future<Output = Result<Infallible, Terminated>>,
+)
+where
+    St: Stream<Item = Change<Key, S>>,
+    Key: Eq + Hash + Clone,
+{
+    let inner = Arc::new(RwLock::new(Ring {
+        services: HashMap::new(),
+        ring: BTreeMap::new(),
+    }));
+    let parked = Parked::default();
+    let balance = ConsistentHash {
+        inner: inner.clone(),
+        key_fn,
+        _parked: parked.clone(),
+    };
+    (balance, worker(inner, parked, changes))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{BTreeMap, HashMap},
+        future::{ready, Ready},
+        hash::BuildHasher,
+        time::Duration,
+    };
+
+    use futures_util::stream;
+    use tokio::time::timeout;
+
+    use crate::{service_fn, ServiceExt};
+
+    use super::{consistent_hash, Change, Members, Ring, RingHasher, Terminated};
+
+    #[test]
+    fn minimal_remapping() {
+        let mut ring = Ring {
+            services: HashMap::new(),
+            ring: BTreeMap::new(),
+        };
+        for key in 0..10 {
+            ring.insert(key, key);
+        }
+        let route = |ring: &Ring<u32, u32>| -> Vec<u32> {
+            (0..1000u32)
+                .map(|request| **ring.route(RingHasher::default().hash_one(request)))
+                .collect()
+        };
+
+        let before = route(&ring);
+        ring.remove(&3);
+        let after = route(&ring);
+
+        // Only requests previously routed to the removed service are remapped.
+        for (before, after) in before.into_iter().zip(after) {
+            if before != 3 {
+                assert_eq!(before, after);
+            } else {
+                assert_ne!(after, 3);
+            }
+        }
+    }
+    #[tokio::test]
+    async fn worker_ends_empty() {
+        let double: fn(u32) -> Ready<u32> = |x| ready(2 * x);
+        let changes = stream::iter([Change::Insert(1, service_fn(double)), Change::Remove(1)]);
+        let (svc, worker) = consistent_hash(changes, |x: &u32| *x);
+        assert_eq!(worker.await, Err(Terminated));
+
+        // Waits rather than routing to no services.
+        let response = timeout(Duration::from_millis(10), svc.oneshot(5)).await;
+        assert!(response.is_err());
+    }
+}
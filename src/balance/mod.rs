@@ -1,9 +1,49 @@
 //! Various load balancer implementations.
+//!
+//! Each balancer is constructed from a [`Stream`] of [`Change`]s, returning the balancer
+//! [`Service`](crate::Service) and a worker [`Future`] which applies the changes. The worker must
+//! be driven for the balancer to make progress. [`Service::acquire`](crate::Service::acquire) on
+//! each balancer waits until at least one service has been inserted. If the worker completes, or is
+//! dropped, while there are no services then it continues to wait.
+//!
+//! Alternatively, [`p2c::p2c_with_handle`] returns a [`p2c::Handle`] which inserts and removes
+//! services imperatively, without a [`Stream`] or worker, and [`p2c::p2c_snapshot`] returns a
+//! balancer which acquires from a published snapshot of the services rather than taking a lock.
+//!
+//! Requests sharing a session are routed to the same service using [`sticky`](mod@sticky).
+//!
+//! With the `tonic` feature enabled, [`from_endpoints`] balances gRPC channels across a set of
+//! endpoints.
+//!
+//! The worker applies ready changes in batches, releasing the lock and yielding between them, so
+//! that a [`Stream`] which is never pending does not starve the balancer.
+//!
+//! Services which fail health checks can be evicted from, and restored to, a balancer by wrapping
+//! the [`Stream`] using the [`health`](crate::health) module. Similarly, services which have stopped
+//! succeeding, while others haven't, are evicted using the [`idle`](crate::idle) module.
 
+pub mod consistent_hash;
 pub mod p2c;
+pub mod sticky;
+
+use std::{convert::Infallible, fmt, future::Future, pin::pin, sync::Arc};
+
+#[cfg(feature = "tonic")]
+use futures_util::stream;
+use futures_util::{FutureExt, Stream, StreamExt};
+use tokio::sync::{Mutex, OwnedRwLockWriteGuard, RwLock};
+#[cfg(feature = "tonic")]
+use tonic::transport::{Endpoint, Uri};
+
+#[cfg(feature = "tonic")]
+use crate::compat::{tonic_channel, TonicChannel};
 
+#[doc(inline)]
+pub use consistent_hash::consistent_hash;
 #[doc(inline)]
 pub use p2c::p2c;
+#[doc(inline)]
+pub use sticky::sticky;
 
 /// Represents a change to a pool of [services](crate::Service).
 #[derive(Debug)]
@@ -13,3 +53,288 @@ pub enum Change<K, V> {
     /// Removes a service from the collection.
     Remove(K),
 }
+
+/// Returns a [`Stream`] inserting a [`TonicChannel`] for each endpoint, keyed by its [`Uri`],
+/// into a balancer.
+///
+/// Each channel connects lazily, using [`tonic_channel`], when the [`Stream`] is polled by the
+/// balancer worker. An unreachable endpoint therefore fails its requests rather than preventing
+/// the others from being inserted.
+///
+/// # Example
+///
+/// ```rust
+/// use burger::*;
+/// use tonic::transport::Uri;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let endpoints = ["http://10.0.0.1:50051", "http://10.0.0.2:50051"].map(Uri::from_static);
+/// let (svc, worker) = balance::p2c(balance::from_endpoints(endpoints));
+/// let worker = tokio::spawn(worker);
+/// // Requests are sent by a `tonic` client using `compat::into_tower(Arc::new(svc))`.
+/// # worker.abort();
+/// # }
+/// ```
+#[cfg(feature = "tonic")]
+pub fn from_endpoints<E>(
+    endpoints: impl IntoIterator<Item = E>,
+) -> impl Stream<Item = Change<Uri, TonicChannel>>
+where
+    E: Into<Endpoint>,
+{
+    let inserts = stream::iter(endpoints).map(|endpoint| {
+        let endpoint = endpoint.into();
+        Change::Insert(endpoint.uri().clone(), tonic_channel(endpoint))
+    });
+    inserts.chain(stream::pending())
+}
+
+/// The change stream has terminated.
+///
+/// The balancer retains its services. A new [`Stream`] may be connected using
+/// [`Balance::reconnect`](p2c::Balance::reconnect). A [`SnapshotBalance`](p2c::SnapshotBalance)
+/// left without services also responds with this.
+#[derive(Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Terminated;
+
+impl fmt::Display for Terminated {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("change stream terminated")
+    }
+}
+
+impl std::error::Error for Terminated {}
+
+/// A collection of services which can be mutated by a [`Change`].
+trait Members<Key, S> {
+    /// Inserts a service, returning whether a service was replaced.
+    fn insert(&mut self, key: Key, service: S) -> bool;
+
+    /// Removes a service, returning whether it was present.
+    fn remove(&mut self, key: &Key) -> bool;
+
+    fn is_empty(&self) -> bool;
+
+    fn len(&self) -> usize;
+}
+
+/// Applies individual [`Change`]s to some [`Members`], holding the write lock while they're empty.
+struct Controller<M> {
+    inner: Arc<RwLock<M>>,
+    empty_guard: Mutex<Option<OwnedRwLockWriteGuard<M>>>,
+}
+
+impl<M> fmt::Debug for Controller<M>
+where
+    M: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Controller")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<M> Controller<M> {
+    /// The [`Members`] must be empty.
+    fn new(inner: Arc<RwLock<M>>) -> Self {
+        let empty_guard = inner.clone().try_write_owned().unwrap();
+        Self {
+            inner,
+            empty_guard: Mutex::new(Some(empty_guard)),
+        }
+    }
+
+    /// Inserts a service, returning whether a service was replaced.
+    async fn insert<Key, S>(&self, key: Key, service: S) -> bool
+    where
+        M: Members<Key, S>,
+    {
+        self.update::<Key, S, _>(|members| {
+            let success = members.insert(key, service);
+            tracing::trace!(len = members.len(), "inserted service");
+            success
+        })
+        .await
+    }
+
+    /// Removes a service, returning whether it was present.
+    async fn remove<Key, S>(&self, key: &Key) -> bool
+    where
+        M: Members<Key, S>,
+    {
+        self.update::<Key, S, _>(|members| {
+            let success = members.remove(key);
+            tracing::trace!(len = members.len(), success, "removed service");
+            success
+        })
+        .await
+    }
+
+    /// Mutates the [`Members`], retaining the write lock if they're left empty.
+    async fn update<Key, S, R>(&self, f: impl FnOnce(&mut M) -> R) -> R
+    where
+        M: Members<Key, S>,
+    {
+        let mut empty_guard = self.empty_guard.lock().await;
+        let mut guard = match empty_guard.take() {
+            Some(guard) => guard,
+            None => self.inner.clone().write_owned().await,
+        };
+        let output = f(&mut guard);
+        // Retain the guard if empty.
+        if guard.is_empty() {
+            *empty_guard = Some(guard);
+        }
+        output
+    }
+
+    /// Takes the write lock, if it's retained because the [`Members`] are empty.
+    fn take_empty_guard(&mut self) -> Option<OwnedRwLockWriteGuard<M>> {
+        self.empty_guard.get_mut().take()
+    }
+}
+
+/// The write lock on empty [`Members`], left by a worker or [`p2c::Handle`] which has gone so that
+/// the balancer continues to wait for services, rather than acquiring from none.
+type Parked<M> = Arc<std::sync::Mutex<Option<OwnedRwLockWriteGuard<M>>>>;
+
+/// The write lock held by a worker while the [`Members`] are empty, parked on drop.
+struct EmptyGuard<M> {
+    guard: Option<OwnedRwLockWriteGuard<M>>,
+    parked: Parked<M>,
+}
+
+impl<M> EmptyGuard<M> {
+    /// Retains the write lock if the [`Members`] are empty, otherwise releases it.
+    fn retain<Key, S>(&mut self, guard: OwnedRwLockWriteGuard<M>)
+    where
+        M: Members<Key, S>,
+    {
+        if guard.is_empty() {
+            self.guard = Some(guard);
+        }
+    }
+}
+
+impl<M> Drop for EmptyGuard<M> {
+    fn drop(&mut self) {
+        if let Some(guard) = self.guard.take() {
+            *self.parked.lock().unwrap() = Some(guard);
+        }
+    }
+}
+
+/// The maximum number of ready [`Change`]s applied by the worker per acquisition of the lock.
+const MAX_DRAINED_CHANGES: usize = 32;
+
+/// Constructs a worker [`Future`] applying a [`Stream`] of [`Change`]s to some [`Members`].
+///
+/// The [`Members`] must be empty. Their write lock is left in `parked` if the worker completes, or
+/// is dropped, while they're empty.
+fn worker<St, Key, S, M>(
+    inner: Arc<RwLock<M>>,
+    parked: Parked<M>,
+    changes: St,
+) -> impl Future<Output = Result<Infallible, Terminated>>
+where
+    St: Stream<Item = Change<Key, S>>,
+    M: Members<Key, S>,
+{
+    // Immediately take guard so that the balancer cannot acquire when empty. Hold it until at least one service has been added.
+    let empty_guard = inner.clone().try_write_owned().unwrap();
+    apply_changes(inner, parked, changes, Some(empty_guard))
+}
+
+/// Constructs a worker [`Future`] applying a [`Stream`] of [`Change`]s to some [`Members`], which
+/// may already have been populated by a previous worker.
+///
+/// The `guard`, if provided, must hold the write lock on `inner`.
+async fn reconnect_worker<St, Key, S, M>(
+    inner: Arc<RwLock<M>>,
+    parked: Parked<M>,
+    guard: Option<OwnedRwLockWriteGuard<M>>,
+    changes: St,
+) -> Result<Infallible, Terminated>
+where
+    St: Stream<Item = Change<Key, S>>,
+    M: Members<Key, S>,
+{
+    let guard = match guard {
+        Some(guard) => guard,
+        None => inner.clone().write_owned().await,
+    };
+    let empty_guard = guard.is_empty().then_some(guard);
+    apply_changes(inner, parked, changes, empty_guard).await
+}
+
+/// Applies the [`Change`]s, holding the `empty_guard` while the [`Members`] are empty.
+async fn apply_changes<St, Key, S, M>(
+    inner: Arc<RwLock<M>>,
+    parked: Parked<M>,
+    changes: St,
+    empty_guard: Option<OwnedRwLockWriteGuard<M>>,
+) -> Result<Infallible, Terminated>
+where
+    St: Stream<Item = Change<Key, S>>,
+    M: Members<Key, S>,
+{
+    let mut empty_guard = EmptyGuard {
+        guard: empty_guard,
+        parked,
+    };
+    let mut changes = pin!(changes);
+    while let Some(mut new_change) = changes.next().await {
+        // Take the guard if not already held.
+        let mut guard = match empty_guard.guard.take() {
+            Some(guard) => guard,
+            None => inner.clone().write_owned().await,
+        };
+
+        // We loop to consume ready changes while holding the lock, up to a bound so that a
+        // stream which is never pending cannot starve the `Service`.
+        let mut drained = 0;
+        loop {
+            // Mutate the members.
+            match new_change {
+                Change::Insert(key, service) => {
+                    guard.insert(key, service);
+                    tracing::trace!(len = guard.len(), "inserted service");
+                }
+                Change::Remove(key) => {
+                    let success = guard.remove(&key);
+                    tracing::trace!(len = guard.len(), success, "removed service")
+                }
+            };
+            drained += 1;
+
+            // Release the lock and yield, letting waiting acquires proceed before continuing.
+            // The lock is retained while empty, as the balancer cannot acquire anyway.
+            if drained >= MAX_DRAINED_CHANGES && !guard.is_empty() {
+                drop(guard);
+                tokio::task::yield_now().await;
+                break;
+            }
+
+            match changes.next().now_or_never() {
+                // Stream yielded.
+                Some(Some(change)) => {
+                    new_change = change;
+                }
+                // Stream terminated.
+                Some(None) => {
+                    empty_guard.retain(guard);
+                    return Err(Terminated);
+                }
+                // Stream pending.
+                None => {
+                    empty_guard.retain(guard);
+                    break;
+                }
+            }
+        }
+    }
+    Err(Terminated)
+}
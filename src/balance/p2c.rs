@@ -1,6 +1,8 @@
 //! The [`p2c`] function returns [`Balance`], which implements the
-//! [Power of Two Random Choices] load balancing algorithm, The implementation acquires two
-//! permits and then chooses the lowest [`Load`] of the two.
+//! [Power of Two Random Choices] load balancing algorithm. Each [`Service::acquire`] samples two
+//! services at random and acquires from the one with the lowest [`Load`], falling back to the other
+//! if its permit becomes available first. Permits are only acquired from the sampled services, so
+//! large pools don't see every service acquired and disarmed on each request.
 //!
 //! # Example
 //!
@@ -24,27 +26,117 @@
 //! # }
 //! ```
 //!
+//! Services may also be inserted and removed imperatively, using [`p2c_with_handle`]:
+//!
+//! ```rust
+//! use burger::*;
+//! # use std::future::ready;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let double: fn(_) -> _ = |x: u32| ready(2 * x);
+//! let (svc, handle) = balance::p2c::p2c_with_handle();
+//! handle.insert("a", service_fn(double).pending_requests()).await;
+//! handle.insert("b", service_fn(double).pending_requests()).await;
+//! let response = svc.oneshot(5u32).await;
+//! assert_eq!(response, 10);
+//! handle.remove(&"a").await;
+//! # }
+//! ```
+//!
+//! # Reconnecting
+//!
+//! When the [`Stream`] of [`Change`] ends, the worker returns [`Terminated`] and the [`Balance`]
+//! retains its current services, waiting for a new worker to insert some if there are none. [`Balance::reconnect`] returns a new worker applying another
+//! [`Stream`], such as a fresh subscription to service discovery, to the existing services. The
+//! new [`Stream`] need only insert services which have changed, or remove those which have gone.
+//! Reconnecting while a previous worker is still running results in both applying changes.
+//!
+//! ```rust
+//! use burger::*;
+//! # use futures::stream::iter;
+//! # use std::future::ready;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let double: fn(_) -> _ = |x: u32| ready(2 * x);
+//! let changes = |key| iter([balance::Change::Insert(key, service_fn(double).pending_requests())]);
+//! let (svc, worker) = balance::p2c(changes("a"));
+//! assert!(worker.await.is_err());
+//! assert_eq!(svc.load_profile().await.len(), 1);
+//!
+//! assert!(svc.reconnect(changes("b")).await.is_err());
+//! assert_eq!(svc.load_profile().await.len(), 2);
+//! assert_eq!(svc.oneshot(5u32).await, 10);
+//! # }
+//! ```
+//!
+//! # Snapshots
+//!
+//! [`Service::acquire`] on [`Balance`] takes a read lock over the services, which contends with the
+//! worker applying changes. [`p2c_snapshot`] instead returns [`SnapshotBalance`], whose worker
+//! publishes an immutable snapshot of the services after each batch of changes. Acquiring loads the
+//! latest snapshot without locking, so changes published while acquiring are not considered until
+//! the next [`Service::acquire`].
+//!
+//! Once the worker has completed, or been dropped, the [`SnapshotBalance`] continues to use the
+//! last snapshot. If it's empty then [`Service::call`] responds with [`Terminated`] rather than
+//! waiting for services which will never be published.
+//!
+//! ```rust
+//! use burger::*;
+//! # use futures::stream::{iter, StreamExt};
+//! # use std::future::ready;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let double: fn(_) -> _ = |x: u32| ready(2 * x);
+//! let svc_stream = iter(["a", "b"])
+//!     .map(move |key| balance::Change::Insert(key, service_fn(double).pending_requests()))
+//!     .chain(futures::stream::pending());
+//! let (svc, worker) = balance::p2c::p2c_snapshot(svc_stream);
+//! tokio::spawn(worker);
+//! let response = svc.oneshot(5u32).await;
+//! assert_eq!(response, Ok(10));
+//! # }
+//! ```
+//!
 //! [Power of Two Random Choices]: http://www.eecs.harvard.edu/%7Emichaelm/postscripts/handbook2001.pdf
 use std::{
     convert::Infallible,
     future::Future,
     hash::Hash,
-    ops::{Deref, DerefMut},
     pin::pin,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
 };
 
-use futures_util::{stream::FuturesUnordered, FutureExt, Stream, StreamExt};
+use arc_swap::ArcSwap;
+use futures_util::{future, FutureExt, Stream, StreamExt};
 use indexmap::IndexMap;
-use tokio::sync::{OwnedRwLockWriteGuard, RwLock, RwLockWriteGuard};
+use tokio::sync::{Notify, RwLock};
 
 use crate::{
+    describe::{Describe, StackNode},
     leak::{Leak, LeakPermit},
     load::Load,
-    Service,
+    random, Service,
 };
 
-use super::Change;
+use super::{reconnect_worker, worker, Change, Controller, Members};
+
+#[doc(inline)]
+pub use super::Terminated;
+
+/// Returns two distinct random indices below `len`, which must be at least two.
+pub(super) fn sample(len: usize) -> (usize, usize) {
+    let below = |bound: usize| (random::next_u64() % bound as u64) as usize;
+    let first = below(len);
+    let second = below(len - 1);
+    (first, second + usize::from(second >= first))
+}
 
 /// Panics if empty.
 #[derive(Debug)]
@@ -61,16 +153,18 @@ where
     }
 }
 
-impl<S, Key> BalanceInner<S, Key>
+impl<S, Key> Members<Key, S> for BalanceInner<Leak<'static, S>, Key>
 where
     Key: Eq + Hash,
 {
-    fn insert(&mut self, key: Key, service: S) -> Option<S> {
-        self.services.insert(key, service)
+    fn insert(&mut self, key: Key, service: S) -> bool {
+        self.services
+            .insert(key, Leak::new(Arc::new(service)))
+            .is_some()
     }
 
-    fn remove(&mut self, key: &Key) -> Option<S> {
-        self.services.swap_remove(key)
+    fn remove(&mut self, key: &Key) -> bool {
+        self.services.swap_remove(key).is_some()
     }
 
     fn is_empty(&self) -> bool {
@@ -92,31 +186,19 @@ where
         S: 'a, Key: 'a;
 
     async fn acquire(&self) -> Self::Permit<'_> {
-        // Race all permits.
-        let mut permits: FuturesUnordered<_> = self
-            .services
-            .values()
-            .map(|s| async move {
-                let permit = s.acquire().await;
-                (s, permit)
-            })
-            .collect();
-
-        // Wait for first permit.
-        let (first, first_permit) = permits.next().await.unwrap();
+        if self.services.len() == 1 {
+            return self.services[0].acquire().await;
+        }
 
-        // Try obtain second permit.
-        let Some((second, second_permit)) = permits.next().now_or_never().flatten() else {
-            return first_permit;
-        };
+        let (first, second) = sample(self.services.len());
+        let (mut first, mut second) = (&self.services[first], &self.services[second]);
+        if second.load() < first.load() {
+            (first, second) = (second, first);
+        }
 
-        // Choose lowest load permit.
-        let first_load = first.load();
-        let second_load = second.load();
-        if first_load < second_load {
-            first_permit
-        } else {
-            second_permit
+        // The lowest load is polled first, so is preferred when both permits are available.
+        match future::select(pin!(first.acquire()), pin!(second.acquire())).await {
+            future::Either::Left((permit, _)) | future::Either::Right((permit, _)) => permit,
         }
     }
 
@@ -134,8 +216,13 @@ where
 #[derive(Debug)]
 pub struct Balance<S, Key> {
     inner: Arc<RwLock<BalanceInner<Leak<'static, S>, Key>>>,
+    parked: Parked<S, Key>,
 }
 
+/// The write lock on an empty [`Balance`], left by a completed worker or dropped [`Handle`] and
+/// taken on reconnecting.
+type Parked<S, Key> = super::Parked<BalanceInner<Leak<'static, S>, Key>>;
+
 impl<S, Key> Balance<S, Key>
 where
     S: Load,
@@ -168,35 +255,44 @@ where
     }
 }
 
-enum EitherLock<'a, T> {
-    Borrowed(RwLockWriteGuard<'a, T>),
-    Owned(OwnedRwLockWriteGuard<T>),
-}
-
-impl<T> Deref for EitherLock<'_, T> {
-    type Target = T;
-
-    fn deref(&self) -> &Self::Target {
-        match self {
-            EitherLock::Borrowed(x) => x.deref(),
-            EitherLock::Owned(x) => x.deref(),
+impl<S, Key> Describe for Balance<S, Key>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        let node = StackNode::new("Balance");
+        // The worker holds the lock while applying changes, or while there are no services.
+        match self.inner.try_read() {
+            Ok(inner) => node.with_children(
+                inner
+                    .services
+                    .values()
+                    .map(|service| service.get_ref().describe()),
+            ),
+            Err(_) => node,
         }
     }
 }
-impl<T> DerefMut for EitherLock<'_, T> {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        match self {
-            EitherLock::Borrowed(x) => x.deref_mut(),
-            EitherLock::Owned(x) => x.deref_mut(),
-        }
+
+impl<S, Key> Balance<S, Key>
+where
+    Key: Eq + Hash,
+{
+    /// Returns a new worker [`Future`], applying a [`Stream`] of [`Change`] to the existing services.
+    ///
+    /// See the [module](mod@crate::balance::p2c#reconnecting) for more information.
+    pub fn reconnect<St>(
+        &self,
+        changes: St,
+    ) -> impl Future<Output = Result<Infallible, Terminated>>
+    where
+        St: Stream<Item = Change<Key, S>>,
+    {
+        let guard = self.parked.lock().unwrap().take();
+        reconnect_worker(self.inner.clone(), self.parked.clone(), guard, changes)
     }
 }
 
-/// The change stream has terminated.
-#[derive(Debug)]
-#[non_exhaustive]
-pub struct Terminated;
-
 /// Constructs a [Power of Two Random Choices] load balancer, [`Balance`] and a worker [`Future`],
 /// from a [`Stream`] of [`Change`].
 ///
@@ -216,62 +312,369 @@ where
     let inner = Arc::new(RwLock::new(BalanceInner {
         services: IndexMap::new(),
     }));
+    let parked = Parked::default();
     let balance = Balance {
         inner: inner.clone(),
+        parked: parked.clone(),
     };
-    // Immediately take guard so that `BalanceInner` cannot acquire when empty. Hold it until at least one service has been added.
-    let empty_guard = inner.clone().try_write_owned().unwrap();
-    let fut = async move {
-        let mut empty_guard = Some(EitherLock::Owned(empty_guard));
-        let mut changes = pin!(changes);
-        while let Some(mut new_change) = changes.next().await {
-            // Take the guard if not already held.
-            let mut guard = if let Some(guard) = empty_guard.take() {
-                guard
-            } else {
-                EitherLock::Borrowed(inner.write().await)
-            };
+    (balance, worker(inner, parked, changes))
+}
+
+/// A handle to insert and remove services from a [`Balance`], returned by [`p2c_with_handle`].
+///
+/// [`Service::acquire`] on the [`Balance`] waits while it has no services. If the [`Handle`] is
+/// dropped while there are no services then it continues to wait, until services are inserted by a
+/// worker returned from [`Balance::reconnect`].
+///
+/// See the [module](mod@crate::balance::p2c) for more information.
+#[derive(Debug)]
+pub struct Handle<S, Key> {
+    controller: Controller<BalanceInner<Leak<'static, S>, Key>>,
+    parked: Parked<S, Key>,
+}
+
+impl<S, Key> Handle<S, Key>
+where
+    Key: Eq + Hash,
+{
+    /// Inserts a service, returning whether an existing service was replaced.
+    pub async fn insert(&self, key: Key, service: S) -> bool {
+        self.controller.insert(key, service).await
+    }
+
+    /// Removes a service, returning whether it was present.
+    pub async fn remove(&self, key: &Key) -> bool {
+        self.controller.remove::<Key, S>(key).await
+    }
+}
+
+impl<S, Key> Drop for Handle<S, Key> {
+    fn drop(&mut self) {
+        // Keep an empty balancer locked, rather than letting it acquire from no services.
+        if let Some(guard) = self.controller.take_empty_guard() {
+            *self.parked.lock().unwrap() = Some(guard);
+        }
+    }
+}
+
+/// Constructs an empty [Power of Two Random Choices] load balancer, [`Balance`], and a [`Handle`]
+/// used to insert and remove services.
+///
+/// See [module](mod@crate::balance::p2c) for more information.
+///
+/// [Power of Two Random Choices]: http://www.eecs.harvard.edu/%7Emichaelm/postscripts/handbook2001.pdf
+pub fn p2c_with_handle<Key, S>() -> (Balance<S, Key>, Handle<S, Key>)
+where
+    Key: Eq + Hash,
+{
+    let inner = Arc::new(RwLock::new(BalanceInner {
+        services: IndexMap::new(),
+    }));
+    let parked = Parked::default();
+    let balance = Balance {
+        inner: inner.clone(),
+        parked: parked.clone(),
+    };
+    let handle = Handle {
+        controller: Controller::new(inner),
+        parked,
+    };
+    (balance, handle)
+}
+
+/// The latest snapshot of services, shared between [`SnapshotBalance`] and its worker.
+#[derive(Debug)]
+struct Snapshot<S, Key> {
+    current: ArcSwap<BalanceInner<Leak<'static, S>, Key>>,
+    published: Notify,
+    /// Whether the worker has completed, or been dropped, so no more snapshots will be published.
+    closed: AtomicBool,
+}
+
+impl<S, Key> Snapshot<S, Key>
+where
+    Key: Eq + Hash + Clone,
+{
+    /// Publishes a new snapshot, waking any callers waiting for a non-empty snapshot.
+    fn publish(&self, services: &IndexMap<Key, Arc<S>>) {
+        let services = services
+            .iter()
+            .map(|(key, service)| (key.clone(), Leak::new(service.clone())))
+            .collect();
+        self.current.store(Arc::new(BalanceInner { services }));
+        self.published.notify_waiters();
+    }
+}
+
+/// Closes the [`Snapshot`] on drop, waking any callers waiting for a non-empty snapshot.
+struct CloseOnDrop<S, Key>(Arc<Snapshot<S, Key>>);
+
+impl<S, Key> Drop for CloseOnDrop<S, Key> {
+    fn drop(&mut self) {
+        self.0.closed.store(true, Ordering::Release);
+        self.0.published.notify_waiters();
+    }
+}
+
+/// A [`Service`] for the [`p2c_snapshot`] constructor.
+///
+/// See the [module](mod@crate::balance::p2c#snapshots) for more information.
+#[derive(Debug)]
+pub struct SnapshotBalance<S, Key> {
+    snapshot: Arc<Snapshot<S, Key>>,
+}
+
+impl<S, Key> SnapshotBalance<S, Key>
+where
+    S: Load,
+{
+    /// Returns [`Load::load`] for all services in the latest snapshot.
+    pub fn load_profile(&self) -> Vec<S::Metric> {
+        self.snapshot
+            .current
+            .load()
+            .services
+            .values()
+            .map(|svc| svc.load())
+            .collect()
+    }
+}
 
-            // We loop to consume as many changes while holding the lock.
-            // NOTE: This will starve the `Service` if the stream is never pending.
-            loop {
-                // Mutate the `BalanceInner`.
-                match new_change {
+impl<Request, S, Key> Service<Request> for SnapshotBalance<S, Key>
+where
+    S: Service<Request> + Load + 'static,
+    Key: 'static,
+{
+    type Response = Result<S::Response, Terminated>;
+    type Permit<'a> = Result<LeakPermit<'static, S, Request>, Terminated>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        loop {
+            // Register for wakeups before loading, so that a publish in between isn't missed.
+            let published = self.snapshot.published.notified();
+            let current = self.snapshot.current.load_full();
+            if !current.services.is_empty() {
+                return Ok(current.acquire().await);
+            }
+            if self.snapshot.closed.load(Ordering::Acquire) {
+                return Err(Terminated);
+            }
+            published.await;
+        }
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        Ok(Leak::call(permit?, request).await)
+    }
+}
+
+impl<S, Key> Describe for SnapshotBalance<S, Key>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        let snapshot = self.snapshot.current.load();
+        StackNode::new("SnapshotBalance").with_children(
+            snapshot
+                .services
+                .values()
+                .map(|service| service.get_ref().describe()),
+        )
+    }
+}
+
+/// Constructs a [Power of Two Random Choices] load balancer, [`SnapshotBalance`], and a worker
+/// [`Future`], from a [`Stream`] of [`Change`]. The worker publishes a snapshot of the services
+/// after applying each batch of ready changes.
+///
+/// See [module](mod@crate::balance::p2c#snapshots) for more information.
+///
+/// [Power of Two Random Choices]: http://www.eecs.harvard.edu/%7Emichaelm/postscripts/handbook2001.pdf
+pub fn p2c_snapshot<St, Key, S>(
+    changes: St,
+) -> (
+    SnapshotBalance<S, Key>,
+    impl Future<Output = Result<Infallible, Terminated>>,
+)
+where
+    St: Stream<Item = Change<Key, S>>,
+    Key: Eq + Hash + Clone,
+{
+    let snapshot = Arc::new(Snapshot {
+        current: ArcSwap::from_pointee(BalanceInner {
+            services: IndexMap::new(),
+        }),
+        published: Notify::new(),
+        closed: AtomicBool::new(false),
+    });
+    let balance = SnapshotBalance {
+        snapshot: snapshot.clone(),
+    };
+    let worker = async move {
+        let snapshot = CloseOnDrop(snapshot);
+        let mut services = IndexMap::new();
+        let mut changes = pin!(changes);
+        while let Some(mut change) = changes.next().await {
+            // Apply every ready change before publishing.
+            let terminated = loop {
+                match change {
                     Change::Insert(key, service) => {
-                        guard.insert(key, Leak::new(Arc::new(service)));
-                        tracing::trace!(len = guard.len(), "inserted service");
+                        services.insert(key, Arc::new(service));
                     }
                     Change::Remove(key) => {
-                        let success = guard.remove(&key).is_some();
-                        tracing::trace!(len = guard.len(), success, "removed service")
+                        services.swap_remove(&key);
                     }
-                };
-
+                }
                 match changes.next().now_or_never() {
-                    // Stream yielded.
-                    Some(Some(change)) => {
-                        new_change = change;
-                    }
-                    // Stream terminated.
-                    Some(None) => {
-                        return Err(Terminated);
-                    }
-                    // Stream pending.
-                    None => {
-                        // Retain the guard if `BalanceInner` is empty.
-                        if guard.is_empty() {
-                            empty_guard = Some(guard);
-                        } else {
-                            let _guard = empty_guard.take();
-                            drop(_guard);
-                        }
-                        break;
-                    }
+                    Some(Some(next)) => change = next,
+                    Some(None) => break true,
+                    None => break false,
                 }
+            };
+            snapshot.0.publish(&services);
+            tracing::trace!(len = services.len(), "published snapshot");
+            if terminated {
+                break;
             }
         }
         Err(Terminated)
     };
+    (balance, worker)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        future::{ready, Ready},
+        time::Duration,
+    };
+
+    use futures_util::{stream, StreamExt};
+    use tokio::{sync::mpsc, time::timeout};
+    use tokio_stream::wrappers::UnboundedReceiverStream;
+
+    use crate::{service_fn, Service, ServiceExt};
+
+    use super::{p2c, p2c_snapshot, p2c_with_handle, sample, Change, Terminated};
+
+    #[test]
+    fn sample_distinct() {
+        for len in 2..10 {
+            let (first, second) = sample(len);
+            assert_ne!(first, second);
+            assert!(first < len && second < len);
+        }
+    }
+
+    #[tokio::test]
+    async fn lowest_load() {
+        let named = |name: &'static str| service_fn(move |()| ready(name));
+        let (svc, handle) = p2c_with_handle();
+        handle.insert("busy", named("busy").constant_load(5)).await;
+        handle.insert("idle", named("idle").constant_load(1)).await;
+        for _ in 0..10 {
+            assert_eq!(svc.oneshot(()).await, "idle");
+        }
+    }
+
+    #[tokio::test]
+    async fn drop_empty_handle() {
+        let double: fn(u32) -> Ready<u32> = |x| ready(2 * x);
+        let (svc, handle) = p2c_with_handle();
+        handle
+            .insert("a", service_fn(double).pending_requests())
+            .await;
+        handle.remove(&"a").await;
+        drop(handle);
+
+        // Waits rather than acquiring from no services.
+        let acquire = timeout(Duration::from_millis(10), svc.acquire()).await;
+        assert!(acquire.is_err());
+
+        let changes = stream::iter([Change::Insert("b", service_fn(double).pending_requests())]);
+        tokio::spawn(svc.reconnect(changes.chain(stream::pending())));
+        assert_eq!(svc.oneshot(5).await, 10);
+    }
+
+    #[tokio::test]
+    async fn worker_ends_empty() {
+        let double: fn(u32) -> Ready<u32> = |x| ready(2 * x);
+        let changes = stream::iter([
+            Change::Insert(1, service_fn(double).pending_requests()),
+            Change::Remove(1),
+        ]);
+        let (svc, worker) = p2c(changes);
+        assert_eq!(worker.await, Err(Terminated));
+
+        // Waits rather than acquiring from no services.
+        let acquire = timeout(Duration::from_millis(10), svc.acquire()).await;
+        assert!(acquire.is_err());
+
+        let changes = stream::iter([Change::Insert(2, service_fn(double).pending_requests())]);
+        assert_eq!(svc.reconnect(changes).await, Err(Terminated));
+        assert_eq!(svc.oneshot(5).await, 10);
+    }
+
+    #[tokio::test]
+    async fn snapshot_waits_until_published() {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let (svc, worker) = p2c_snapshot(UnboundedReceiverStream::new(receiver));
+        tokio::spawn(worker);
 
-    (balance, fut)
+        let double: fn(u32) -> Ready<u32> = |x| ready(2 * x);
+        let insert = async {
+            tokio::task::yield_now().await;
+            sender
+                .send(Change::Insert(1, service_fn(double).pending_requests()))
+                .unwrap();
+        };
+        let (response, ()) = tokio::join!(svc.oneshot(5), insert);
+        assert_eq!(response, Ok(10));
+        assert_eq!(svc.load_profile(), [0]);
+
+        sender.send(Change::Remove(1)).unwrap();
+        let removed = async {
+            while !svc.load_profile().is_empty() {
+                tokio::task::yield_now().await;
+            }
+        };
+        timeout(Duration::from_secs(1), removed).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn snapshot_closed_when_empty() {
+        let double: fn(u32) -> Ready<u32> = |x| ready(2 * x);
+        let changes = stream::iter([
+            Change::Insert(1, service_fn(double).pending_requests()),
+            Change::Remove(1),
+        ]);
+        let (svc, worker) = p2c_snapshot(changes.chain(stream::pending()));
+        let worker = tokio::spawn(worker);
+
+        // Waiting callers are woken when the worker is dropped.
+        let abort = async {
+            tokio::task::yield_now().await;
+            worker.abort();
+        };
+        let (response, ()) = tokio::join!(svc.oneshot(5), abort);
+        assert_eq!(response, Err(Terminated));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn chatty_changes() {
+        let double: fn(u32) -> Ready<u32> = |x| ready(2 * x);
+        // Never pending, so the worker must release the lock between batches.
+        let changes = stream::iter(0..)
+            .map(move |index| Change::Insert(index % 4, service_fn(double).pending_requests()));
+        let (svc, worker) = p2c(changes);
+        tokio::spawn(worker);
+
+        let response = timeout(Duration::from_secs(1), svc.oneshot(5)).await;
+        assert_eq!(response.unwrap(), 10);
+    }
 }
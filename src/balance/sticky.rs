@@ -0,0 +1,333 @@
+//! The [`sticky`] function returns [`Sticky`], which routes requests sharing an affinity key, such
+//! as a session identifier, to the same service.
+//!
+//! The affinity key is extracted from each request during [`Service::call`]. A key seen before is
+//! routed to the service it was last routed to, while that service remains in the balancer.
+//! Otherwise, a service is chosen using the [Power of Two Random Choices], as in
+//! [`p2c`](super::p2c), and remembered for the key. Unhealthy services can be removed, and their
+//! sessions moved elsewhere, by wrapping the [`Stream`] using the [`health`](crate::health) module.
+//!
+//! The affinity table holds at most `capacity` keys, forgetting the least recently used key when
+//! full.
+//!
+//! As the request is unknown until [`Service::call`], [`Service::acquire`] on [`Sticky`] only waits
+//! until a service is present. The chosen service's permit is acquired during [`Service::call`].
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::*;
+//! # use futures::stream::{iter, StreamExt};
+//! # use std::future::ready;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc_stream = iter(["a", "b", "c"]).map(|name| {
+//!     let svc = service_fn(move |_: (String, u32)| ready(name)).pending_requests();
+//!     balance::Change::Insert(name, svc)
+//! });
+//! let key_fn = |(session, _): &(String, u32)| session.clone();
+//! let (svc, worker) = balance::sticky(svc_stream, key_fn, 1024);
+//! tokio::spawn(worker);
+//! let first = svc.oneshot(("alice".to_string(), 1)).await;
+//! let second = svc.oneshot(("alice".to_string(), 2)).await;
+//! assert_eq!(first, second);
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! This has _no_ [`Load`] implementation.
+//!
+//! [Power of Two Random Choices]: http://www.eecs.harvard.edu/%7Emichaelm/postscripts/handbook2001.pdf
+
+use std::{
+    any,
+    collections::{BTreeMap, HashMap},
+    convert::Infallible,
+    fmt,
+    future::Future,
+    hash::Hash,
+    sync::{Arc, Mutex},
+};
+
+use futures_util::Stream;
+use indexmap::IndexMap;
+use tokio::sync::RwLock;
+
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Service, ServiceExt,
+};
+
+use super::{p2c::sample, worker, Change, Members, Parked, Terminated};
+
+#[derive(Debug)]
+struct Backends<S, Key> {
+    services: IndexMap<Key, Arc<S>>,
+}
+
+impl<S, Key> Backends<S, Key>
+where
+    S: Load,
+{
+    /// Chooses the lower load of two random services. Panics if empty.
+    fn pick(&self) -> (&Key, &Arc<S>) {
+        if self.services.len() == 1 {
+            return self.services.get_index(0).expect("not empty");
+        }
+        let (first, second) = sample(self.services.len());
+        let first = self.services.get_index(first).expect("in bounds");
+        let second = self.services.get_index(second).expect("in bounds");
+        if second.1.load() < first.1.load() {
+            second
+        } else {
+            first
+        }
+    }
+}
+
+impl<S, Key> Members<Key, S> for Backends<S, Key>
+where
+    Key: Eq + Hash,
+{
+    fn insert(&mut self, key: Key, service: S) -> bool {
+        self.services.insert(key, Arc::new(service)).is_some()
+    }
+
+    fn remove(&mut self, key: &Key) -> bool {
+        self.services.swap_remove(key).is_some()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.services.is_empty()
+    }
+
+    fn len(&self) -> usize {
+        self.services.len()
+    }
+}
+
+/// A least recently used mapping from affinity keys to service keys.
+#[derive(Debug)]
+struct Affinity<A, Key> {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<A, (Key, u64)>,
+    recency: BTreeMap<u64, A>,
+}
+
+impl<A, Key> Affinity<A, Key> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+        }
+    }
+}
+
+impl<A, Key> Affinity<A, Key>
+where
+    A: Eq + Hash + Clone,
+{
+    /// Returns the service key for the affinity key, marking it as recently used.
+    fn get(&mut self, affinity: &A) -> Option<&Key> {
+        let (key, last_used) = self.entries.get_mut(affinity)?;
+        self.tick += 1;
+        let affinity = self
+            .recency
+            .remove(last_used)
+            .expect("recency is consistent");
+        self.recency.insert(self.tick, affinity);
+        *last_used = self.tick;
+        Some(key)
+    }
+
+    /// Associates the service key with the affinity key, forgetting the least recently used key if
+    /// full.
+    fn insert(&mut self, affinity: A, key: Key) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.tick += 1;
+        if let Some((_, last_used)) = self.entries.remove(&affinity) {
+            self.recency.remove(&last_used);
+        } else if self.entries.len() == self.capacity {
+            let (_, oldest) = self.recency.pop_first().expect("not empty");
+            self.entries.remove(&oldest);
+        }
+        self.recency.insert(self.tick, affinity.clone());
+        self.entries.insert(affinity, (key, self.tick));
+    }
+}
+
+/// A [`Service`] for the [`sticky`] constructor.
+///
+/// See the [module](mod@crate::balance::sticky) for more information.
+pub struct Sticky<S, Key, F, A> {
+    inner: Arc<RwLock<Backends<S, Key>>>,
+    affinity: Mutex<Affinity<A, Key>>,
+    key_fn: F,
+    /// Holds the write lock if the worker ends while there are no services.
+    _parked: Parked<Backends<S, Key>>,
+}
+
+impl<S, Key, F, A> fmt::Debug for Sticky<S, Key, F, A>
+where
+    S: fmt::Debug,
+    Key: fmt::Debug,
+    A: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sticky")
+            .field("inner", &self.inner)
+            .field("affinity", &self.affinity)
+            .field("key_fn", &format_args!("{}", any::type_name::<F>()))
+            .finish()
+    }
+}
+
+impl<S, Key, F, A> Sticky<S, Key, F, A>
+where
+    S: Load,
+{
+    /// Returns [`Load::load`] for all current services.
+    pub async fn load_profile(&self) -> Vec<S::Metric> {
+        self.inner
+            .read()
+            .await
+            .services
+            .values()
+            .map(|svc| svc.load())
+            .collect()
+    }
+}
+
+/// The [`Service::Permit`] type for [`Sticky`].
+pub struct StickyPermit<'a, S, Key, F, A> {
+    service: &'a Sticky<S, Key, F, A>,
+}
+
+impl<'a, S, Key, F, A> fmt::Debug for StickyPermit<'a, S, Key, F, A>
+where
+    S: fmt::Debug,
+    Key: fmt::Debug,
+    A: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StickyPermit")
+            .field("service", &self.service)
+            .finish()
+    }
+}
+
+impl<Request, S, Key, F, A> Service<Request> for Sticky<S, Key, F, A>
+where
+    S: Service<Request> + Load,
+    Key: Eq + Hash + Clone,
+    F: Fn(&Request) -> A,
+    A: Eq + Hash + Clone,
+{
+    type Response = S::Response;
+    type Permit<'a> = StickyPermit<'a, S, Key, F, A>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        // The worker holds the write lock while there are no services.
+        drop(self.inner.read().await);
+        StickyPermit { service: self }
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        let StickyPermit { service } = permit;
+        let affinity_key = (service.key_fn)(&request);
+        let chosen = {
+            let backends = service.inner.read().await;
+            let mut affinity = service.affinity.lock().unwrap();
+            let remembered = affinity
+                .get(&affinity_key)
+                .and_then(|key| backends.services.get(key));
+            match remembered {
+                Some(chosen) => chosen.clone(),
+                None => {
+                    let (key, chosen) = backends.pick();
+                    affinity.insert(affinity_key, key.clone());
+                    chosen.clone()
+                }
+            }
+        };
+        chosen.oneshot(request).await
+    }
+}
+
+impl<S, Key, F, A> Describe for Sticky<S, Key, F, A>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        let node = StackNode::new("Sticky");
+        // The worker holds the lock while applying changes, or while there are no services.
+        match self.inner.try_read() {
+            Ok(backends) => {
+                node.with_children(backends.services.values().map(|service| service.describe()))
+            }
+            Err(_) => node,
+        }
+    }
+}
+
+/// Constructs a sticky session load balancer, [`Sticky`], and a worker [`Future`], from a
+/// [`Stream`] of [`Change`], a closure extracting an affinity key from each request, and the
+/// capacity of the affinity table.
+///
+/// See [module](mod@crate::balance::sticky) for more information.
+pub fn sticky<St, Key, S, F, A>(
+    changes: St,
+    key_fn: F,
+    capacity: usize,
+) -> (
+    Sticky<S, Key, F, A>,
+    impl Future<Output = Result<Infallible, Terminated>>,
+)
+where
+    St: Stream<Item = Change<Key, S>>,
+    Key: Eq + Hash,
+{
+    let inner = Arc::new(RwLock::new(Backends {
+        services: IndexMap::new(),
+    }));
+    let parked = Parked::default();
+    let balance = Sticky {
+        inner: inner.clone(),
+        affinity: Mutex::new(Affinity::new(capacity)),
+        key_fn,
+        _parked: parked.clone(),
+    };
+    (balance, worker(inner, parked, changes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Affinity;
+
+    #[test]
+    fn least_recently_used() {
+        let mut affinity = Affinity::new(2);
+        affinity.insert("alice", 1);
+        affinity.insert("bob", 2);
+        assert_eq!(affinity.get(&"alice"), Some(&1));
+
+        // Bob is the least recently used.
+        affinity.insert("carol", 3);
+        assert_eq!(affinity.get(&"bob"), None);
+        assert_eq!(affinity.get(&"alice"), Some(&1));
+        assert_eq!(affinity.get(&"carol"), Some(&3));
+    }
+}
@@ -0,0 +1,243 @@
+//! The [`sync_service_fn`] function accepts a synchronous closure and returns [`SyncServiceFn`], a
+//! [`SyncService`] which is also a [`Service`] running the closure inline.
+//!
+//! Running CPU-heavy or blocking work inline stalls the executor. The
+//! [`ServiceExt::blocking`](crate::ServiceExt::blocking) combinator returns [`Blocking`], whose
+//! [`Service::call`] instead runs the [`SyncService`] on a thread dedicated to blocking work, using
+//! [`spawn_blocking`], and waits for the response. If the closure panics, the panic is resumed on
+//! the caller. If the runtime shuts down before the blocking task starts, the task is cancelled and
+//! [`Service::call`] panics, as there is no response to return.
+//!
+//! The blocking work can't be interrupted, so cancelling [`Service::call`] stops waiting for the
+//! response but not the work itself. The number of concurrent blocking calls may be bounded using
+//! [`ServiceExt::concurrency_limit`](crate::ServiceExt::concurrency_limit).
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::*;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc = sync_service_fn(|input: Vec<u8>| input.iter().map(|x| *x as u64).sum::<u64>())
+//!     .blocking()
+//!     .concurrency_limit(4);
+//! let response = svc.oneshot(vec![1, 2, 3]).await;
+//! assert_eq!(response, 6);
+//! # }
+//! ```
+//!
+//! # Calling from synchronous code
+//!
+//! The reverse bridge, for synchronous call sites which need to use a stack built from
+//! [`Service`]s, is the [`ServiceExt::block_on`](crate::ServiceExt::block_on) combinator. It
+//! returns [`BlockOn`], a [`SyncService`] whose [`BlockOn::call`] acquires a permit and calls the
+//! inner service, blocking the current thread on a [`Handle`] until it responds.
+//!
+//! As with [`Handle::block_on`], [`BlockOn::call`] panics when used from within an asynchronous
+//! context, and timers and IO only make progress while a worker thread is driving them, so the
+//! handle should belong to a multi-threaded runtime.
+//!
+//! ```rust
+//! use burger::*;
+//! use tokio::runtime::Runtime;
+//!
+//! let runtime = Runtime::new().unwrap();
+//! let svc = service_fn(|x: u32| async move { x * 2 })
+//!     .concurrency_limit(2)
+//!     .block_on(runtime.handle().clone());
+//! assert_eq!(svc.call(3), 6);
+//! ```
+//!
+//! # Load
+//!
+//! This has _no_ [`Load`](crate::load::Load) implementation, one can be added using
+//! [`ServiceExt::constant_load`](crate::ServiceExt::constant_load).
+
+use std::{any, fmt, panic, sync::Arc};
+
+use tokio::{runtime::Handle, task::spawn_blocking};
+
+use crate::{
+    describe::{Describe, StackNode},
+    Service, ServiceExt,
+};
+
+/// A synchronous function call.
+///
+/// See the [module](mod@crate::blocking) for more information.
+pub trait SyncService<Request> {
+    /// The type produced by the call.
+    type Response;
+
+    /// Calls the service, blocking until it responds.
+    fn call(&self, request: Request) -> Self::Response;
+}
+
+/// The [`SyncService`] returned by the [`sync_service_fn`] constructor.
+///
+/// See the [module](mod@crate::blocking) for more information.
+#[derive(Clone)]
+pub struct SyncServiceFn<F> {
+    closure: F,
+}
+
+impl<F> fmt::Debug for SyncServiceFn<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyncServiceFn")
+            .field("closure", &format_args!("{}", any::type_name::<F>()))
+            .finish()
+    }
+}
+
+impl<Request, F, Response> SyncService<Request> for SyncServiceFn<F>
+where
+    F: Fn(Request) -> Response,
+{
+    type Response = Response;
+
+    fn call(&self, request: Request) -> Self::Response {
+        (self.closure)(request)
+    }
+}
+
+impl<Request, F, Response> Service<Request> for SyncServiceFn<F>
+where
+    F: Fn(Request) -> Response,
+{
+    type Response = Response;
+    type Permit<'a> = &'a F
+    where
+        F: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        &self.closure
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        permit(request)
+    }
+}
+
+impl<F> Describe for SyncServiceFn<F> {
+    fn describe(&self) -> StackNode {
+        StackNode::new("SyncServiceFn")
+    }
+}
+
+/// Constructs a [`SyncService`] from a synchronous closure.
+///
+/// See the [module](mod@crate::blocking) for more information.
+pub fn sync_service_fn<F>(closure: F) -> SyncServiceFn<F> {
+    SyncServiceFn { closure }
+}
+
+/// A wrapper [`Service`] for the [`ServiceExt::blocking`](crate::ServiceExt::blocking) combinator.
+///
+/// See the [module](mod@crate::blocking) for more information.
+#[derive(Debug)]
+pub struct Blocking<S> {
+    inner: Arc<S>,
+}
+
+impl<S> Clone for Blocking<S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<S> Blocking<S> {
+    pub(crate) fn new(inner: S) -> Self {
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+}
+
+impl<Request, S> Service<Request> for Blocking<S>
+where
+    S: SyncService<Request> + Send + Sync + 'static,
+    S::Response: Send + 'static,
+    Request: Send + 'static,
+{
+    type Response = S::Response;
+    type Permit<'a> = &'a Arc<S>
+    where
+        S: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        &self.inner
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        let inner = permit.clone();
+        match spawn_blocking(move || inner.call(request)).await {
+            Ok(response) => response,
+            Err(error) if error.is_panic() => panic::resume_unwind(error.into_panic()),
+            Err(_) => panic!("blocking task was cancelled by runtime shutdown"),
+        }
+    }
+}
+
+impl<S> Describe for Blocking<S>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("Blocking").with_child(self.inner.describe())
+    }
+}
+
+/// A synchronous facade for the [`ServiceExt::block_on`](crate::ServiceExt::block_on) combinator.
+///
+/// See the [module](mod@crate::blocking) for more information.
+#[derive(Clone, Debug)]
+pub struct BlockOn<S> {
+    inner: S,
+    handle: Handle,
+}
+
+impl<S> BlockOn<S> {
+    pub(crate) fn new(inner: S, handle: Handle) -> Self {
+        Self { inner, handle }
+    }
+
+    /// Returns a reference to the [`Handle`] calls are blocked on.
+    pub fn handle(&self) -> &Handle {
+        &self.handle
+    }
+
+    /// Calls the inner service, blocking the current thread until it responds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from within an asynchronous context.
+    pub fn call<Request>(&self, request: Request) -> S::Response
+    where
+        S: Service<Request>,
+    {
+        self.handle.block_on(self.inner.oneshot(request))
+    }
+}
+
+impl<Request, S> SyncService<Request> for BlockOn<S>
+where
+    S: Service<Request>,
+{
+    type Response = S::Response;
+
+    fn call(&self, request: Request) -> Self::Response {
+        BlockOn::call(self, request)
+    }
+}
+
+impl<S> Describe for BlockOn<S>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("BlockOn").with_child(self.inner.describe())
+    }
+}
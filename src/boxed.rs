@@ -0,0 +1,160 @@
+//! The [`ServiceExt::boxed`](crate::ServiceExt::boxed) combinator returns [`BoxService`], which
+//! erases the type of the inner service and its [`Service::Permit`], leaving only the request and
+//! response types.
+//!
+//! This allows services with differing types to be stored in a collection, to be returned from a
+//! function without naming the full combinator chain, and to be used within
+//! [`steer`](crate::steer()) or [`select`](crate::select()).
+//!
+//! Each [`Service::acquire`] and [`Service::call`] incurs an allocation.
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::{boxed::BoxService, *};
+//!
+//! # #[cfg(feature = "tokio")]
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svcs: Vec<BoxService<u32, u32>> = vec![
+//!     service_fn(|x| async move { x + 1 }).boxed(),
+//!     service_fn(|x| async move { x * 2 })
+//!         .concurrency_limit(1)
+//!         .boxed(),
+//!     service_fn(|x| async move { x }).map(|x| x - 1).boxed(),
+//! ];
+//! let svc = select(svcs);
+//! let response = svc.oneshot(3).await;
+//! # }
+//! # #[cfg(not(feature = "tokio"))]
+//! # fn main() {}
+//! ```
+//!
+//! # Load
+//!
+//! This has _no_ [`Load`](crate::load::Load) implementation.
+
+use alloc::boxed::Box;
+use core::{any, fmt, future::Future, marker::PhantomData, pin::Pin};
+
+use crate::{
+    describe::{Describe, StackNode},
+    Service,
+};
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+
+/// An object safe version of [`Service`], with the permit type erased.
+trait DynService<Request, Response> {
+    fn acquire<'a>(&'a self) -> BoxFuture<'a, BoxPermit<'a, Request, Response>>
+    where
+        Request: 'a;
+}
+
+/// An object safe, consumable, permit.
+trait DynPermit<'a, Request, Response> {
+    fn call(self: Box<Self>, request: Request) -> BoxFuture<'a, Response>;
+}
+
+struct ErasedPermit<'a, S, Request>
+where
+    S: Service<Request> + 'a,
+{
+    inner: S::Permit<'a>,
+}
+
+impl<'a, S, Request> DynPermit<'a, Request, S::Response> for ErasedPermit<'a, S, Request>
+where
+    S: Service<Request> + 'a,
+    Request: 'a,
+{
+    fn call(self: Box<Self>, request: Request) -> BoxFuture<'a, S::Response> {
+        Box::pin(S::call(self.inner, request))
+    }
+}
+
+impl<Request, S> DynService<Request, S::Response> for S
+where
+    S: Service<Request>,
+{
+    fn acquire<'a>(&'a self) -> BoxFuture<'a, BoxPermit<'a, Request, S::Response>>
+    where
+        Request: 'a,
+    {
+        Box::pin(async move {
+            let inner: Box<dyn DynPermit<'a, Request, S::Response> + 'a> =
+                Box::new(ErasedPermit::<S, Request> {
+                    inner: S::acquire(self).await,
+                });
+            BoxPermit { inner }
+        })
+    }
+}
+
+/// A type erased [`Service`] returned by the [`ServiceExt::boxed`](crate::ServiceExt::boxed)
+/// combinator.
+///
+/// See the [module](crate::boxed) for more information.
+pub struct BoxService<Request, Response> {
+    inner: Box<dyn DynService<Request, Response>>,
+    _marker: PhantomData<fn(Request) -> Response>,
+}
+
+impl<Request, Response> BoxService<Request, Response> {
+    pub(crate) fn new<S>(inner: S) -> Self
+    where
+        S: Service<Request, Response = Response> + 'static,
+    {
+        Self {
+            inner: Box::new(inner),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<Request, Response> fmt::Debug for BoxService<Request, Response> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoxService")
+            .field("request", &format_args!("{}", any::type_name::<Request>()))
+            .field(
+                "response",
+                &format_args!("{}", any::type_name::<Response>()),
+            )
+            .finish()
+    }
+}
+
+/// The [`Service::Permit`] type for [`BoxService`].
+pub struct BoxPermit<'a, Request, Response> {
+    inner: Box<dyn DynPermit<'a, Request, Response> + 'a>,
+}
+
+impl<'a, Request, Response> fmt::Debug for BoxPermit<'a, Request, Response> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoxPermit").finish_non_exhaustive()
+    }
+}
+
+impl<Request, Response> Service<Request> for BoxService<Request, Response> {
+    type Response = Response;
+    type Permit<'a> = BoxPermit<'a, Request, Response>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        self.inner.acquire().await
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        permit.inner.call(request).await
+    }
+}
+
+impl<Request, Response> Describe for BoxService<Request, Response> {
+    fn describe(&self) -> StackNode {
+        StackNode::new("BoxService")
+    }
+}
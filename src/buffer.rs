@@ -3,6 +3,9 @@
 //! it defers to the inner service's [`Service::acquire`]. The buffer is drained when the inner
 //! service's permit becomes available.
 //!
+//! Callers, rather than requests, are buffered. To queue requests for a worker owning the inner
+//! service, see the [`worker`](mod@crate::worker) module.
+//!
 //! # Example
 //!
 //! ```rust
@@ -32,31 +35,150 @@
 //! # }
 //! ```
 //!
+//! # Ordering
+//!
+//! Callers which are buffered obtain the inner service's permit in first-in, first-out order. While
+//! any callers are buffered, subsequent [`Service::acquire`]s are also buffered, rather than
+//! overtaking those already waiting. Dropping a buffered permit without calling removes it from the
+//! queue.
+//!
+//! # Cloning
+//!
+//! Cloning a [`Buffer`] clones the inner service, while the buffer and its queue are shared between
+//! the clones.
+//!
+//! # Inspection
+//!
+//! [`Buffer::queued`] returns the number of buffered callers, which have acquired a permit from the
+//! [`Buffer`] but not yet from the inner service, and [`Buffer::available_capacity`] returns the
+//! number of further callers which may be buffered.
+//!
 //! # Load
 //!
 //! The [`Load::load`] on [`Buffer`] defers to the inner service.
 
-use std::fmt;
+use std::{
+    collections::BTreeSet,
+    fmt,
+    pin::pin,
+    sync::{Arc, Mutex},
+};
 
 use futures_util::FutureExt;
-use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio::sync::{Notify, SemaphorePermit};
 
-use crate::{load::Load, Middleware, Service};
+use crate::{
+    describe::{Describe, StackNode},
+    limit::{FixedLimit, Permits},
+    load::Load,
+    Middleware, Service,
+};
 
 /// A wrapper [`Service`] for the [`ServiceExt::buffer`](crate::ServiceExt::buffer) combinator.
 ///
 /// See the [module](crate::buffer) for more information.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Buffer<S> {
     inner: S,
-    semaphore: Semaphore,
+    semaphore: Arc<FixedLimit>,
+    queue: Arc<Queue>,
+    capacity: usize,
 }
 
 impl<S> Buffer<S> {
     pub(crate) fn new(inner: S, capacity: usize) -> Self {
         Self {
             inner,
-            semaphore: Semaphore::new(capacity),
+            semaphore: Arc::new(FixedLimit::new(capacity)),
+            queue: Arc::default(),
+            capacity,
+        }
+    }
+
+    /// Returns the number of buffered callers waiting for the inner service's permit.
+    pub fn queued(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Returns the number of further callers which may be buffered.
+    pub fn available_capacity(&self) -> usize {
+        self.semaphore.available_permits()
+    }
+}
+
+/// A first-in, first-out queue of buffered callers.
+#[derive(Debug, Default)]
+struct Queue {
+    state: Mutex<QueueState>,
+    notify: Notify,
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    /// The next ticket to be issued.
+    next: u64,
+    /// The ticket currently permitted to acquire from the inner service.
+    serving: u64,
+    /// Tickets, ahead of `serving`, which have been released.
+    released: BTreeSet<u64>,
+}
+
+impl Queue {
+    fn is_empty(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state.next == state.serving
+    }
+
+    /// Returns the number of tickets which have been issued but not released.
+    fn len(&self) -> usize {
+        let state = self.state.lock().unwrap();
+        (state.next - state.serving) as usize - state.released.len()
+    }
+
+    fn ticket(&self) -> Ticket<'_> {
+        let mut state = self.state.lock().unwrap();
+        let index = state.next;
+        state.next += 1;
+        Ticket { queue: self, index }
+    }
+}
+
+/// A position in the [`Queue`], released on drop.
+#[derive(Debug)]
+struct Ticket<'a> {
+    queue: &'a Queue,
+    index: u64,
+}
+
+impl Ticket<'_> {
+    /// Waits until this ticket is at the front of the queue.
+    async fn ready(&self) {
+        loop {
+            let mut notified = pin!(self.queue.notify.notified());
+            notified.as_mut().enable();
+            if self.queue.state.lock().unwrap().serving == self.index {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+impl Drop for Ticket<'_> {
+    fn drop(&mut self) {
+        let mut guard = self.queue.state.lock().unwrap();
+        let QueueState {
+            serving, released, ..
+        } = &mut *guard;
+        released.insert(self.index);
+        let mut advanced = false;
+        while released.remove(serving) {
+            *serving += 1;
+            advanced = true;
+        }
+        drop(guard);
+        if advanced {
+            self.queue.notify.notify_waiters();
         }
     }
 }
@@ -86,7 +208,7 @@ where
     S: Service<Request>,
 {
     Eager(S::Permit<'a>),
-    Buffered(&'a S, SemaphorePermit<'a>),
+    Buffered(&'a S, SemaphorePermit<'a>, Ticket<'a>),
 }
 
 impl<'a, S, Request> fmt::Debug for BufferPermitInner<'a, S, Request>
@@ -97,9 +219,12 @@ where
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Eager(arg0) => f.debug_tuple("Eager").field(arg0).finish(),
-            Self::Buffered(arg0, arg1) => {
-                f.debug_tuple("Buffered").field(arg0).field(arg1).finish()
-            }
+            Self::Buffered(arg0, arg1, arg2) => f
+                .debug_tuple("Buffered")
+                .field(arg0)
+                .field(arg1)
+                .field(arg2)
+                .finish(),
         }
     }
 }
@@ -114,22 +239,28 @@ where
         S: 'a;
 
     async fn acquire(&self) -> Self::Permit<'_> {
+        // Only attempt to overtake the queue when it's empty.
+        if self.queue.is_empty() {
+            if let Some(permit) = self.inner.acquire().now_or_never() {
+                return BufferPermit {
+                    inner: BufferPermitInner::Eager(permit),
+                };
+            }
+        }
+
+        let semaphore_permit = self.semaphore.acquire().await;
         BufferPermit {
-            inner: match self.inner.acquire().now_or_never() {
-                Some(some) => BufferPermitInner::Eager(some),
-                None => BufferPermitInner::Buffered(
-                    &self.inner,
-                    self.semaphore.acquire().await.expect("not closed"),
-                ),
-            },
+            inner: BufferPermitInner::Buffered(&self.inner, semaphore_permit, self.queue.ticket()),
         }
     }
 
     async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
         let permit = match permit.inner {
             BufferPermitInner::Eager(permit) => permit,
-            BufferPermitInner::Buffered(service, _permit) => {
+            BufferPermitInner::Buffered(service, _permit, ticket) => {
+                ticket.ready().await;
                 let permit = service.acquire().await;
+                drop(ticket);
                 drop(_permit);
                 permit
             }
@@ -149,6 +280,17 @@ where
     }
 }
 
+impl<S> Describe for Buffer<S>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("Buffer")
+            .with_config("capacity", self.capacity)
+            .with_child(self.inner.describe())
+    }
+}
+
 impl<S, T> Middleware<S> for Buffer<T>
 where
     T: Middleware<S>,
@@ -156,10 +298,81 @@ where
     type Service = Buffer<T::Service>;
 
     fn apply(self, svc: S) -> Self::Service {
-        let Self { inner, semaphore } = self;
-        Buffer {
-            inner: inner.apply(svc),
-            semaphore,
-        }
+        Buffer::new(self.inner.apply(svc), self.capacity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Mutex, time::Duration};
+
+    use futures_util::{future::join_all, FutureExt};
+    use tokio::time::sleep;
+
+    use crate::{service_fn, Service, ServiceExt};
+
+    async fn call<'a, S, Request>(
+        _svc: &'a S,
+        permit: S::Permit<'a>,
+        request: Request,
+    ) -> S::Response
+    where
+        S: Service<Request>,
+    {
+        S::call(permit, request).await
+    }
+
+    #[tokio::test]
+    async fn fifo() {
+        let order = Mutex::new(Vec::new());
+        let svc = service_fn(|x: usize| {
+            let order = &order;
+            async move {
+                order.lock().unwrap().push(x);
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .concurrency_limit(1)
+        .buffer(8);
+
+        join_all((0..8).map(|x| svc.oneshot(x))).await;
+        assert_eq!(*order.lock().unwrap(), (0..8).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn no_overtaking() {
+        let svc = service_fn(|x: usize| async move { x })
+            .concurrency_limit(1)
+            .buffer(2);
+
+        let eager = svc.acquire().await;
+        let buffered = svc.acquire().now_or_never().unwrap();
+        drop(eager);
+
+        // The inner permit is available, but there is a buffered caller ahead.
+        let next = svc.acquire().now_or_never().unwrap();
+        let mut next = Box::pin(call(&svc, next, 2));
+        assert!(next.as_mut().now_or_never().is_none());
+
+        assert_eq!(call(&svc, buffered, 1).await, 1);
+        assert_eq!(next.await, 2);
+    }
+
+    #[tokio::test]
+    async fn dropped_ticket() {
+        let svc = service_fn(|x: usize| async move { x })
+            .concurrency_limit(1)
+            .buffer(2);
+
+        let eager = svc.acquire().await;
+        let abandoned = svc.acquire().now_or_never().unwrap();
+        let buffered = svc.acquire().now_or_never().unwrap();
+        assert_eq!((svc.queued(), svc.available_capacity()), (2, 0));
+        drop(eager);
+        drop(abandoned);
+        assert_eq!((svc.queued(), svc.available_capacity()), (1, 1));
+
+        assert_eq!(call(&svc, buffered, 1).await, 1);
+        assert_eq!((svc.queued(), svc.available_capacity()), (0, 2));
     }
 }
@@ -0,0 +1,369 @@
+//! The [`ServiceExt::cache`](crate::ServiceExt::cache) combinator returns [`Cache`], which memoizes
+//! responses keyed by their request.
+//!
+//! Cached responses expire after a time-to-live. Once the capacity has been reached, the least
+//! recently used response is evicted to make room for a new one. Hits and evictions take constant
+//! time, amortized, regardless of the capacity.
+//!
+//! Concurrent [calls](Service::call) with identical requests are de-duplicated: the first call
+//! is forwarded to the inner service while subsequent calls wait for, and share, its response. If
+//! the first call is cancelled then a waiting call takes its place.
+//!
+//! The [`Service::acquire`] on [`Cache`] waits to acquire the inner [`Service::Permit`], as the
+//! request isn't known until [`Service::call`]. If the response is then found in the cache, or
+//! another call is already in flight, the inner permit is released without being used.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::{
+//!     sync::atomic::{AtomicUsize, Ordering},
+//!     time::Duration,
+//! };
+//!
+//! use burger::*;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let calls = AtomicUsize::new(0);
+//! let svc = service_fn(|x: u32| {
+//!     calls.fetch_add(1, Ordering::SeqCst);
+//!     async move { x.to_string() }
+//! })
+//! .cache(128, Duration::from_secs(60));
+//!
+//! assert_eq!(svc.oneshot(3).await, "3");
+//! assert_eq!(svc.oneshot(3).await, "3");
+//! assert_eq!(calls.load(Ordering::SeqCst), 1);
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`Cache`] defers to the inner service.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    hash::Hash,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    describe::{Describe, StackNode},
+    inflight::{self, Inflight, InflightState, Lookup},
+    load::Load,
+    Middleware, Service,
+};
+
+#[derive(Debug)]
+struct Entry<Response> {
+    response: Response,
+    inserted: Instant,
+    /// The generation of the last use.
+    used: u64,
+}
+
+#[derive(Debug)]
+struct State<Request, Response> {
+    entries: HashMap<Request, Entry<Response>>,
+    /// Uses of the entries, from least to most recent. A use is stale once its entry is used again,
+    /// or removed, and is skipped on eviction.
+    recency: VecDeque<(u64, Request)>,
+    generation: u64,
+    inflight: Inflight<Request, Response>,
+}
+
+impl<Request, Response> State<Request, Response>
+where
+    Request: Hash + Eq + Clone,
+{
+    /// Makes the entry the most recently used.
+    fn touch(&mut self, request: &Request) {
+        let Some(entry) = self.entries.get_mut(request) else {
+            return;
+        };
+        self.generation += 1;
+        entry.used = self.generation;
+        self.recency.push_back((self.generation, request.clone()));
+
+        // Discard the stale uses once they outnumber the entries, so hits don't grow the queue.
+        if self.recency.len() > 2 * self.entries.len() {
+            let entries = &self.entries;
+            self.recency
+                .retain(|(used, request)| entries.get(request).is_some_and(|e| e.used == *used));
+        }
+    }
+
+    /// Removes the least recently used entry.
+    fn evict(&mut self) {
+        while let Some((used, request)) = self.recency.pop_front() {
+            if self.entries.get(&request).is_some_and(|e| e.used == used) {
+                self.entries.remove(&request);
+                return;
+            }
+        }
+    }
+}
+
+impl<Request, Response> InflightState<Request, Response> for State<Request, Response> {
+    fn inflight(&mut self) -> &mut Inflight<Request, Response> {
+        &mut self.inflight
+    }
+}
+
+/// A wrapper for the [`ServiceExt::cache`](crate::ServiceExt::cache) combinator.
+///
+/// See the [module](crate::cache) for more information.
+#[derive(Debug)]
+pub struct Cache<S, Request, Response> {
+    inner: S,
+    state: Mutex<State<Request, Response>>,
+    capacity: usize,
+    ttl: Duration,
+}
+
+impl<S, Request, Response> Cache<S, Request, Response> {
+    pub(crate) fn new(inner: S, capacity: usize, ttl: Duration) -> Self {
+        Self {
+            inner,
+            state: Mutex::new(State {
+                entries: HashMap::new(),
+                recency: VecDeque::new(),
+                generation: 0,
+                inflight: HashMap::new(),
+            }),
+            capacity,
+            ttl,
+        }
+    }
+
+    /// Returns the number of cached responses, including those which have expired but are yet to
+    /// be evicted.
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().entries.len()
+    }
+
+    /// Returns whether there are no cached responses.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes all cached responses.
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.entries.clear();
+        state.recency.clear();
+    }
+}
+
+impl<S, Request, Response> Cache<S, Request, Response>
+where
+    Request: Hash + Eq + Clone,
+    Response: Clone,
+{
+    fn lookup(
+        &self,
+        request: &Request,
+        now: Instant,
+    ) -> Lookup<'_, State<Request, Response>, Request, Response> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(entry) = state.entries.get(request) {
+            if now.saturating_duration_since(entry.inserted) < self.ttl {
+                let response = entry.response.clone();
+                state.touch(request);
+                return Lookup::Hit(response);
+            }
+            state.entries.remove(request);
+            tracing::trace!("expired cached response");
+        }
+        inflight::join(&self.state, &mut state, request)
+    }
+
+    fn insert(&self, request: Request, response: Response, now: Instant) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        state.entries.remove(&request);
+        while state.entries.len() >= self.capacity {
+            state.evict();
+        }
+        let entry = Entry {
+            response,
+            inserted: now,
+            used: 0,
+        };
+        state.entries.insert(request.clone(), entry);
+        state.touch(&request);
+    }
+}
+
+/// The [`Service::Permit`] type for [`Cache`].
+pub struct CachePermit<'a, S, Request>
+where
+    S: Service<Request>,
+{
+    service: &'a Cache<S, Request, S::Response>,
+    inner: S::Permit<'a>,
+}
+
+impl<'a, S, Request> fmt::Debug for CachePermit<'a, S, Request>
+where
+    S: Service<Request> + fmt::Debug,
+    Request: fmt::Debug,
+    S::Response: fmt::Debug,
+    S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CachePermit")
+            .field("service", &self.service)
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<Request, S> Service<Request> for Cache<S, Request, S::Response>
+where
+    S: Service<Request>,
+    Request: Hash + Eq + Clone,
+    S::Response: Clone,
+{
+    type Response = S::Response;
+    type Permit<'a> = CachePermit<'a, S, Request>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        CachePermit {
+            service: self,
+            inner: self.inner.acquire().await,
+        }
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        let CachePermit { service, inner } = permit;
+        let lookup = |request: &Request| service.lookup(request, Instant::now());
+        let insert = |request: &Request, response: &S::Response| {
+            service.insert(request.clone(), response.clone(), Instant::now());
+        };
+        inflight::call(&service.inner, inner, request, lookup, insert).await
+    }
+}
+
+impl<S, Request, Response> Load for Cache<S, Request, Response>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S, Request, Response> Describe for Cache<S, Request, Response>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("Cache")
+            .with_config("capacity", self.capacity)
+            .with_config("ttl", format_args!("{:?}", self.ttl))
+            .with_child(self.inner.describe())
+    }
+}
+
+impl<S, T, Request, Response> Middleware<S> for Cache<T, Request, Response>
+where
+    T: Middleware<S>,
+{
+    type Service = Cache<T::Service, Request, Response>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self {
+            inner,
+            state,
+            capacity,
+            ttl,
+        } = self;
+        Cache {
+            inner: inner.apply(svc),
+            state,
+            capacity,
+            ttl,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    use tokio::time::sleep;
+
+    use crate::{service_fn, ServiceExt};
+
+    #[tokio::test]
+    async fn evict_and_expire() {
+        let calls = AtomicUsize::new(0);
+        let svc = service_fn(|x: u32| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async move { x }
+        })
+        .cache(2, Duration::from_millis(50));
+
+        svc.oneshot(1).await;
+        svc.oneshot(2).await;
+        // 1 becomes the most recently used, so 2 is evicted.
+        svc.oneshot(1).await;
+        svc.oneshot(3).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        svc.oneshot(1).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        svc.oneshot(2).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+
+        sleep(Duration::from_millis(60)).await;
+        svc.oneshot(2).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 5);
+    }
+
+    #[tokio::test]
+    async fn repeated_hits() {
+        let svc = service_fn(|x: u32| async move { x }).cache(2, Duration::from_secs(60));
+        for _ in 0..100 {
+            svc.oneshot(1).await;
+            svc.oneshot(2).await;
+        }
+
+        // Stale uses are discarded, rather than accumulating.
+        assert!(svc.state.lock().unwrap().recency.len() <= 4);
+        svc.oneshot(3).await;
+        assert_eq!(svc.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn single_flight() {
+        let calls = AtomicUsize::new(0);
+        let svc = service_fn(|x: u32| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async move {
+                sleep(Duration::from_millis(20)).await;
+                x
+            }
+        })
+        .cache(0, Duration::from_secs(60));
+
+        let (a, b) = tokio::join!(svc.oneshot(1), svc.oneshot(1));
+        assert_eq!((a, b), (1, 1));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
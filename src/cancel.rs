@@ -0,0 +1,206 @@
+//! The [`ServiceExt::with_cancellation`](crate::ServiceExt::with_cancellation) combinator returns
+//! [`WithCancellation`], which ties a service to a [`CancellationToken`], allowing the work of many
+//! callers, for example those serving a client which has disconnected, to be abandoned at once.
+//!
+//! Once the token is cancelled, [`Service::acquire`] on [`WithCancellation`] immediately returns a
+//! permit which fails the request with [`Cancelled`], without calling the inner service. Pending
+//! [`Service::acquire`]s stop waiting on the inner service and do likewise. Inflight
+//! [calls](Service::call) are dropped, cancelling the inner call, and also return [`Cancelled`].
+//!
+//! Child tokens, constructed using [`CancellationToken::child_token`], allow a subset of services
+//! to be cancelled while still being cancelled alongside their parent.
+//!
+//! This module requires the `cancellation` feature.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::time::Duration;
+//!
+//! use burger::{cancel::Cancelled, *};
+//! use tokio::time::sleep;
+//! use tokio_util::sync::CancellationToken;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let token = CancellationToken::new();
+//! let svc = service_fn(|x: u32| async move {
+//!     sleep(Duration::from_secs(10)).await;
+//!     x + 1
+//! })
+//! .with_cancellation(token.clone());
+//! let (response, ()) = tokio::join!(svc.oneshot(3), async {
+//!     sleep(Duration::from_millis(10)).await;
+//!     token.cancel();
+//! });
+//! assert_eq!(response, Err(Cancelled));
+//! assert_eq!(svc.oneshot(4).await, Err(Cancelled));
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`WithCancellation`] defers to the inner service.
+
+use std::fmt;
+
+use tokio::select;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Middleware, Service,
+};
+
+/// The [`CancellationToken`] was cancelled before the [call](Service::call) completed.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// A wrapper [`Service`] for the
+/// [`ServiceExt::with_cancellation`](crate::ServiceExt::with_cancellation) combinator.
+///
+/// See the [module](crate::cancel) for more information.
+#[derive(Clone, Debug)]
+pub struct WithCancellation<S> {
+    inner: S,
+    token: CancellationToken,
+}
+
+impl<S> WithCancellation<S> {
+    pub(crate) fn new(inner: S, token: CancellationToken) -> Self {
+        Self { inner, token }
+    }
+
+    /// Returns the [`CancellationToken`].
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+}
+
+/// The [`Service::Permit`] type for [`WithCancellation`].
+pub struct WithCancellationPermit<'a, S, Request>
+where
+    S: Service<Request> + 'a,
+{
+    inner: Option<S::Permit<'a>>,
+    token: &'a CancellationToken,
+}
+
+impl<'a, S, Request> fmt::Debug for WithCancellationPermit<'a, S, Request>
+where
+    S: Service<Request>,
+    S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WithCancellationPermit")
+            .field("inner", &self.inner)
+            .field("token", &self.token)
+            .finish()
+    }
+}
+
+impl<Request, S> Service<Request> for WithCancellation<S>
+where
+    S: Service<Request>,
+{
+    type Response = Result<S::Response, Cancelled>;
+    type Permit<'a> = WithCancellationPermit<'a, S, Request>
+    where
+        S: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        let inner = select! {
+            biased;
+            _ = self.token.cancelled() => None,
+            permit = self.inner.acquire() => Some(permit),
+        };
+        WithCancellationPermit {
+            inner,
+            token: &self.token,
+        }
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        let WithCancellationPermit { inner, token } = permit;
+        let permit = inner.ok_or(Cancelled)?;
+        select! {
+            biased;
+            _ = token.cancelled() => Err(Cancelled),
+            response = S::call(permit, request) => Ok(response),
+        }
+    }
+}
+
+impl<S> Load for WithCancellation<S>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S> Describe for WithCancellation<S>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("WithCancellation").with_child(self.inner.describe())
+    }
+}
+
+impl<S, T> Middleware<S> for WithCancellation<T>
+where
+    T: Middleware<S>,
+{
+    type Service = WithCancellation<T::Service>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self { inner, token } = self;
+        WithCancellation {
+            inner: inner.apply(svc),
+            token,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::pin;
+
+    use futures_util::poll;
+    use tokio_util::sync::CancellationToken;
+
+    use crate::{service_fn, Service, ServiceExt};
+
+    use super::{Cancelled, WithCancellation};
+
+    #[tokio::test]
+    async fn cancels_waiting_acquire() {
+        let token = CancellationToken::new();
+        let svc = service_fn(|x: u32| async move { x })
+            .concurrency_limit(1)
+            .with_cancellation(token.child_token());
+        let held = svc.acquire().await;
+
+        let mut waiting = pin!(svc.acquire());
+        assert!(poll!(waiting.as_mut()).is_pending());
+        token.cancel();
+        let permit = waiting.await;
+        assert_eq!(WithCancellation::call(permit, 1).await, Err(Cancelled));
+
+        // Permits acquired before cancellation also fail.
+        assert_eq!(WithCancellation::call(held, 2).await, Err(Cancelled));
+    }
+}
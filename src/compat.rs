@@ -1,10 +1,20 @@
 //! [`tower`] is an established service abstraction.
 //!
 //! A [`tower::Service`] is converted into a [`burger::Service`](crate::Service) using the
-//! [`compat`] function.
+//! [`compat`] function. Conversely, a [`burger::Service`](crate::Service) within an [`Arc`] is
+//! converted into a [`tower::Service`] using the [`into_tower`] function.
+//!
+//! Layers may also be converted between the two:
+//!
+//! - [`compat_layer`] converts a [`tower::Layer`] into a [`Middleware`], returning [`CompatLayer`].
+//! - [`tower_layer`] converts a [`Middleware`] into a [`tower::Layer`], returning [`TowerLayer`].
+//!
+//! [`IntoTower`] acquires an [`OwnedPermit`] in [`tower::Service::poll_ready`] and holds it until
+//! [`tower::Service::call`]. Its futures are boxed and are not [`Send`].
 //!
 //! Note that [`tower`], in general, has no disarm mechanism. This means that
-//! dropping the permit is _not_ sufficient to restore the service to a reasonable state.
+//! dropping the permit is _not_ sufficient to restore the service to a reasonable state, unlike
+//! the [disarming](crate::Service#disarming) of other services.
 //!
 //! # Example
 //!
@@ -20,18 +30,98 @@
 //! # }
 //! ```
 //!
+//!
+//! A [`tower::Layer`] is applied to a [`burger::Service`](crate::Service) as follows:
+//!
+//! ```rust
+//! use burger::{compat::compat_layer, *};
+//! use tower::util::MapResponseLayer;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc = service_fn(|x: u32| async move { x + 1 });
+//! let svc = compat_layer(MapResponseLayer::new(|x: u32| x.to_string())).apply(svc);
+//! let response = svc.oneshot(3).await;
+//! assert_eq!(response, Ok("4".to_string()));
+//! # }
+//! ```
+//!
+//! and a [`Middleware`] is applied to a [`tower::Service`] as follows:
+//!
+//! ```rust
+//! use burger::{compat::tower_layer, *};
+//! use tower::{Layer, ServiceExt as _};
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let layer = tower_layer(MiddlewareBuilder.map(|x: Result<u32, ()>| x.map(|x| x + 1)));
+//! let svc = layer.layer(tower::service_fn(|x: u32| async move { Ok::<_, ()>(x) }));
+//! let response = svc.oneshot(3).await;
+//! assert_eq!(response, Ok(Ok(4)));
+//! # }
+//! ```
+//!
+//! # Balancing
+//!
+//! Clients built on [`tower`], such as a `tonic::transport::Channel` for gRPC, can be balanced by
+//! converting a client for each endpoint using [`compat`]. As [`Compat`] reports the
+//! [`tower::load::Load`] of the client, which such clients don't usually implement,
+//! [`ServiceExt::pending_requests`](crate::ServiceExt::pending_requests) supplies the number of
+//! inflight requests instead. The endpoints are then inserted into a [balancer](crate::balance)
+//! using [`discover::fixed`](crate::discover::fixed), or any dynamic
+//! [`Discover`](crate::discover::Discover) source.
+//!
+//! Each client should connect lazily, for example using `Endpoint::connect_lazy` in `tonic`, so
+//! that an unreachable endpoint doesn't prevent the others from being inserted. With the `tonic`
+//! feature enabled, [`tonic_channel`] constructs such a client and
+//! [`balance::from_endpoints`](crate::balance::from_endpoints) inserts one for each endpoint.
+//!
+//! ```rust
+//! use burger::{discover, *};
+//! # use futures::FutureExt;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! // Stands in for a client connecting to each endpoint.
+//! let connect = |uri: &'static str| {
+//!     let svc = tower::service_fn(move |x: u32| async move { Ok::<_, ()>((uri, x)) });
+//!     compat(svc).pending_requests()
+//! };
+//! let endpoints = ["http://10.0.0.1:50051", "http://10.0.0.2:50051"];
+//! let changes = discover::fixed(endpoints.map(|uri| (uri, connect(uri))));
+//! let (svc, worker) = balance::p2c(changes);
+//! let worker = tokio::spawn(worker.map(|_| ()));
+//! let (uri, response) = svc.oneshot(3).await.unwrap();
+//! assert!(endpoints.contains(&uri));
+//! assert_eq!(response, 3);
+//! # worker.abort();
+//! # }
+//! ```
+//!
 //! # Load
 //!
 //! The [`Load::load`] on [`Compat`] implementation uses [`tower::load::Load`].
 
 use std::{
-    future::poll_fn,
-    sync::{Mutex, MutexGuard},
+    any,
+    convert::Infallible,
+    fmt,
+    future::{poll_fn, Future},
+    marker::PhantomData,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context, Poll},
 };
 
-use tower::{load::Load, Service as TowerService};
+use tower::{load::Load, Layer, Service as TowerService};
 
-use crate::Service;
+#[cfg(feature = "tonic")]
+use crate::load::PendingRequests;
+use crate::{
+    describe::{Describe, StackNode},
+    leak::OwnedPermit,
+    Middleware, Service, ServiceExt,
+};
 
 /// A compatibility wrapper for [`tower::Service`].
 ///
@@ -85,3 +175,212 @@ where
         self.inner.lock().unwrap().load()
     }
 }
+
+impl<S> Describe for Compat<S> {
+    fn describe(&self) -> StackNode {
+        StackNode::new("Compat")
+    }
+}
+
+/// A [`tonic::transport::Channel`], converted using [`compat`], with
+/// [`Load`](crate::load::Load) measured by the number of pending requests.
+///
+/// See the [module](mod@crate::compat) for more information.
+#[cfg(feature = "tonic")]
+pub type TonicChannel = PendingRequests<Compat<tonic::transport::Channel>>;
+
+/// Lazily connects a [`tonic::transport::Channel`] to the endpoint, returning a [`TonicChannel`].
+///
+/// The channel spawns a background task, so this must be called within a [`tokio`] runtime.
+///
+/// See the [module](mod@crate::compat) for more information.
+#[cfg(feature = "tonic")]
+pub fn tonic_channel(endpoint: impl Into<tonic::transport::Endpoint>) -> TonicChannel {
+    compat(endpoint.into().connect_lazy()).pending_requests()
+}
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T>>>;
+
+/// A compatibility wrapper, implementing [`tower::Service`], for a
+/// [`burger::Service`](crate::Service).
+///
+/// See [module](mod@crate::compat) for more information.
+pub struct IntoTower<S, Request>
+where
+    S: Service<Request> + 'static,
+{
+    inner: Arc<S>,
+    acquiring: Option<BoxFuture<OwnedPermit<S, Request>>>,
+    permit: Option<OwnedPermit<S, Request>>,
+}
+
+impl<S, Request> fmt::Debug for IntoTower<S, Request>
+where
+    S: Service<Request> + fmt::Debug + 'static,
+    for<'a> S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IntoTower")
+            .field("inner", &self.inner)
+            .field("acquiring", &self.acquiring.is_some())
+            .field("permit", &self.permit)
+            .finish()
+    }
+}
+
+impl<S, Request> Clone for IntoTower<S, Request>
+where
+    S: Service<Request> + 'static,
+{
+    fn clone(&self) -> Self {
+        into_tower(self.inner.clone())
+    }
+}
+
+/// Converts a [`burger::Service`](Service) to a [`tower::Service`].
+///
+/// See the [module](mod@crate::compat) for more information.
+pub fn into_tower<S, Request>(inner: Arc<S>) -> IntoTower<S, Request>
+where
+    S: Service<Request> + 'static,
+{
+    IntoTower {
+        inner,
+        acquiring: None,
+        permit: None,
+    }
+}
+
+impl<S, Request> TowerService<Request> for IntoTower<S, Request>
+where
+    S: Service<Request> + 'static,
+    Request: 'static,
+{
+    type Response = S::Response;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<S::Response, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.permit.is_some() {
+            return Poll::Ready(Ok(()));
+        }
+        let acquiring = self
+            .acquiring
+            .get_or_insert_with(|| Box::pin(self.inner.clone().acquire_owned()));
+        let permit = std::task::ready!(acquiring.as_mut().poll(cx));
+        self.acquiring = None;
+        self.permit = Some(permit);
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let permit = self
+            .permit
+            .take()
+            .expect("`poll_ready` must be called before `call`");
+        Box::pin(async move { Ok(permit.call(request).await) })
+    }
+}
+
+/// A [`Middleware`] applying a [`tower::Layer`], returned by [`compat_layer`].
+///
+/// See the [module](mod@crate::compat) for more information.
+pub struct CompatLayer<L, Request> {
+    layer: L,
+    _request: PhantomData<fn(Request)>,
+}
+
+impl<L, Request> fmt::Debug for CompatLayer<L, Request>
+where
+    L: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompatLayer")
+            .field("layer", &self.layer)
+            .field("_request", &format_args!("{}", any::type_name::<Request>()))
+            .finish()
+    }
+}
+
+impl<L, Request> Clone for CompatLayer<L, Request>
+where
+    L: Clone,
+{
+    fn clone(&self) -> Self {
+        compat_layer(self.layer.clone())
+    }
+}
+
+/// Converts a [`tower::Layer`] to a [`Middleware`].
+///
+/// See the [module](mod@crate::compat) for more information.
+pub fn compat_layer<L, Request>(layer: L) -> CompatLayer<L, Request> {
+    CompatLayer {
+        layer,
+        _request: PhantomData,
+    }
+}
+
+impl<S, L, Request> Middleware<S> for CompatLayer<L, Request>
+where
+    S: Service<Request> + 'static,
+    L: Layer<IntoTower<S, Request>>,
+{
+    type Service = Compat<L::Service>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        compat(self.layer.layer(into_tower(Arc::new(svc))))
+    }
+}
+
+/// A [`tower::Layer`] applying a [`Middleware`], returned by [`tower_layer`].
+///
+/// See the [module](mod@crate::compat) for more information.
+pub struct TowerLayer<M, Request> {
+    middleware: M,
+    _request: PhantomData<fn(Request)>,
+}
+
+impl<M, Request> fmt::Debug for TowerLayer<M, Request>
+where
+    M: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TowerLayer")
+            .field("middleware", &self.middleware)
+            .field("_request", &format_args!("{}", any::type_name::<Request>()))
+            .finish()
+    }
+}
+
+impl<M, Request> Clone for TowerLayer<M, Request>
+where
+    M: Clone,
+{
+    fn clone(&self) -> Self {
+        tower_layer(self.middleware.clone())
+    }
+}
+
+/// Converts a [`Middleware`] to a [`tower::Layer`]. As [`Middleware::apply`] consumes the
+/// middleware, it's cloned each time the layer is applied.
+///
+/// See the [module](mod@crate::compat) for more information.
+pub fn tower_layer<M, Request>(middleware: M) -> TowerLayer<M, Request> {
+    TowerLayer {
+        middleware,
+        _request: PhantomData,
+    }
+}
+
+impl<S, M, Request> Layer<S> for TowerLayer<M, Request>
+where
+    M: Middleware<Compat<S>> + Clone,
+    M::Service: Service<Request> + 'static,
+{
+    type Service = IntoTower<M::Service, Request>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        into_tower(Arc::new(self.middleware.clone().apply(compat(inner))))
+    }
+}
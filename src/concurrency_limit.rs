@@ -23,70 +23,222 @@
 //! # }
 //! ```
 //!
+//! # Cloning
+//!
+//! Cloning a [`ConcurrencyLimit`] clones the inner service, while the limit is shared between the
+//! clones.
+//!
+//! ```rust
+//! use burger::*;
+//! # use tokio::{join, time::sleep};
+//! # use std::time::{Duration, Instant};
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc = service_fn(|x: u32| async move {
+//!     sleep(Duration::from_millis(50)).await;
+//!     x
+//! })
+//! .concurrency_limit(1);
+//! let clone = svc.clone();
+//! let start = Instant::now();
+//! let (a, b) = join! {
+//!     svc.oneshot(1),
+//!     clone.oneshot(2)
+//! };
+//! assert!(start.elapsed() >= Duration::from_millis(100));
+//! # }
+//! ```
+//!
+//! # Inspection
+//!
+//! [`ConcurrencyLimit::available_permits`] returns the number of calls which may currently be
+//! permitted. As clones share the limit, a clone can be used to report this elsewhere, for example
+//! as a [`Load`] source using [`load_fn`](crate::load::load_fn).
+//!
+//! # Permit sources
+//!
+//! By default, permits are drawn from a [`FixedLimit`] local to the process. The
+//! [`ServiceExt::concurrency_limit_with`](crate::ServiceExt::concurrency_limit_with) combinator
+//! instead draws permits from any implementation of [`Permits`], such as an [`AdjustableLimit`],
+//! whose limit can be changed while in use, or a limiter shared between processes. These are
+//! described in the [`limit`](crate::limit#permit-sources) module and re-exported here.
+//!
+//! When a [`ConcurrencyLimit`] is applied as a [`Middleware`], a [`FixedLimit`] is created afresh
+//! for each service, whereas a custom source of permits is shared by every service it's applied to.
+//!
+//! # Acquire order
+//!
+//! A [`ConcurrencyLimitPermit`] holds both a permit from the limit and the inner
+//! [`Service::Permit`]. [`ConcurrencyLimit::with_order`] chooses which is acquired first, with
+//! [`AcquireOrder`]:
+//!
+//! - [`AcquireOrder::SemaphoreFirst`], the default, waits for the limit and then the inner
+//!   service. Waiters on a [`FixedLimit`] are served in FIFO order, so callers are admitted fairly
+//!   and only admitted callers contend for the inner service. However, a slot is held while the
+//!   inner acquire is pending, so a slow inner service, such as a saturated
+//!   [`buffer`](crate::ServiceExt::buffer), reduces the throughput of every clone sharing the limit.
+//! - [`AcquireOrder::InnerFirst`] waits for the inner service and then the limit. A slot is only
+//!   held by a caller which is ready to call, so the limit is never occupied by waiting. However,
+//!   the inner permit is held while waiting for the limit, and callers are admitted in the order
+//!   the inner service grants its permits, rather than the order of the limit.
+//!
+//! The order also decides what is churned when acquires are raced and the losers dropped, as
+//! [`select`](fn@crate::select) does. With [`AcquireOrder::SemaphoreFirst`] each losing limit
+//! briefly holds, then releases, a slot, which may cause others waiting on a shared limit to wake
+//! and requeue. With [`AcquireOrder::InnerFirst`] an available inner permit is taken and released
+//! instead, and the limit is only touched by a caller with an inner permit.
+//!
+//! ```rust
+//! use burger::{concurrency_limit::AcquireOrder, *};
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc = service_fn(|x: u32| async move { 2 * x })
+//!     .buffer(4)
+//!     .concurrency_limit(2)
+//!     .with_order(AcquireOrder::InnerFirst);
+//! let response = svc.oneshot(4).await;
+//! assert_eq!(response, 8);
+//! # }
+//! ```
+//!
 //! # Load
 //!
 //! The [`Load::load`] on [ConcurrencyLimit] defers to the inner service.
 
-use std::fmt;
+use std::{any, fmt, sync::Arc};
 
-use tokio::sync::{Semaphore, SemaphorePermit};
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Middleware, Service,
+};
 
-use crate::{load::Load, Middleware, Service};
+pub use crate::limit::{AdjustableLimit, AdjustableLimitPermit, FixedLimit, Permits};
+
+/// The order in which [`ConcurrencyLimit`] acquires its permits.
+///
+/// See the [module](crate::concurrency_limit#acquire-order) for more information.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AcquireOrder {
+    /// Acquire a permit from the limit, then from the inner service.
+    #[default]
+    SemaphoreFirst,
+    /// Acquire a permit from the inner service, then from the limit.
+    InnerFirst,
+}
 
 /// A wrapper for the [`ServiceExt::concurrency_limit`](crate::ServiceExt::concurrency_limit)
-/// combinator.
+/// and [`ServiceExt::concurrency_limit_with`](crate::ServiceExt::concurrency_limit_with)
+/// combinators.
 ///
 /// See the [module](crate::concurrency_limit) for more information.
 #[derive(Debug)]
-pub struct ConcurrencyLimit<S> {
+pub struct ConcurrencyLimit<S, P = FixedLimit> {
     inner: S,
-    semaphore: Semaphore,
+    permits: Arc<P>,
+    order: AcquireOrder,
+    /// The size of a fixed limit, and its constructor, used to create a fresh limit when applied.
+    limit: Option<(usize, NewLimit<P>)>,
+}
+
+/// Constructs a limit with the specified number of permits.
+type NewLimit<P> = fn(usize) -> P;
+
+impl<S, P> Clone for ConcurrencyLimit<S, P>
+where
+    S: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            permits: self.permits.clone(),
+            order: self.order,
+            limit: self.limit,
+        }
+    }
 }
 
 impl<S> ConcurrencyLimit<S> {
     pub(crate) fn new(inner: S, n_permits: usize) -> Self {
+        Self {
+            limit: Some((n_permits, FixedLimit::new)),
+            ..Self::with_permits(inner, FixedLimit::new(n_permits))
+        }
+    }
+
+    /// Returns the number of permits currently available.
+    pub fn available_permits(&self) -> usize {
+        self.permits.available_permits()
+    }
+}
+
+impl<S, P> ConcurrencyLimit<S, P> {
+    pub(crate) fn with_permits(inner: S, permits: P) -> Self {
         Self {
             inner,
-            semaphore: Semaphore::new(n_permits),
+            permits: Arc::new(permits),
+            order: AcquireOrder::default(),
+            limit: None,
         }
     }
+
+    /// Sets the order in which the permits are acquired.
+    ///
+    /// See the [module](crate::concurrency_limit#acquire-order) for more information.
+    pub fn with_order(mut self, order: AcquireOrder) -> Self {
+        self.order = order;
+        self
+    }
 }
 
 /// The [`Service::Permit`] type for [`ConcurrencyLimit`].
-pub struct ConcurrencyLimitPermit<'a, S, Request>
+pub struct ConcurrencyLimitPermit<'a, S, Request, P = FixedLimit>
 where
     S: Service<Request> + 'a,
+    P: Permits + 'a,
 {
     inner: S::Permit<'a>,
-    _semaphore_permit: SemaphorePermit<'a>,
+    _permit: P::Permit<'a>,
 }
 
-impl<'a, S, Request> fmt::Debug for ConcurrencyLimitPermit<'a, S, Request>
+impl<'a, S, Request, P> fmt::Debug for ConcurrencyLimitPermit<'a, S, Request, P>
 where
     S: Service<Request>,
     S::Permit<'a>: fmt::Debug,
+    P: Permits,
+    P::Permit<'a>: fmt::Debug,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("ConcurrencyLimitPermit")
             .field("inner", &self.inner)
-            .field("_semaphore_permit", &self._semaphore_permit)
+            .field("_permit", &self._permit)
             .finish()
     }
 }
 
-impl<Request, S> Service<Request> for ConcurrencyLimit<S>
+impl<Request, S, P> Service<Request> for ConcurrencyLimit<S, P>
 where
     S: Service<Request>,
+    P: Permits,
 {
     type Response = S::Response;
-    type Permit<'a> = ConcurrencyLimitPermit<'a, S, Request>
+    type Permit<'a> = ConcurrencyLimitPermit<'a, S, Request, P>
     where
-        S: 'a;
+        S: 'a,
+        P: 'a;
 
     async fn acquire(&self) -> Self::Permit<'_> {
-        ConcurrencyLimitPermit {
-            _semaphore_permit: self.semaphore.acquire().await.expect("not closed"),
-            inner: self.inner.acquire().await,
+        match self.order {
+            AcquireOrder::SemaphoreFirst => ConcurrencyLimitPermit {
+                _permit: self.permits.acquire().await,
+                inner: self.inner.acquire().await,
+            },
+            AcquireOrder::InnerFirst => ConcurrencyLimitPermit {
+                inner: self.inner.acquire().await,
+                _permit: self.permits.acquire().await,
+            },
         }
     }
 
@@ -95,7 +247,7 @@ where
     }
 }
 
-impl<S> Load for ConcurrencyLimit<S>
+impl<S, P> Load for ConcurrencyLimit<S, P>
 where
     S: Load,
 {
@@ -106,17 +258,103 @@ where
     }
 }
 
-impl<S, T> Middleware<S> for ConcurrencyLimit<T>
+impl<S, P> Describe for ConcurrencyLimit<S, P>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        let node = StackNode::new("ConcurrencyLimit");
+        let node = match self.limit {
+            Some((limit, _)) => node.with_config("limit", limit),
+            None => node.with_config("permits", any::type_name::<P>()),
+        };
+        node.with_config("order", format_args!("{:?}", self.order))
+            .with_child(self.inner.describe())
+    }
+}
+
+impl<S, T, P> Middleware<S> for ConcurrencyLimit<T, P>
 where
     T: Middleware<S>,
 {
-    type Service = ConcurrencyLimit<T::Service>;
+    type Service = ConcurrencyLimit<T::Service, P>;
 
     fn apply(self, svc: S) -> Self::Service {
-        let Self { inner, semaphore } = self;
+        let Self {
+            inner,
+            permits,
+            order,
+            limit,
+        } = self;
+        // A fixed limit is created afresh, while other permit sources are shared.
+        let permits = match limit {
+            Some((n_permits, new)) => Arc::new(new(n_permits)),
+            None => permits,
+        };
         ConcurrencyLimit {
             inner: inner.apply(svc),
-            semaphore,
+            permits,
+            order,
+            limit,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use futures_util::FutureExt;
+
+    use crate::{load::Load, service_fn, Middleware, MiddlewareBuilder, Service, ServiceExt};
+
+    use super::{AcquireOrder, FixedLimit, Permits};
+
+    #[tokio::test]
+    async fn applied_independently() {
+        let middleware = MiddlewareBuilder
+            .concurrency_limit(1)
+            .buffer(1)
+            .pending_requests();
+        let a = middleware
+            .clone()
+            .apply(service_fn(|x: u32| async move { x }));
+        let b = middleware.apply(service_fn(|x: u32| async move { x }));
+
+        // Each service has its own limit, buffer and count.
+        let _permit = a.acquire().await;
+        assert_eq!(a.load(), 1);
+        assert_eq!(b.load(), 0);
+        assert_eq!(b.oneshot(2).now_or_never(), Some(2));
+    }
+
+    #[tokio::test]
+    async fn slow_inner_acquire() {
+        let inner = Arc::new(FixedLimit::new(1));
+        let svc = service_fn(|x: u32| async move { x }).concurrency_limit_with(inner.clone());
+
+        // The inner service has no permits available.
+        let held = Permits::acquire(&inner).await;
+        let limited = svc.clone().concurrency_limit(1);
+        let mut acquire = Box::pin(limited.acquire());
+        assert!((&mut acquire).now_or_never().is_none());
+        assert_eq!(limited.available_permits(), 0);
+        drop(acquire);
+        assert_eq!(limited.available_permits(), 1);
+
+        let limited = svc
+            .concurrency_limit(1)
+            .with_order(AcquireOrder::InnerFirst);
+        let mut acquire = Box::pin(limited.acquire());
+        assert!((&mut acquire).now_or_never().is_none());
+        assert_eq!(limited.available_permits(), 1);
+
+        drop(held);
+        let permit = acquire.await;
+        assert_eq!(limited.available_permits(), 0);
+        assert_eq!(inner.available_permits(), 0);
+        drop(permit);
+        assert_eq!(limited.available_permits(), 1);
+        assert_eq!(inner.available_permits(), 1);
+    }
+}
@@ -0,0 +1,196 @@
+//! A [`StackConfig`] describes a stack of middleware declaratively. It implements
+//! [`Deserialize`], so can be read from a configuration file, such as TOML or YAML, allowing limits
+//! to be tuned without recompiling. [`StackConfig::middleware`] then constructs the
+//! [`Middleware`](crate::Middleware).
+//!
+//! Each layer is applied only if it's configured. From outermost to innermost:
+//!
+//! - `retries`, the maximum number of retries, applies [`ServiceExt::retry`] with a
+//!   [`CloneRequest`] policy. Requests are retried while the closure passed to
+//!   [`StackConfig::middleware`] returns `true` for the response.
+//! - `buffer`, the capacity, applies [`ServiceExt::buffer`].
+//! - `rate_limit`, a table of `permits` per `interval_ms` milliseconds, applies
+//!   [`ServiceExt::rate_limit`].
+//! - `concurrency_limit`, the number of permits, applies [`ServiceExt::concurrency_limit`].
+//!
+//! Unknown fields are rejected, so that a misspelled field is reported rather than ignored. The
+//! resulting stack implements [`Describe`](crate::describe::Describe), which may be used to log the
+//! layers which were applied.
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::{config::StackConfig, describe::Describe, *};
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let config: StackConfig = toml::from_str(
+//!     r#"
+//!     concurrency_limit = 8
+//!     retries = 2
+//!
+//!     [rate_limit]
+//!     permits = 100
+//!     interval_ms = 1000
+//!     "#,
+//! )
+//! .unwrap();
+//! let svc = config
+//!     .middleware(|response: &Result<u32, u32>| response.is_err())
+//!     .apply(service_fn(|x: u32| async move { Ok::<_, u32>(x + 1) }));
+//! assert_eq!(svc.oneshot(3).await, Ok(4));
+//! assert_eq!(
+//!     svc.describe().to_string(),
+//!     "\
+//! Retry
+//! └── RateLimit { permits: 100, interval: 1s }
+//!     └── ConcurrencyLimit { limit: 8, order: SemaphoreFirst }
+//!         └── ServiceFn
+//! "
+//! );
+//! # }
+//! ```
+
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::{
+    buffer::Buffer,
+    concurrency_limit::ConcurrencyLimit,
+    either::Either,
+    rate_limit::RateLimit,
+    retry::{CloneRequest, Retry},
+    MiddlewareBuilder, ServiceExt,
+};
+
+/// The configuration of a [`ServiceExt::rate_limit`] layer.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+struct RateLimitConfig {
+    permits: usize,
+    interval_ms: u64,
+}
+
+/// A declarative description of a stack of middleware.
+///
+/// See the [module](crate::config) for more information.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct StackConfig {
+    retries: Option<usize>,
+    buffer: Option<usize>,
+    rate_limit: Option<RateLimitConfig>,
+    concurrency_limit: Option<usize>,
+}
+
+type ConcurrencyLimited = Either<ConcurrencyLimit<MiddlewareBuilder>, MiddlewareBuilder>;
+type RateLimited = Either<RateLimit<ConcurrencyLimited>, ConcurrencyLimited>;
+type Buffered = Either<Buffer<RateLimited>, RateLimited>;
+
+/// The [`Middleware`](crate::Middleware) constructed by [`StackConfig::middleware`].
+pub type StackMiddleware<F> = Either<Retry<Buffered, CloneRequest<F>>, Buffered>;
+
+impl StackConfig {
+    /// Constructs a [`StackConfig`] without any layers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of retries.
+    pub fn retries(mut self, max_retries: usize) -> Self {
+        self.retries = Some(max_retries);
+        self
+    }
+
+    /// Sets the capacity of the buffer.
+    pub fn buffer(mut self, capacity: usize) -> Self {
+        self.buffer = Some(capacity);
+        self
+    }
+
+    /// Sets the number of permits per interval of the rate limit.
+    pub fn rate_limit(mut self, interval: Duration, permits: usize) -> Self {
+        self.rate_limit = Some(RateLimitConfig {
+            permits,
+            interval_ms: interval.as_millis().try_into().unwrap_or(u64::MAX),
+        });
+        self
+    }
+
+    /// Sets the number of permits of the concurrency limit.
+    pub fn concurrency_limit(mut self, n_permits: usize) -> Self {
+        self.concurrency_limit = Some(n_permits);
+        self
+    }
+
+    /// Constructs the [`Middleware`](crate::Middleware), retrying requests while `retry_if` returns
+    /// `true` for the response.
+    pub fn middleware<F>(&self, retry_if: F) -> StackMiddleware<F> {
+        let stack = match self.concurrency_limit {
+            Some(n_permits) => Either::Left(MiddlewareBuilder.concurrency_limit(n_permits)),
+            None => Either::Right(MiddlewareBuilder),
+        };
+        let stack = match &self.rate_limit {
+            Some(RateLimitConfig {
+                permits,
+                interval_ms,
+            }) => Either::Left(stack.rate_limit(Duration::from_millis(*interval_ms), *permits)),
+            None => Either::Right(stack),
+        };
+        let stack = match self.buffer {
+            Some(capacity) => Either::Left(stack.buffer(capacity)),
+            None => Either::Right(stack),
+        };
+        match self.retries {
+            Some(max_retries) => {
+                Either::Left(stack.retry(CloneRequest::new(max_retries, retry_if)))
+            }
+            None => Either::Right(stack),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{describe::Describe, service_fn, Middleware, ServiceExt};
+
+    use super::StackConfig;
+
+    #[test]
+    fn deserialize() {
+        let config: StackConfig = toml::from_str(
+            r#"
+            retries = 3
+            buffer = 16
+            concurrency_limit = 4
+
+            [rate_limit]
+            permits = 10
+            interval_ms = 500
+            "#,
+        )
+        .unwrap();
+        let expected = StackConfig::new()
+            .retries(3)
+            .buffer(16)
+            .rate_limit(Duration::from_millis(500), 10)
+            .concurrency_limit(4);
+        assert_eq!(config, expected);
+
+        let error = toml::from_str::<StackConfig>("concurrency_limt = 4").unwrap_err();
+        assert!(error.to_string().contains("unknown field"));
+    }
+
+    #[tokio::test]
+    async fn empty() {
+        let config: StackConfig = toml::from_str("").unwrap();
+        let svc = config
+            .middleware(|_: &u32| false)
+            .apply(service_fn(|x: u32| async move { x + 1 }));
+        assert_eq!(svc.oneshot(1).await, 2);
+        assert_eq!(svc.describe().name(), "ServiceFn");
+    }
+}
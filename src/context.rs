@@ -0,0 +1,358 @@
+//! Request context carries cross-cutting metadata, such as trace IDs, authentication claims or
+//! deadlines, alongside a request without it being part of each request type.
+//!
+//! A [`Cx`] pairs a request with [`Extensions`], a map holding at most one value of each type.
+//! Services and middleware accepting a [`Cx`] may read and write its [`Extensions`], for example
+//! using [`ServiceExt::map_request`](crate::ServiceExt::map_request) or
+//! [`ServiceExt::filter`](crate::ServiceExt::filter).
+//!
+//! The [`ServiceExt::with_context`](crate::ServiceExt::with_context) combinator returns
+//! [`WithContext`], which accepts a plain request, wraps it in a [`Cx`] and populates its
+//! [`Extensions`] using a closure. The
+//! [`ServiceExt::without_context`](crate::ServiceExt::without_context) combinator returns
+//! [`WithoutContext`], which accepts a [`Cx`] and passes only the request to the inner service,
+//! discarding the [`Extensions`].
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::{
+//!     context::{Cx, Extensions},
+//!     *,
+//! };
+//!
+//! #[derive(Clone, Copy, Debug, PartialEq)]
+//! struct TraceId(u64);
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc = service_fn(|x: u32| async move { 2 * x })
+//!     .without_context()
+//!     .map_request(|cx: Cx<u32>| {
+//!         assert_eq!(cx.extensions().get::<TraceId>(), Some(&TraceId(7)));
+//!         cx
+//!     })
+//!     .with_context(|_: &u32, extensions: &mut Extensions| {
+//!         extensions.insert(TraceId(7));
+//!     });
+//! let response = svc.oneshot(4).await;
+//! assert_eq!(response, 8);
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`WithContext`] and [`WithoutContext`] defers to the inner service.
+
+use alloc::{boxed::Box, collections::BTreeMap};
+use core::{
+    any::{self, Any, TypeId},
+    fmt,
+};
+
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Middleware, Service,
+};
+
+/// A map of values keyed by their type.
+///
+/// See the [module](crate::context) for more information.
+#[derive(Default)]
+pub struct Extensions {
+    map: BTreeMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl Extensions {
+    /// Constructs an empty [`Extensions`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts a value, returning the previous value of the same type.
+    pub fn insert<T>(&mut self, value: T) -> Option<T>
+    where
+        T: Send + Sync + 'static,
+    {
+        self.map
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|previous| previous.downcast().ok())
+            .map(|previous| *previous)
+    }
+
+    /// Returns a reference to the value of a type.
+    pub fn get<T>(&self) -> Option<&T>
+    where
+        T: Send + Sync + 'static,
+    {
+        self.map.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+
+    /// Returns a mutable reference to the value of a type.
+    pub fn get_mut<T>(&mut self) -> Option<&mut T>
+    where
+        T: Send + Sync + 'static,
+    {
+        self.map.get_mut(&TypeId::of::<T>())?.downcast_mut()
+    }
+
+    /// Removes and returns the value of a type.
+    pub fn remove<T>(&mut self) -> Option<T>
+    where
+        T: Send + Sync + 'static,
+    {
+        self.map
+            .remove(&TypeId::of::<T>())?
+            .downcast()
+            .ok()
+            .map(|value| *value)
+    }
+
+    /// Returns the number of values.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if there are no values.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.map.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A request paired with [`Extensions`].
+///
+/// See the [module](crate::context) for more information.
+#[derive(Debug)]
+pub struct Cx<Request> {
+    extensions: Extensions,
+    request: Request,
+}
+
+impl<Request> Cx<Request> {
+    /// Constructs a [`Cx`] with empty [`Extensions`].
+    pub fn new(request: Request) -> Self {
+        Self::from_parts(Extensions::new(), request)
+    }
+
+    /// Constructs a [`Cx`] from existing [`Extensions`] and a request.
+    pub fn from_parts(extensions: Extensions, request: Request) -> Self {
+        Self {
+            extensions,
+            request,
+        }
+    }
+
+    /// Returns a reference to the [`Extensions`].
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    /// Returns a mutable reference to the [`Extensions`].
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
+    /// Returns a reference to the request.
+    pub fn request(&self) -> &Request {
+        &self.request
+    }
+
+    /// Returns a mutable reference to the request.
+    pub fn request_mut(&mut self) -> &mut Request {
+        &mut self.request
+    }
+
+    /// Replaces the request using a closure, retaining the [`Extensions`].
+    pub fn map<F, T>(self, closure: F) -> Cx<T>
+    where
+        F: FnOnce(Request) -> T,
+    {
+        Cx::from_parts(self.extensions, closure(self.request))
+    }
+
+    /// Returns the [`Extensions`] and the request.
+    pub fn into_parts(self) -> (Extensions, Request) {
+        (self.extensions, self.request)
+    }
+
+    /// Returns the request, discarding the [`Extensions`].
+    pub fn into_request(self) -> Request {
+        self.request
+    }
+}
+
+/// A wrapper [`Service`] for the [`ServiceExt::with_context`](crate::ServiceExt::with_context)
+/// combinator.
+///
+/// See the [module](crate::context) for more information.
+#[derive(Clone, Debug)]
+pub struct WithContext<S, F> {
+    inner: S,
+    closure: F,
+}
+
+impl<S, F> WithContext<S, F> {
+    pub(crate) fn new(inner: S, closure: F) -> Self {
+        Self { inner, closure }
+    }
+}
+
+/// The [`Service::Permit`] type for [`WithContext`].
+pub struct WithContextPermit<'a, S, F, Request>
+where
+    S: Service<Cx<Request>> + 'a,
+{
+    inner: S::Permit<'a>,
+    closure: &'a F,
+}
+
+impl<'a, S, F, Request> fmt::Debug for WithContextPermit<'a, S, F, Request>
+where
+    S: Service<Cx<Request>>,
+    S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WithContextPermit")
+            .field("inner", &self.inner)
+            .field("closure", &format_args!("{}", any::type_name::<F>()))
+            .finish()
+    }
+}
+
+impl<Request, S, F> Service<Request> for WithContext<S, F>
+where
+    S: Service<Cx<Request>>,
+    F: Fn(&Request, &mut Extensions),
+{
+    type Response = S::Response;
+    type Permit<'a> = WithContextPermit<'a, S, F, Request>
+    where
+        S: 'a,
+        F: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        WithContextPermit {
+            inner: self.inner.acquire().await,
+            closure: &self.closure,
+        }
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        let mut extensions = Extensions::new();
+        (permit.closure)(&request, &mut extensions);
+        S::call(permit.inner, Cx::from_parts(extensions, request)).await
+    }
+}
+
+impl<S, F> Load for WithContext<S, F>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S, F> Describe for WithContext<S, F>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("WithContext").with_child(self.inner.describe())
+    }
+}
+
+impl<S, T, F> Middleware<S> for WithContext<T, F>
+where
+    T: Middleware<S>,
+{
+    type Service = WithContext<T::Service, F>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self { inner, closure } = self;
+        WithContext {
+            inner: inner.apply(svc),
+            closure,
+        }
+    }
+}
+
+/// A wrapper [`Service`] for the
+/// [`ServiceExt::without_context`](crate::ServiceExt::without_context) combinator.
+///
+/// See the [module](crate::context) for more information.
+#[derive(Clone, Debug)]
+pub struct WithoutContext<S> {
+    inner: S,
+}
+
+impl<S> WithoutContext<S> {
+    pub(crate) fn new(inner: S) -> Self {
+        Self { inner }
+    }
+}
+
+impl<Request, S> Service<Cx<Request>> for WithoutContext<S>
+where
+    S: Service<Request>,
+{
+    type Response = S::Response;
+    type Permit<'a> = S::Permit<'a>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        self.inner.acquire().await
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Cx<Request>) -> Self::Response
+    where
+        Self: 'a,
+    {
+        S::call(permit, request.into_request()).await
+    }
+}
+
+impl<S> Load for WithoutContext<S>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S> Describe for WithoutContext<S>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("WithoutContext").with_child(self.inner.describe())
+    }
+}
+
+impl<S, T> Middleware<S> for WithoutContext<T>
+where
+    T: Middleware<S>,
+{
+    type Service = WithoutContext<T::Service>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        WithoutContext {
+            inner: self.inner.apply(svc),
+        }
+    }
+}
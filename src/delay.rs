@@ -0,0 +1,261 @@
+//! The [`ServiceExt::delay`](crate::ServiceExt::delay) combinator returns [`Delay`], which sleeps
+//! for a fixed [`Duration`] before each [`Service::call`] on the inner service.
+//!
+//! The [`ServiceExt::delay_until`](crate::ServiceExt::delay_until) combinator returns
+//! [`DelayUntil`], a variant which uses a closure to choose, from the request, the [`Instant`] at
+//! which to call the inner service.
+//!
+//! In both cases the inner [permit](Service::Permit) is held while sleeping, so a delayed call
+//! continues to count against limits such as
+//! [`ServiceExt::concurrency_limit`](crate::ServiceExt::concurrency_limit). This may be used to pace
+//! calls to a third-party service or to simulate latency.
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::*;
+//! use tokio::time::Instant;
+//! # use std::time::Duration;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc = service_fn(|x: u32| async move { x + 1 }).delay(Duration::from_millis(10));
+//! let start = Instant::now();
+//! assert_eq!(svc.oneshot(3).await, 4);
+//! assert!(start.elapsed() >= Duration::from_millis(10));
+//!
+//! let svc = service_fn(|x: u64| async move { x + 1 })
+//!     .delay_until(move |x: &u64| start + Duration::from_millis(*x));
+//! assert_eq!(svc.oneshot(20).await, 21);
+//! assert!(start.elapsed() >= Duration::from_millis(20));
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`Delay`] and [`DelayUntil`] defers to the inner service.
+
+use std::{any, fmt, time::Duration};
+
+use tokio::time::Instant;
+
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    rt, Middleware, Service,
+};
+
+/// A wrapper [`Service`] for the [`ServiceExt::delay`](crate::ServiceExt::delay) combinator.
+///
+/// See the [module](crate::delay) for more information.
+#[derive(Clone, Debug)]
+pub struct Delay<S> {
+    inner: S,
+    duration: Duration,
+}
+
+impl<S> Delay<S> {
+    pub(crate) fn new(inner: S, duration: Duration) -> Self {
+        Self { inner, duration }
+    }
+}
+
+/// The [`Service::Permit`] type for [`Delay`].
+pub struct DelayPermit<'a, S, Request>
+where
+    S: Service<Request> + 'a,
+{
+    inner: S::Permit<'a>,
+    duration: Duration,
+}
+
+impl<'a, S, Request> fmt::Debug for DelayPermit<'a, S, Request>
+where
+    S: Service<Request>,
+    S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DelayPermit")
+            .field("inner", &self.inner)
+            .field("duration", &self.duration)
+            .finish()
+    }
+}
+
+impl<Request, S> Service<Request> for Delay<S>
+where
+    S: Service<Request>,
+{
+    type Response = S::Response;
+    type Permit<'a> = DelayPermit<'a, S, Request>
+    where
+        S: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        DelayPermit {
+            inner: self.inner.acquire().await,
+            duration: self.duration,
+        }
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        rt::sleep(permit.duration).await;
+        S::call(permit.inner, request).await
+    }
+}
+
+impl<S> Load for Delay<S>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S> Describe for Delay<S>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("Delay")
+            .with_config("duration", format_args!("{:?}", self.duration))
+            .with_child(self.inner.describe())
+    }
+}
+
+impl<S, T> Middleware<S> for Delay<T>
+where
+    T: Middleware<S>,
+{
+    type Service = Delay<T::Service>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self { inner, duration } = self;
+        Delay {
+            inner: inner.apply(svc),
+            duration,
+        }
+    }
+}
+
+/// A wrapper [`Service`] for the [`ServiceExt::delay_until`](crate::ServiceExt::delay_until)
+/// combinator.
+///
+/// See the [module](crate::delay) for more information.
+#[derive(Clone, Debug)]
+pub struct DelayUntil<S, F> {
+    inner: S,
+    closure: F,
+}
+
+impl<S, F> DelayUntil<S, F> {
+    pub(crate) fn new(inner: S, closure: F) -> Self {
+        Self { inner, closure }
+    }
+}
+
+/// The [`Service::Permit`] type for [`DelayUntil`].
+pub struct DelayUntilPermit<'a, S, F, Request>
+where
+    S: Service<Request> + 'a,
+{
+    inner: S::Permit<'a>,
+    closure: &'a F,
+}
+
+impl<'a, S, F, Request> fmt::Debug for DelayUntilPermit<'a, S, F, Request>
+where
+    S: Service<Request>,
+    S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DelayUntilPermit")
+            .field("inner", &self.inner)
+            .field("closure", &format_args!("{}", any::type_name::<F>()))
+            .finish()
+    }
+}
+
+impl<Request, S, F> Service<Request> for DelayUntil<S, F>
+where
+    S: Service<Request>,
+    F: Fn(&Request) -> Instant,
+{
+    type Response = S::Response;
+    type Permit<'a> = DelayUntilPermit<'a, S, F, Request>
+    where
+        S: 'a,
+        F: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        DelayUntilPermit {
+            inner: self.inner.acquire().await,
+            closure: &self.closure,
+        }
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        rt::sleep_until((permit.closure)(&request).into_std()).await;
+        S::call(permit.inner, request).await
+    }
+}
+
+impl<S, F> Load for DelayUntil<S, F>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S, F> Describe for DelayUntil<S, F>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("DelayUntil").with_child(self.inner.describe())
+    }
+}
+
+impl<S, T, F> Middleware<S> for DelayUntil<T, F>
+where
+    T: Middleware<S>,
+{
+    type Service = DelayUntil<T::Service, F>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self { inner, closure } = self;
+        DelayUntil {
+            inner: inner.apply(svc),
+            closure,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::Instant;
+
+    use crate::{service_fn, ServiceExt};
+
+    #[tokio::test]
+    async fn holds_permit() {
+        let svc = service_fn(|x: u32| async move { x })
+            .concurrency_limit(1)
+            .delay(Duration::from_millis(20));
+
+        // The second call waits for the first's delay to elapse.
+        let start = Instant::now();
+        let (a, b) = tokio::join!(svc.oneshot(1), svc.oneshot(2));
+        assert_eq!((a, b), (1, 2));
+        assert!(start.elapsed() >= Duration::from_millis(40));
+    }
+}
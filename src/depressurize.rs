@@ -11,6 +11,7 @@
 //! # use tokio::time::sleep;
 //! # use std::time::Duration;
 //!
+//! # #[cfg(feature = "tokio")]
 //! # #[tokio::main]
 //! # async fn main() {
 //! let svc = service_fn(|x: usize| async move {
@@ -21,13 +22,19 @@
 //! .depressurize();
 //! let permit = svc.acquire().now_or_never().unwrap();
 //! # }
+//! # #[cfg(not(feature = "tokio"))]
+//! # fn main() {}
 //! ```
 //!
 //! # Load
 //!
 //! The [`Load::load`] on [`Depressurize`] defers to the inner service.
 
-use crate::{load::Load, Middleware, Service, ServiceExt};
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Middleware, Service, ServiceExt,
+};
 
 /// A wrapper for the [`ServiceExt::depressurize`] combinator.
 ///
@@ -72,6 +79,15 @@ where
     }
 }
 
+impl<S> Describe for Depressurize<S>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("Depressurize").with_child(self.inner.describe())
+    }
+}
+
 impl<S, T> Middleware<S> for Depressurize<T>
 where
     T: Middleware<S>,
@@ -0,0 +1,178 @@
+//! The [`Describe`] trait reports the structure of a composed [`Service`](crate::Service) as a tree
+//! of [`StackNode`]s. Each node names a layer, lists its notable configuration, such as limits and
+//! durations, and holds the nodes of the services it wraps.
+//!
+//! [`Describe`] is implemented by the wrappers and constructors of this crate. Closures and runtime
+//! state, such as the number of inflight calls, are not reported. Services defined elsewhere can
+//! implement [`Describe`] to appear within the tree, and a [`StackNode`] has a [`Display`]
+//! implementation to dump it, for example to a log or a debug endpoint.
+//!
+//! The children of a [balancer](crate::balance) are its current services. While its worker is
+//! applying changes, or before any service has been inserted, the balancer reports no children.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::time::Duration;
+//!
+//! use burger::{describe::Describe, *};
+//!
+//! # #[cfg(feature = "tokio")]
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc = service_fn(|x: u32| async move { x + 1 })
+//!     .concurrency_limit(2)
+//!     .buffer(4)
+//!     .delay(Duration::from_millis(5));
+//! let tree = svc.describe();
+//! assert_eq!(tree.name(), "Delay");
+//! assert_eq!(tree.children()[0].config(), [("capacity", "4".to_string())]);
+//! assert_eq!(
+//!     tree.to_string(),
+//!     "\
+//! Delay { duration: 5ms }
+//! └── Buffer { capacity: 4 }
+//!     └── ConcurrencyLimit { limit: 2, order: SemaphoreFirst }
+//!         └── ServiceFn
+//! "
+//! );
+//! # }
+//! # #[cfg(not(feature = "tokio"))]
+//! # fn main() {}
+//! ```
+//!
+//! [`Display`]: fmt::Display
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt;
+
+/// A layer within a composed service, returned by [`Describe::describe`].
+///
+/// See the [module](crate::describe) for more information.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StackNode {
+    name: &'static str,
+    config: Vec<(&'static str, String)>,
+    children: Vec<StackNode>,
+}
+
+impl StackNode {
+    /// Constructs a [`StackNode`] without configuration or children.
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            config: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    /// Adds a configuration value.
+    pub fn with_config(mut self, key: &'static str, value: impl fmt::Display) -> Self {
+        self.config.push((key, value.to_string()));
+        self
+    }
+
+    /// Adds a child, a service wrapped by this layer.
+    pub fn with_child(mut self, child: StackNode) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    /// Adds several children, services wrapped by this layer.
+    pub fn with_children(mut self, children: impl IntoIterator<Item = StackNode>) -> Self {
+        self.children.extend(children);
+        self
+    }
+
+    /// Returns the name of the layer.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the configuration of the layer, as key-value pairs.
+    pub fn config(&self) -> &[(&'static str, String)] {
+        &self.config
+    }
+
+    /// Returns the nodes of the services wrapped by this layer.
+    pub fn children(&self) -> &[StackNode] {
+        &self.children
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>, prefix: &str) -> fmt::Result {
+        f.write_str(self.name)?;
+        if !self.config.is_empty() {
+            f.write_str(" { ")?;
+            for (index, (key, value)) in self.config.iter().enumerate() {
+                if index != 0 {
+                    f.write_str(", ")?;
+                }
+                write!(f, "{key}: {value}")?;
+            }
+            f.write_str(" }")?;
+        }
+        writeln!(f)?;
+        for (index, child) in self.children.iter().enumerate() {
+            let (branch, indent) = if index + 1 == self.children.len() {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            write!(f, "{prefix}{branch}")?;
+            child.write(f, &format!("{prefix}{indent}"))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for StackNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, "")
+    }
+}
+
+/// Describes the structure of a service.
+///
+/// See the [module](crate::describe) for more information.
+pub trait Describe {
+    /// Returns the tree of layers making up the service.
+    fn describe(&self) -> StackNode;
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use crate::{fanout, service_fn, ServiceExt};
+
+    use super::{Describe, StackNode};
+
+    #[test]
+    fn display_tree() {
+        let svc = fanout([
+            service_fn(|x: u32| async move { x }).left(),
+            service_fn(|x: u32| async move { x })
+                .load_shed_after(3)
+                .right(),
+        ]);
+        assert_eq!(
+            svc.describe().to_string(),
+            "\
+Fanout
+├── ServiceFn
+└── LoadShedAfter { threshold: 3 }
+    └── ServiceFn
+"
+        );
+
+        let node = StackNode::new("Custom")
+            .with_config("a", 1)
+            .with_children([StackNode::new("Left"), StackNode::new("Right")]);
+        assert_eq!(node.config(), [("a", "1".to_string())]);
+        assert_eq!(node.children().len(), 2);
+    }
+}
@@ -0,0 +1,440 @@
+//! Service discovery produces a [`Stream`] of [`Change`]s, which can be used to construct the
+//! load balancers found in the [`balance`](crate::balance) module.
+//!
+//! - [`fixed`] constructs a [`Stream`] from a static collection of services.
+//! - [`dns`] constructs a [`Stream`] by periodically resolving a DNS name. This requires the `dns`
+//!   feature.
+//!
+//! Any such [`Stream`] implements [`Discover`], which provides adapters to build pipelines:
+//!
+//! - [`Discover::map_service`] transforms each inserted service, for example to apply
+//!   [`Middleware`](crate::Middleware), returning [`MapService`].
+//! - [`Discover::filter_keys`] drops changes whose key fails a predicate, returning [`FilterKeys`].
+//! - [`Discover::merge`] interleaves the changes of two sources, returning [`Select`].
+//! - [`Discover::debounce`] delays each change until its key has been stable for a period,
+//!   returning [`Debounce`].
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::*;
+//! # use std::future::ready;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let double: fn(_) -> _ = |x: u32| ready(2 * x);
+//! let changes = discover::fixed([
+//!     ("a", service_fn(double).pending_requests()),
+//!     ("b", service_fn(double).pending_requests()),
+//! ]);
+//! let (svc, worker) = balance::p2c(changes);
+//! tokio::spawn(worker);
+//! let response = svc.oneshot(5u32).await;
+//! # }
+//! ```
+//!
+//! The adapters compose as follows:
+//!
+//! ```rust
+//! use burger::{discover::Discover, *};
+//! # use std::{future::ready, time::Duration};
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let double: fn(_) -> _ = |x: u32| ready(2 * x);
+//! let changes = discover::fixed([("a", service_fn(double)), ("b", service_fn(double))])
+//!     .merge(discover::fixed([("c", service_fn(double))]))
+//!     .filter_keys(|key| *key != "b")
+//!     .map_service(|svc| svc.pending_requests())
+//!     .debounce(Duration::from_millis(10));
+//! let (svc, worker) = balance::p2c(changes);
+//! tokio::spawn(worker);
+//! let response = svc.oneshot(5u32).await;
+//! # }
+//! ```
+//!
+//! # Debouncing
+//!
+//! [`Debounce`] holds each change until no other change for its key has arrived within the
+//! window, at which point only the latest is emitted. A key which is inserted and then removed
+//! within the window is never emitted at all. When the source terminates, held changes are emitted
+//! immediately.
+
+use std::{
+    collections::HashSet,
+    future::Future,
+    hash::Hash,
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+pub use futures_util::stream::Select;
+use futures_util::{stream, Stream, StreamExt};
+use indexmap::IndexMap;
+use pin_project_lite::pin_project;
+
+use crate::{
+    balance::Change,
+    rt::{self, Sleep},
+};
+
+/// A source of [`Change`]s to a pool of services.
+///
+/// This is implemented for every [`Stream`] of [`Change`]s and provides adapters for them.
+///
+/// See the [module](crate::discover) for more information.
+pub trait Discover<Key, S>: Stream<Item = Change<Key, S>> {
+    /// Transforms each inserted service using a closure.
+    fn map_service<F, T>(self, closure: F) -> MapService<Self, F>
+    where
+        Self: Sized,
+        F: FnMut(S) -> T,
+    {
+        MapService {
+            changes: self,
+            closure,
+        }
+    }
+
+    /// Drops each change whose key doesn't satisfy a predicate.
+    fn filter_keys<F>(self, predicate: F) -> FilterKeys<Self, F>
+    where
+        Self: Sized,
+        F: FnMut(&Key) -> bool,
+    {
+        FilterKeys {
+            changes: self,
+            predicate,
+        }
+    }
+
+    /// Interleaves the changes of two sources, polling each in turn.
+    ///
+    /// Keys are not deduplicated between sources, a key inserted by both is replaced by the latest.
+    fn merge<D>(self, other: D) -> Select<Self, D>
+    where
+        Self: Sized,
+        D: Discover<Key, S>,
+    {
+        stream::select(self, other)
+    }
+
+    /// Delays each change until its key has been stable for the `window`, emitting only the latest.
+    ///
+    /// See the [module](crate::discover#debouncing) for more information.
+    fn debounce(self, window: Duration) -> Debounce<Self, Key, S>
+    where
+        Self: Sized,
+        Key: Hash + Eq + Clone,
+    {
+        Debounce {
+            changes: self,
+            window,
+            held: IndexMap::new(),
+            inserted: HashSet::new(),
+            sleep: None,
+            terminated: false,
+        }
+    }
+}
+
+impl<St, Key, S> Discover<Key, S> for St where St: Stream<Item = Change<Key, S>> {}
+
+impl<K, V> Change<K, V> {
+    fn key(&self) -> &K {
+        match self {
+            Change::Insert(key, _) | Change::Remove(key) => key,
+        }
+    }
+}
+
+pin_project! {
+    /// The [`Stream`] returned by [`Discover::map_service`].
+    ///
+    /// See the [module](crate::discover) for more information.
+    #[derive(Debug)]
+    pub struct MapService<St, F> {
+        #[pin]
+        changes: St,
+        closure: F,
+    }
+}
+
+impl<St, F, Key, S, T> Stream for MapService<St, F>
+where
+    St: Stream<Item = Change<Key, S>>,
+    F: FnMut(S) -> T,
+{
+    type Item = Change<Key, T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        this.changes.poll_next(cx).map(|change| {
+            change.map(|change| match change {
+                Change::Insert(key, service) => Change::Insert(key, (this.closure)(service)),
+                Change::Remove(key) => Change::Remove(key),
+            })
+        })
+    }
+}
+
+pin_project! {
+    /// The [`Stream`] returned by [`Discover::filter_keys`].
+    ///
+    /// See the [module](crate::discover) for more information.
+    #[derive(Debug)]
+    pub struct FilterKeys<St, F> {
+        #[pin]
+        changes: St,
+        predicate: F,
+    }
+}
+
+impl<St, F, Key, S> Stream for FilterKeys<St, F>
+where
+    St: Stream<Item = Change<Key, S>>,
+    F: FnMut(&Key) -> bool,
+{
+    type Item = Change<Key, S>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        loop {
+            match this.changes.as_mut().poll_next(cx) {
+                Poll::Ready(Some(change)) if !(this.predicate)(change.key()) => continue,
+                poll => return poll,
+            }
+        }
+    }
+}
+
+pin_project! {
+    /// The [`Stream`] returned by [`Discover::debounce`].
+    ///
+    /// See the [module](crate::discover#debouncing) for more information.
+    #[derive(Debug)]
+    pub struct Debounce<St, Key, S> {
+        #[pin]
+        changes: St,
+        window: Duration,
+        // Changes awaiting their deadline, ordered by deadline.
+        held: IndexMap<Key, (Change<Key, S>, Instant)>,
+        // Keys whose latest emitted change was an insert.
+        inserted: HashSet<Key>,
+        sleep: Option<Sleep>,
+        terminated: bool,
+    }
+}
+
+impl<St, Key, S> Stream for Debounce<St, Key, S>
+where
+    St: Stream<Item = Change<Key, S>>,
+    Key: Hash + Eq + Clone,
+{
+    type Item = Change<Key, S>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        loop {
+            if !*this.terminated {
+                match this.changes.as_mut().poll_next(cx) {
+                    Poll::Ready(Some(change)) => {
+                        let key = change.key().clone();
+                        // Move to the back, as its deadline is now the latest.
+                        this.held.shift_remove(&key);
+                        this.held
+                            .insert(key, (change, Instant::now() + *this.window));
+                        continue;
+                    }
+                    Poll::Ready(None) => *this.terminated = true,
+                    Poll::Pending => {}
+                }
+            }
+
+            let Some((_, (_, deadline))) = this.held.first() else {
+                return if *this.terminated {
+                    Poll::Ready(None)
+                } else {
+                    Poll::Pending
+                };
+            };
+            if !*this.terminated {
+                let sleep = this.sleep.get_or_insert_with(|| rt::sleep_until(*deadline));
+                sleep.reset(*deadline);
+                if Pin::new(sleep).poll(cx).is_pending() {
+                    return Poll::Pending;
+                }
+            }
+
+            let (_, (change, _)) = this.held.shift_remove_index(0).expect("checked above");
+            match change {
+                Change::Insert(key, service) => {
+                    this.inserted.insert(key.clone());
+                    return Poll::Ready(Some(Change::Insert(key, service)));
+                }
+                // Only emit removals of keys which were emitted as inserted.
+                Change::Remove(key) if this.inserted.remove(&key) => {
+                    return Poll::Ready(Some(Change::Remove(key)));
+                }
+                Change::Remove(_) => {}
+            }
+        }
+    }
+}
+
+/// Constructs a [`Stream`] inserting each of a static collection of services.
+///
+/// Note that the [`Stream`] then remains pending, rather than terminating, so that balancer
+/// workers continue to run.
+///
+/// See the [module](crate::discover) for more information.
+pub fn fixed<Key, S>(
+    services: impl IntoIterator<Item = (Key, S)>,
+) -> impl Stream<Item = Change<Key, S>> {
+    let inserts = services
+        .into_iter()
+        .map(|(key, service)| Change::Insert(key, service));
+    stream::iter(inserts).chain(stream::pending())
+}
+
+#[cfg(feature = "dns")]
+pub use dns::dns;
+
+#[cfg(feature = "dns")]
+mod dns {
+    use std::{
+        collections::{HashSet, VecDeque},
+        io,
+        net::SocketAddr,
+        time::Duration,
+    };
+
+    use futures_util::{stream, Stream};
+    use tokio::net::lookup_host;
+
+    use crate::{balance::Change, rt};
+
+    struct State<F> {
+        host: String,
+        interval: Duration,
+        make_service: F,
+        current: HashSet<SocketAddr>,
+        pending: VecDeque<SocketAddr>,
+        removed: VecDeque<SocketAddr>,
+        first: bool,
+    }
+
+    async fn resolve(host: &str) -> io::Result<HashSet<SocketAddr>> {
+        Ok(lookup_host(host).await?.collect())
+    }
+
+    /// Constructs a [`Stream`] of [`Change`]s by resolving a `host:port` name every `interval`.
+    ///
+    /// Each newly resolved address is inserted, using `make_service` to construct the service,
+    /// and each address which is no longer resolved is removed. Resolution errors are logged and
+    /// the current services retained.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use burger::*;
+    /// # use std::{future::ready, time::Duration};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let changes = discover::dns("example.com:80", Duration::from_secs(30), |addr| {
+    ///     service_fn(move |x: u32| ready((addr, x))).pending_requests()
+    /// });
+    /// let (svc, worker) = balance::p2c(changes);
+    /// tokio::spawn(worker);
+    /// # }
+    /// ```
+    ///
+    /// See the [module](crate::discover) for more information.
+    pub fn dns<F, S>(
+        host: impl Into<String>,
+        interval: Duration,
+        make_service: F,
+    ) -> impl Stream<Item = Change<SocketAddr, S>>
+    where
+        F: FnMut(SocketAddr) -> S,
+    {
+        let state = State {
+            host: host.into(),
+            interval,
+            make_service,
+            current: HashSet::new(),
+            pending: VecDeque::new(),
+            removed: VecDeque::new(),
+            first: true,
+        };
+        stream::unfold(state, |mut state| async move {
+            loop {
+                if let Some(addr) = state.removed.pop_front() {
+                    return Some((Change::Remove(addr), state));
+                }
+                if let Some(addr) = state.pending.pop_front() {
+                    let service = (state.make_service)(addr);
+                    return Some((Change::Insert(addr, service), state));
+                }
+
+                if !state.first {
+                    rt::sleep(state.interval).await;
+                }
+                state.first = false;
+
+                match resolve(&state.host).await {
+                    Ok(resolved) => {
+                        state.removed.extend(state.current.difference(&resolved));
+                        state.pending.extend(resolved.difference(&state.current));
+                        tracing::trace!(
+                            host = state.host,
+                            inserted = state.pending.len(),
+                            removed = state.removed.len(),
+                            "resolved"
+                        );
+                        state.current = resolved;
+                    }
+                    Err(error) => {
+                        tracing::warn!(host = state.host, %error, "failed to resolve");
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures_util::StreamExt;
+    use tokio::sync::mpsc;
+    use tokio_stream::wrappers::UnboundedReceiverStream;
+
+    use crate::balance::Change;
+
+    use super::Discover;
+
+    #[tokio::test]
+    async fn debounce() {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let mut changes =
+            UnboundedReceiverStream::new(receiver).debounce(Duration::from_millis(20));
+
+        // Inserted then removed within the window, so never emitted.
+        sender.send(Change::Insert(1, "a")).unwrap();
+        sender.send(Change::Remove(1)).unwrap();
+        // Only the latest insert is emitted.
+        sender.send(Change::Insert(2, "b")).unwrap();
+        sender.send(Change::Insert(2, "c")).unwrap();
+        assert!(matches!(changes.next().await, Some(Change::Insert(2, "c"))));
+
+        sender.send(Change::Remove(2)).unwrap();
+        assert!(matches!(changes.next().await, Some(Change::Remove(2))));
+
+        drop(sender);
+        assert!(changes.next().await.is_none());
+    }
+}
@@ -0,0 +1,248 @@
+//! The [`dispatch`] function drives a [`Service`] with requests from a [`Stream`], returning a
+//! [`Stream`] of responses. At most `parallelism` calls are inflight at once.
+//!
+//! A request is only taken from the [`Stream`] once a [`Service::Permit`] has been acquired for
+//! it, so backpressure from the service propagates to the requests. Inflight calls continue to be
+//! driven while waiting for a permit or the next request. Responses are yielded as calls complete,
+//! and the [`Stream`] ends once the requests have ended and every call has completed.
+//!
+//! The [`dispatch_ordered`] function instead yields responses in the order of the requests, as
+//! [`StreamExt::buffered`] does for futures, whereas [`dispatch`] corresponds to
+//! [`StreamExt::buffer_unordered`]. Responses which complete ahead of an earlier call are held
+//! until it completes. They continue to count towards `parallelism`, so a slow call limits how far
+//! ahead the others may run.
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::*;
+//! use futures_util::{stream, StreamExt};
+//! # use std::time::Duration;
+//! # use tokio::time::sleep;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc = service_fn(|x: u64| async move {
+//!     sleep(Duration::from_millis(10 * (5 - x))).await;
+//!     2 * x
+//! })
+//! .concurrency_limit(3);
+//! let responses = dispatch_ordered(&svc, stream::iter(0..5), 4);
+//! let responses: Vec<_> = responses.collect().await;
+//! assert_eq!(responses, [0, 2, 4, 6, 8]);
+//! # }
+//! ```
+
+use std::{future::Future, pin::Pin};
+
+use futures_util::{
+    stream::{self, FuturesOrdered, FuturesUnordered},
+    Stream, StreamExt,
+};
+
+use crate::Service;
+
+/// The inflight calls, yielding responses as they become available.
+trait Calls<F>: Stream<Item = F::Output> + Unpin
+where
+    F: Future,
+{
+    fn push(&mut self, call: F);
+
+    fn len(&self) -> usize;
+}
+
+impl<F> Calls<F> for FuturesUnordered<F>
+where
+    F: Future,
+{
+    fn push(&mut self, call: F) {
+        FuturesUnordered::push(self, call);
+    }
+
+    fn len(&self) -> usize {
+        FuturesUnordered::len(self)
+    }
+}
+
+impl<F> Calls<F> for FuturesOrdered<F>
+where
+    F: Future,
+{
+    fn push(&mut self, call: F) {
+        self.push_back(call);
+    }
+
+    fn len(&self) -> usize {
+        FuturesOrdered::len(self)
+    }
+}
+
+struct State<'a, S, Request, St, C>
+where
+    S: Service<Request> + 'a,
+{
+    service: &'a S,
+    requests: Pin<Box<St>>,
+    exhausted: bool,
+    permit: Option<S::Permit<'a>>,
+    calls: C,
+    parallelism: usize,
+}
+
+fn drive<'a, S, Request, St, Fut, C>(
+    service: &'a S,
+    requests: St,
+    parallelism: usize,
+    calls: C,
+    call: fn(S::Permit<'a>, Request) -> Fut,
+) -> impl Stream<Item = S::Response> + 'a
+where
+    S: Service<Request>,
+    St: Stream<Item = Request> + 'a,
+    Fut: Future<Output = S::Response> + 'a,
+    C: Calls<Fut> + 'a,
+    Request: 'a,
+{
+    let state = State {
+        service,
+        requests: Box::pin(requests),
+        exhausted: false,
+        permit: None,
+        calls,
+        parallelism: parallelism.max(1),
+    };
+    stream::unfold(state, move |mut state| async move {
+        loop {
+            if state.exhausted && state.calls.len() == 0 {
+                return None;
+            }
+            let has_capacity = !state.exhausted && state.calls.len() < state.parallelism;
+            tokio::select! {
+                Some(response) = state.calls.next(), if state.calls.len() != 0 => {
+                    return Some((response, state));
+                }
+                permit = state.service.acquire(), if has_capacity && state.permit.is_none() => {
+                    state.permit = Some(permit);
+                }
+                request = state.requests.next(), if state.permit.is_some() => {
+                    match request {
+                        Some(request) => {
+                            let permit = state.permit.take().expect("permit was acquired");
+                            state.calls.push(call(permit, request));
+                        }
+                        None => {
+                            state.exhausted = true;
+                            state.permit = None;
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// Drives the [`Service`] with requests from a [`Stream`], yielding responses as they complete.
+///
+/// See the [module](mod@crate::dispatch) for more information.
+pub fn dispatch<'a, S, Request, St>(
+    service: &'a S,
+    requests: St,
+    parallelism: usize,
+) -> impl Stream<Item = S::Response> + 'a
+where
+    S: Service<Request>,
+    St: Stream<Item = Request> + 'a,
+    Request: 'a,
+{
+    drive(
+        service,
+        requests,
+        parallelism,
+        FuturesUnordered::new(),
+        S::call,
+    )
+}
+
+/// Drives the [`Service`] with requests from a [`Stream`], yielding responses in request order.
+///
+/// See the [module](mod@crate::dispatch) for more information.
+pub fn dispatch_ordered<'a, S, Request, St>(
+    service: &'a S,
+    requests: St,
+    parallelism: usize,
+) -> impl Stream<Item = S::Response> + 'a
+where
+    S: Service<Request>,
+    St: Stream<Item = Request> + 'a,
+    Request: 'a,
+{
+    drive(
+        service,
+        requests,
+        parallelism,
+        FuturesOrdered::new(),
+        S::call,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    use futures_util::{stream, StreamExt};
+
+    use crate::{service_fn, ServiceExt};
+
+    use super::{dispatch, dispatch_ordered};
+
+    #[tokio::test]
+    async fn bounded_and_lazy() {
+        let inflight = AtomicUsize::new(0);
+        let max_inflight = AtomicUsize::new(0);
+        let pulled = AtomicUsize::new(0);
+        let svc = service_fn(|x: u32| {
+            let (inflight, max_inflight) = (&inflight, &max_inflight);
+            async move {
+                let current = inflight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_inflight.fetch_max(current, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                inflight.fetch_sub(1, Ordering::SeqCst);
+                x
+            }
+        })
+        .concurrency_limit(2);
+        let requests = stream::iter(0..10).inspect(|_| {
+            pulled.fetch_add(1, Ordering::SeqCst);
+        });
+
+        let mut responses = Box::pin(dispatch(&svc, requests, 4));
+        responses.next().await.unwrap();
+        // Only requests with a permit have been pulled.
+        assert!(pulled.load(Ordering::SeqCst) <= 3);
+
+        assert_eq!(responses.count().await, 9);
+        assert_eq!(max_inflight.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn ordered_holds_completed() {
+        let svc = service_fn(|x: u64| async move {
+            tokio::time::sleep(Duration::from_millis(5 * (4 - x))).await;
+            x
+        });
+        let started = AtomicUsize::new(0);
+        let requests = stream::iter(0..4).inspect(|_| {
+            started.fetch_add(1, Ordering::SeqCst);
+        });
+
+        let mut responses = Box::pin(dispatch_ordered(&svc, requests, 2));
+        // The first response waits for the slowest call, and later calls wait for capacity.
+        assert_eq!(responses.next().await, Some(0));
+        assert!(started.load(Ordering::SeqCst) <= 3);
+        assert_eq!(responses.collect::<Vec<_>>().await, [1, 2, 3]);
+    }
+}
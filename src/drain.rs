@@ -0,0 +1,281 @@
+//! The [`ServiceExt::drainable`](crate::ServiceExt::drainable) combinator returns [`Drain`] and a
+//! [`DrainHandle`], which are used to gracefully shutdown a service.
+//!
+//! Once [`DrainHandle::drain`] has been called, [`Service::acquire`] on [`Drain`] immediately
+//! returns a permit which sheds the request, returning it as [`Err`], in the same manner as
+//! [`ServiceExt::load_shed`](crate::ServiceExt::load_shed). Any pending [`Service::acquire`]s
+//! are also shed. Permits acquired before draining, and their [calls](Service::call), are
+//! unaffected.
+//!
+//! [`DrainHandle::drained`] resolves when draining has begun and all outstanding permits and calls
+//! have completed.
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::*;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let (svc, handle) = service_fn(|x: u32| async move { x + 1 }).drainable();
+//! let permit = svc.acquire().await;
+//! handle.drain();
+//! assert_eq!(svc.oneshot(3).await, Err(3));
+//!
+//! // The permit acquired before draining is still usable.
+//! # async fn call<'a, S: Service<u32>>(_: &'a S, permit: S::Permit<'a>, request: u32) -> S::Response {
+//! #     S::call(permit, request).await
+//! # }
+//! let response = call(&svc, permit, 2).await;
+//! assert_eq!(response, Ok(3));
+//! handle.drained().await;
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`Drain`] defers to the inner service.
+
+use std::{
+    fmt,
+    pin::pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use tokio::{
+    select,
+    sync::{watch, Notify},
+};
+
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Middleware, Service,
+};
+
+#[derive(Debug)]
+struct State {
+    draining: watch::Sender<bool>,
+    inflight: AtomicUsize,
+    idle: Notify,
+}
+
+impl State {
+    fn is_draining(&self) -> bool {
+        *self.draining.borrow()
+    }
+}
+
+/// Tracks an outstanding permit or call.
+#[derive(Debug)]
+struct Inflight<'a> {
+    state: &'a State,
+}
+
+impl<'a> Inflight<'a> {
+    fn new(state: &'a State) -> Self {
+        state.inflight.fetch_add(1, Ordering::AcqRel);
+        Self { state }
+    }
+}
+
+impl Drop for Inflight<'_> {
+    fn drop(&mut self) {
+        if self.state.inflight.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.state.idle.notify_waiters();
+        }
+    }
+}
+
+/// A wrapper [`Service`] for the [`ServiceExt::drainable`](crate::ServiceExt::drainable)
+/// combinator.
+///
+/// See the [module](crate::drain) for more information.
+#[derive(Debug)]
+pub struct Drain<S> {
+    inner: S,
+    state: Arc<State>,
+}
+
+/// A handle, returned by [`ServiceExt::drainable`](crate::ServiceExt::drainable), used to drain a
+/// [`Drain`].
+///
+/// See the [module](crate::drain) for more information.
+#[derive(Debug, Clone)]
+pub struct DrainHandle {
+    state: Arc<State>,
+}
+
+impl<S> Drain<S> {
+    pub(crate) fn new(inner: S) -> (Self, DrainHandle) {
+        let state = Arc::new(State {
+            draining: watch::Sender::new(false),
+            inflight: AtomicUsize::new(0),
+            idle: Notify::new(),
+        });
+        let handle = DrainHandle {
+            state: state.clone(),
+        };
+        (Self { inner, state }, handle)
+    }
+}
+
+impl DrainHandle {
+    /// Begins draining, causing all subsequent [`Service::acquire`]s to shed.
+    pub fn drain(&self) {
+        self.state.draining.send_replace(true);
+    }
+
+    /// Returns whether draining has begun.
+    pub fn is_draining(&self) -> bool {
+        self.state.is_draining()
+    }
+
+    /// Resolves when draining has begun and all outstanding permits and calls have completed.
+    pub async fn drained(&self) {
+        let mut draining = self.state.draining.subscribe();
+        let _ = draining.wait_for(|draining| *draining).await;
+        loop {
+            let mut idle = pin!(self.state.idle.notified());
+            idle.as_mut().enable();
+            if self.state.inflight.load(Ordering::Acquire) == 0 {
+                return;
+            }
+            idle.await;
+        }
+    }
+}
+
+/// The [`Service::Permit`] type for [`Drain`].
+pub struct DrainPermit<'a, S, Request>
+where
+    S: Service<Request> + 'a,
+{
+    inner: Option<(S::Permit<'a>, Inflight<'a>)>,
+}
+
+impl<'a, S, Request> fmt::Debug for DrainPermit<'a, S, Request>
+where
+    S: Service<Request>,
+    S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DrainPermit")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<Request, S> Service<Request> for Drain<S>
+where
+    S: Service<Request>,
+{
+    type Response = Result<S::Response, Request>;
+    type Permit<'a> = DrainPermit<'a, S, Request>
+    where
+        S: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        // Register before checking, so that a concurrent drain either observes this acquisition or
+        // is observed by it.
+        let inflight = Inflight::new(&self.state);
+        let mut draining = self.state.draining.subscribe();
+        if *draining.borrow_and_update() {
+            return DrainPermit { inner: None };
+        }
+        let inner = select! {
+            biased;
+            _ = draining.wait_for(|draining| *draining) => None,
+            permit = self.inner.acquire() => Some((permit, inflight)),
+        };
+        DrainPermit { inner }
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        if let Some((permit, _inflight)) = permit.inner {
+            Ok(S::call(permit, request).await)
+        } else {
+            Err(request)
+        }
+    }
+}
+
+impl<S> Load for Drain<S>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S> Describe for Drain<S>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("Drain").with_child(self.inner.describe())
+    }
+}
+
+impl<S, T> Middleware<S> for Drain<T>
+where
+    T: Middleware<S>,
+{
+    type Service = Drain<T::Service>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self { inner, state } = self;
+        Drain {
+            inner: inner.apply(svc),
+            state,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::pin;
+
+    use futures_util::poll;
+
+    use crate::{service_fn, Service, ServiceExt};
+
+    use super::Drain;
+
+    #[tokio::test]
+    async fn acquire_during_drain_is_rejected() {
+        let (svc, handle) = service_fn(|x: u32| async move { x })
+            .concurrency_limit(1)
+            .drainable();
+        let held = svc.acquire().await;
+
+        let mut waiting = pin!(svc.acquire());
+        assert!(poll!(waiting.as_mut()).is_pending());
+        handle.drain();
+        let permit = waiting.await;
+        assert_eq!(Drain::call(permit, 1).await, Err(1));
+        assert_eq!(svc.oneshot(2).await, Err(2));
+
+        // The permit acquired before draining is unaffected.
+        assert_eq!(Drain::call(held, 3).await, Ok(3));
+    }
+
+    #[tokio::test]
+    async fn drain_waits_for_outstanding_permits() {
+        let (svc, handle) = service_fn(|x: u32| async move { x }).drainable();
+        let permit = svc.acquire().await;
+
+        let mut drained = pin!(handle.drained());
+        assert!(poll!(drained.as_mut()).is_pending());
+        handle.drain();
+        assert!(poll!(drained.as_mut()).is_pending());
+        drop(permit);
+        assert!(poll!(drained.as_mut()).is_ready());
+    }
+}
@@ -9,6 +9,7 @@
 //! ```rust
 //! use burger::*;
 //!
+//! # #[cfg(feature = "tokio")]
 //! # #[tokio::main]
 //! # async fn main() {
 //! # let max_concurrency = Some(3);
@@ -20,13 +21,66 @@
 //! };
 //! let response = svc.oneshot(10u32).await;
 //! # }
+//! # #[cfg(not(feature = "tokio"))]
+//! # fn main() {}
+//! ```
+//!
+//! Similarly, [`Either`] is a [`Middleware`] which applies the variant, allowing a stack to be
+//! conditionally configured using [`MiddlewareBuilder`](crate::MiddlewareBuilder).
+//!
+//! # Conditional middleware
+//!
+//! The [`ServiceExt::when`](crate::ServiceExt::when) combinator applies a [`Middleware`] only if a
+//! condition holds, returning [`Either::Left`] with the middleware applied, or [`Either::Right`]
+//! without. Each optional part of a configuration-driven stack is then a single call, rather than
+//! a branch.
+//!
+//! ```rust
+//! use burger::*;
+//!
+//! # #[cfg(feature = "tokio")]
+//! # #[tokio::main]
+//! # async fn main() {
+//! # struct Config { limit_concurrency: bool, buffer: bool }
+//! let config = Config { limit_concurrency: true, buffer: false };
+//! let stack = MiddlewareBuilder
+//!     .when(config.limit_concurrency, MiddlewareBuilder.concurrency_limit(3))
+//!     .when(config.buffer, MiddlewareBuilder.buffer(2));
+//! let svc = stack.apply(service_fn(|x: u32| async move { x + 2 }));
+//! assert_eq!(svc.oneshot(10).await, 12);
+//!
+//! // Services may be wrapped directly.
+//! let svc = service_fn(|x: u32| async move { x + 2 }).when(true, MiddlewareBuilder.buffer(1));
+//! assert_eq!(svc.oneshot(10).await, 12);
+//! # }
+//! # #[cfg(not(feature = "tokio"))]
+//! # fn main() {}
 //! ```
 //!
 //! # Load
 //!
-//! The [`Load::load`] on [`Either`] defers to the variant.
+//! The [`Load::load`] on [`Either`] defers to the variant. Both variants must share a
+//! [`Load::Metric`], differing metrics can be mapped to a common one using
+//! [`ServiceExt::map_load`](crate::ServiceExt::map_load).
+//!
+//! ```rust
+//! use burger::{load::Load, *};
+//!
+//! # let pending = true;
+//! let svc = service_fn(|x: u32| async move { x + 2 });
+//! let svc = if pending {
+//!     svc.pending_requests().map_load(|n| n as f64).left()
+//! } else {
+//!     svc.constant_load(1.0).right()
+//! };
+//! assert_eq!(svc.load(), 0.0);
+//! ```
 
-use crate::{load::Load, Service};
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Middleware, Service,
+};
 
 /// A wrapper [`Service`] for [`ServiceExt::left`](crate::ServiceExt::left) and
 /// [`ServiceExt::right`](crate::ServiceExt::right) which consolidates two types.
@@ -82,3 +136,31 @@ where
         }
     }
 }
+
+impl<A, B> Describe for Either<A, B>
+where
+    A: Describe,
+    B: Describe,
+{
+    fn describe(&self) -> StackNode {
+        match self {
+            Either::Left(left) => left.describe(),
+            Either::Right(right) => right.describe(),
+        }
+    }
+}
+
+impl<S, A, B> Middleware<S> for Either<A, B>
+where
+    A: Middleware<S>,
+    B: Middleware<S>,
+{
+    type Service = Either<A::Service, B::Service>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        match self {
+            Either::Left(left) => Either::Left(left.apply(svc)),
+            Either::Right(right) => Either::Right(right.apply(svc)),
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! The [`ServiceExt::err_into`](crate::ServiceExt::err_into) combinator returns [`ErrInto`], which
+//! converts the [`Err`] variant of a [fallible service's](crate::TryService) response using
+//! [`Into`]. The [`Ok`] variant is passed through unchanged.
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::*;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc = service_fn(|x: u32| async move { x.checked_sub(3).ok_or(x) }).err_into::<u64>();
+//! assert_eq!(svc.oneshot(5).await, Ok(2));
+//! assert_eq!(svc.oneshot(2).await, Err(2u64));
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`ErrInto`] defers to the inner service.
+
+use core::{fmt, marker::PhantomData};
+
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Middleware, Service, TryService,
+};
+
+/// A wrapper [`Service`] for the [`ServiceExt::err_into`](crate::ServiceExt::err_into) combinator.
+///
+/// See the [module](crate::err_into) for more information.
+pub struct ErrInto<S, E> {
+    inner: S,
+    _error: PhantomData<fn() -> E>,
+}
+
+impl<S, E> ErrInto<S, E> {
+    pub(crate) fn new(inner: S) -> Self {
+        Self {
+            inner,
+            _error: PhantomData,
+        }
+    }
+}
+
+impl<S, E> Clone for ErrInto<S, E>
+where
+    S: Clone,
+{
+    fn clone(&self) -> Self {
+        Self::new(self.inner.clone())
+    }
+}
+
+impl<S, E> fmt::Debug for ErrInto<S, E>
+where
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ErrInto")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<Request, S, E> Service<Request> for ErrInto<S, E>
+where
+    S: TryService<Request>,
+    S::Error: Into<E>,
+{
+    type Response = Result<S::Ok, E>;
+    type Permit<'a> = S::Permit<'a>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        self.inner.acquire().await
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        S::call(permit, request).await.map_err(Into::into)
+    }
+}
+
+impl<S, E> Load for ErrInto<S, E>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S, E> Describe for ErrInto<S, E>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("ErrInto").with_child(self.inner.describe())
+    }
+}
+
+impl<S, T, E> Middleware<S> for ErrInto<T, E>
+where
+    T: Middleware<S>,
+{
+    type Service = ErrInto<T::Service, E>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        ErrInto::new(self.inner.apply(svc))
+    }
+}
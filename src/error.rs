@@ -0,0 +1,81 @@
+//! The [`Error`] enum unifies the failures produced by this crate, so that applications can
+//! propagate them using `?`, for example into a `Box<dyn core::error::Error>` or `anyhow::Error`.
+//!
+//! Module specific errors, such as [`balance::Terminated`](crate::balance::Terminated) and
+//! [`worker::Closed`](crate::worker::Closed), convert into [`Error`] using [`From`]. Combinators
+//! which reject work return the request, so that it may be retried elsewhere, and can be mapped
+//! onto [`Error::Overloaded`] using [`ServiceExt::map_err`](crate::ServiceExt::map_err) once the
+//! request is no longer needed.
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::*;
+//!
+//! async fn double<S>(svc: &S, x: u32) -> Result<u32, Box<dyn core::error::Error>>
+//! where
+//!     S: Service<u32, Response = Result<u32, Error>>,
+//! {
+//!     Ok(2 * svc.oneshot(x).await?)
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc = service_fn(|x: u32| async move { x })
+//!     .load_shed()
+//!     .map_err(|_request| Error::Overloaded);
+//! assert_eq!(double(&svc, 3).await.unwrap(), 6);
+//! # }
+//! ```
+
+use core::fmt;
+
+/// The errors produced by this crate.
+///
+/// See the [module](crate::error) for more information.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The request was rejected, for example by load shedding or admission control.
+    Overloaded,
+    /// A balancer's [`Stream`](futures_util::Stream) of changes has terminated.
+    Terminated,
+    /// The [`Future`](core::future::Future) driving a service has been dropped.
+    Closed,
+    /// An attempt exceeded its timeout.
+    TimedOut,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Overloaded => f.write_str("service overloaded"),
+            Error::Terminated => f.write_str("change stream terminated"),
+            Error::Closed => f.write_str("service closed"),
+            Error::TimedOut => f.write_str("attempt timed out"),
+        }
+    }
+}
+
+impl core::error::Error for Error {}
+
+#[cfg(feature = "tokio")]
+impl From<crate::balance::Terminated> for Error {
+    fn from(_: crate::balance::Terminated) -> Self {
+        Error::Terminated
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl From<crate::worker::Closed> for Error {
+    fn from(_: crate::worker::Closed) -> Self {
+        Error::Closed
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl From<crate::retry::failover::TimedOut> for Error {
+    fn from(_: crate::retry::failover::TimedOut) -> Self {
+        Error::TimedOut
+    }
+}
@@ -0,0 +1,144 @@
+//! The [`ServiceExt::fallback`](crate::ServiceExt::fallback) combinator returns [`Fallback`], which
+//! extends a [fallible service](crate::TryService) with a secondary service. When the primary
+//! service's response is [`Err`], the request is sent to the secondary service and its response is
+//! returned instead. The [`Err`] from the primary service is discarded.
+//!
+//! The [`Service::acquire`] on [`Fallback`] waits only for the primary service's permit. The request
+//! must be [`Clone`], a copy is retained during the primary [`Service::call`] and, on failure,
+//! passed to the secondary service using [`ServiceExt::oneshot`].
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::*;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let primary = service_fn(|x: u32| async move { x.checked_sub(3).ok_or("underflow") });
+//! let secondary = service_fn(|x: u32| async move { Ok::<_, ()>(x + 3) });
+//! let svc = primary.fallback(secondary);
+//! assert_eq!(svc.oneshot(5).await, Ok(2));
+//! assert_eq!(svc.oneshot(2).await, Ok(5));
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`Fallback`] defers to the primary service.
+
+use core::fmt;
+
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Middleware, Service, ServiceExt, TryService,
+};
+
+/// A wrapper [`Service`] for the [`ServiceExt::fallback`](crate::ServiceExt::fallback) combinator.
+///
+/// See the [module](crate::fallback) for more information.
+#[derive(Clone, Debug)]
+pub struct Fallback<S, T> {
+    inner: S,
+    secondary: T,
+}
+
+impl<S, T> Fallback<S, T> {
+    pub(crate) fn new(inner: S, secondary: T) -> Self {
+        Self { inner, secondary }
+    }
+}
+
+/// The [`Service::Permit`] type for [`Fallback`].
+pub struct FallbackPermit<'a, S, T, Request>
+where
+    S: Service<Request> + 'a,
+{
+    inner: S::Permit<'a>,
+    secondary: &'a T,
+}
+
+impl<'a, S, T, Request> fmt::Debug for FallbackPermit<'a, S, T, Request>
+where
+    S: Service<Request>,
+    S::Permit<'a>: fmt::Debug,
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FallbackPermit")
+            .field("inner", &self.inner)
+            .field("secondary", &self.secondary)
+            .finish()
+    }
+}
+
+impl<Request, S, T> Service<Request> for Fallback<S, T>
+where
+    Request: Clone,
+    S: TryService<Request>,
+    T: TryService<Request, Ok = S::Ok>,
+{
+    type Response = Result<S::Ok, T::Error>;
+    type Permit<'a> = FallbackPermit<'a, S, T, Request>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        FallbackPermit {
+            inner: self.inner.acquire().await,
+            secondary: &self.secondary,
+        }
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        let FallbackPermit { inner, secondary } = permit;
+        match S::call(inner, request.clone()).await {
+            Ok(ok) => Ok(ok),
+            Err(_) => {
+                tracing::trace!("primary failed, falling back to secondary");
+                secondary.oneshot(request).await
+            }
+        }
+    }
+}
+
+impl<S, T> Load for Fallback<S, T>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S, T> Describe for Fallback<S, T>
+where
+    S: Describe,
+    T: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("Fallback")
+            .with_child(self.inner.describe())
+            .with_child(self.secondary.describe())
+    }
+}
+
+impl<S, T, U> Middleware<S> for Fallback<T, U>
+where
+    T: Middleware<S>,
+{
+    type Service = Fallback<T::Service, U>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self { inner, secondary } = self;
+        Fallback {
+            inner: inner.apply(svc),
+            secondary,
+        }
+    }
+}
@@ -0,0 +1,443 @@
+//! Given a collection of [services](Service), [`fanout`] constructs a [`Fanout`] [`Service`] which
+//! sends each request to every service and responds with all of their responses.
+//!
+//! The [`Service::acquire`] on [`Fanout`] acquires _all_ [permits](Service::Permit) from the
+//! collection. The [`Service::call`] then calls every service concurrently, each with a clone of the
+//! request, and resolves once all have responded. Responses are in the same order as the services.
+//!
+//! The collection must be homogeneous. To fan out to differently typed services, [`fanout_tuple`]
+//! accepts a tuple of up to four services and responds with a tuple of their responses, which
+//! needn't share a type.
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::*;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let replicas = (0..3).map(|index| service_fn(move |x: u32| async move { index + x }));
+//! let svc = fanout(replicas);
+//! let responses = svc.oneshot(7).await;
+//! assert_eq!(responses, [7, 8, 9]);
+//! # }
+//! ```
+//!
+//! Differently typed services are fanned out to using [`fanout_tuple`]:
+//!
+//! ```rust
+//! use burger::*;
+//!
+//! # #[cfg(feature = "tokio")]
+//! # #[tokio::main]
+//! # async fn main() {
+//! let primary = service_fn(|x: u32| async move { x + 1 });
+//! let audit = service_fn(|x: u32| async move { x.to_string() }).concurrency_limit(1);
+//! let svc = fanout_tuple((primary, audit));
+//! let response = svc.oneshot(7).await;
+//! assert_eq!(response, (8, "7".to_string()));
+//! # }
+//! # #[cfg(not(feature = "tokio"))]
+//! # fn main() {}
+//! ```
+//!
+//! # Quorum
+//!
+//! Replicated writes typically need only a majority of replicas to acknowledge. Given a collection
+//! of [fallible services](crate::TryService), [`quorum`] constructs a [`Quorum`] [`Service`] which,
+//! like [`Fanout`], acquires every permit and calls every service. It resolves with the first `n`
+//! [`Ok`] responses as soon as they arrive, cancelling the remaining calls. Once `n` successes are no
+//! longer possible, it resolves with the [`Err`] responses received so far.
+//!
+//! ```rust
+//! use burger::*;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let replicas = (0..3).map(|index| {
+//!     service_fn(move |x: u32| async move {
+//!         if index == 1 {
+//!             Err("unavailable")
+//!         } else {
+//!             Ok(x)
+//!         }
+//!     })
+//! });
+//! let svc = quorum(replicas, 2);
+//! let response = svc.oneshot(7).await;
+//! assert_eq!(response, Ok(vec![7, 7]));
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! This has _no_ [`Load`](crate::load::Load) implementation. As every service is called, the loads
+//! can be combined using [`ServiceExt::compose_load`](crate::ServiceExt::compose_load).
+
+use alloc::{boxed::Box, vec::Vec};
+use core::fmt;
+
+use futures_util::{
+    future::{join, join3, join4, join_all},
+    stream::FuturesUnordered,
+    StreamExt,
+};
+
+use crate::{
+    describe::{Describe, StackNode},
+    Service, TryService,
+};
+
+/// A wrapper [`Service`] for the [`fanout`] constructor.
+///
+/// See the [module](mod@crate::fanout) for more information.
+#[derive(Debug)]
+pub struct Fanout<S> {
+    services: Box<[S]>,
+}
+
+/// The [`Service::Permit`] type for [`Fanout`].
+pub struct FanoutPermit<'a, S, Request>
+where
+    S: Service<Request> + 'a,
+{
+    permits: Vec<S::Permit<'a>>,
+}
+
+impl<'a, S, Request> fmt::Debug for FanoutPermit<'a, S, Request>
+where
+    S: Service<Request> + 'a,
+    S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FanoutPermit")
+            .field("permits", &self.permits)
+            .finish()
+    }
+}
+
+impl<Request, S> Service<Request> for Fanout<S>
+where
+    Request: Clone,
+    S: Service<Request>,
+{
+    type Response = Vec<S::Response>;
+    type Permit<'a> = FanoutPermit<'a, S, Request>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        FanoutPermit {
+            permits: join_all(self.services.iter().map(|x| x.acquire())).await,
+        }
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        let calls = permit
+            .permits
+            .into_iter()
+            .map(|permit| S::call(permit, request.clone()));
+        join_all(calls).await
+    }
+}
+
+impl<S> Describe for Fanout<S>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("Fanout").with_children(self.services.iter().map(S::describe))
+    }
+}
+
+/// Constructs a [`Service`] from a [collection](IntoIterator) of services whose [`Service::call`]
+/// calls every service.
+///
+/// See [module](mod@crate::fanout) for more information.
+pub fn fanout<S>(services: impl IntoIterator<Item = S>) -> Fanout<S> {
+    Fanout {
+        services: services.into_iter().collect(),
+    }
+}
+
+/// A wrapper [`Service`] for the [`fanout_tuple`] constructor.
+///
+/// See the [module](mod@crate::fanout) for more information.
+#[derive(Clone, Debug)]
+pub struct FanoutTuple<T> {
+    services: T,
+}
+
+/// Constructs a [`Service`] from a tuple of up to four services whose [`Service::call`] calls every
+/// service.
+///
+/// See [module](mod@crate::fanout) for more information.
+pub fn fanout_tuple<T>(services: T) -> FanoutTuple<T> {
+    FanoutTuple { services }
+}
+
+impl<Request, A, B> Service<Request> for FanoutTuple<(A, B)>
+where
+    Request: Clone,
+    A: Service<Request>,
+    B: Service<Request>,
+{
+    type Response = (A::Response, B::Response);
+    type Permit<'a> = (A::Permit<'a>, B::Permit<'a>)
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        let (a, b) = &self.services;
+        join(a.acquire(), b.acquire()).await
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        let (a, b) = permit;
+        join(A::call(a, request.clone()), B::call(b, request)).await
+    }
+}
+
+impl<A, B> Describe for FanoutTuple<(A, B)>
+where
+    A: Describe,
+    B: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("FanoutTuple")
+            .with_children([self.services.0.describe(), self.services.1.describe()])
+    }
+}
+
+impl<Request, A, B, C> Service<Request> for FanoutTuple<(A, B, C)>
+where
+    Request: Clone,
+    A: Service<Request>,
+    B: Service<Request>,
+    C: Service<Request>,
+{
+    type Response = (A::Response, B::Response, C::Response);
+    type Permit<'a> = (A::Permit<'a>, B::Permit<'a>, C::Permit<'a>)
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        let (a, b, c) = &self.services;
+        join3(a.acquire(), b.acquire(), c.acquire()).await
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        let (a, b, c) = permit;
+        join3(
+            A::call(a, request.clone()),
+            B::call(b, request.clone()),
+            C::call(c, request),
+        )
+        .await
+    }
+}
+
+impl<A, B, C> Describe for FanoutTuple<(A, B, C)>
+where
+    A: Describe,
+    B: Describe,
+    C: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("FanoutTuple").with_children([
+            self.services.0.describe(),
+            self.services.1.describe(),
+            self.services.2.describe(),
+        ])
+    }
+}
+
+impl<Request, A, B, C, D> Service<Request> for FanoutTuple<(A, B, C, D)>
+where
+    Request: Clone,
+    A: Service<Request>,
+    B: Service<Request>,
+    C: Service<Request>,
+    D: Service<Request>,
+{
+    type Response = (A::Response, B::Response, C::Response, D::Response);
+    type Permit<'a> = (A::Permit<'a>, B::Permit<'a>, C::Permit<'a>, D::Permit<'a>)
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        let (a, b, c, d) = &self.services;
+        join4(a.acquire(), b.acquire(), c.acquire(), d.acquire()).await
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        let (a, b, c, d) = permit;
+        join4(
+            A::call(a, request.clone()),
+            B::call(b, request.clone()),
+            C::call(c, request.clone()),
+            D::call(d, request),
+        )
+        .await
+    }
+}
+
+impl<A, B, C, D> Describe for FanoutTuple<(A, B, C, D)>
+where
+    A: Describe,
+    B: Describe,
+    C: Describe,
+    D: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("FanoutTuple").with_children([
+            self.services.0.describe(),
+            self.services.1.describe(),
+            self.services.2.describe(),
+            self.services.3.describe(),
+        ])
+    }
+}
+
+/// A wrapper [`Service`] for the [`quorum`] constructor.
+///
+/// See the [module](mod@crate::fanout#quorum) for more information.
+#[derive(Debug)]
+pub struct Quorum<S> {
+    services: Box<[S]>,
+    quorum: usize,
+}
+
+/// The [`Service::Permit`] type for [`Quorum`].
+pub struct QuorumPermit<'a, S, Request>
+where
+    S: Service<Request> + 'a,
+{
+    permits: Vec<S::Permit<'a>>,
+    quorum: usize,
+}
+
+impl<'a, S, Request> fmt::Debug for QuorumPermit<'a, S, Request>
+where
+    S: Service<Request> + 'a,
+    S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QuorumPermit")
+            .field("permits", &self.permits)
+            .field("quorum", &self.quorum)
+            .finish()
+    }
+}
+
+impl<Request, S> Service<Request> for Quorum<S>
+where
+    Request: Clone,
+    S: TryService<Request>,
+{
+    type Response = Result<Vec<S::Ok>, Vec<S::Error>>;
+    type Permit<'a> = QuorumPermit<'a, S, Request>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        QuorumPermit {
+            permits: join_all(self.services.iter().map(|x| x.acquire())).await,
+            quorum: self.quorum,
+        }
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        let QuorumPermit { permits, quorum } = permit;
+        let mut remaining = permits.len();
+        let mut calls: FuturesUnordered<_> = permits
+            .into_iter()
+            .map(|permit| S::call(permit, request.clone()))
+            .collect();
+        let mut oks = Vec::with_capacity(quorum.min(remaining));
+        let mut errors = Vec::new();
+        while oks.len() < quorum {
+            if oks.len() + remaining < quorum {
+                tracing::trace!(errors = errors.len(), "quorum unreachable");
+                return Err(errors);
+            }
+            let response = calls.next().await.expect("calls remain");
+            remaining -= 1;
+            match response {
+                Ok(ok) => oks.push(ok),
+                Err(error) => errors.push(error),
+            }
+        }
+        // Dropping `calls` cancels those yet to respond.
+        Ok(oks)
+    }
+}
+
+impl<S> Describe for Quorum<S>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("Quorum")
+            .with_config("quorum", self.quorum)
+            .with_children(self.services.iter().map(S::describe))
+    }
+}
+
+/// Constructs a [`Service`] from a [collection](IntoIterator) of
+/// [fallible services](crate::TryService) whose [`Service::call`] calls every service and resolves
+/// once `quorum` have succeeded.
+///
+/// See [module](mod@crate::fanout#quorum) for more information.
+pub fn quorum<S>(services: impl IntoIterator<Item = S>, quorum: usize) -> Quorum<S> {
+    Quorum {
+        services: services.into_iter().collect(),
+        quorum,
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use std::time::Duration;
+
+    use futures_util::future::pending;
+    use tokio::time::timeout;
+
+    use crate::{service_fn, ServiceExt};
+
+    use super::quorum;
+
+    #[tokio::test]
+    async fn unreachable() {
+        let replicas = (0..3).map(|index| {
+            service_fn(move |x: u32| async move {
+                if index == 0 {
+                    pending::<()>().await;
+                }
+                Err::<u32, _>(x + index)
+            })
+        });
+        let svc = quorum(replicas, 2);
+
+        // Two failures rule out a quorum, without waiting for the pending replica.
+        let response = timeout(Duration::from_secs(1), svc.oneshot(7)).await;
+        let mut errors = response.unwrap().unwrap_err();
+        errors.sort();
+        assert_eq!(errors, [8, 9]);
+    }
+}
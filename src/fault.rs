@@ -0,0 +1,271 @@
+//! The [`ServiceExt::inject_faults`](crate::ServiceExt::inject_faults) combinator returns
+//! [`InjectFaults`], which randomly injects faults into [calls](Service::call), as configured by
+//! [`FaultConfig`]. This may be used to test the behavior of a stack, such as its retries and load
+//! shedding, under failure.
+//!
+//! Each call independently, and in order:
+//!
+//! 1. Is delayed, with the configured probability, for the configured duration.
+//! 2. Never resolves, with the configured probability, without calling the inner service.
+//! 3. Returns [`Err(InjectedFault)`](InjectedFault), with the configured probability, without
+//!    calling the inner service.
+//!
+//! Otherwise the inner service is called and its response is returned as [`Ok`]. When the inner
+//! service is [fallible](crate::TryService), the errors can be combined by first converting the
+//! [`InjectedFault`] using [`ServiceExt::map_err`](crate::ServiceExt::map_err) and then using
+//! [`ServiceExt::flatten_err`](crate::ServiceExt::flatten_err).
+//!
+//! The random number generator is seeded by [`FaultConfig::new`], so a given sequence of calls
+//! experiences the same faults on each run.
+//!
+//! This module is enabled by the `test-util` feature.
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::{fault::FaultConfig, *};
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let config = FaultConfig::new(7).error(0.5);
+//! let svc = service_fn(|x: u32| async move { x + 1 }).inject_faults(config);
+//! let mut responses = Vec::new();
+//! for x in 0..16 {
+//!     responses.push(svc.oneshot(x).await);
+//! }
+//! assert!(responses.iter().any(Result::is_ok));
+//! assert!(responses.iter().any(Result::is_err));
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`InjectFaults`] defers to the inner service.
+
+use std::{fmt, sync::Mutex, time::Duration};
+
+use futures_util::future::pending;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    rt, Middleware, Service,
+};
+
+/// The configuration of the faults injected by [`InjectFaults`].
+///
+/// See the [module](crate::fault) for more information.
+#[derive(Debug, Clone)]
+pub struct FaultConfig {
+    seed: u64,
+    delay: f64,
+    delay_duration: Duration,
+    drop: f64,
+    error: f64,
+}
+
+impl FaultConfig {
+    /// Constructs a configuration, injecting no faults, with a seed for the random number
+    /// generator.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            delay: 0.0,
+            delay_duration: Duration::ZERO,
+            drop: 0.0,
+            error: 0.0,
+        }
+    }
+
+    /// Sets the probability, between 0 and 1, that a call is delayed, and the duration of the
+    /// delay.
+    pub fn delay(mut self, probability: f64, duration: Duration) -> Self {
+        self.delay = probability.clamp(0.0, 1.0);
+        self.delay_duration = duration;
+        self
+    }
+
+    /// Sets the probability, between 0 and 1, that a call never resolves.
+    pub fn drop_response(mut self, probability: f64) -> Self {
+        self.drop = probability.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets the probability, between 0 and 1, that a call returns [`InjectedFault`].
+    pub fn error(mut self, probability: f64) -> Self {
+        self.error = probability.clamp(0.0, 1.0);
+        self
+    }
+}
+
+/// The error returned by a [call](Service::call) on [`InjectFaults`] when a fault is injected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct InjectedFault;
+
+/// A wrapper [`Service`] for the [`ServiceExt::inject_faults`](crate::ServiceExt::inject_faults)
+/// combinator.
+///
+/// See the [module](crate::fault) for more information.
+#[derive(Debug)]
+pub struct InjectFaults<S> {
+    inner: S,
+    rng: Mutex<StdRng>,
+    config: FaultConfig,
+}
+
+impl<S> InjectFaults<S> {
+    pub(crate) fn new(inner: S, config: FaultConfig) -> Self {
+        Self {
+            inner,
+            rng: Mutex::new(StdRng::seed_from_u64(config.seed)),
+            config,
+        }
+    }
+}
+
+/// The faults chosen for a single call.
+#[derive(Debug)]
+struct Faults {
+    delay: bool,
+    drop: bool,
+    error: bool,
+}
+
+/// The [`Service::Permit`] type for [`InjectFaults`].
+pub struct InjectFaultsPermit<'a, S, Request>
+where
+    S: Service<Request> + 'a,
+{
+    service: &'a InjectFaults<S>,
+    inner: S::Permit<'a>,
+}
+
+impl<'a, S, Request> fmt::Debug for InjectFaultsPermit<'a, S, Request>
+where
+    S: Service<Request> + fmt::Debug,
+    S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InjectFaultsPermit")
+            .field("service", &self.service)
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<S> InjectFaults<S> {
+    fn roll(&self) -> Faults {
+        let mut rng = self.rng.lock().unwrap();
+        Faults {
+            delay: rng.gen_bool(self.config.delay),
+            drop: rng.gen_bool(self.config.drop),
+            error: rng.gen_bool(self.config.error),
+        }
+    }
+}
+
+impl<Request, S> Service<Request> for InjectFaults<S>
+where
+    S: Service<Request>,
+{
+    type Response = Result<S::Response, InjectedFault>;
+    type Permit<'a> = InjectFaultsPermit<'a, S, Request>
+    where
+        S: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        InjectFaultsPermit {
+            service: self,
+            inner: self.inner.acquire().await,
+        }
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        let InjectFaultsPermit { service, inner } = permit;
+        let faults = service.roll();
+        if faults.delay {
+            tracing::trace!("injecting delay");
+            rt::sleep(service.config.delay_duration).await;
+        }
+        if faults.drop {
+            tracing::trace!("injecting dropped response");
+            return pending().await;
+        }
+        if faults.error {
+            tracing::trace!("injecting error");
+            return Err(InjectedFault);
+        }
+        Ok(S::call(inner, request).await)
+    }
+}
+
+impl<S> Load for InjectFaults<S>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S> Describe for InjectFaults<S>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("InjectFaults")
+            .with_config("delay", self.config.delay)
+            .with_config(
+                "delay_duration",
+                format_args!("{:?}", self.config.delay_duration),
+            )
+            .with_config("drop", self.config.drop)
+            .with_config("error", self.config.error)
+            .with_child(self.inner.describe())
+    }
+}
+
+impl<S, T> Middleware<S> for InjectFaults<T>
+where
+    T: Middleware<S>,
+{
+    type Service = InjectFaults<T::Service>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self { inner, rng, config } = self;
+        InjectFaults {
+            inner: inner.apply(svc),
+            rng,
+            config,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{service_fn, ServiceExt};
+
+    use super::{FaultConfig, InjectedFault};
+
+    #[tokio::test]
+    async fn deterministic() {
+        let outcomes = || async {
+            let config = FaultConfig::new(3).error(0.5);
+            let svc = service_fn(|x: u32| async move { x }).inject_faults(config);
+            let mut outcomes = Vec::new();
+            for x in 0..32 {
+                outcomes.push(svc.oneshot(x).await);
+            }
+            outcomes
+        };
+
+        // The same seed injects the same faults.
+        let first = outcomes().await;
+        assert!(first.contains(&Err(InjectedFault)));
+        assert_eq!(first, outcomes().await);
+    }
+}
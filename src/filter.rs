@@ -0,0 +1,212 @@
+//! The [`ServiceExt::filter`](crate::ServiceExt::filter) and
+//! [`ServiceExt::filter_async`](crate::ServiceExt::filter_async) combinators return [`Filter`] and
+//! [`AsyncFilter`] respectively, which extend a service with a predicate which may reject the
+//! request.
+//!
+//! The predicate is a closure accepting the request and returning either [`Ok`] with the request
+//! passed to the inner service or [`Err`] with a rejection. For [`AsyncFilter`] the closure returns
+//! a [`Future`] resolving to the same.
+//!
+//! The [`Service::acquire`] on [`Filter`] and [`AsyncFilter`] resolves immediately. The inner
+//! [`Service::Permit`] is only acquired during [`Service::call`], once the request has been
+//! accepted, and therefore rejected requests never consume one.
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::*;
+//!
+//! # #[cfg(feature = "tokio")]
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc = service_fn(|x: String| async move { x.len() })
+//!     .concurrency_limit(1)
+//!     .filter(|x: String| if x.len() < 8 { Ok(x) } else { Err("too long") });
+//! let response = svc.oneshot("hello".to_string()).await;
+//! assert_eq!(response, Ok(5));
+//! let response = svc.oneshot("hello world".to_string()).await;
+//! assert_eq!(response, Err("too long"));
+//! # }
+//! # #[cfg(not(feature = "tokio"))]
+//! # fn main() {}
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`Filter`] and [`AsyncFilter`] defers to the inner service.
+
+use core::{any, fmt, future::Future};
+
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Middleware, Service, ServiceExt,
+};
+
+/// A wrapper [`Service`] for the [`ServiceExt::filter`](crate::ServiceExt::filter) combinator.
+///
+/// See the [module](crate::filter) for more information.
+#[derive(Clone, Debug)]
+pub struct Filter<S, F> {
+    inner: S,
+    closure: F,
+}
+
+impl<S, F> Filter<S, F> {
+    pub(crate) fn new(inner: S, closure: F) -> Self {
+        Self { inner, closure }
+    }
+}
+
+/// A wrapper [`Service`] for the [`ServiceExt::filter_async`](crate::ServiceExt::filter_async)
+/// combinator.
+///
+/// See the [module](crate::filter) for more information.
+#[derive(Clone, Debug)]
+pub struct AsyncFilter<S, F> {
+    inner: S,
+    closure: F,
+}
+
+impl<S, F> AsyncFilter<S, F> {
+    pub(crate) fn new(inner: S, closure: F) -> Self {
+        Self { inner, closure }
+    }
+}
+
+/// The [`Service::Permit`] type for [`Filter`] and [`AsyncFilter`].
+pub struct FilterPermit<'a, S, F> {
+    service: &'a S,
+    closure: &'a F,
+}
+
+impl<S, F> fmt::Debug for FilterPermit<'_, S, F>
+where
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FilterPermit")
+            .field("service", &self.service)
+            .field("closure", &format_args!("{}", any::type_name::<F>()))
+            .finish()
+    }
+}
+
+impl<Request, S, F, Inner, Rejection> Service<Request> for Filter<S, F>
+where
+    S: Service<Inner>,
+    F: Fn(Request) -> Result<Inner, Rejection>,
+{
+    type Response = Result<S::Response, Rejection>;
+    type Permit<'a> = FilterPermit<'a, S, F>
+    where
+        S: 'a,
+        F: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        FilterPermit {
+            service: &self.inner,
+            closure: &self.closure,
+        }
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        let request = (permit.closure)(request)?;
+        Ok(permit.service.oneshot(request).await)
+    }
+}
+
+impl<Request, S, F, Fut, Inner, Rejection> Service<Request> for AsyncFilter<S, F>
+where
+    S: Service<Inner>,
+    F: Fn(Request) -> Fut,
+    Fut: Future<Output = Result<Inner, Rejection>>,
+{
+    type Response = Result<S::Response, Rejection>;
+    type Permit<'a> = FilterPermit<'a, S, F>
+    where
+        S: 'a,
+        F: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        FilterPermit {
+            service: &self.inner,
+            closure: &self.closure,
+        }
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        let request = (permit.closure)(request).await?;
+        Ok(permit.service.oneshot(request).await)
+    }
+}
+
+impl<S, F> Load for Filter<S, F>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S, F> Describe for Filter<S, F>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("Filter").with_child(self.inner.describe())
+    }
+}
+
+impl<S, F> Load for AsyncFilter<S, F>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S, F> Describe for AsyncFilter<S, F>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("AsyncFilter").with_child(self.inner.describe())
+    }
+}
+
+impl<S, T, F> Middleware<S> for Filter<T, F>
+where
+    T: Middleware<S>,
+{
+    type Service = Filter<T::Service, F>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self { inner, closure } = self;
+        Filter {
+            inner: inner.apply(svc),
+            closure,
+        }
+    }
+}
+
+impl<S, T, F> Middleware<S> for AsyncFilter<T, F>
+where
+    T: Middleware<S>,
+{
+    type Service = AsyncFilter<T::Service, F>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self { inner, closure } = self;
+        AsyncFilter {
+            inner: inner.apply(svc),
+            closure,
+        }
+    }
+}
@@ -0,0 +1,115 @@
+//! The [`ServiceExt::flatten_err`](crate::ServiceExt::flatten_err) combinator returns
+//! [`FlattenErr`], which flattens a [fallible service](crate::TryService) whose [`Ok`] variant is
+//! itself a [`Result`]. The inner [`Err`] variant is converted, using [`Into`], into the outer.
+//!
+//! Nested results are produced by combinators such as
+//! [`ServiceExt::load_shed`](crate::ServiceExt::load_shed) wrapping a fallible service. The outer
+//! [`Err`] variant can first be modified using [`ServiceExt::map_err`](crate::ServiceExt::map_err).
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::*;
+//!
+//! #[derive(Debug, PartialEq)]
+//! enum Error {
+//!     Overloaded,
+//!     TooSmall(u32),
+//! }
+//!
+//! impl From<u32> for Error {
+//!     fn from(x: u32) -> Self {
+//!         Error::TooSmall(x)
+//!     }
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc = service_fn(|x: u32| async move { x.checked_sub(3).ok_or(x) })
+//!     .load_shed()
+//!     .map_err(|_| Error::Overloaded)
+//!     .flatten_err();
+//! assert_eq!(svc.oneshot(5).await, Ok(2));
+//! assert_eq!(svc.oneshot(2).await, Err(Error::TooSmall(2)));
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`FlattenErr`] defers to the inner service.
+
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Middleware, Service, TryService,
+};
+
+/// A wrapper [`Service`] for the [`ServiceExt::flatten_err`](crate::ServiceExt::flatten_err)
+/// combinator.
+///
+/// See the [module](crate::flatten_err) for more information.
+#[derive(Clone, Debug)]
+pub struct FlattenErr<S> {
+    inner: S,
+}
+
+impl<S> FlattenErr<S> {
+    pub(crate) fn new(inner: S) -> Self {
+        Self { inner }
+    }
+}
+
+impl<Request, S, T, E> Service<Request> for FlattenErr<S>
+where
+    S: TryService<Request, Ok = Result<T, E>>,
+    E: Into<S::Error>,
+{
+    type Response = Result<T, S::Error>;
+    type Permit<'a> = S::Permit<'a>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        self.inner.acquire().await
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        S::call(permit, request).await?.map_err(Into::into)
+    }
+}
+
+impl<S> Load for FlattenErr<S>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S> Describe for FlattenErr<S>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("FlattenErr").with_child(self.inner.describe())
+    }
+}
+
+impl<S, T> Middleware<S> for FlattenErr<T>
+where
+    T: Middleware<S>,
+{
+    type Service = FlattenErr<T::Service>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        FlattenErr {
+            inner: self.inner.apply(svc),
+        }
+    }
+}
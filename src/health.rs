@@ -0,0 +1,240 @@
+//! Health checking evicts unhealthy services from the load balancers found in the
+//! [`balance`](crate::balance) module, until they recover.
+//!
+//! The [`checked`] function wraps a [`Stream`] of [`Change`]s, such as those found in the
+//! [`discover`](crate::discover) module. Each inserted service is moved into an [`Arc`], which is
+//! shared between the balancer and the health checker. Every `interval`, each service is probed
+//! using a [`HealthCheck`]:
+//!
+//! - A healthy service which fails `threshold` consecutive probes is removed from the balancer.
+//! - An unhealthy service which passes a single probe is inserted back into the balancer.
+//!
+//! Services are assumed to be healthy when first inserted. Changes from the wrapped [`Stream`] are
+//! not consumed while probes are in progress.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::sync::Arc;
+//!
+//! use burger::{load::PendingRequests, service_fn::ServiceFn, *};
+//! # use std::{future::{ready, Ready}, time::Duration};
+//!
+//! type Double = PendingRequests<ServiceFn<fn(u32) -> Ready<u32>>>;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let double: fn(_) -> _ = |x: u32| ready(2 * x);
+//! let changes = discover::fixed([
+//!     ("a", service_fn(double).pending_requests()),
+//!     ("b", service_fn(double).pending_requests()),
+//! ]);
+//! let check = |svc: Arc<Double>| async move { svc.oneshot(0).await == 0 };
+//! let changes = health::checked(changes, Duration::from_secs(5), 3, check);
+//! let (svc, worker) = balance::p2c(changes);
+//! let response = tokio::select! {
+//!     response = svc.oneshot(5u32) => response,
+//!     _ = worker => unreachable!(),
+//! };
+//! assert_eq!(response, 10);
+//! # }
+//! ```
+
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    hash::Hash,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use futures_util::{future::join_all, stream, Stream, StreamExt};
+
+use crate::{balance::Change, rt};
+
+/// Probes whether a service is healthy.
+///
+/// This is implemented for closures accepting an [`Arc`] of the service and returning a [`Future`]
+/// which outputs a [`bool`].
+///
+/// See the [module](crate::health) for more information.
+pub trait HealthCheck<S> {
+    /// The [`Future`] returned by [`HealthCheck::check`].
+    type Future: Future<Output = bool>;
+
+    /// Returns whether the service is healthy.
+    fn check(&self, service: Arc<S>) -> Self::Future;
+}
+
+impl<S, F, Fut> HealthCheck<S> for F
+where
+    F: Fn(Arc<S>) -> Fut,
+    Fut: Future<Output = bool>,
+{
+    type Future = Fut;
+
+    fn check(&self, service: Arc<S>) -> Self::Future {
+        self(service)
+    }
+}
+
+struct Member<S> {
+    service: Arc<S>,
+    healthy: bool,
+    failures: usize,
+}
+
+struct State<St, Key, S, H> {
+    changes: Pin<Box<St>>,
+    members: HashMap<Key, Member<S>>,
+    pending: VecDeque<Change<Key, Arc<S>>>,
+    interval: Duration,
+    next_probe: Option<Instant>,
+    threshold: usize,
+    check: H,
+}
+
+impl<St, Key, S, H> State<St, Key, S, H>
+where
+    Key: Hash + Eq + Clone,
+    H: HealthCheck<S>,
+{
+    /// Applies a [`Change`] from the wrapped [`Stream`].
+    fn apply(&mut self, change: Change<Key, S>) {
+        match change {
+            Change::Insert(key, service) => {
+                let service = Arc::new(service);
+                self.members.insert(
+                    key.clone(),
+                    Member {
+                        service: service.clone(),
+                        healthy: true,
+                        failures: 0,
+                    },
+                );
+                self.pending.push_back(Change::Insert(key, service));
+            }
+            Change::Remove(key) => {
+                // Unhealthy services have already been removed.
+                if let Some(Member { healthy: true, .. }) = self.members.remove(&key) {
+                    self.pending.push_back(Change::Remove(key));
+                }
+            }
+        }
+    }
+
+    /// Probes every member, queuing a [`Change`] for each whose health has changed.
+    async fn probe(&mut self) {
+        let (keys, probes): (Vec<_>, Vec<_>) = self
+            .members
+            .iter()
+            .map(|(key, member)| (key.clone(), self.check.check(member.service.clone())))
+            .unzip();
+        let results = join_all(probes).await;
+
+        for (key, passed) in keys.into_iter().zip(results) {
+            let member = self
+                .members
+                .get_mut(&key)
+                .expect("not removed during probe");
+            if passed {
+                member.failures = 0;
+                if !member.healthy {
+                    tracing::debug!("service recovered");
+                    member.healthy = true;
+                    self.pending
+                        .push_back(Change::Insert(key, member.service.clone()));
+                }
+            } else {
+                member.failures += 1;
+                if member.healthy && member.failures >= self.threshold {
+                    tracing::debug!(failures = member.failures, "service unhealthy");
+                    member.healthy = false;
+                    self.pending.push_back(Change::Remove(key));
+                }
+            }
+        }
+    }
+}
+
+/// Wraps a [`Stream`] of [`Change`]s, removing services which fail `threshold` consecutive probes,
+/// performed every `interval`, and inserting them once they pass again.
+///
+/// A `threshold` of zero is treated as one.
+///
+/// See the [module](crate::health) for more information.
+pub fn checked<St, Key, S, H>(
+    changes: St,
+    interval: Duration,
+    threshold: usize,
+    check: H,
+) -> impl Stream<Item = Change<Key, Arc<S>>>
+where
+    St: Stream<Item = Change<Key, S>>,
+    Key: Hash + Eq + Clone,
+    H: HealthCheck<S>,
+{
+    let state = State {
+        changes: Box::pin(changes),
+        members: HashMap::new(),
+        pending: VecDeque::new(),
+        interval,
+        next_probe: None,
+        threshold: threshold.max(1),
+        check,
+    };
+    stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(change) = state.pending.pop_front() {
+                return Some((change, state));
+            }
+            // The first probe is scheduled when first polled, rather than on construction.
+            let next_probe = *state
+                .next_probe
+                .get_or_insert_with(|| Instant::now() + state.interval);
+            tokio::select! {
+                change = state.changes.next() => state.apply(change?),
+                _ = rt::sleep_until(next_probe) => {
+                    // Probes which overrun delay the next, rather than bursting to catch up.
+                    state.next_probe = Some(Instant::now() + state.interval);
+                    state.probe().await;
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        pin::pin,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use futures_util::{stream, StreamExt};
+
+    use crate::balance::Change;
+
+    use super::checked;
+
+    #[tokio::test]
+    async fn evict_and_recover() {
+        let changes =
+            stream::iter([Change::Insert(1, AtomicBool::new(true))]).chain(stream::pending());
+        let check = |healthy: Arc<AtomicBool>| async move { healthy.load(Ordering::SeqCst) };
+        let mut changes = pin!(checked(changes, Duration::from_millis(10), 2, check));
+
+        let Some(Change::Insert(1, healthy)) = changes.next().await else {
+            panic!("expected insert");
+        };
+        healthy.store(false, Ordering::SeqCst);
+        assert!(matches!(changes.next().await, Some(Change::Remove(1))));
+        healthy.store(true, Ordering::SeqCst);
+        assert!(matches!(changes.next().await, Some(Change::Insert(1, _))));
+    }
+}
@@ -0,0 +1,268 @@
+//! The [`ServiceExt::with_hooks`](crate::ServiceExt::with_hooks) combinator returns [`WithHooks`],
+//! which calls back into an implementation of [`Hooks`] as each permit is acquired and each
+//! [call](Service::call) is made. This allows custom logging, metrics or context propagation
+//! without writing a wrapper [`Service`] and naming the inner permit type.
+//!
+//! Every method of [`Hooks`] has an empty default, so only those of interest need be implemented:
+//!
+//! - [`Hooks::on_acquire_start`] and [`Hooks::on_acquire_end`] surround [`Service::acquire`].
+//! - [`Hooks::on_call_start`] receives a mutable reference to the request before it's passed to the
+//!   inner service, for example to attach a trace context.
+//! - [`Hooks::on_call_end`] receives a reference to the response.
+//!
+//! If an acquisition or call is cancelled then the corresponding end hook isn't called. For the
+//! latencies and cancellations of calls, see [`ServiceExt::metrics`](crate::ServiceExt::metrics).
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::sync::atomic::{AtomicUsize, Ordering};
+//!
+//! use burger::{hooks::Hooks, *};
+//!
+//! #[derive(Default)]
+//! struct CountErrors(AtomicUsize);
+//!
+//! impl Hooks<u32, Result<u32, u32>> for CountErrors {
+//!     fn on_call_end(&self, response: &Result<u32, u32>) {
+//!         if response.is_err() {
+//!             self.0.fetch_add(1, Ordering::Relaxed);
+//!         }
+//!     }
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let errors = CountErrors::default();
+//! let svc = service_fn(|x: u32| async move { x.checked_sub(1).ok_or(x) }).with_hooks(&errors);
+//! assert_eq!(svc.oneshot(0).await, Err(0));
+//! assert_eq!(svc.oneshot(1).await, Ok(0));
+//! assert_eq!(errors.0.load(Ordering::Relaxed), 1);
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`WithHooks`] defers to the inner service.
+
+use alloc::sync::Arc;
+use core::fmt;
+
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Middleware, Service,
+};
+
+/// Callbacks invoked by [`WithHooks`].
+///
+/// See the [module](crate::hooks) for more information.
+#[allow(unused_variables)]
+pub trait Hooks<Request, Response> {
+    /// Called before the inner permit is acquired.
+    fn on_acquire_start(&self) {}
+
+    /// Called once the inner permit has been acquired.
+    fn on_acquire_end(&self) {}
+
+    /// Called with the request before the inner service is called.
+    fn on_call_start(&self, request: &mut Request) {}
+
+    /// Called with the response once the inner service has returned it.
+    fn on_call_end(&self, response: &Response) {}
+}
+
+impl<Request, Response, H> Hooks<Request, Response> for &H
+where
+    H: Hooks<Request, Response>,
+{
+    fn on_acquire_start(&self) {
+        H::on_acquire_start(self)
+    }
+
+    fn on_acquire_end(&self) {
+        H::on_acquire_end(self)
+    }
+
+    fn on_call_start(&self, request: &mut Request) {
+        H::on_call_start(self, request)
+    }
+
+    fn on_call_end(&self, response: &Response) {
+        H::on_call_end(self, response)
+    }
+}
+
+impl<Request, Response, H> Hooks<Request, Response> for Arc<H>
+where
+    H: Hooks<Request, Response>,
+{
+    fn on_acquire_start(&self) {
+        H::on_acquire_start(self)
+    }
+
+    fn on_acquire_end(&self) {
+        H::on_acquire_end(self)
+    }
+
+    fn on_call_start(&self, request: &mut Request) {
+        H::on_call_start(self, request)
+    }
+
+    fn on_call_end(&self, response: &Response) {
+        H::on_call_end(self, response)
+    }
+}
+
+/// A wrapper [`Service`] for the [`ServiceExt::with_hooks`](crate::ServiceExt::with_hooks)
+/// combinator.
+///
+/// See the [module](crate::hooks) for more information.
+#[derive(Clone, Debug)]
+pub struct WithHooks<S, H> {
+    inner: S,
+    hooks: H,
+}
+
+impl<S, H> WithHooks<S, H> {
+    pub(crate) fn new(inner: S, hooks: H) -> Self {
+        Self { inner, hooks }
+    }
+
+    /// Returns a reference to the [`Hooks`].
+    pub fn hooks(&self) -> &H {
+        &self.hooks
+    }
+}
+
+/// The [`Service::Permit`] type for [`WithHooks`].
+pub struct WithHooksPermit<'a, S, H, Request>
+where
+    S: Service<Request> + 'a,
+{
+    inner: S::Permit<'a>,
+    hooks: &'a H,
+}
+
+impl<'a, S, H, Request> fmt::Debug for WithHooksPermit<'a, S, H, Request>
+where
+    S: Service<Request>,
+    S::Permit<'a>: fmt::Debug,
+    H: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WithHooksPermit")
+            .field("inner", &self.inner)
+            .field("hooks", &self.hooks)
+            .finish()
+    }
+}
+
+impl<Request, S, H> Service<Request> for WithHooks<S, H>
+where
+    S: Service<Request>,
+    H: Hooks<Request, S::Response>,
+{
+    type Response = S::Response;
+    type Permit<'a> = WithHooksPermit<'a, S, H, Request>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        self.hooks.on_acquire_start();
+        let inner = self.inner.acquire().await;
+        self.hooks.on_acquire_end();
+        WithHooksPermit {
+            inner,
+            hooks: &self.hooks,
+        }
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, mut request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        let WithHooksPermit { inner, hooks } = permit;
+        hooks.on_call_start(&mut request);
+        let response = S::call(inner, request).await;
+        hooks.on_call_end(&response);
+        response
+    }
+}
+
+impl<S, H> Load for WithHooks<S, H>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S, H> Describe for WithHooks<S, H>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("WithHooks").with_child(self.inner.describe())
+    }
+}
+
+impl<S, T, H> Middleware<S> for WithHooks<T, H>
+where
+    T: Middleware<S>,
+{
+    type Service = WithHooks<T::Service, H>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self { inner, hooks } = self;
+        WithHooks {
+            inner: inner.apply(svc),
+            hooks,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use std::sync::Mutex;
+
+    use crate::{service_fn, ServiceExt};
+
+    use super::Hooks;
+
+    #[derive(Default)]
+    struct Record(Mutex<Vec<&'static str>>);
+
+    impl Hooks<u32, u32> for Record {
+        fn on_acquire_start(&self) {
+            self.0.lock().unwrap().push("acquire_start");
+        }
+
+        fn on_acquire_end(&self) {
+            self.0.lock().unwrap().push("acquire_end");
+        }
+
+        fn on_call_start(&self, request: &mut u32) {
+            *request *= 10;
+            self.0.lock().unwrap().push("call_start");
+        }
+
+        fn on_call_end(&self, _response: &u32) {
+            self.0.lock().unwrap().push("call_end");
+        }
+    }
+
+    #[tokio::test]
+    async fn hooks_in_order() {
+        let record = Record::default();
+        let svc = service_fn(|x: u32| async move { x + 1 }).with_hooks(&record);
+        assert_eq!(svc.oneshot(1).await, 11);
+        assert_eq!(
+            *record.0.lock().unwrap(),
+            vec!["acquire_start", "acquire_end", "call_start", "call_end"]
+        );
+    }
+}
@@ -0,0 +1,278 @@
+//! The [`client`] function accepts a [`hyper_util`] legacy [`Client`](LegacyClient) and returns
+//! [`Client`], a [`Service`] accepting [`http::Request`]s and returning [`http::Response`]s.
+//!
+//! The [`Service::acquire`] on [`Client`] resolves immediately, connection pooling is left to the
+//! underlying [`hyper_util`] client. Requests which fail before a response is received return an
+//! [`Error`].
+//!
+//! This module is enabled by the `http` feature.
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::*;
+//! use hyper_util::{client::legacy::Client, rt::TokioExecutor};
+//!
+//! # async fn run() {
+//! let hyper_client = Client::builder(TokioExecutor::new()).build_http::<String>();
+//! let svc = http::client(hyper_client).retry(http::RetryPolicy::new(3));
+//! let request = ::http::Request::get("http://example.com")
+//!     .body(String::new())
+//!     .unwrap();
+//! let response = svc.oneshot(request).await;
+//! # let _ = response;
+//! # }
+//! ```
+//!
+//! # Retries
+//!
+//! [`RetryPolicy`] is a [`retry::Policy`](crate::retry::Policy) for use with
+//! [`ServiceExt::retry`](crate::ServiceExt::retry). It retries, up to a maximum number of times,
+//! those responses for which [`is_retryable`] returns `true`:
+//!
+//! - Requests which failed to connect, as these were never sent.
+//! - Requests with an [idempotent](http::Method::is_idempotent) method which failed for any other
+//!   reason, or whose response has a `502 Bad Gateway`, `503 Service Unavailable` or
+//!   `504 Gateway Timeout` status.
+//!
+//! As [`http::Request`] isn't [`Clone`], each retry is rebuilt from the method, URI, version,
+//! headers and body of the original request. [Extensions](http::Extensions) are not preserved.
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`Client`] is the number of inflight [calls](Service::call).
+
+use std::{
+    fmt,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use http::{HeaderMap, Method, Request, Response, StatusCode, Uri, Version};
+use hyper::body::{Body, Incoming};
+use hyper_util::client::legacy::{connect::Connect, Client as LegacyClient, Error};
+
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    retry::Policy,
+    Service,
+};
+
+/// The [`Service`] returned by the [`client`] constructor.
+///
+/// See the [module](crate::http) for more information.
+#[derive(Debug)]
+pub struct Client<C, B> {
+    client: LegacyClient<C, B>,
+    pending: AtomicUsize,
+}
+
+/// The [`Service::Permit`] type for [`Client`].
+#[derive(Debug)]
+pub struct ClientPermit<'a, C, B> {
+    service: &'a Client<C, B>,
+}
+
+/// Decrements the pending count on drop, including on cancellation.
+struct Pending<'a>(&'a AtomicUsize);
+
+impl Drop for Pending<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Release);
+    }
+}
+
+impl<C, B> Service<Request<B>> for Client<C, B>
+where
+    C: Connect + Clone + Send + Sync + 'static,
+    B: Body + Send + 'static + Unpin,
+    B::Data: Send,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    type Response = Result<Response<Incoming>, Error>;
+    type Permit<'a> = ClientPermit<'a, C, B>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        ClientPermit { service: self }
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request<B>) -> Self::Response
+    where
+        Self: 'a,
+    {
+        let service = permit.service;
+        service.pending.fetch_add(1, Ordering::Release);
+        let _pending = Pending(&service.pending);
+        service.client.request(request).await
+    }
+}
+
+impl<C, B> Load for Client<C, B> {
+    type Metric = usize;
+
+    fn load(&self) -> Self::Metric {
+        self.pending.load(Ordering::Acquire)
+    }
+}
+
+impl<C, B> Describe for Client<C, B> {
+    fn describe(&self) -> StackNode {
+        StackNode::new("Client")
+    }
+}
+
+/// Constructs a [`Service`] from a [`hyper_util`] legacy [`Client`](LegacyClient).
+///
+/// See the [module](crate::http) for more details.
+pub fn client<C, B>(client: LegacyClient<C, B>) -> Client<C, B> {
+    Client {
+        client,
+        pending: AtomicUsize::new(0),
+    }
+}
+
+/// Returns whether a request should be retried given its response.
+///
+/// See the [module](crate::http#retries) for more details.
+pub fn is_retryable<T>(method: &Method, response: &Result<Response<T>, Error>) -> bool {
+    match response {
+        Err(error) => error.is_connect() || method.is_idempotent(),
+        Ok(response) => {
+            method.is_idempotent()
+                && matches!(
+                    response.status(),
+                    StatusCode::BAD_GATEWAY
+                        | StatusCode::SERVICE_UNAVAILABLE
+                        | StatusCode::GATEWAY_TIMEOUT
+                )
+        }
+    }
+}
+
+/// A [`retry::Policy`](crate::retry::Policy) for [`Client`].
+///
+/// See the [module](crate::http#retries) for more information.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    max_retries: usize,
+}
+
+impl RetryPolicy {
+    /// Constructs a [`RetryPolicy`] which retries each request at most `max_retries` times.
+    pub fn new(max_retries: usize) -> Self {
+        Self { max_retries }
+    }
+}
+
+/// The [`Policy::RequestState`] for [`RetryPolicy`].
+pub struct RetryState<B> {
+    method: Method,
+    uri: Uri,
+    version: Version,
+    headers: HeaderMap,
+    body: B,
+    remaining: usize,
+}
+
+impl<B> fmt::Debug for RetryState<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryState")
+            .field("method", &self.method)
+            .field("uri", &self.uri)
+            .field("version", &self.version)
+            .field("headers", &self.headers)
+            .field("remaining", &self.remaining)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<B> RetryState<B>
+where
+    B: Clone,
+{
+    fn request(&self) -> Request<B> {
+        let mut request = Request::new(self.body.clone());
+        *request.method_mut() = self.method.clone();
+        *request.uri_mut() = self.uri.clone();
+        *request.version_mut() = self.version;
+        *request.headers_mut() = self.headers.clone();
+        request
+    }
+}
+
+impl<S, B, T> Policy<S, Request<B>> for RetryPolicy
+where
+    S: Service<Request<B>, Response = Result<Response<T>, Error>>,
+    B: Clone,
+{
+    type RequestState<'a> = RetryState<B>;
+
+    fn create(&self, request: &Request<B>) -> Self::RequestState<'_> {
+        RetryState {
+            method: request.method().clone(),
+            uri: request.uri().clone(),
+            version: request.version(),
+            headers: request.headers().clone(),
+            body: request.body().clone(),
+            remaining: self.max_retries,
+        }
+    }
+
+    async fn classify<'a>(
+        &self,
+        mut state: Self::RequestState<'a>,
+        response: S::Response,
+    ) -> Result<S::Response, (Request<B>, Self::RequestState<'a>)> {
+        if state.remaining == 0 || !is_retryable(&state.method, &response) {
+            return Ok(response);
+        }
+        state.remaining -= 1;
+        tracing::trace!(remaining = state.remaining, "retrying request");
+        Err((state.request(), state))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::{Request, StatusCode};
+    use hyper_util::{client::legacy::Client, rt::TokioExecutor};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use crate::{load::Load, ServiceExt};
+
+    use super::{client, RetryPolicy};
+
+    #[tokio::test]
+    async fn retry_unavailable() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            for status in [
+                "503 Service Unavailable",
+                "503 Service Unavailable",
+                "200 OK",
+            ] {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = [0; 1024];
+                let _ = stream.read(&mut buf).await.unwrap();
+                let response =
+                    format!("HTTP/1.1 {status}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n");
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        let hyper_client = Client::builder(TokioExecutor::new()).build_http::<String>();
+        let svc = client(hyper_client).retry(RetryPolicy::new(2));
+        let request = Request::get(format!("http://{addr}/"))
+            .body(String::new())
+            .unwrap();
+        let response = svc.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(svc.load(), 0);
+    }
+}
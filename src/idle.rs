@@ -0,0 +1,310 @@
+//! Idle eviction removes stale services from the load balancers found in the
+//! [`balance`](crate::balance) module, protecting long-running processes from backends whose
+//! removal was missed by discovery.
+//!
+//! The [`evicting`] function wraps a [`Stream`] of [`Change`]s, such as those found in the
+//! [`discover`](crate::discover) module. Each inserted service is wrapped in a [`Tracked`], which
+//! records the time of its last successful [call](Service::call), and of the first call since.
+//! Every `timeout`, services which have been called without success since more than `timeout`
+//! before the most recent success across all services are removed from the balancer.
+//!
+//! Only services which have been called are evicted, so one which the balancer hasn't happened to
+//! pick, such as under low traffic, is retained. Staleness is also relative to the other services,
+//! so an idle balancer never evicts its services. An evicted service is inserted again if the
+//! wrapped [`Stream`] inserts its key again. Changes from the wrapped [`Stream`] are consumed as
+//! they arrive.
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::*;
+//! # use std::{future::ready, time::Duration};
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let double: fn(_) -> _ = |x: u32| ready(Ok::<_, ()>(2 * x));
+//! let changes = discover::fixed([
+//!     ("a", service_fn(double).pending_requests()),
+//!     ("b", service_fn(double).pending_requests()),
+//! ]);
+//! let changes = idle::evicting(changes, Duration::from_secs(60));
+//! let (svc, worker) = balance::p2c(changes);
+//! let response = tokio::select! {
+//!     response = svc.oneshot(5u32) => response,
+//!     _ = worker => unreachable!(),
+//! };
+//! assert_eq!(response, Ok(10));
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`Tracked`] defers to the inner service.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    hash::Hash,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use futures_util::{stream, Stream, StreamExt};
+
+use crate::{
+    balance::Change,
+    describe::{Describe, StackNode},
+    load::Load,
+    rt, Service, TryService,
+};
+
+/// A wrapper [`Service`] recording the time of its last successful [call](Service::call), and of
+/// the first call since.
+///
+/// See the [module](crate::idle) for more information.
+#[derive(Debug)]
+pub struct Tracked<S> {
+    inner: S,
+    activity: Arc<Mutex<Activity>>,
+}
+
+impl<S> Tracked<S> {
+    /// Returns the time of the last successful [call](Service::call), if any.
+    pub fn last_success(&self) -> Option<Instant> {
+        self.activity.lock().unwrap().last_success
+    }
+}
+
+/// The calls made to a [`Tracked`] service.
+#[derive(Debug, Default)]
+struct Activity {
+    last_success: Option<Instant>,
+    /// The first call since the last success, if any.
+    unanswered_since: Option<Instant>,
+}
+
+/// The [`Service::Permit`] type for [`Tracked`].
+pub struct TrackedPermit<'a, S, Request>
+where
+    S: Service<Request> + 'a,
+{
+    inner: S::Permit<'a>,
+    activity: &'a Mutex<Activity>,
+}
+
+impl<'a, S, Request> fmt::Debug for TrackedPermit<'a, S, Request>
+where
+    S: Service<Request> + 'a,
+    S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TrackedPermit")
+            .field("inner", &self.inner)
+            .field("activity", &self.activity)
+            .finish()
+    }
+}
+
+impl<Request, S> Service<Request> for Tracked<S>
+where
+    S: TryService<Request>,
+{
+    type Response = S::Response;
+    type Permit<'a> = TrackedPermit<'a, S, Request>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        TrackedPermit {
+            inner: self.inner.acquire().await,
+            activity: &self.activity,
+        }
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        let TrackedPermit { inner, activity } = permit;
+        activity
+            .lock()
+            .unwrap()
+            .unanswered_since
+            .get_or_insert_with(Instant::now);
+        let response = S::call(inner, request).await;
+        if response.is_ok() {
+            let mut activity = activity.lock().unwrap();
+            activity.last_success = Some(Instant::now());
+            activity.unanswered_since = None;
+        }
+        response
+    }
+}
+
+impl<S> Load for Tracked<S>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S> Describe for Tracked<S>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("Tracked").with_child(self.inner.describe())
+    }
+}
+
+struct State<St, Key, S> {
+    changes: Pin<Box<St>>,
+    members: HashMap<Key, Arc<Mutex<Activity>>>,
+    pending: VecDeque<Change<Key, Tracked<S>>>,
+    timeout: Duration,
+    next_check: Option<Instant>,
+}
+
+impl<St, Key, S> State<St, Key, S>
+where
+    Key: Hash + Eq + Clone,
+{
+    /// Applies a [`Change`] from the wrapped [`Stream`].
+    fn apply(&mut self, change: Change<Key, S>) {
+        match change {
+            Change::Insert(key, service) => {
+                let activity = Arc::new(Mutex::new(Activity::default()));
+                self.members.insert(key.clone(), activity.clone());
+                let service = Tracked {
+                    inner: service,
+                    activity,
+                };
+                self.pending.push_back(Change::Insert(key, service));
+            }
+            Change::Remove(key) => {
+                // Evicted services have already been removed.
+                if self.members.remove(&key).is_some() {
+                    self.pending.push_back(Change::Remove(key));
+                }
+            }
+        }
+    }
+
+    /// Evicts every member which has been called without success, since long before the most
+    /// recent success.
+    fn evict(&mut self) {
+        let latest = self
+            .members
+            .values()
+            .filter_map(|activity| activity.lock().unwrap().last_success)
+            .max();
+        let Some(latest) = latest else {
+            return;
+        };
+        let timeout = self.timeout;
+        let pending = &mut self.pending;
+        self.members.retain(|key, activity| {
+            let unanswered_since = activity.lock().unwrap().unanswered_since;
+            let stale = unanswered_since
+                .is_some_and(|since| latest.saturating_duration_since(since) > timeout);
+            if stale {
+                tracing::debug!("evicting idle service");
+                pending.push_back(Change::Remove(key.clone()));
+            }
+            !stale
+        });
+    }
+}
+
+/// Wraps a [`Stream`] of [`Change`]s, removing services which have been called without success
+/// since more than `timeout` before the most recent success across all services.
+///
+/// See the [module](crate::idle) for more information.
+pub fn evicting<St, Key, S>(
+    changes: St,
+    timeout: Duration,
+) -> impl Stream<Item = Change<Key, Tracked<S>>>
+where
+    St: Stream<Item = Change<Key, S>>,
+    Key: Hash + Eq + Clone,
+{
+    let state = State {
+        changes: Box::pin(changes),
+        members: HashMap::new(),
+        pending: VecDeque::new(),
+        timeout,
+        next_check: None,
+    };
+    stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(change) = state.pending.pop_front() {
+                return Some((change, state));
+            }
+            // The first check is scheduled when first polled, rather than on construction.
+            let next_check = *state
+                .next_check
+                .get_or_insert_with(|| Instant::now() + state.timeout);
+            tokio::select! {
+                change = state.changes.next() => state.apply(change?),
+                _ = rt::sleep_until(next_check) => {
+                    state.next_check = Some(Instant::now() + state.timeout);
+                    state.evict();
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{pin::pin, time::Duration};
+
+    use futures_util::{stream, StreamExt};
+    use tokio::time::timeout;
+
+    use crate::{balance::Change, service_fn, ServiceExt};
+
+    use super::evicting;
+
+    #[tokio::test]
+    async fn evict_stale() {
+        let inserts = (0..3).map(|key| {
+            let svc = service_fn(move |x: u32| async move {
+                if key == 0 {
+                    Err(())
+                } else {
+                    Ok(x)
+                }
+            });
+            Change::Insert(key, svc)
+        });
+        let changes = stream::iter(inserts).chain(stream::pending());
+        let mut changes = pin!(evicting(changes, Duration::from_millis(10)));
+
+        let Some(Change::Insert(0, stale)) = changes.next().await else {
+            panic!("expected insert");
+        };
+        let Some(Change::Insert(1, active)) = changes.next().await else {
+            panic!("expected insert");
+        };
+        let Some(Change::Insert(2, _uncalled)) = changes.next().await else {
+            panic!("expected insert");
+        };
+        stale.oneshot(1).await.unwrap_err();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        active.oneshot(1).await.unwrap();
+        assert!(active.last_success().is_some());
+
+        assert!(matches!(changes.next().await, Some(Change::Remove(0))));
+        assert!(stale.last_success().is_none());
+
+        // A service which hasn't been called isn't evicted.
+        let next = timeout(Duration::from_millis(30), changes.next()).await;
+        assert!(next.is_err());
+    }
+}
@@ -0,0 +1,127 @@
+//! The de-duplication of concurrent calls with equal requests, shared by the
+//! [`cache`](crate::cache) and [`singleflight`](crate::singleflight) modules.
+
+use std::{collections::HashMap, hash::Hash, sync::Mutex};
+
+use tokio::sync::watch;
+
+use crate::Service;
+
+/// The calls in flight, keyed by request, each receiving its response once available.
+pub(crate) type Inflight<Request, Response> = HashMap<Request, watch::Receiver<Option<Response>>>;
+
+/// State, behind a [`Mutex`], which includes the [`Inflight`] calls.
+pub(crate) trait InflightState<Request, Response> {
+    fn inflight(&mut self) -> &mut Inflight<Request, Response>;
+}
+
+impl<Request, Response> InflightState<Request, Response> for Inflight<Request, Response> {
+    fn inflight(&mut self) -> &mut Inflight<Request, Response> {
+        self
+    }
+}
+
+/// How a call proceeds, given its request.
+pub(crate) enum Lookup<'a, T, Request, Response>
+where
+    T: InflightState<Request, Response>,
+    Request: Hash + Eq,
+{
+    /// The response is already available.
+    Hit(Response),
+    /// Wait for the response of an equal call in flight.
+    Wait(watch::Receiver<Option<Response>>),
+    /// Forward the call to the inner service, on behalf of any equal calls which follow.
+    Lead(Leader<'a, T, Request, Response>),
+}
+
+/// Joins the call in flight with an equal request, otherwise registers the caller as its leader.
+///
+/// The `state` must be that locked by `mutex`.
+pub(crate) fn join<'a, T, Request, Response>(
+    mutex: &'a Mutex<T>,
+    state: &mut T,
+    request: &Request,
+) -> Lookup<'a, T, Request, Response>
+where
+    T: InflightState<Request, Response>,
+    Request: Hash + Eq + Clone,
+{
+    let inflight = state.inflight();
+    if let Some(receiver) = inflight.get(request) {
+        return Lookup::Wait(receiver.clone());
+    }
+    let (sender, receiver) = watch::channel(None);
+    inflight.insert(request.clone(), receiver);
+    Lookup::Lead(Leader {
+        state: mutex,
+        request: request.clone(),
+        sender,
+    })
+}
+
+/// The call forwarded to the inner service on behalf of equal concurrent calls. Withdraws from the
+/// in flight calls on drop, including on cancellation.
+pub(crate) struct Leader<'a, T, Request, Response>
+where
+    T: InflightState<Request, Response>,
+    Request: Hash + Eq,
+{
+    state: &'a Mutex<T>,
+    request: Request,
+    sender: watch::Sender<Option<Response>>,
+}
+
+impl<T, Request, Response> Drop for Leader<'_, T, Request, Response>
+where
+    T: InflightState<Request, Response>,
+    Request: Hash + Eq,
+{
+    fn drop(&mut self) {
+        self.state.lock().unwrap().inflight().remove(&self.request);
+    }
+}
+
+/// Calls the inner service, or waits for an equal call in flight, as directed by `lookup`. The
+/// leader's response is passed to `complete` before it's shared with the waiting calls.
+///
+/// The inner `permit` is released while waiting. If the call waited on is cancelled then this takes
+/// its place, acquiring a new permit.
+pub(crate) async fn call<'a, S, Request, T>(
+    inner: &'a S,
+    permit: S::Permit<'a>,
+    request: Request,
+    lookup: impl Fn(&Request) -> Lookup<'a, T, Request, S::Response>,
+    complete: impl FnOnce(&Request, &S::Response),
+) -> S::Response
+where
+    S: Service<Request>,
+    S::Response: Clone,
+    T: InflightState<Request, S::Response> + 'a,
+    Request: Hash + Eq,
+{
+    let mut permit = Some(permit);
+    loop {
+        match lookup(&request) {
+            Lookup::Hit(response) => return response,
+            Lookup::Wait(mut receiver) => {
+                // Release the inner permit while waiting.
+                permit = None;
+                if let Ok(response) = receiver.wait_for(Option::is_some).await {
+                    return response.clone().expect("checked above");
+                }
+                // The in flight call was cancelled, take its place.
+            }
+            Lookup::Lead(leader) => {
+                let permit = match permit.take() {
+                    Some(permit) => permit,
+                    None => inner.acquire().await,
+                };
+                let response = S::call(permit, request).await;
+                complete(&leader.request, &response);
+                leader.sender.send_replace(Some(response.clone()));
+                return response;
+            }
+        }
+    }
+}
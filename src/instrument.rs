@@ -0,0 +1,172 @@
+//! The [`ServiceExt::instrument`](crate::ServiceExt::instrument) combinator returns
+//! [`Instrument`], which instruments a service using [`tracing`].
+//!
+//! The [`Service::acquire`] on [`Instrument`] is instrumented with an `acquire` span, as the request
+//! isn't yet known, and emits an event recording how long it waited. The [`Service::call`] is
+//! instrumented with the [`Span`] returned by the specified closure, which is passed the request,
+//! and emits an event recording the `latency` and `outcome` of the call. The `outcome` is
+//! `completed` when the inner call resolves and `cancelled` when it's dropped before doing so.
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::*;
+//! use tracing::info_span;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc = service_fn(|x: u32| async move { x.to_string() })
+//!     .instrument(|x: &u32| info_span!("request", x));
+//! let response = svc.oneshot(7).await;
+//! assert_eq!(response, "7");
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`Instrument`] defers to the inner service.
+
+use std::{any, fmt, time::Instant};
+
+use tracing::{Instrument as _, Span};
+
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Middleware, Service,
+};
+
+/// A wrapper [`Service`] for the [`ServiceExt::instrument`](crate::ServiceExt::instrument)
+/// combinator.
+///
+/// See the [module](crate::instrument) for more information.
+#[derive(Clone, Debug)]
+pub struct Instrument<S, F> {
+    inner: S,
+    closure: F,
+}
+
+impl<S, F> Instrument<S, F> {
+    pub(crate) fn new(inner: S, closure: F) -> Self {
+        Self { inner, closure }
+    }
+}
+
+/// The [`Service::Permit`] type for [`Instrument`].
+pub struct InstrumentPermit<'a, S, F, Request>
+where
+    S: Service<Request> + 'a,
+{
+    inner: S::Permit<'a>,
+    closure: &'a F,
+}
+
+impl<'a, S, F, Request> fmt::Debug for InstrumentPermit<'a, S, F, Request>
+where
+    S: Service<Request>,
+    S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InstrumentPermit")
+            .field("inner", &self.inner)
+            .field("closure", &format_args!("{}", any::type_name::<F>()))
+            .finish()
+    }
+}
+
+/// Emits the outcome of a call, as `cancelled` if dropped before completion.
+struct Outcome {
+    start: Instant,
+    completed: bool,
+}
+
+impl Drop for Outcome {
+    fn drop(&mut self) {
+        let latency = self.start.elapsed();
+        if self.completed {
+            tracing::debug!(?latency, outcome = "completed", "call finished");
+        } else {
+            tracing::debug!(?latency, outcome = "cancelled", "call finished");
+        }
+    }
+}
+
+impl<Request, S, F> Service<Request> for Instrument<S, F>
+where
+    S: Service<Request>,
+    F: Fn(&Request) -> Span,
+{
+    type Response = S::Response;
+    type Permit<'a> = InstrumentPermit<'a, S, F, Request>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        let start = Instant::now();
+        let inner = async {
+            let inner = self.inner.acquire().await;
+            tracing::trace!(wait = ?start.elapsed(), "acquired permit");
+            inner
+        }
+        .instrument(tracing::trace_span!("acquire"))
+        .await;
+        InstrumentPermit {
+            inner,
+            closure: &self.closure,
+        }
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        let InstrumentPermit { inner, closure } = permit;
+        let span = closure(&request);
+        async move {
+            let mut outcome = Outcome {
+                start: Instant::now(),
+                completed: false,
+            };
+            let response = S::call(inner, request).await;
+            outcome.completed = true;
+            response
+        }
+        .instrument(span)
+        .await
+    }
+}
+
+impl<S, F> Load for Instrument<S, F>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S, F> Describe for Instrument<S, F>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("Instrument").with_child(self.inner.describe())
+    }
+}
+
+impl<S, T, F> Middleware<S> for Instrument<T, F>
+where
+    T: Middleware<S>,
+{
+    type Service = Instrument<T::Service, F>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self { inner, closure } = self;
+        Instrument {
+            inner: inner.apply(svc),
+            closure,
+        }
+    }
+}
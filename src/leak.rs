@@ -1,7 +1,45 @@
 //! The [`ServiceExt::leak`](crate::ServiceExt::leak) combinator returns [`Leak`], which extends
 //! the lifetime of the [`Service::Permit`].
 //!
-//! This can be only called on [services](Service) within an [`Arc`].
+//! This can be only called on [services](Service) within an [`Arc`]. Each [`LeakPermit`] holds a
+//! clone of the [`Arc`], so the service lives at least as long as its permits, even once the
+//! [`Leak`] itself has been dropped. The lifetime `'t` can't exceed that of the service, so a
+//! permit can't outlive data the service borrows:
+//!
+//! ```rust,compile_fail,E0597
+//! use burger::*;
+//! # use std::sync::Arc;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let permit = {
+//!     let offset = 4;
+//!     let svc = Arc::new(service_fn(|x: u32| {
+//!         let offset = &offset;
+//!         async move { x + offset }
+//!     }))
+//!     .leak();
+//!     svc.acquire().await
+//! };
+//! # }
+//! ```
+//!
+//! Relatedly, [`ServiceExt::acquire_owned`](crate::ServiceExt::acquire_owned) acquires an
+//! [`OwnedPermit`] from a [`Service`] within an [`Arc`]. The [`OwnedPermit`] is a named type,
+//! holding the service alive, which can be stored or moved into a spawned task. Similarly,
+//! [`ServiceExt::oneshot_owned`](crate::ServiceExt::oneshot_owned) acquires and then immediately
+//! calls.
+//!
+//! When used as a [`Middleware`], [`Leak`] moves the inner middleware out of its [`Arc`], so
+//! [`Middleware::apply`] panics if the [`Arc`] is shared, for example by an outstanding
+//! [`LeakPermit`].
+//!
+//! # Safety
+//!
+//! A [`Service::Permit`] may borrow from its service, so extending its lifetime requires `unsafe`
+//! code. This is confined to acquiring a [`LeakPermit`], which holds the borrowed [`Arc`] alongside
+//! the permit, and releases the permit before the [`Arc`]. The [`OwnedPermit`] is built upon
+//! [`LeakPermit`] and contains no further `unsafe` code.
 //!
 //! # Example
 //!
@@ -9,44 +47,76 @@
 //! use burger::*;
 //! # use std::sync::Arc;
 //!
+//! # #[cfg(feature = "tokio")]
 //! # #[tokio::main]
 //! # async fn main() {
 //! let svc = Arc::new(service_fn(|x| async move { x + 4 })).leak();
 //! let response = svc.oneshot(3u32).await;
 //! assert_eq!(7, response);
+//!
+//! let svc = Arc::new(service_fn(|x| async move { x + 4 }).concurrency_limit(1));
+//! let permit = svc.clone().acquire_owned().await;
+//! drop(svc);
+//! let response = permit.call(3u32).await;
+//! assert_eq!(7, response);
 //! # }
+//! # #[cfg(not(feature = "tokio"))]
+//! # fn main() {}
 //! ```
 //!
 //! # Load
 //!
 //! The [`Load::load`] on [`Leak`] defers to the inner service.
 
-use std::{fmt, sync::Arc};
+use alloc::sync::Arc;
+use core::{fmt, marker::PhantomData};
 
-use crate::{load::Load, Service};
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Middleware, Service,
+};
 
 /// A wrapper [`Service`] for the [`ServiceExt::leak`](crate::ServiceExt::leak) combinator.
 ///
 /// See the [module](crate::leak) for more information.
 #[derive(Debug)]
 pub struct Leak<'t, S> {
-    _ref: &'t (),
+    _lifetime: PhantomData<&'t ()>,
     inner: Arc<S>,
 }
 
 impl<'t, S> Leak<'t, S> {
     pub(crate) fn new(inner: Arc<S>) -> Leak<'t, S> {
-        Leak { _ref: &(), inner }
+        Leak {
+            _lifetime: PhantomData,
+            inner,
+        }
+    }
+
+    /// Returns a reference to the inner service.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn get_ref(&self) -> &S {
+        &self.inner
     }
 }
 
 /// The [`Service::Permit`] type for [`Leak`].
+///
+/// This holds a permit, whose lifetime has been extended, alongside the [`Arc`] it was acquired
+/// from. This is sound as:
+///
+/// 1. The service is within an [`Arc`], so doesn't move or drop while this holds a clone.
+/// 2. The permit is declared before the [`Arc`], so is dropped first.
+/// 3. The permit is private and only consumed when calling the service, which holds the [`Arc`]
+///    until the call completes or is cancelled.
 pub struct LeakPermit<'t, S, Request>
 where
     S: Service<Request> + 't,
 {
-    _svc: Arc<S>,
+    // NOTE: The permit must be dropped before the service it borrows from.
     inner: S::Permit<'t>,
+    _svc: Arc<S>,
 }
 
 impl<'t, S, Request> fmt::Debug for LeakPermit<'t, S, Request>
@@ -56,12 +126,38 @@ where
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("LeakPermit")
-            .field("_svc", &self._svc)
             .field("inner", &self.inner)
+            .field("_svc", &self._svc)
             .finish()
     }
 }
 
+impl<'t, S, Request> LeakPermit<'t, S, Request>
+where
+    S: Service<Request> + 't,
+{
+    /// Acquires a permit from the service, extending its lifetime to `'t`.
+    async fn acquire(service: Arc<S>) -> Self {
+        let inner = service.acquire().await;
+        // SAFETY: The types differ only by lifetime, and the permit is stored alongside `service`,
+        // see the invariants documented on `LeakPermit`.
+        let inner = unsafe { core::mem::transmute::<S::Permit<'_>, S::Permit<'t>>(inner) };
+        Self {
+            inner,
+            _svc: service,
+        }
+    }
+
+    /// Consumes the permit to call the service.
+    async fn call(self, request: Request) -> S::Response {
+        let Self { inner, _svc } = self;
+        // `_svc` is declared before the call's future, so is dropped after it, even if cancelled.
+        let response = S::call(inner, request).await;
+        drop(_svc);
+        response
+    }
+}
+
 impl<'t, Request, S> Service<Request> for Leak<'t, S>
 where
     S: Service<Request> + 't,
@@ -72,19 +168,14 @@ where
         S: 'a, 't: 'a;
 
     async fn acquire(&self) -> Self::Permit<'_> {
-        LeakPermit {
-            _svc: self.inner.clone(),
-            inner: unsafe {
-                std::mem::transmute::<S::Permit<'_>, S::Permit<'t>>(self.inner.acquire().await)
-            },
-        }
+        LeakPermit::acquire(self.inner.clone()).await
     }
 
     async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
     where
         Self: 'a,
     {
-        S::call(permit.inner, request).await
+        permit.call(request).await
     }
 }
 
@@ -98,3 +189,160 @@ where
         self.inner.load()
     }
 }
+
+impl<S> Describe for Leak<'_, S>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("Leak").with_child(self.inner.describe())
+    }
+}
+
+impl<'t, S, T> Middleware<S> for Leak<'t, T>
+where
+    T: Middleware<S>,
+{
+    type Service = Leak<'t, T::Service>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self { inner, .. } = self;
+        let inner = Arc::into_inner(inner).expect("middleware is not shared");
+        Leak::new(Arc::new(inner.apply(svc)))
+    }
+}
+
+/// An owned [`Service::Permit`], returned by
+/// [`ServiceExt::acquire_owned`](crate::ServiceExt::acquire_owned).
+///
+/// See the [module](crate::leak) for more information.
+pub struct OwnedPermit<S, Request>
+where
+    S: Service<Request> + 'static,
+{
+    inner: LeakPermit<'static, S, Request>,
+}
+
+impl<S, Request> fmt::Debug for OwnedPermit<S, Request>
+where
+    S: Service<Request> + fmt::Debug + 'static,
+    for<'a> S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OwnedPermit")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<S, Request> OwnedPermit<S, Request>
+where
+    S: Service<Request> + 'static,
+{
+    pub(crate) async fn acquire(service: Arc<S>) -> Self {
+        Self {
+            inner: LeakPermit::acquire(service).await,
+        }
+    }
+
+    /// Consumes the permit to [call](Service::call) the service.
+    pub async fn call(self, request: Request) -> S::Response {
+        self.inner.call(request).await
+    }
+}
+
+// These run under Miri, checking that the service outlives its permits, including when a call is
+// cancelled.
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use alloc::sync::Arc;
+    use core::{
+        future::pending,
+        sync::atomic::{AtomicBool, Ordering},
+    };
+
+    use futures_util::FutureExt;
+
+    use crate::{Service, ServiceExt};
+
+    use super::Leak;
+
+    /// A service whose permit borrows it, checking on drop that the service is still alive.
+    struct Checked {
+        dropped: Arc<AtomicBool>,
+    }
+
+    impl Drop for Checked {
+        fn drop(&mut self) {
+            self.dropped.store(true, Ordering::SeqCst);
+        }
+    }
+
+    struct CheckedPermit<'a>(&'a Checked);
+
+    impl Drop for CheckedPermit<'_> {
+        fn drop(&mut self) {
+            assert!(!self.0.dropped.load(Ordering::SeqCst));
+        }
+    }
+
+    impl Service<bool> for Checked {
+        type Response = ();
+        type Permit<'a> = CheckedPermit<'a>;
+
+        async fn acquire(&self) -> Self::Permit<'_> {
+            CheckedPermit(self)
+        }
+
+        async fn call(permit: Self::Permit<'_>, block: bool) -> Self::Response {
+            if block {
+                pending::<()>().await;
+            }
+            drop(permit);
+        }
+    }
+
+    fn checked() -> (Arc<Checked>, Arc<AtomicBool>) {
+        let dropped = Arc::new(AtomicBool::new(false));
+        let svc = Checked {
+            dropped: dropped.clone(),
+        };
+        (Arc::new(svc), dropped)
+    }
+
+    #[tokio::test]
+    async fn owned_permit_outlives_handle() {
+        let (svc, dropped) = checked();
+        let permit = svc.acquire_owned().await;
+        assert!(!dropped.load(Ordering::SeqCst));
+        permit.call(false).await;
+        assert!(dropped.load(Ordering::SeqCst));
+
+        // Dropped without calling.
+        let (svc, dropped) = checked();
+        drop(svc.acquire_owned().await);
+        assert!(dropped.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn leak_permit_outlives_service() {
+        let (svc, dropped) = checked();
+        let svc = svc.leak();
+        let permit = svc.acquire().await;
+        drop(svc);
+        assert!(!dropped.load(Ordering::SeqCst));
+        Leak::<Checked>::call(permit, false).await;
+        assert!(dropped.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn cancelled_call() {
+        let (svc, dropped) = checked();
+        let permit = svc.acquire_owned().await;
+        let mut call = Box::pin(permit.call(true));
+        assert!((&mut call).now_or_never().is_none());
+        assert!(!dropped.load(Ordering::SeqCst));
+        drop(call);
+        assert!(dropped.load(Ordering::SeqCst));
+    }
+}
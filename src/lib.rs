@@ -1,3 +1,4 @@
+#![cfg_attr(not(feature = "std"), no_std)]
 #![allow(async_fn_in_trait)]
 #![deny(missing_docs, missing_debug_implementations)]
 
@@ -11,6 +12,10 @@
 //! combinators to modify a [`Service`]. Both the combinators and constructors each have an
 //! associated module containing related documentation, traits, and types.
 //!
+//! [`Service`], [`Load`](load::Load) and [`Describe`](describe::Describe) are also implemented for
+//! references, [`Box`], [`Rc`] and [`Arc`] of a service, deferring to the service. As [`Service`] is not object safe, services of
+//! differing types are stored uniformly using [`ServiceExt::boxed`].
+//!
 //! # Example
 //!
 //! ```rust
@@ -18,6 +23,7 @@
 //! # use tokio::time::sleep;
 //! # use std::time::Duration;
 //!
+//! # #[cfg(feature = "tokio")]
 //! # #[tokio::main]
 //! # async fn main() {
 //! let svc = service_fn(|x| async move {
@@ -31,8 +37,36 @@
 //! let response = svc.oneshot(30).await;
 //! assert_eq!(Ok(63), response);
 //! # }
+//! # #[cfg(not(feature = "tokio"))]
+//! # fn main() {}
 //! ```
 //!
+//! # Runtimes
+//!
+//! The synchronization primitives used by this crate, from [`tokio::sync`], work with any executor.
+//! Timers, as used by [`ServiceExt::retry`] with [backoff](retry::backoff),
+//! [`ServiceExt::rate_limit`], [`ServiceExt::delay`], [`health::checked`] and others, default to
+//! [`tokio::time`] and so require a Tokio runtime with the time driver enabled. Enabling the
+//! `futures-timer` feature switches these to
+//! [`futures-timer`](https://docs.rs/futures-timer), allowing use with other executors, such as
+//! `async-std` or `smol`.
+//!
+//! The [`ServiceExt::spawned`] combinator and the DNS source in [`discover`] always require Tokio.
+//!
+//! # Features
+//!
+//! Middleware built on [`tokio`], such as [`ServiceExt::concurrency_limit`], [`ServiceExt::buffer`]
+//! and the [`balance`] module, is enabled by the default `tokio` feature. The `std` feature, implied
+//! by `tokio`, enables [`ServiceExt::retry`], [`ServiceExt::instrument`], [`ServiceExt::metrics`],
+//! [`ServiceExt::peak_ewma`] and [`router`](fn@router).
+//!
+//! With default features disabled, the crate is `no_std` and requires only `alloc`. The [`Service`],
+//! [`ServiceExt`] and [`Middleware`] traits remain, along with the combinators and constructors
+//! which don't depend on a runtime, such as [`ServiceExt::map`], [`ServiceExt::then`],
+//! [`ServiceExt::left`], [`steer`](fn@steer), [`select`](fn@select) and
+//! [`service_fn`](fn@service_fn). These can be driven by any executor, including those for
+//! embedded targets.
+//!
 //! # Usage
 //!
 //! A typical [`Service`] will consist of distinct layers, each providing specific dynamics. The
@@ -47,48 +81,233 @@
 //! mermaid.initialize(config);
 //! </script>
 
+extern crate alloc;
+
+#[cfg(feature = "tokio")]
+pub mod adaptive_concurrency;
+#[cfg(feature = "std")]
+pub mod admission;
+pub mod and_then;
+#[cfg(feature = "tokio")]
 pub mod balance;
+#[cfg(feature = "tokio")]
+pub mod blocking;
+pub mod boxed;
+#[cfg(feature = "tokio")]
 pub mod buffer;
+#[cfg(feature = "tokio")]
+pub mod cache;
+#[cfg(feature = "cancellation")]
+pub mod cancel;
 #[cfg(feature = "compat")]
 pub mod compat;
+#[cfg(feature = "tokio")]
 pub mod concurrency_limit;
+#[cfg(feature = "config")]
+pub mod config;
+pub mod context;
+#[cfg(feature = "tokio")]
+pub mod delay;
 pub mod depressurize;
+pub mod describe;
+#[cfg(feature = "tokio")]
+pub mod discover;
+#[cfg(feature = "tokio")]
+pub mod dispatch;
+#[cfg(feature = "tokio")]
+pub mod drain;
 pub mod either;
+pub mod err_into;
+pub mod error;
+pub mod fallback;
+pub mod fanout;
+#[cfg(feature = "test-util")]
+pub mod fault;
+pub mod filter;
+pub mod flatten_err;
+#[cfg(feature = "tokio")]
+pub mod health;
+pub mod hooks;
+#[cfg(feature = "http")]
+pub mod http;
+#[cfg(feature = "tokio")]
+pub mod idle;
+#[cfg(feature = "tokio")]
+mod inflight;
+#[cfg(feature = "std")]
+pub mod instrument;
 pub mod leak;
+#[cfg(feature = "tokio")]
+pub mod limit;
 pub mod load;
 pub mod load_shed;
+#[cfg(feature = "tokio")]
+pub mod make;
 pub mod map;
+pub mod map_err;
+pub mod map_ok;
+pub mod map_request;
+#[cfg(feature = "std")]
+pub mod metrics;
+#[cfg(feature = "tokio")]
+pub mod mirror;
+#[cfg(feature = "test-util")]
+pub mod mock;
+pub mod or_else;
+#[cfg(feature = "tokio")]
+pub mod pipeline;
+#[cfg(feature = "tokio")]
+pub mod priority;
+#[cfg(feature = "tokio")]
 pub mod rate_limit;
+#[cfg(feature = "std")]
+mod random;
+#[cfg(feature = "tokio")]
+pub mod ready_cache;
+#[cfg(feature = "std")]
 pub mod retry;
+#[cfg(feature = "std")]
+pub mod router;
+#[cfg(feature = "tokio")]
+mod rt;
+#[cfg(feature = "std")]
+pub mod scope;
 pub mod select;
 pub mod service_fn;
+#[cfg(feature = "tokio")]
+pub mod shared_mut;
+#[cfg(feature = "tokio")]
+pub mod singleflight;
+#[cfg(feature = "tokio")]
+pub mod spawn;
 pub mod steer;
+#[cfg(feature = "tokio")]
+pub mod streaming;
 pub mod then;
+pub mod then_request;
+#[cfg(feature = "tokio")]
+pub mod token_bucket;
+pub mod unwrap_or_else;
+#[cfg(feature = "tokio")]
+pub mod worker;
 
-use std::{convert::Infallible, sync::Arc, time::Duration};
+use alloc::{boxed::Box, rc::Rc, sync::Arc, vec::Vec};
+use core::convert::Infallible;
+#[cfg(feature = "std")]
+use core::time::Duration;
 
+#[cfg(feature = "tokio")]
+use adaptive_concurrency::{AdaptiveConcurrency, Aimd};
+#[cfg(feature = "std")]
+use admission::{Admit, Throttle};
+use and_then::AndThen;
+#[cfg(feature = "tokio")]
+use blocking::{BlockOn, Blocking, SyncService};
+use boxed::BoxService;
+#[cfg(feature = "tokio")]
 use buffer::Buffer;
+#[cfg(feature = "tokio")]
+use cache::Cache;
+#[cfg(feature = "cancellation")]
+use cancel::WithCancellation;
+#[cfg(feature = "tokio")]
 use concurrency_limit::ConcurrencyLimit;
+use context::{WithContext, WithoutContext};
+#[cfg(feature = "tokio")]
+use delay::{Delay, DelayUntil};
 use depressurize::Depressurize;
+use describe::{Describe, StackNode};
+#[cfg(feature = "tokio")]
+use drain::{Drain, DrainHandle};
 use either::Either;
-use leak::Leak;
-use load::{Load, PendingRequests};
-use load_shed::LoadShed;
+use err_into::ErrInto;
+use fallback::Fallback;
+#[cfg(feature = "test-util")]
+use fault::{FaultConfig, InjectFaults};
+use filter::{AsyncFilter, Filter};
+use flatten_err::FlattenErr;
+use hooks::WithHooks;
+#[cfg(feature = "std")]
+use instrument::Instrument;
+use leak::{Leak, OwnedPermit};
+#[cfg(feature = "tokio")]
+use load::WatchLoad;
+#[cfg(feature = "std")]
+use load::{AverageLatency, PeakEwma, SuccessRate};
+use load::{ComposeLoad, ConstantLoad, Load, MapLoad, PendingRequests};
+use load_shed::{LoadShed, LoadShedAfter, LoadShedWith};
 use map::Map;
-use rate_limit::RateLimit;
+use map_err::MapErr;
+use map_ok::MapOk;
+use map_request::MapRequest;
+#[cfg(feature = "std")]
+use metrics::Metrics;
+#[cfg(feature = "tokio")]
+use mirror::Mirror;
+use or_else::OrElse;
+#[cfg(feature = "tokio")]
+use priority::PriorityBuffer;
+#[cfg(feature = "tokio")]
+use rate_limit::{RateLimit, RateLimitPerKey};
+#[cfg(feature = "tokio")]
+use retry::backoff::WithBackoff;
+#[cfg(feature = "tokio")]
+use retry::failover::RetryWith;
+#[cfg(feature = "std")]
 use retry::Retry;
+#[cfg(feature = "tokio")]
+use singleflight::Singleflight;
+#[cfg(feature = "tokio")]
+use spawn::Spawned;
+#[cfg(feature = "tokio")]
+use streaming::{MapItems, PendingStreams, StreamConcurrencyLimit, ThenItems};
 use then::Then;
+use then_request::ThenRequest;
+#[cfg(feature = "tokio")]
+use token_bucket::TokenBucket;
+#[cfg(feature = "tokio")]
 use tokio::sync::{Mutex, RwLock};
+#[cfg(feature = "cancellation")]
+use tokio_util::sync::CancellationToken;
+use unwrap_or_else::UnwrapOrElse;
 
+#[cfg(feature = "tokio")]
+#[doc(inline)]
+pub use blocking::sync_service_fn;
 #[cfg(feature = "compat")]
 #[doc(inline)]
 pub use compat::compat;
+#[cfg(feature = "tokio")]
+#[doc(inline)]
+pub use dispatch::{dispatch, dispatch_ordered};
+#[doc(inline)]
+pub use error::Error;
+#[doc(inline)]
+pub use fanout::{fanout, fanout_tuple, quorum};
+#[cfg(feature = "tokio")]
+#[doc(inline)]
+pub use pipeline::pipeline;
+#[cfg(feature = "tokio")]
 #[doc(inline)]
-pub use select::select;
+pub use ready_cache::ready_cache;
+#[cfg(feature = "std")]
 #[doc(inline)]
-pub use service_fn::service_fn;
+pub use router::router;
+#[cfg(feature = "std")]
 #[doc(inline)]
-pub use steer::steer;
+pub use scope::scope;
+#[doc(inline)]
+pub use select::{select, select_tuple};
+#[doc(inline)]
+pub use service_fn::{service_fn, service_ref_fn};
+#[cfg(feature = "tokio")]
+#[doc(inline)]
+pub use shared_mut::shared_mut;
+#[doc(inline)]
+pub use steer::{steer, steer_array, steer_lazy, try_steer, try_steer_lazy};
+#[cfg(feature = "tokio")]
+#[doc(inline)]
+pub use worker::worker;
 
 /// An asynchronous function call, which can only be executed _after_ obtaining a permit.
 ///
@@ -104,6 +323,81 @@ pub use steer::steer;
 /// let response = ServiceFn::call(permit, 32).await;
 /// # }
 /// ```
+///
+/// # Disarming
+///
+/// A permit may be dropped without being passed to [`Service::call`], which disarms it. Disarming
+/// releases whatever the permit reserved, such as a concurrency slot, a buffered position or a rate
+/// limit token, leaving the service as though [`Service::acquire`] had never been called. Whether a
+/// permit is dropped explicitly, using [`ServiceExt::disarm`], or implicitly, for example when the
+/// caller is cancelled, makes no difference.
+///
+/// Similarly, cancelling [`Service::acquire`] or [`Service::call`], by dropping the future, must
+/// release any resources held and undo any accounting, such as the count of pending requests.
+///
+/// Every service in this crate upholds this, with the exception of the [`tower`] compatibility
+/// layer, as a [`tower::Service`] cannot be disarmed once ready.
+///
+/// # Writing middleware
+///
+/// [`Service::Permit`] is a named associated type, so middleware outside this crate can hold the
+/// permit of an inner service in its own permit type and bound it in where clauses, just as the
+/// combinators in this crate do. Only the futures returned by [`Service::acquire`] and
+/// [`Service::call`] are unnameable.
+///
+/// ```rust
+/// use burger::*;
+///
+/// struct Tagged<S> {
+///     inner: S,
+///     tag: &'static str,
+/// }
+///
+/// struct TaggedPermit<'a, S, Request>
+/// where
+///     S: Service<Request> + 'a,
+/// {
+///     inner: S::Permit<'a>,
+///     tag: &'static str,
+/// }
+///
+/// impl<Request, S> Service<Request> for Tagged<S>
+/// where
+///     S: Service<Request>,
+/// {
+///     type Response = (&'static str, S::Response);
+///     type Permit<'a> = TaggedPermit<'a, S, Request>
+///     where
+///         S: 'a;
+///
+///     async fn acquire(&self) -> Self::Permit<'_> {
+///         TaggedPermit {
+///             inner: self.inner.acquire().await,
+///             tag: self.tag,
+///         }
+///     }
+///
+///     async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+///     where
+///         S: 'a,
+///     {
+///         (permit.tag, S::call(permit.inner, request).await)
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let svc = Tagged {
+///     inner: service_fn(|x: u32| async move { x + 1 }),
+///     tag: "a",
+/// };
+/// let response = svc.oneshot(1).await;
+/// assert_eq!(response, ("a", 2));
+/// # }
+/// ```
+///
+/// [`tower`]: https://docs.rs/tower
+/// [`tower::Service`]: https://docs.rs/tower/latest/tower/trait.Service.html
 pub trait Service<Request> {
     /// The type produced by the service call.
     type Response;
@@ -118,126 +412,827 @@ pub trait Service<Request> {
     /// Consumes a permit to call the service.
     async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
     where
-        Self: 'a;
-}
+        Self: 'a;
+}
+
+/// An extension trait for [`Service`].
+pub trait ServiceExt<Request>: Service<Request> {
+    /// Acquires the [`Service::Permit`] and then immediately uses it to [call](Service::call) the
+    /// [`Service`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use burger::*;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let svc = service_fn(|x: usize| async move { x.to_string() });
+    /// let response = svc.oneshot(32).await;
+    /// # }
+    /// ```
+    async fn oneshot(&self, request: Request) -> Self::Response
+    where
+        Self: Sized,
+    {
+        let permit = self.acquire().await;
+        Self::call(permit, request).await
+    }
+
+    /// Disarms a permit without calling the service, releasing whatever it reserved.
+    ///
+    /// This is equivalent to dropping the permit, and exists to make the intent explicit. See
+    /// [disarming](Service#disarming) for more information.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "tokio")]
+    /// use burger::{concurrency_limit::ConcurrencyLimit, *};
+    /// # use futures::FutureExt;
+    ///
+    /// # #[cfg(feature = "tokio")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let svc = service_fn(|x: u32| async move { x }).concurrency_limit(1);
+    /// let permit = svc.acquire().await;
+    /// assert!(svc.acquire().now_or_never().is_none());
+    /// ConcurrencyLimit::disarm(permit);
+    /// assert_eq!(svc.oneshot(1).await, 1);
+    /// # }
+    /// # #[cfg(not(feature = "tokio"))]
+    /// # fn main() {}
+    /// ```
+    fn disarm(permit: Self::Permit<'_>) {
+        drop(permit);
+    }
+
+    /// Acquires a batch of [permits](Service::Permit), each of which may be used for one
+    /// [call](Service::call).
+    ///
+    /// This suits callers issuing several calls at once, such as over a pipelined connection. Each
+    /// permit is accounted for separately, so concurrency and rate limits apply to every call, and
+    /// unused permits are disarmed when dropped.
+    ///
+    /// # Deadlocks
+    ///
+    /// Permits are acquired in turn, holding those already acquired. This waits indefinitely if the
+    /// service can't grant `n` permits at once. Concurrent callers can also deadlock: two callers
+    /// each acquiring 2 permits from a [`ServiceExt::concurrency_limit`] of 3 may each hold one
+    /// while waiting for the last. Callers contending for the same permits should be serialized,
+    /// for example by holding an asynchronous mutex while acquiring, so that at most one batch is
+    /// partially acquired at a time.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "tokio")]
+    /// use burger::{concurrency_limit::ConcurrencyLimit, *};
+    /// # use futures::future::join_all;
+    ///
+    /// # #[cfg(feature = "tokio")]
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let svc = service_fn(|x: u32| async move { 2 * x }).concurrency_limit(3);
+    /// let permits = svc.acquire_many(2).await;
+    /// assert_eq!(svc.available_permits(), 1);
+    /// let calls = permits
+    ///     .into_iter()
+    ///     .zip([1, 2])
+    ///     .map(|(permit, x)| ConcurrencyLimit::call(permit, x));
+    /// assert_eq!(join_all(calls).await, [2, 4]);
+    /// assert_eq!(svc.available_permits(), 3);
+    /// # }
+    /// # #[cfg(not(feature = "tokio"))]
+    /// # fn main() {}
+    /// ```
+    async fn acquire_many(&self, n: usize) -> Vec<Self::Permit<'_>> {
+        // Bound the preallocation, as the batch may never be granted.
+        let mut permits = Vec::with_capacity(n.min(64));
+        for _ in 0..n {
+            permits.push(self.acquire().await);
+        }
+        permits
+    }
+
+    /// Acquires an [`OwnedPermit`], which holds the service alive rather than borrowing it.
+    ///
+    /// See the [module](leak) for more information.
+    async fn acquire_owned(self: Arc<Self>) -> OwnedPermit<Self, Request>
+    where
+        Self: Sized + 'static,
+    {
+        OwnedPermit::acquire(self).await
+    }
+
+    /// Acquires an [`OwnedPermit`] and then immediately uses it to [call](Service::call) the
+    /// [`Service`].
+    ///
+    /// See the [module](leak) for more information.
+    async fn oneshot_owned(self: Arc<Self>, request: Request) -> Self::Response
+    where
+        Self: Sized + 'static,
+    {
+        self.acquire_owned().await.call(request).await
+    }
+
+    /// Extends the service using a closure accepting [`Self::Response`](Service::Response) and
+    /// returning a [`Future`](std::future::Future).
+    ///
+    /// See the [module](then) for more information.
+    fn then<F>(self, closure: F) -> Then<Self, F>
+    where
+        Self: Sized,
+    {
+        Then::new(self, closure)
+    }
+
+    /// Extends the service using a closure accepting [Self::Response](Service::Response) and returning a
+    /// [`Future`](std::future::Future).
+    ///
+    /// See the [module](map) for more information.
+    fn map<F>(self, closure: F) -> Map<Self, F>
+    where
+        Self: Sized,
+    {
+        Map::new(self, closure)
+    }
+
+    /// Extends the service using a closure accepting a request and returning the request passed to
+    /// the inner service.
+    ///
+    /// See the [module](map_request) for more information.
+    fn map_request<F>(self, closure: F) -> MapRequest<Self, F>
+    where
+        Self: Sized,
+    {
+        MapRequest::new(self, closure)
+    }
+
+    /// Extends the service using a closure accepting a request and returning a
+    /// [`Future`](std::future::Future) resolving to the request passed to the inner service.
+    ///
+    /// See the [module](then_request) for more information.
+    fn then_request<F>(self, closure: F) -> ThenRequest<Self, F>
+    where
+        Self: Sized,
+    {
+        ThenRequest::new(self, closure)
+    }
+
+    /// Extends a service accepting a [`Cx`](context::Cx) using a closure populating the
+    /// [`Extensions`](context::Extensions) of each request.
+    ///
+    /// See the [module](context) for more information.
+    fn with_context<F>(self, closure: F) -> WithContext<Self, F>
+    where
+        Self: Sized,
+    {
+        WithContext::new(self, closure)
+    }
+
+    /// Extends the service to accept a [`Cx`](context::Cx), discarding its
+    /// [`Extensions`](context::Extensions).
+    ///
+    /// See the [module](context) for more information.
+    fn without_context(self) -> WithoutContext<Self>
+    where
+        Self: Sized,
+    {
+        WithoutContext::new(self)
+    }
+
+    /// Extends a [fallible service](TryService) using a closure accepting the
+    /// [`Ok`] variant of [Self::Response](Service::Response) and returning a
+    /// [`Future`](std::future::Future).
+    ///
+    /// See the [module](and_then) for more information.
+    fn and_then<F>(self, closure: F) -> AndThen<Self, F>
+    where
+        Self: Sized,
+    {
+        AndThen::new(self, closure)
+    }
+
+    /// Extends a [fallible service](TryService) using a closure accepting the
+    /// [`Err`] variant of [Self::Response](Service::Response) and returning a
+    /// [`Future`](std::future::Future).
+    ///
+    /// See the [module](or_else) for more information.
+    fn or_else<F>(self, closure: F) -> OrElse<Self, F>
+    where
+        Self: Sized,
+    {
+        OrElse::new(self, closure)
+    }
+
+    /// Extends a [fallible service](TryService) with a secondary service, which is called with
+    /// the request when the [`Err`] variant of [Self::Response](Service::Response) is returned.
+    ///
+    /// See the [module](fallback) for more information.
+    fn fallback<T>(self, secondary: T) -> Fallback<Self, T>
+    where
+        Self: Sized,
+    {
+        Fallback::new(self, secondary)
+    }
+
+    /// Extends a [fallible service](TryService) using a closure modifying the [`Ok`] variant of
+    /// [Self::Response](Service::Response).
+    ///
+    /// See the [module](map_ok) for more information.
+    fn map_ok<F>(self, closure: F) -> MapOk<Self, F>
+    where
+        Self: Sized,
+    {
+        MapOk::new(self, closure)
+    }
+
+    /// Extends a [fallible service](TryService) using a closure modifying the [`Err`] variant of
+    /// [Self::Response](Service::Response).
+    ///
+    /// See the [module](map_err) for more information.
+    fn map_err<F>(self, closure: F) -> MapErr<Self, F>
+    where
+        Self: Sized,
+    {
+        MapErr::new(self, closure)
+    }
+
+    /// Converts the [`Err`] variant of [Self::Response](Service::Response), from a
+    /// [fallible service](TryService), using [`Into`].
+    ///
+    /// See the [module](err_into) for more information.
+    fn err_into<E>(self) -> ErrInto<Self, E>
+    where
+        Self: Sized,
+    {
+        ErrInto::new(self)
+    }
+
+    /// Flattens a [fallible service](TryService) whose [`Ok`] variant is itself a [`Result`],
+    /// converting the inner [`Err`] variant using [`Into`].
+    ///
+    /// See the [module](flatten_err) for more information.
+    fn flatten_err(self) -> FlattenErr<Self>
+    where
+        Self: Sized,
+    {
+        FlattenErr::new(self)
+    }
+
+    /// Extends a [fallible service](TryService) using a closure converting the [`Err`] variant of
+    /// [Self::Response](Service::Response) into the [`Ok`] type.
+    ///
+    /// See the [module](unwrap_or_else) for more information.
+    fn unwrap_or_else<F>(self, closure: F) -> UnwrapOrElse<Self, F>
+    where
+        Self: Sized,
+    {
+        UnwrapOrElse::new(self, closure)
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Extends a [streaming service](StreamService) using a closure modifying each item of
+    /// [Self::Response](Service::Response).
+    ///
+    /// See the [module](streaming) for more information.
+    fn map_items<F>(self, closure: F) -> MapItems<Self, F>
+    where
+        Self: Sized,
+    {
+        MapItems::new(self, closure)
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Extends a [streaming service](StreamService) using a closure accepting each item of
+    /// [Self::Response](Service::Response) and returning a [`Future`](std::future::Future).
+    ///
+    /// See the [module](streaming) for more information.
+    fn then_items<F>(self, closure: F) -> ThenItems<Self, F>
+    where
+        Self: Sized,
+    {
+        ThenItems::new(self, closure)
+    }
+
+    /// Extends the service using a closure accepting a request and either returning the request
+    /// passed to the inner service or rejecting it.
+    ///
+    /// See the [module](filter) for more information.
+    fn filter<F>(self, closure: F) -> Filter<Self, F>
+    where
+        Self: Sized,
+    {
+        Filter::new(self, closure)
+    }
+
+    /// Extends the service using a closure accepting a request and returning a
+    /// [`Future`](std::future::Future) resolving to either the request passed to the inner service
+    /// or a rejection.
+    ///
+    /// See the [module](filter) for more information.
+    fn filter_async<F>(self, closure: F) -> AsyncFilter<Self, F>
+    where
+        Self: Sized,
+    {
+        AsyncFilter::new(self, closure)
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Applies a concurrency limit to the service with a specified number of permits.
+    ///
+    /// See [concurrency limit](concurrency_limit) module for more information.
+    fn concurrency_limit(self, n_permits: usize) -> ConcurrencyLimit<Self>
+    where
+        Self: Sized,
+    {
+        ConcurrencyLimit::new(self, n_permits)
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Applies a concurrency limit to the service, drawing permits from a custom
+    /// [`Permits`](limit::Permits) source.
+    ///
+    /// See the [limit](limit#permit-sources) module for more information.
+    fn concurrency_limit_with<P>(self, permits: P) -> ConcurrencyLimit<Self, P>
+    where
+        Self: Sized,
+        P: limit::Permits,
+    {
+        ConcurrencyLimit::with_permits(self, permits)
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Applies a concurrency limit to a [streaming service](StreamService), with a specified number
+    /// of permits, each held until the response stream has ended.
+    ///
+    /// See the [module](streaming) for more information.
+    fn stream_concurrency_limit(self, n_permits: usize) -> StreamConcurrencyLimit<Self>
+    where
+        Self: Sized,
+    {
+        StreamConcurrencyLimit::new(self, n_permits)
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Applies a concurrency limit to the service, which is automatically tuned using the
+    /// specified [`Aimd`] configuration.
+    ///
+    /// See [adaptive concurrency](adaptive_concurrency) module for more information.
+    fn adaptive_concurrency(self, config: Aimd) -> AdaptiveConcurrency<Self>
+    where
+        Self: Sized,
+    {
+        AdaptiveConcurrency::new(self, config)
+    }
+
+    /// Applies load shedding to the service.
+    ///
+    /// See [module](load_shed) for more information.
+    fn load_shed(self) -> LoadShed<Self>
+    where
+        Self: Sized,
+    {
+        LoadShed::new(self)
+    }
+
+    /// Applies load shedding to the service, responding to shed requests using a closure.
+    ///
+    /// See [module](load_shed) for more information.
+    fn load_shed_with<F>(self, closure: F) -> LoadShedWith<Self, F>
+    where
+        Self: Sized,
+    {
+        LoadShedWith::new(self, closure)
+    }
+
+    #[cfg(feature = "std")]
+    /// Consults an [`Admission`](admission::Admission) before acquiring from the service, rejecting
+    /// unadmitted requests, and classifying responses as successes using a closure.
+    ///
+    /// See the [module](admission) for more information.
+    fn admit<A, F>(self, admission: A, classify: F) -> Admit<Self, A, F>
+    where
+        Self: Sized,
+    {
+        Admit::new(self, admission, classify)
+    }
+
+    #[cfg(feature = "std")]
+    /// Sheds requests with a probability which rises as the recent ratio of successes to requests
+    /// falls, classifying responses as successes using a closure.
+    ///
+    /// See the [module](admission#adaptive-throttling) for more information.
+    fn auto_shed<F>(
+        self,
+        multiplier: f64,
+        window: Duration,
+        classify: F,
+    ) -> Admit<Self, Throttle, F>
+    where
+        Self: Sized,
+    {
+        Admit::new(self, Throttle::new(multiplier, window), classify)
+    }
+
+    /// Applies load shedding to the service, once a specified number of callers are waiting.
+    ///
+    /// See [module](load_shed) for more information.
+    fn load_shed_after(self, threshold: usize) -> LoadShedAfter<Self>
+    where
+        Self: Sized,
+    {
+        LoadShedAfter::new(self, threshold)
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Sleeps for a fixed duration before each call to the service, while holding its permit.
+    ///
+    /// See the [module](delay) for more information.
+    fn delay(self, duration: Duration) -> Delay<Self>
+    where
+        Self: Sized,
+    {
+        Delay::new(self, duration)
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Sleeps until an [`Instant`](tokio::time::Instant), chosen from the request by a closure,
+    /// before each call to the service, while holding its permit.
+    ///
+    /// See the [module](delay) for more information.
+    fn delay_until<F>(self, closure: F) -> DelayUntil<Self, F>
+    where
+        Self: Sized,
+    {
+        DelayUntil::new(self, closure)
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Applies buffering to the service with a specified capacity.
+    ///
+    /// See the [module](buffer) for more information.
+    fn buffer(self, capacity: usize) -> Buffer<Self>
+    where
+        Self: Sized,
+    {
+        Buffer::new(self, capacity)
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Applies a priority queue to the service, handing permits to the waiting caller with the
+    /// highest priority, as extracted from the request by a closure.
+    ///
+    /// See the [module](priority) for more information.
+    fn priority<F, P>(self, closure: F) -> PriorityBuffer<Self, F, P>
+    where
+        Self: Sized,
+    {
+        PriorityBuffer::new(self, closure)
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Caches responses of the service, keyed by request, with a specified capacity and
+    /// time-to-live. The request and response types are inferred from usage.
+    ///
+    /// See the [module](cache) for more information.
+    fn cache<R, Response>(self, capacity: usize, ttl: Duration) -> Cache<Self, R, Response>
+    where
+        Self: Sized,
+    {
+        Cache::new(self, capacity, ttl)
+    }
+
+    #[cfg(feature = "tokio")]
+    /// De-duplicates concurrent calls with equal requests, sharing the response of a single inner
+    /// call. The request and response types are inferred from usage.
+    ///
+    /// See the [module](singleflight) for more information.
+    fn singleflight<R, Response>(self) -> Singleflight<Self, R, Response>
+    where
+        Self: Sized,
+    {
+        Singleflight::new(self)
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Applies rate limiting to the service with a specified interval and number of permits.
+    ///
+    /// See the [module](rate_limit) for more information.
+    fn rate_limit(self, interval: Duration, permits: usize) -> RateLimit<Self>
+    where
+        Self: Sized,
+    {
+        RateLimit::new(self, interval, permits)
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Applies rate limiting to the service, with a specified interval and number of permits, for
+    /// each key extracted from the request by a closure.
+    ///
+    /// See the [module](rate_limit) for more information.
+    fn rate_limit_per_key<F, K>(
+        self,
+        interval: Duration,
+        permits: usize,
+        closure: F,
+    ) -> RateLimitPerKey<Self, F, K>
+    where
+        Self: Sized,
+    {
+        RateLimitPerKey::new(self, interval, permits, closure)
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Applies token bucket rate limiting to the service, replenishing a token every interval up to
+    /// a maximum burst size.
+    ///
+    /// See the [module](token_bucket) for more information.
+    fn token_bucket(self, interval: Duration, burst: usize) -> TokenBucket<Self>
+    where
+        Self: Sized,
+    {
+        TokenBucket::new(self, interval, burst)
+    }
+
+    #[cfg(feature = "std")]
+    /// Applies retries to tbe service with a specified [Policy](crate::retry::Policy).
+    ///
+    /// See the [module](retry) for more information.
+    fn retry<P>(self, policy: P) -> Retry<Self, P>
+    where
+        Self: Sized,
+    {
+        Retry::new(self, policy)
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Applies retries to the service with a specified [Policy](crate::retry::Policy), waiting for
+    /// a [Backoff](crate::retry::backoff::Backoff) before each retry.
+    ///
+    /// See the [module](retry::backoff) for more information.
+    fn retry_with_backoff<P, B>(self, policy: P, backoff: B) -> Retry<Self, WithBackoff<P, B>>
+    where
+        Self: Sized,
+    {
+        Retry::new(self, WithBackoff::new(policy, backoff))
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Applies retries to the service with a specified [Policy](crate::retry::Policy), directing
+    /// each retry to the next of the `fallbacks`, and then to the last of them.
+    ///
+    /// See the [module](retry::failover) for more information.
+    fn retry_with<I, P>(self, fallbacks: I, policy: P) -> RetryWith<Self, P>
+    where
+        Self: Sized,
+        I: IntoIterator<Item = Self>,
+    {
+        RetryWith::new(self, fallbacks, policy)
+    }
+
+    /// Depressurizes the service.
+    ///
+    /// See the [module](depressurize) for more information,
+    fn depressurize(self) -> Depressurize<Self>
+    where
+        Self: Sized,
+    {
+        Depressurize::new(self)
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Allows the service to be gracefully drained, returning a [`DrainHandle`].
+    ///
+    /// See the [module](drain) for more information.
+    fn drainable(self) -> (Drain<Self>, DrainHandle)
+    where
+        Self: Sized,
+    {
+        Drain::new(self)
+    }
+
+    #[cfg(feature = "cancellation")]
+    /// Fails pending [acquires](Service::acquire) and [calls](Service::call) of the service with
+    /// [`Cancelled`](cancel::Cancelled) once the [`CancellationToken`] is cancelled.
+    ///
+    /// See the [module](cancel) for more information.
+    fn with_cancellation(self, token: CancellationToken) -> WithCancellation<Self>
+    where
+        Self: Sized,
+    {
+        WithCancellation::new(self, token)
+    }
+
+    #[cfg(feature = "std")]
+    /// Instruments the service using [`tracing`], entering the span returned by a closure accepting
+    /// a reference to the request during the call.
+    ///
+    /// See the [module](instrument) for more information.
+    fn instrument<F>(self, closure: F) -> Instrument<Self, F>
+    where
+        Self: Sized,
+    {
+        Instrument::new(self, closure)
+    }
+
+    #[cfg(feature = "std")]
+    /// Reports the calls to the service, and their latencies, through a
+    /// [`MetricsRecorder`](metrics::MetricsRecorder).
+    ///
+    /// See the [module](metrics) for more information.
+    fn metrics<R>(self, recorder: R) -> Metrics<Self, R>
+    where
+        Self: Sized,
+    {
+        Metrics::new(self, recorder)
+    }
+
+    /// Calls back into [`Hooks`](hooks::Hooks) as permits are acquired and calls are made.
+    ///
+    /// See the [module](hooks) for more information.
+    fn with_hooks<H>(self, hooks: H) -> WithHooks<Self, H>
+    where
+        Self: Sized,
+    {
+        WithHooks::new(self, hooks)
+    }
 
-/// An extension trait for [`Service`].
-pub trait ServiceExt<Request>: Service<Request> {
-    /// Acquires the [`Service::Permit`] and then immediately uses it to [call](Service::call) the
-    /// [`Service`].
+    /// Randomly injects delays, dropped responses and errors into calls to the service, as
+    /// configured by a [`FaultConfig`].
     ///
-    /// # Example
+    /// See the [module](fault) for more information.
+    #[cfg(feature = "test-util")]
+    fn inject_faults(self, config: FaultConfig) -> InjectFaults<Self>
+    where
+        Self: Sized,
+    {
+        InjectFaults::new(self, config)
+    }
+
+    /// Records [`Load`] on the service, measured by number of pending requests, including those
+    /// waiting to acquire a permit.
     ///
-    /// ```rust
-    /// use burger::*;
+    /// See the [load] module for more information.
+    fn pending_requests(self) -> PendingRequests<Self>
+    where
+        Self: Sized,
+    {
+        PendingRequests::new(self)
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Records [`Load`] on a [streaming service](StreamService), measured by the number of response
+    /// streams yet to end.
     ///
-    /// # #[tokio::main]
-    /// # async fn main() {
-    /// let svc = service_fn(|x: usize| async move { x.to_string() });
-    /// let response = svc.oneshot(32).await;
-    /// # }
-    /// ```
-    async fn oneshot(&self, request: Request) -> Self::Response
+    /// See the [module](streaming) for more information.
+    fn pending_streams(self) -> PendingStreams<Self>
     where
         Self: Sized,
     {
-        let permit = self.acquire().await;
-        Self::call(permit, request).await
+        PendingStreams::new(self)
     }
 
-    /// Extends the service using a closure accepting [`Self::Response`](Service::Response) and
-    /// returning a [`Future`](std::future::Future).
+    /// Records a fixed [`Load`] on the service.
     ///
-    /// See the [module](then) for more information.
-    fn then<F>(self, closure: F) -> Then<Self, F>
+    /// See the [load] module for more information.
+    fn constant_load<M>(self, metric: M) -> ConstantLoad<Self, M>
     where
         Self: Sized,
     {
-        Then::new(self, closure)
+        ConstantLoad::new(self, metric)
     }
 
-    /// Extends the service using a closure accepting [Self::Response](Service::Response) and returning a
-    /// [`Future`](std::future::Future).
+    /// Modifies the [`Load`] of the service using a closure.
     ///
-    /// See the [module](map) for more information.
-    fn map<F>(self, closure: F) -> Map<Self, F>
+    /// See the [load](load#transforming) module for more information.
+    fn map_load<F>(self, closure: F) -> MapLoad<Self, F>
     where
         Self: Sized,
     {
-        Map::new(self, closure)
+        MapLoad::new(self, closure)
     }
 
-    /// Applies a concurrency limit to the service with a specified number of permits.
+    /// Combines the [`Load`] of the service with that of another source using a closure.
     ///
-    /// See [concurrency limit](concurrency_limit) module for more information.
-    fn concurrency_limit(self, n_permits: usize) -> ConcurrencyLimit<Self>
+    /// See the [load](load#transforming) module for more information.
+    fn compose_load<L, F>(self, source: L, closure: F) -> ComposeLoad<Self, L, F>
     where
         Self: Sized,
     {
-        ConcurrencyLimit::new(self, n_permits)
+        ComposeLoad::new(self, source, closure)
     }
 
-    /// Applies load shedding to the service.
+    #[cfg(feature = "tokio")]
+    /// Publishes the [`Load`] of the service to a [`watch`](tokio::sync::watch) channel as
+    /// [calls](Service::call) start and finish.
     ///
-    /// See [module](load_shed) for more information.
-    fn load_shed(self) -> LoadShed<Self>
+    /// See the [load](load#watching) module for more information.
+    fn watch_load(self) -> WatchLoad<Self, Self::Metric>
+    where
+        Self: Sized + Load,
+    {
+        WatchLoad::new(self)
+    }
+
+    #[cfg(feature = "std")]
+    /// Records [`Load`] on the service, measured by the peak exponentially weighted moving average
+    /// of the call latency, decaying over the specified duration and starting from a default
+    /// round-trip time.
+    ///
+    /// See the [load] module for more information.
+    fn peak_ewma(self, decay: Duration, default_rtt: Duration) -> PeakEwma<Self>
     where
         Self: Sized,
     {
-        LoadShed::new(self)
+        PeakEwma::new(self, decay, default_rtt)
     }
 
-    /// Applies buffering to the service with a specified capacity.
+    #[cfg(feature = "std")]
+    /// Records [`Load`] on the service, measured by the exponentially weighted moving average of
+    /// the call latency, where `weight` is the weight of each new observation.
     ///
-    /// See the [module](buffer) for more information.
-    fn buffer(self, capacity: usize) -> Buffer<Self>
+    /// See the [load] module for more information.
+    fn average_latency(self, weight: f64) -> AverageLatency<Self>
     where
         Self: Sized,
     {
-        Buffer::new(self, capacity)
+        AverageLatency::new(self, weight)
     }
 
-    /// Applies rate limiting to the service with a specified interval and number of permits.
+    #[cfg(feature = "std")]
+    /// Records [`Load`] on a [fallible service](TryService), measured by the number of pending
+    /// requests and inflated, by up to `penalty` times, according to the proportion of failures
+    /// among the most recent `window` calls.
     ///
-    /// See the [module](rate_limit) for more information.
-    fn rate_limit(self, interval: Duration, permits: usize) -> RateLimit<Self>
+    /// See the [load] module for more information.
+    fn success_rate(self, window: usize, penalty: f64) -> SuccessRate<Self>
     where
         Self: Sized,
     {
-        RateLimit::new(self, interval, permits)
+        SuccessRate::new(self, window, penalty)
     }
 
-    /// Applies retries to tbe service with a specified [Policy](crate::retry::Policy).
+    #[cfg(feature = "tokio")]
+    /// Runs each call of a [synchronous service](blocking::SyncService) on a thread dedicated to
+    /// blocking work.
     ///
-    /// See the [module](retry) for more information.
-    fn retry<P>(self, policy: P) -> Retry<Self, P>
+    /// See the [module](blocking) for more information.
+    fn blocking(self) -> Blocking<Self>
+    where
+        Self: Sized + SyncService<Request>,
+    {
+        Blocking::new(self)
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Wraps the service in a [synchronous facade](blocking::BlockOn), which blocks the current
+    /// thread on a [`Handle`](tokio::runtime::Handle) until each call responds.
+    ///
+    /// See the [module](blocking) for more information.
+    fn block_on(self, handle: tokio::runtime::Handle) -> BlockOn<Self>
     where
         Self: Sized,
     {
-        Retry::new(self, policy)
+        BlockOn::new(self, handle)
     }
 
-    /// Depressurizes the service.
+    #[cfg(feature = "tokio")]
+    /// Executes each call of the service on a spawned task, returning its
+    /// [`JoinHandle`](tokio::task::JoinHandle).
     ///
-    /// See the [module](depressurize) for more information,
-    fn depressurize(self) -> Depressurize<Self>
+    /// See the [module](spawn) for more information.
+    ///
+    /// # Panics
+    ///
+    /// The [`Service::call`] on [`Spawned`] panics outside of a
+    /// [`LocalSet`](tokio::task::LocalSet).
+    fn spawned(self) -> Spawned<Self>
     where
         Self: Sized,
     {
-        Depressurize::new(self)
+        Spawned::new(self)
     }
 
-    /// Records [`Load`] on the service, measured by number of pending requests.
+    #[cfg(feature = "tokio")]
+    /// Sends a clone of a fraction, `sample_rate`, of requests to a secondary service, without
+    /// waiting for its response.
     ///
-    /// See the [load] module for more information.
-    fn pending_requests(self) -> PendingRequests<Self>
+    /// See the [module](mirror) for more information.
+    ///
+    /// # Panics
+    ///
+    /// Panics outside of a [`LocalSet`](tokio::task::LocalSet).
+    fn mirror<T>(self, secondary: T, sample_rate: f64) -> Mirror<Self, T>
     where
         Self: Sized,
     {
-        PendingRequests::new(self)
+        Mirror::new(self, secondary, sample_rate)
     }
 
-    /// Extends the lifetime of the permit.
+    /// Extends the lifetime of the permit, up to `'t`, by holding the service alive within each
+    /// permit.
     ///
     /// See the [module](leak) for more information.
     fn leak<'t>(self: Arc<Self>) -> Leak<'t, Self>
@@ -247,6 +1242,32 @@ pub trait ServiceExt<Request>: Service<Request> {
         Leak::new(self)
     }
 
+    /// Erases the type of the service, returning a [`BoxService`].
+    ///
+    /// See the [module](boxed) for more information.
+    fn boxed(self) -> BoxService<Request, Self::Response>
+    where
+        Self: Sized + 'static,
+    {
+        BoxService::new(self)
+    }
+
+    /// Applies the [`Middleware`] only if the condition holds, as [`Either::Left`], and otherwise
+    /// returns the service unchanged as [`Either::Right`].
+    ///
+    /// See the [module](either#conditional-middleware) for more information.
+    fn when<M>(self, condition: bool, middleware: M) -> Either<M::Service, Self>
+    where
+        Self: Sized,
+        M: Middleware<Self>,
+    {
+        if condition {
+            Either::Left(middleware.apply(self))
+        } else {
+            Either::Right(self)
+        }
+    }
+
     /// Wraps as [Either::Left]. For the other variant see [ServiceExt::right].
     ///
     /// See the [module](either) for more information.
@@ -286,6 +1307,25 @@ where
     type Error = Error;
 }
 
+/// A [`Service`] whose [`Service::Response`] is a [`Stream`](futures_util::Stream).
+///
+/// See the [streaming] module for more information.
+pub trait StreamService<Request>: Service<Request, Response = Self::Stream> {
+    /// The [`Stream::Item`](futures_util::Stream::Item) of the [`Service::Response`].
+    type Item;
+    /// The [`Service::Response`].
+    type Stream: futures_util::Stream<Item = Self::Item>;
+}
+
+impl<Request, S> StreamService<Request> for S
+where
+    S: Service<Request>,
+    S::Response: futures_util::Stream,
+{
+    type Item = <S::Response as futures_util::Stream>::Item;
+    type Stream = S::Response;
+}
+
 impl<Request, S> Service<Request> for Arc<S>
 where
     S: Service<Request>,
@@ -315,6 +1355,15 @@ where
     }
 }
 
+impl<S> Describe for Arc<S>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        S::describe(self)
+    }
+}
+
 impl<'t, Request, S> Service<Request> for &'t S
 where
     S: Service<Request>,
@@ -336,7 +1385,125 @@ where
     }
 }
 
-impl<'t, S> Load for &'t S
+impl<S> Load for &S
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        S::load(self)
+    }
+}
+
+impl<S> Describe for &S
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        S::describe(self)
+    }
+}
+
+impl<'t, Request, S> Service<Request> for &'t mut S
+where
+    S: Service<Request>,
+{
+    type Response = S::Response;
+    type Permit<'a> = S::Permit<'a>
+    where
+        S: 'a,
+        't: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        S::acquire(self).await
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        S::call(permit, request).await
+    }
+}
+
+impl<S> Load for &mut S
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        S::load(self)
+    }
+}
+
+impl<S> Describe for &mut S
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        S::describe(self)
+    }
+}
+
+impl<Request, S> Service<Request> for Box<S>
+where
+    S: Service<Request>,
+{
+    type Response = S::Response;
+    type Permit<'a> = S::Permit<'a>
+    where
+        S: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        S::acquire(self).await
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        S::call(permit, request).await
+    }
+}
+
+impl<S> Load for Box<S>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        S::load(self)
+    }
+}
+
+impl<S> Describe for Box<S>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        S::describe(self)
+    }
+}
+
+impl<Request, S> Service<Request> for Rc<S>
+where
+    S: Service<Request>,
+{
+    type Response = S::Response;
+    type Permit<'a> = S::Permit<'a>
+    where
+        S: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        S::acquire(self).await
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        S::call(permit, request).await
+    }
+}
+
+impl<S> Load for Rc<S>
 where
     S: Load,
 {
@@ -347,6 +1514,16 @@ where
     }
 }
 
+impl<S> Describe for Rc<S>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        S::describe(self)
+    }
+}
+
+#[cfg(feature = "tokio")]
 impl<Request, Permit, S> Service<Request> for Mutex<S>
 where
     // NOTE: These bounds seem too tight
@@ -368,6 +1545,7 @@ where
     }
 }
 
+#[cfg(feature = "tokio")]
 impl<Request, S, Permit> Service<Request> for RwLock<S>
 where
     // NOTE: These bounds seem too tight
@@ -389,6 +1567,18 @@ where
 }
 
 /// A middleware, used to incrementally add behaviour to a [`Service`].
+///
+/// Each wrapper returned by a [`ServiceExt`] combinator implements [`Middleware`], applying the
+/// combinator to the given service. The exceptions are [`ServiceExt::boxed`], whose type is erased,
+/// [`ServiceExt::watch_load`], which measures the service on construction,
+/// [`ServiceExt::retry_with`], which holds fallback services, and [`ServiceExt::blocking`] and
+/// [`ServiceExt::block_on`], which bridge between synchronous and asynchronous services. Any state,
+/// such as a rate limit's window, is created afresh when applied.
+///
+/// Constructors which combine several services, such as [`steer`](fn@steer) and
+/// [`select`](fn@select), or take ownership of a service, such as [`worker`](fn@worker) and
+/// [`ready_cache`](fn@ready_cache), have no [`Middleware`]. Instead, a
+/// [`Middleware`] can be applied to each service before they're combined.
 pub trait Middleware<S> {
     /// The resultant service.
     type Service;
@@ -406,9 +1596,43 @@ pub trait Middleware<S> {
 /// ```
 /// use burger::*;
 ///
+/// # #[cfg(feature = "tokio")]
+/// # fn main() {
 /// let middleware = MiddlewareBuilder.concurrency_limit(3).buffer(2).load_shed();
 /// let svc = service_fn(|x: u32| async move { x.to_string() });
 /// let svc = middleware.apply(svc);
+/// # }
+/// # #[cfg(not(feature = "tokio"))]
+/// # fn main() {}
+/// ```
+///
+/// Note that the [`MiddlewareBuilder`] is a [`Service`] whose request and [`Service::Response`] are
+/// [`Infallible`]. Hence, combinators whose wrapper places bounds on the request or response, such
+/// as [`ServiceExt::map`], [`ServiceExt::priority`] and [`ServiceExt::cache`], must be the last in
+/// the chain.
+///
+/// A stack can be described once and applied to many services, each receiving its own state:
+///
+/// ```
+/// use burger::*;
+/// # use std::time::Duration;
+///
+/// # #[cfg(feature = "tokio")]
+/// # #[tokio::main]
+/// # async fn main() {
+/// let stack = || {
+///     MiddlewareBuilder
+///         .rate_limit(Duration::from_secs(1), 10)
+///         .pending_requests()
+///         .map(|x: u32| x + 1)
+/// };
+/// let double = stack().apply(service_fn(|x: u32| async move { 2 * x }));
+/// let triple = stack().apply(service_fn(|x: u32| async move { 3 * x }));
+/// assert_eq!(double.oneshot(2).await, 5);
+/// assert_eq!(triple.oneshot(2).await, 7);
+/// # }
+/// # #[cfg(not(feature = "tokio"))]
+/// # fn main() {}
 /// ```
 #[derive(Debug, Clone)]
 pub struct MiddlewareBuilder;
@@ -427,6 +1651,12 @@ impl Service<Infallible> for MiddlewareBuilder {
     }
 }
 
+impl Describe for MiddlewareBuilder {
+    fn describe(&self) -> StackNode {
+        StackNode::new("MiddlewareBuilder")
+    }
+}
+
 impl<S> Middleware<S> for MiddlewareBuilder {
     type Service = S;
 
@@ -434,3 +1664,77 @@ impl<S> Middleware<S> for MiddlewareBuilder {
         svc
     }
 }
+
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use std::time::Duration;
+
+    use futures_util::FutureExt;
+    use tokio::time::{sleep, timeout};
+
+    use crate::{adaptive_concurrency::Aimd, load::Load, service_fn, Service, ServiceExt};
+
+    /// Asserts that a single permit is available and that disarming it makes it available again.
+    fn assert_disarms<S>(svc: &S)
+    where
+        S: Service<u32>,
+    {
+        let permit = svc.acquire().now_or_never().expect("permit available");
+        assert!(svc.acquire().now_or_never().is_none());
+        S::disarm(permit);
+        assert!(svc.acquire().now_or_never().is_some());
+    }
+
+    /// Asserts that cancelling a call releases its permit.
+    async fn assert_cancel_releases<S>(svc: &S)
+    where
+        S: Service<u32>,
+    {
+        let call = timeout(Duration::from_millis(5), svc.oneshot(1));
+        assert!(call.await.is_err());
+        assert!(svc.acquire().now_or_never().is_some());
+    }
+
+    fn slow() -> impl Service<u32, Response = u32> + Load<Metric = usize> {
+        service_fn(|x: u32| async move {
+            sleep(Duration::from_secs(1)).await;
+            x
+        })
+        .pending_requests()
+    }
+
+    fn aimd() -> Aimd {
+        Aimd::new(Duration::from_secs(10))
+            .initial_limit(1)
+            .max_limit(1)
+    }
+
+    #[tokio::test]
+    async fn disarm() {
+        assert_disarms(&slow().concurrency_limit(1));
+        let buffer = slow().concurrency_limit(1).buffer(1);
+        let _eager = buffer.acquire().await;
+        assert_disarms(&buffer);
+        assert_disarms(&slow().rate_limit(Duration::from_secs(10), 1));
+        assert_disarms(&slow().token_bucket(Duration::from_secs(10), 1));
+        assert_disarms(&slow().adaptive_concurrency(aimd()));
+    }
+
+    #[tokio::test]
+    async fn cancelled_call() {
+        let svc = slow();
+        assert_cancel_releases(&svc).await;
+        assert_eq!(svc.load(), 0);
+
+        assert_cancel_releases(&slow().concurrency_limit(1)).await;
+        assert_cancel_releases(&slow().concurrency_limit(1).buffer(1)).await;
+        assert_cancel_releases(&slow().adaptive_concurrency(aimd())).await;
+    }
+
+    #[tokio::test]
+    async fn load_forwarding() {
+        let svc = slow().map(|x| x + 1).rate_limit(Duration::from_secs(10), 1);
+        let _permit = svc.acquire().await;
+        assert_eq!(svc.load(), 1);
+    }
+}
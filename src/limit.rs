@@ -0,0 +1,322 @@
+//! Limiters restricting the rate or concurrency of [calls](crate::Service::call), and the sources
+//! of permits they share.
+//!
+//! Each limiter is applied by a [`ServiceExt`](crate::ServiceExt) combinator, documented in its
+//! own module, and re-exported here:
+//!
+//! - [`ServiceExt::concurrency_limit`](crate::ServiceExt::concurrency_limit) and
+//!   [`ServiceExt::concurrency_limit_with`](crate::ServiceExt::concurrency_limit_with) bound the
+//!   number of inflight calls, see [`concurrency_limit`](crate::concurrency_limit).
+//! - [`ServiceExt::adaptive_concurrency`](crate::ServiceExt::adaptive_concurrency) bounds the
+//!   number of inflight calls by a limit tuned from their latency, see
+//!   [`adaptive_concurrency`](crate::adaptive_concurrency).
+//! - [`ServiceExt::stream_concurrency_limit`](crate::ServiceExt::stream_concurrency_limit) bounds
+//!   the number of open response streams, see [`streaming`](crate::streaming).
+//! - [`ServiceExt::rate_limit`](crate::ServiceExt::rate_limit) bounds the number of calls per
+//!   fixed window of time, and
+//!   [`ServiceExt::rate_limit_per_key`](crate::ServiceExt::rate_limit_per_key) does so separately
+//!   for each key, see [`rate_limit`](crate::rate_limit).
+//! - [`ServiceExt::token_bucket`](crate::ServiceExt::token_bucket) bounds the rate of calls while
+//!   allowing bursts, see [`token_bucket`](crate::token_bucket).
+//!
+//! # Permits
+//!
+//! Each limiter takes its share of the limit in [`Service::acquire`](crate::Service::acquire) and
+//! holds it within its [`Service::Permit`](crate::Service::Permit), alongside the inner permit.
+//! Dropping a permit without calling the service returns the share. Once called, a concurrency
+//! permit is held until the call completes, or the response stream ends, while a rate permit is
+//! consumed. The exception is [`RateLimitPerKey`], which can't take its share until the request,
+//! and so its key, is known, so waits during [`Service::call`](crate::Service::call) instead.
+//!
+//! # Permit sources
+//!
+//! By default, permits are drawn from a [`FixedLimit`] local to the process. The
+//! [`ServiceExt::concurrency_limit_with`](crate::ServiceExt::concurrency_limit_with) combinator
+//! instead draws permits from any implementation of [`Permits`], such as a limiter shared between
+//! processes. A permit is released when its [`Permits::Permit`] is dropped, so an implementation
+//! requiring asynchronous work to release should start it from [`Drop`], for example by spawning a
+//! task.
+//!
+//! [`Permits::acquire`] is infallible, so neither [`FixedLimit`] nor [`AdjustableLimit`] exposes a
+//! way to close it. To limit several services together, share one using an [`Arc`] rather than a
+//! [`Semaphore`], which could be closed elsewhere.
+//!
+//! ```rust
+//! use burger::{limit::Permits, *};
+//! # use std::{
+//! #     sync::atomic::{AtomicUsize, Ordering},
+//! #     time::Duration,
+//! # };
+//! # use tokio::time::sleep;
+//!
+//! // Stands in for a limiter whose count is held remotely.
+//! struct Remote {
+//!     inflight: AtomicUsize,
+//!     limit: usize,
+//! }
+//!
+//! struct RemotePermit<'a>(&'a Remote);
+//!
+//! impl Drop for RemotePermit<'_> {
+//!     fn drop(&mut self) {
+//!         self.0.inflight.fetch_sub(1, Ordering::SeqCst);
+//!     }
+//! }
+//!
+//! impl Permits for Remote {
+//!     type Permit<'a> = RemotePermit<'a>;
+//!
+//!     async fn acquire(&self) -> Self::Permit<'_> {
+//!         while self.inflight.fetch_add(1, Ordering::SeqCst) >= self.limit {
+//!             self.inflight.fetch_sub(1, Ordering::SeqCst);
+//!             sleep(Duration::from_millis(10)).await;
+//!         }
+//!         RemotePermit(self)
+//!     }
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let remote = Remote {
+//!     inflight: AtomicUsize::new(0),
+//!     limit: 2,
+//! };
+//! let svc = service_fn(|x: u32| async move { 2 * x }).concurrency_limit_with(remote);
+//! let response = svc.oneshot(4).await;
+//! assert_eq!(response, 8);
+//! # }
+//! ```
+//!
+//! # Adjusting the limit
+//!
+//! An [`AdjustableLimit`] is a permit source whose limit can be changed with
+//! [`AdjustableLimit::set_limit`] while the service is in use, for example in response to an
+//! upstream signalling overload or an operator override. Raising the limit adds permits
+//! immediately. Lowering it removes the available permits immediately and the remainder as held
+//! permits are released, so inflight calls aren't interrupted but no new calls are admitted until
+//! the inflight calls fall below the new limit.
+//!
+//! Wrapping it in an [`Arc`] allows it to be adjusted while shared with the service.
+//!
+//! ```rust
+//! use std::sync::Arc;
+//!
+//! use burger::{limit::AdjustableLimit, *};
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let limit = Arc::new(AdjustableLimit::new(4));
+//! let svc = service_fn(|x: u32| async move { 2 * x }).concurrency_limit_with(limit.clone());
+//!
+//! let permit = svc.acquire().await;
+//! limit.set_limit(1);
+//! assert_eq!(limit.available_permits(), 0);
+//! drop(permit);
+//! assert_eq!(limit.available_permits(), 1);
+//! assert_eq!(svc.oneshot(4).await, 8);
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`](crate::load::Load::load) on each limiter defers to the inner service.
+
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+pub use crate::{
+    adaptive_concurrency::{AdaptiveConcurrency, AdaptiveConcurrencyPermit, Aimd},
+    concurrency_limit::{AcquireOrder, ConcurrencyLimit, ConcurrencyLimitPermit},
+    rate_limit::{
+        RateLimit, RateLimitHandle, RateLimitPerKey, RateLimitPerKeyPermit, RateLimitPermit,
+    },
+    streaming::{StreamConcurrencyLimit, StreamConcurrencyLimitPermit},
+    token_bucket::{TokenBucket, TokenBucketPermit},
+};
+
+/// A source of permits for [`ConcurrencyLimit`].
+///
+/// See the [module](crate::limit#permit-sources) for more information.
+pub trait Permits {
+    /// The type of the permit, which releases itself when dropped.
+    type Permit<'a>
+    where
+        Self: 'a;
+
+    /// Waits for a permit to become available.
+    async fn acquire(&self) -> Self::Permit<'_>;
+}
+
+/// A source of permits for [`ConcurrencyLimit`] with a fixed limit, used by
+/// [`ServiceExt::concurrency_limit`](crate::ServiceExt::concurrency_limit).
+///
+/// Unlike a [`Semaphore`], a [`FixedLimit`] can't be closed, so acquiring from it can't fail. It
+/// may be shared, using an [`Arc`], between services which should be limited together.
+///
+/// See the [module](crate::limit#permit-sources) for more information.
+#[derive(Debug)]
+pub struct FixedLimit {
+    semaphore: Semaphore,
+}
+
+impl FixedLimit {
+    /// Constructs a [`FixedLimit`] with the specified number of permits.
+    pub fn new(n_permits: usize) -> Self {
+        Self {
+            semaphore: Semaphore::new(n_permits),
+        }
+    }
+
+    /// Returns the number of permits currently available.
+    pub fn available_permits(&self) -> usize {
+        self.semaphore.available_permits()
+    }
+}
+
+impl Permits for FixedLimit {
+    type Permit<'a> = SemaphorePermit<'a>;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        // The semaphore is private and never closed.
+        self.semaphore.acquire().await.expect("never closed")
+    }
+}
+
+impl<P> Permits for Arc<P>
+where
+    P: Permits,
+{
+    type Permit<'a> = P::Permit<'a>
+    where
+        P: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        P::acquire(self).await
+    }
+}
+
+/// A source of permits for [`ConcurrencyLimit`] whose limit can be adjusted while permits are held.
+///
+/// See the [module](crate::limit#adjusting-the-limit) for more information.
+#[derive(Debug)]
+pub struct AdjustableLimit {
+    semaphore: Semaphore,
+    state: Mutex<Adjustment>,
+}
+
+#[derive(Debug)]
+struct Adjustment {
+    limit: usize,
+    /// Permits removed while they were held, which are forgotten, rather than released, on drop.
+    owed: usize,
+}
+
+impl AdjustableLimit {
+    /// Constructs an [`AdjustableLimit`] with the specified number of permits.
+    pub fn new(n_permits: usize) -> Self {
+        Self {
+            semaphore: Semaphore::new(n_permits),
+            state: Mutex::new(Adjustment {
+                limit: n_permits,
+                owed: 0,
+            }),
+        }
+    }
+
+    /// Returns the current limit.
+    pub fn limit(&self) -> usize {
+        self.state.lock().unwrap().limit
+    }
+
+    /// Returns the number of permits currently available.
+    pub fn available_permits(&self) -> usize {
+        self.semaphore.available_permits()
+    }
+
+    /// Sets the limit, adding or removing permits.
+    ///
+    /// Permits which are held when the limit is lowered are removed as they're released.
+    pub fn set_limit(&self, n_permits: usize) {
+        let mut state = self.state.lock().unwrap();
+        if n_permits >= state.limit {
+            let added = n_permits - state.limit;
+            // Permits still owed are cancelled rather than added.
+            let cancelled = state.owed.min(added);
+            state.owed -= cancelled;
+            self.semaphore.add_permits(added - cancelled);
+        } else {
+            let removed = state.limit - n_permits;
+            state.owed += removed - self.semaphore.forget_permits(removed);
+        }
+        state.limit = n_permits;
+    }
+}
+
+/// The [`Permits::Permit`] type for [`AdjustableLimit`].
+#[derive(Debug)]
+pub struct AdjustableLimitPermit<'a> {
+    permit: Option<SemaphorePermit<'a>>,
+    limit: &'a AdjustableLimit,
+}
+
+impl Drop for AdjustableLimitPermit<'_> {
+    fn drop(&mut self) {
+        let mut state = self.limit.state.lock().unwrap();
+        if state.owed > 0 {
+            state.owed -= 1;
+            if let Some(permit) = self.permit.take() {
+                permit.forget();
+            }
+        }
+    }
+}
+
+impl Permits for AdjustableLimit {
+    type Permit<'a> = AdjustableLimitPermit<'a>;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        AdjustableLimitPermit {
+            // The semaphore is private and never closed.
+            permit: Some(self.semaphore.acquire().await.expect("never closed")),
+            limit: self,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use futures_util::FutureExt;
+
+    use crate::{service_fn, Service, ServiceExt};
+
+    use super::AdjustableLimit;
+
+    #[tokio::test]
+    async fn adjust_limit_while_held() {
+        let limit = Arc::new(AdjustableLimit::new(2));
+        let svc = service_fn(|x: u32| async move { x }).concurrency_limit_with(limit.clone());
+        let first = svc.acquire().await;
+        let second = svc.acquire().await;
+
+        // Both permits are held, so neither can be removed yet.
+        limit.set_limit(1);
+        assert_eq!(limit.limit(), 1);
+        drop(first);
+        assert_eq!(limit.available_permits(), 0);
+        let mut acquire = Box::pin(svc.acquire());
+        assert!((&mut acquire).now_or_never().is_none());
+        drop(second);
+        let permit = acquire.await;
+        assert_eq!(limit.available_permits(), 0);
+
+        // Raising the limit while permits are owed cancels them first.
+        limit.set_limit(0);
+        limit.set_limit(3);
+        assert_eq!(limit.available_permits(), 2);
+        drop(permit);
+        assert_eq!(limit.available_permits(), 3);
+    }
+}
@@ -1,13 +1,137 @@
 //! Load is a measurement of the amount of work a service is experiencing. The [`Load`] trait
 //! provides an interface to measure it and therefore informs business logic in applications such
 //! as load balancers.
+//!
+//! Five [`Load`] wrappers are provided:
+//!
+//! - [`ServiceExt::pending_requests`](crate::ServiceExt::pending_requests) returns
+//!   [`PendingRequests`], measuring the number of inflight [calls](Service::call).
+//! - [`ServiceExt::peak_ewma`](crate::ServiceExt::peak_ewma) returns [`PeakEwma`], measuring the
+//!   peak exponentially weighted moving average of the [call](Service::call) latency, weighted by
+//!   the number of inflight calls.
+//! - [`ServiceExt::average_latency`](crate::ServiceExt::average_latency) returns
+//!   [`AverageLatency`], measuring the exponentially weighted moving average of the
+//!   [call](Service::call) latency alone. Unlike [`PendingRequests`], this distinguishes a slow
+//!   service with few calls from a fast one with many.
+//! - [`ServiceExt::success_rate`](crate::ServiceExt::success_rate) returns [`SuccessRate`],
+//!   measuring the number of pending requests of a [fallible service](crate::TryService), inflated
+//!   by the proportion of recent calls returning [`Err`]. Balancing on this steers traffic away
+//!   from failing services rather than only busy ones.
+//! - [`ServiceExt::constant_load`](crate::ServiceExt::constant_load) returns [`ConstantLoad`],
+//!   reporting a fixed metric. This allows any service, such as one constructed using
+//!   [`service_fn`](fn@crate::service_fn), to be used where [`Load`] is required.
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::{load::Load, *};
+//! # use std::time::Duration;
+//!
+//! # #[cfg(feature = "tokio")]
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc = service_fn(|x: u32| async move { x + 1 })
+//!     .peak_ewma(Duration::from_secs(10), Duration::from_millis(30));
+//! let response = svc.oneshot(3).await;
+//! assert_eq!(response, 4);
+//! let load: f64 = svc.load();
+//! # }
+//! # #[cfg(not(feature = "tokio"))]
+//! # fn main() {}
+//! ```
+//!
+//! # Transforming
+//!
+//! The [`ServiceExt::map_load`](crate::ServiceExt::map_load) combinator returns [`MapLoad`], which
+//! modifies the inner service's metric using a closure, for example to normalize or invert it.
+//!
+//! The [`ServiceExt::compose_load`](crate::ServiceExt::compose_load) combinator returns
+//! [`ComposeLoad`], which combines the inner service's metric with that of another [`Load`] source
+//! using a closure, for example a weighted sum. Sources which aren't services, such as the depth
+//! of a queue, can be constructed using [`load_fn`].
+//!
+//! ```rust
+//! use std::sync::{
+//!     atomic::{AtomicUsize, Ordering},
+//!     Arc,
+//! };
+//!
+//! use burger::{load::{load_fn, Load}, *};
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let queued = Arc::new(AtomicUsize::new(4));
+//! let depth = queued.clone();
+//! let svc = service_fn(|x: u32| async move { x + 1 })
+//!     .pending_requests()
+//!     .compose_load(load_fn(move || depth.load(Ordering::Relaxed)), |pending, queued| {
+//!         pending as f64 + 0.5 * queued as f64
+//!     })
+//!     .map_load(|load| load / 10.0);
+//! assert_eq!(svc.load(), 0.2);
+//! # }
+//! ```
+//!
+//! # Watching
+//!
+//! Rather than polling [`Load::load`], changes can be subscribed to. The
+//! [`ServiceExt::watch_load`](crate::ServiceExt::watch_load) combinator returns [`WatchLoad`],
+//! which measures the inner service's load as each [call](Service::call) starts and finishes,
+//! including when it's cancelled, and publishes it to a [`watch`] channel whenever it changes.
+//!
+//! ```rust
+//! use burger::*;
+//! # use std::time::Duration;
+//! # use tokio::{join, time::sleep};
+//!
+//! # #[cfg(feature = "tokio")]
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc = service_fn(|x: u32| async move {
+//!     sleep(Duration::from_millis(10)).await;
+//!     x + 1
+//! })
+//! .pending_requests()
+//! .watch_load();
+//! let mut load = svc.subscribe();
+//! let watch = async {
+//!     load.changed().await.unwrap();
+//!     assert_eq!(*load.borrow_and_update(), 1);
+//!     load.changed().await.unwrap();
+//!     assert_eq!(*load.borrow_and_update(), 0);
+//! };
+//! let (response, ()) = join!(svc.oneshot(3), watch);
+//! assert_eq!(response, 4);
+//! # }
+//! # #[cfg(not(feature = "tokio"))]
+//! # fn main() {}
+//! ```
 
-use std::{
-    fmt,
+use alloc::sync::Arc;
+use core::{
+    any, fmt,
     sync::atomic::{AtomicUsize, Ordering},
 };
+#[cfg(feature = "tokio")]
+use core::{pin::pin, task::Poll};
+#[cfg(feature = "std")]
+use std::{
+    collections::VecDeque,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "tokio")]
+use futures_util::poll;
+#[cfg(feature = "tokio")]
+use tokio::sync::watch;
 
-use crate::{Middleware, Service};
+#[cfg(feature = "std")]
+use crate::TryService;
+use crate::{
+    describe::{Describe, StackNode},
+    Middleware, Service,
+};
 
 /// A measurement of load on a [`Service`].
 pub trait Load {
@@ -18,13 +142,29 @@ pub trait Load {
     fn load(&self) -> Self::Metric;
 }
 
+/// Decrements a pending count on drop, including on cancellation.
+struct Pending<'a>(&'a AtomicUsize);
+
+impl Drop for Pending<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Release);
+    }
+}
+
 /// A wrapper [`Service`] providing a [`Load`] implementation based on the number of pending requests.
 ///
-/// TODO: Make it so.
-#[derive(Debug)]
+/// Clones share the count of pending requests.
+///
+/// A request is pending from when [`Service::acquire`] is called, including while waiting for the
+/// inner permit, for example behind a [concurrency limit](crate::ServiceExt::concurrency_limit),
+/// until [`Service::call`] has returned. Hence a saturated service reports the callers queued on it
+/// as well as those in flight. Cancelling either, or dropping the permit, ends the request. For
+/// services responding with a stream, see
+/// [`ServiceExt::pending_streams`](crate::ServiceExt::pending_streams).
+#[derive(Clone, Debug)]
 pub struct PendingRequests<S> {
     inner: S,
-    count: AtomicUsize,
+    count: Arc<AtomicUsize>,
 }
 
 /// The [`Service::Permit`] type for [PendingRequests].
@@ -33,7 +173,7 @@ where
     S: Service<Request> + 'a,
 {
     inner: S::Permit<'a>,
-    count: &'a AtomicUsize,
+    pending: Pending<'a>,
 }
 
 impl<'a, S, Request> fmt::Debug for PendingRequestsPermit<'a, S, Request>
@@ -44,7 +184,7 @@ where
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("PendingRequestsPermit")
             .field("inner", &self.inner)
-            .field("count", &self.count)
+            .field("count", &self.pending.0)
             .finish()
     }
 }
@@ -53,7 +193,7 @@ impl<S> PendingRequests<S> {
     pub(crate) fn new(inner: S) -> Self {
         Self {
             inner,
-            count: AtomicUsize::new(0),
+            count: Arc::new(AtomicUsize::new(0)),
         }
     }
 }
@@ -68,9 +208,11 @@ where
         Self: 'a;
 
     async fn acquire(&self) -> Self::Permit<'_> {
+        self.count.fetch_add(1, Ordering::Release);
+        let pending = Pending(&self.count);
         PendingRequestsPermit {
             inner: self.inner.acquire().await,
-            count: &self.count,
+            pending,
         }
     }
 
@@ -78,10 +220,11 @@ where
     where
         Self: 'a,
     {
-        permit.count.fetch_add(1, Ordering::Release);
-        let response = S::call(permit.inner, request).await;
-        permit.count.fetch_sub(1, Ordering::Release);
-        response
+        let PendingRequestsPermit {
+            inner,
+            pending: _pending,
+        } = permit;
+        S::call(inner, request).await
     }
 }
 
@@ -93,6 +236,15 @@ impl<S> Load for PendingRequests<S> {
     }
 }
 
+impl<S> Describe for PendingRequests<S>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("PendingRequests").with_child(self.inner.describe())
+    }
+}
+
 impl<S, T> Middleware<S> for PendingRequests<T>
 where
     T: Middleware<S>,
@@ -100,10 +252,1013 @@ where
     type Service = PendingRequests<T::Service>;
 
     fn apply(self, svc: S) -> Self::Service {
-        let Self { inner, count } = self;
-        PendingRequests {
+        PendingRequests::new(self.inner.apply(svc))
+    }
+}
+
+/// A wrapper [`Service`] providing a [`Load`] implementation which reports a fixed metric.
+#[derive(Clone, Debug)]
+pub struct ConstantLoad<S, M> {
+    inner: S,
+    metric: M,
+}
+
+impl<S, M> ConstantLoad<S, M> {
+    pub(crate) fn new(inner: S, metric: M) -> Self {
+        Self { inner, metric }
+    }
+}
+
+impl<Request, S, M> Service<Request> for ConstantLoad<S, M>
+where
+    S: Service<Request>,
+{
+    type Response = S::Response;
+    type Permit<'a> = S::Permit<'a>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        self.inner.acquire().await
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        S::call(permit, request).await
+    }
+}
+
+impl<S, M> Load for ConstantLoad<S, M>
+where
+    M: PartialOrd + Clone,
+{
+    type Metric = M;
+
+    fn load(&self) -> Self::Metric {
+        self.metric.clone()
+    }
+}
+
+impl<S, M> Describe for ConstantLoad<S, M>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("ConstantLoad").with_child(self.inner.describe())
+    }
+}
+
+impl<S, T, M> Middleware<S> for ConstantLoad<T, M>
+where
+    T: Middleware<S>,
+{
+    type Service = ConstantLoad<T::Service, M>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self { inner, metric } = self;
+        ConstantLoad {
+            inner: inner.apply(svc),
+            metric,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+/// A wrapper [`Service`] providing a [`Load`] implementation based on the peak exponentially
+/// weighted moving average (EWMA) of the [call](Service::call) latency.
+///
+/// The latency estimate immediately rises to any observed latency larger than the current
+/// estimate, and otherwise decays towards newer observations according to the decay period. The
+/// [`Load::load`] is the estimate multiplied by the number of pending requests plus one.
+///
+/// See the [module](crate::load) for more information.
+#[derive(Debug)]
+pub struct PeakEwma<S> {
+    inner: S,
+    pending: AtomicUsize,
+    estimate: Mutex<RttEstimate>,
+    decay_ns: f64,
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug)]
+struct RttEstimate {
+    rtt_ns: f64,
+    updated_at: Instant,
+}
+
+#[cfg(feature = "std")]
+impl RttEstimate {
+    /// Incorporates a new observation, returning the new estimate.
+    fn update(&mut self, rtt: Duration, now: Instant, decay_ns: f64) -> f64 {
+        let rtt_ns = nanos(rtt);
+        if self.rtt_ns < rtt_ns {
+            // Peak sensitivity: jump immediately to the larger observation.
+            self.rtt_ns = rtt_ns;
+        } else {
+            let elapsed = nanos(now.saturating_duration_since(self.updated_at));
+            let decay = (-elapsed / decay_ns).exp();
+            self.rtt_ns = self.rtt_ns * decay + rtt_ns * (1.0 - decay);
+        }
+        self.updated_at = now;
+        self.rtt_ns
+    }
+
+    /// Returns the estimate decayed towards zero by the time elapsed since the last update.
+    fn decayed(&self, now: Instant, decay_ns: f64) -> f64 {
+        let elapsed = nanos(now.saturating_duration_since(self.updated_at));
+        self.rtt_ns * (-elapsed / decay_ns).exp()
+    }
+}
+
+#[cfg(feature = "std")]
+fn nanos(duration: Duration) -> f64 {
+    duration.as_nanos() as f64
+}
+
+#[cfg(feature = "std")]
+impl<S> PeakEwma<S> {
+    pub(crate) fn new(inner: S, decay: Duration, default_rtt: Duration) -> Self {
+        Self {
+            inner,
+            pending: AtomicUsize::new(0),
+            estimate: Mutex::new(RttEstimate {
+                rtt_ns: nanos(default_rtt),
+                updated_at: Instant::now(),
+            }),
+            // Avoid dividing by zero when decaying.
+            decay_ns: nanos(decay).max(1.0),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+/// The [`Service::Permit`] type for [PeakEwma].
+pub struct PeakEwmaPermit<'a, S, Request>
+where
+    S: Service<Request> + 'a,
+{
+    inner: S::Permit<'a>,
+    service: &'a PeakEwma<S>,
+}
+
+#[cfg(feature = "std")]
+impl<'a, S, Request> fmt::Debug for PeakEwmaPermit<'a, S, Request>
+where
+    S: Service<Request> + fmt::Debug + 'a,
+    S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PeakEwmaPermit")
+            .field("inner", &self.inner)
+            .field("service", &self.service)
+            .finish()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<Request, S> Service<Request> for PeakEwma<S>
+where
+    S: Service<Request>,
+{
+    type Response = S::Response;
+    type Permit<'a> = PeakEwmaPermit<'a, S, Request>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        PeakEwmaPermit {
+            inner: self.inner.acquire().await,
+            service: self,
+        }
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        let PeakEwmaPermit { inner, service } = permit;
+        service.pending.fetch_add(1, Ordering::Release);
+        let _pending = Pending(&service.pending);
+        let start = Instant::now();
+        let response = S::call(inner, request).await;
+        let now = Instant::now();
+        service.estimate.lock().unwrap().update(
+            now.saturating_duration_since(start),
+            now,
+            service.decay_ns,
+        );
+        response
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S> Load for PeakEwma<S> {
+    type Metric = f64;
+
+    fn load(&self) -> Self::Metric {
+        let estimate = self
+            .estimate
+            .lock()
+            .unwrap()
+            .decayed(Instant::now(), self.decay_ns);
+        let pending = self.pending.load(Ordering::Acquire);
+        estimate * (pending + 1) as f64
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S> Describe for PeakEwma<S>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("PeakEwma")
+            .with_config(
+                "decay",
+                format_args!("{:?}", Duration::from_nanos(self.decay_ns as u64)),
+            )
+            .with_child(self.inner.describe())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S, T> Middleware<S> for PeakEwma<T>
+where
+    T: Middleware<S>,
+{
+    type Service = PeakEwma<T::Service>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self {
+            inner,
+            pending,
+            estimate,
+            decay_ns,
+        } = self;
+        PeakEwma {
+            inner: inner.apply(svc),
+            pending,
+            estimate,
+            decay_ns,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+/// A wrapper [`Service`] providing a [`Load`] implementation based on the exponentially weighted
+/// moving average of the [call](Service::call) latency.
+///
+/// Each completed call moves the average towards its latency by the specified weight, between zero
+/// and one. Cancelled calls aren't observed. The [`Load::load`] is [`Duration::ZERO`] until the
+/// first call completes.
+///
+/// # Example
+///
+/// ```rust
+/// use burger::{load::Load, *};
+/// # use std::time::Duration;
+/// # use tokio::time::sleep;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let svc = service_fn(|x: u32| async move {
+///     sleep(Duration::from_millis(10)).await;
+///     x + 1
+/// })
+/// .average_latency(0.2);
+/// assert_eq!(svc.load(), Duration::ZERO);
+/// svc.oneshot(3).await;
+/// assert!(svc.load() >= Duration::from_millis(10));
+/// # }
+/// ```
+///
+/// See the [module](crate::load) for more information.
+#[derive(Debug)]
+pub struct AverageLatency<S> {
+    inner: S,
+    average_ns: Mutex<Option<f64>>,
+    weight: f64,
+}
+
+#[cfg(feature = "std")]
+impl<S> AverageLatency<S> {
+    pub(crate) fn new(inner: S, weight: f64) -> Self {
+        Self {
+            inner,
+            average_ns: Mutex::new(None),
+            weight: weight.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Incorporates a new observation.
+    fn observe(&self, latency: Duration) {
+        let latency_ns = nanos(latency);
+        let mut average_ns = self.average_ns.lock().unwrap();
+        *average_ns = Some(match *average_ns {
+            Some(average_ns) => average_ns + self.weight * (latency_ns - average_ns),
+            None => latency_ns,
+        });
+    }
+}
+
+#[cfg(feature = "std")]
+/// The [`Service::Permit`] type for [`AverageLatency`].
+pub struct AverageLatencyPermit<'a, S, Request>
+where
+    S: Service<Request> + 'a,
+{
+    inner: S::Permit<'a>,
+    service: &'a AverageLatency<S>,
+}
+
+#[cfg(feature = "std")]
+impl<'a, S, Request> fmt::Debug for AverageLatencyPermit<'a, S, Request>
+where
+    S: Service<Request> + fmt::Debug + 'a,
+    S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AverageLatencyPermit")
+            .field("inner", &self.inner)
+            .field("service", &self.service)
+            .finish()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<Request, S> Service<Request> for AverageLatency<S>
+where
+    S: Service<Request>,
+{
+    type Response = S::Response;
+    type Permit<'a> = AverageLatencyPermit<'a, S, Request>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        AverageLatencyPermit {
+            inner: self.inner.acquire().await,
+            service: self,
+        }
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        let AverageLatencyPermit { inner, service } = permit;
+        let start = Instant::now();
+        let response = S::call(inner, request).await;
+        service.observe(start.elapsed());
+        response
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S> Load for AverageLatency<S> {
+    type Metric = Duration;
+
+    fn load(&self) -> Self::Metric {
+        let average_ns = self.average_ns.lock().unwrap().unwrap_or_default();
+        Duration::from_nanos(average_ns as u64)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S> Describe for AverageLatency<S>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("AverageLatency")
+            .with_config("weight", self.weight)
+            .with_child(self.inner.describe())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S, T> Middleware<S> for AverageLatency<T>
+where
+    T: Middleware<S>,
+{
+    type Service = AverageLatency<T::Service>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self {
+            inner,
+            average_ns,
+            weight,
+        } = self;
+        AverageLatency {
             inner: inner.apply(svc),
-            count,
+            average_ns,
+            weight,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+/// A wrapper [`Service`] providing a [`Load`] implementation which penalizes a
+/// [fallible service](crate::TryService) according to the proportion of recent calls returning
+/// [`Err`].
+///
+/// The outcomes of the most recent calls, up to the specified window, are retained. Cancelled calls
+/// aren't observed. The [`Load::load`] is the number of pending requests, counted as in
+/// [`PendingRequests`], plus one, multiplied by one plus the penalty times the proportion of
+/// failures within the window. Hence an idle service which fails every call, with a penalty of
+/// `4.0`, has the same load as a healthy service with four pending requests. Before any call
+/// completes, the service is assumed to be healthy.
+///
+/// # Example
+///
+/// ```rust
+/// use burger::{load::Load, *};
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let svc = service_fn(|x: u32| async move { (x % 2 == 0).then_some(x).ok_or(x) })
+///     .success_rate(10, 4.0);
+/// assert_eq!(svc.load(), 1.0);
+/// let _ = svc.oneshot(1).await;
+/// let _ = svc.oneshot(2).await;
+/// assert_eq!(svc.success_ratio(), 0.5);
+/// assert_eq!(svc.load(), 3.0);
+/// # }
+/// ```
+///
+/// See the [module](crate::load) for more information.
+#[derive(Debug)]
+pub struct SuccessRate<S> {
+    inner: S,
+    pending: AtomicUsize,
+    outcomes: Mutex<Outcomes>,
+    window: usize,
+    penalty: f64,
+}
+
+#[cfg(feature = "std")]
+/// The outcomes of the most recent calls, where `true` is a failure.
+#[derive(Debug, Default)]
+struct Outcomes {
+    recent: VecDeque<bool>,
+    failures: usize,
+}
+
+#[cfg(feature = "std")]
+impl<S> SuccessRate<S> {
+    pub(crate) fn new(inner: S, window: usize, penalty: f64) -> Self {
+        Self {
+            inner,
+            pending: AtomicUsize::new(0),
+            outcomes: Mutex::new(Outcomes::default()),
+            window: window.max(1),
+            penalty: penalty.max(0.0),
+        }
+    }
+
+    /// Returns the proportion of calls within the window which returned [`Ok`], or `1.0` if none
+    /// have completed.
+    pub fn success_ratio(&self) -> f64 {
+        let outcomes = self.outcomes.lock().unwrap();
+        if outcomes.recent.is_empty() {
+            return 1.0;
         }
+        1.0 - outcomes.failures as f64 / outcomes.recent.len() as f64
+    }
+
+    /// Records the outcome of a call, evicting the oldest once the window is full.
+    fn observe(&self, failed: bool) {
+        let mut outcomes = self.outcomes.lock().unwrap();
+        if outcomes.recent.len() == self.window && outcomes.recent.pop_front() == Some(true) {
+            outcomes.failures -= 1;
+        }
+        outcomes.recent.push_back(failed);
+        outcomes.failures += usize::from(failed);
+    }
+}
+
+#[cfg(feature = "std")]
+/// The [`Service::Permit`] type for [`SuccessRate`].
+pub struct SuccessRatePermit<'a, S, Request>
+where
+    S: Service<Request> + 'a,
+{
+    inner: S::Permit<'a>,
+    pending: Pending<'a>,
+    service: &'a SuccessRate<S>,
+}
+
+#[cfg(feature = "std")]
+impl<'a, S, Request> fmt::Debug for SuccessRatePermit<'a, S, Request>
+where
+    S: Service<Request> + fmt::Debug + 'a,
+    S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SuccessRatePermit")
+            .field("inner", &self.inner)
+            .field("service", &self.service)
+            .finish()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<Request, S> Service<Request> for SuccessRate<S>
+where
+    S: TryService<Request>,
+{
+    type Response = S::Response;
+    type Permit<'a> = SuccessRatePermit<'a, S, Request>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        self.pending.fetch_add(1, Ordering::Release);
+        let pending = Pending(&self.pending);
+        SuccessRatePermit {
+            inner: self.inner.acquire().await,
+            pending,
+            service: self,
+        }
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        let SuccessRatePermit {
+            inner,
+            pending: _pending,
+            service,
+        } = permit;
+        let response = S::call(inner, request).await;
+        service.observe(response.is_err());
+        response
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S> Load for SuccessRate<S> {
+    type Metric = f64;
+
+    fn load(&self) -> Self::Metric {
+        let pending = self.pending.load(Ordering::Acquire) as f64;
+        (pending + 1.0) * (1.0 + self.penalty * (1.0 - self.success_ratio()))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S> Describe for SuccessRate<S>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("SuccessRate")
+            .with_config("window", self.window)
+            .with_config("penalty", self.penalty)
+            .with_child(self.inner.describe())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S, T> Middleware<S> for SuccessRate<T>
+where
+    T: Middleware<S>,
+{
+    type Service = SuccessRate<T::Service>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self {
+            inner,
+            pending,
+            outcomes,
+            window,
+            penalty,
+        } = self;
+        SuccessRate {
+            inner: inner.apply(svc),
+            pending,
+            outcomes,
+            window,
+            penalty,
+        }
+    }
+}
+
+/// A wrapper [`Service`] providing a [`Load`] implementation which modifies the inner service's
+/// metric using a closure.
+///
+/// See the [module](crate::load#transforming) for more information.
+#[derive(Clone, Debug)]
+pub struct MapLoad<S, F> {
+    inner: S,
+    closure: F,
+}
+
+impl<S, F> MapLoad<S, F> {
+    pub(crate) fn new(inner: S, closure: F) -> Self {
+        Self { inner, closure }
+    }
+}
+
+impl<Request, S, F> Service<Request> for MapLoad<S, F>
+where
+    S: Service<Request>,
+{
+    type Response = S::Response;
+    type Permit<'a> = S::Permit<'a>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        self.inner.acquire().await
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        S::call(permit, request).await
+    }
+}
+
+impl<S, F, M> Load for MapLoad<S, F>
+where
+    S: Load,
+    F: Fn(S::Metric) -> M,
+    M: PartialOrd,
+{
+    type Metric = M;
+
+    fn load(&self) -> Self::Metric {
+        (self.closure)(self.inner.load())
+    }
+}
+
+impl<S, F> Describe for MapLoad<S, F>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("MapLoad").with_child(self.inner.describe())
+    }
+}
+
+impl<S, T, F> Middleware<S> for MapLoad<T, F>
+where
+    T: Middleware<S>,
+{
+    type Service = MapLoad<T::Service, F>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self { inner, closure } = self;
+        MapLoad {
+            inner: inner.apply(svc),
+            closure,
+        }
+    }
+}
+
+/// A wrapper [`Service`] providing a [`Load`] implementation which combines the inner service's
+/// metric with that of another [`Load`] source using a closure.
+///
+/// See the [module](crate::load#transforming) for more information.
+#[derive(Clone, Debug)]
+pub struct ComposeLoad<S, L, F> {
+    inner: S,
+    source: L,
+    closure: F,
+}
+
+impl<S, L, F> ComposeLoad<S, L, F> {
+    pub(crate) fn new(inner: S, source: L, closure: F) -> Self {
+        Self {
+            inner,
+            source,
+            closure,
+        }
+    }
+}
+
+impl<Request, S, L, F> Service<Request> for ComposeLoad<S, L, F>
+where
+    S: Service<Request>,
+{
+    type Response = S::Response;
+    type Permit<'a> = S::Permit<'a>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        self.inner.acquire().await
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        S::call(permit, request).await
+    }
+}
+
+impl<S, L, F, M> Load for ComposeLoad<S, L, F>
+where
+    S: Load,
+    L: Load,
+    F: Fn(S::Metric, L::Metric) -> M,
+    M: PartialOrd,
+{
+    type Metric = M;
+
+    fn load(&self) -> Self::Metric {
+        (self.closure)(self.inner.load(), self.source.load())
+    }
+}
+
+impl<S, L, F> Describe for ComposeLoad<S, L, F>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("ComposeLoad").with_child(self.inner.describe())
+    }
+}
+
+impl<S, T, L, F> Middleware<S> for ComposeLoad<T, L, F>
+where
+    T: Middleware<S>,
+{
+    type Service = ComposeLoad<T::Service, L, F>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self {
+            inner,
+            source,
+            closure,
+        } = self;
+        ComposeLoad {
+            inner: inner.apply(svc),
+            source,
+            closure,
+        }
+    }
+}
+
+/// A [`Load`] source, which isn't a [`Service`], returned by the [`load_fn`] constructor.
+///
+/// See the [module](crate::load#transforming) for more information.
+#[derive(Clone)]
+pub struct LoadFn<F> {
+    closure: F,
+}
+
+impl<F> fmt::Debug for LoadFn<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoadFn")
+            .field("closure", &format_args!("{}", any::type_name::<F>()))
+            .finish()
+    }
+}
+
+impl<F, M> Load for LoadFn<F>
+where
+    F: Fn() -> M,
+    M: PartialOrd,
+{
+    type Metric = M;
+
+    fn load(&self) -> Self::Metric {
+        (self.closure)()
+    }
+}
+
+/// Constructs a [`Load`] source from a closure returning a metric.
+///
+/// See the [module](crate::load#transforming) for more information.
+pub fn load_fn<F>(closure: F) -> LoadFn<F> {
+    LoadFn { closure }
+}
+
+#[cfg(feature = "tokio")]
+/// A wrapper [`Service`] publishing the [`Load`] of the inner service to a [`watch`] channel.
+///
+/// See the [module](crate::load#watching) for more information.
+#[derive(Debug)]
+pub struct WatchLoad<S, M> {
+    inner: S,
+    sender: watch::Sender<M>,
+}
+
+#[cfg(feature = "tokio")]
+impl<S> WatchLoad<S, S::Metric>
+where
+    S: Load,
+{
+    pub(crate) fn new(inner: S) -> Self {
+        let (sender, _) = watch::channel(inner.load());
+        Self { inner, sender }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<S> WatchLoad<S, S::Metric>
+where
+    S: Load,
+{
+    /// Returns a [`watch::Receiver`] which is notified as the load changes.
+    pub fn subscribe(&self) -> watch::Receiver<S::Metric> {
+        self.sender.subscribe()
+    }
+
+    fn update(&self) {
+        let load = self.inner.load();
+        self.sender.send_if_modified(|current| {
+            if *current == load {
+                false
+            } else {
+                *current = load;
+                true
+            }
+        });
+    }
+}
+
+#[cfg(feature = "tokio")]
+/// Updates the [`WatchLoad`] on drop, including on cancellation.
+struct Update<'a, S>(&'a WatchLoad<S, S::Metric>)
+where
+    S: Load;
+
+#[cfg(feature = "tokio")]
+impl<S> Drop for Update<'_, S>
+where
+    S: Load,
+{
+    fn drop(&mut self) {
+        self.0.update();
+    }
+}
+
+#[cfg(feature = "tokio")]
+/// The [`Service::Permit`] type for [`WatchLoad`].
+pub struct WatchLoadPermit<'a, S, Request>
+where
+    S: Service<Request> + Load + 'a,
+{
+    inner: S::Permit<'a>,
+    service: &'a WatchLoad<S, S::Metric>,
+}
+
+#[cfg(feature = "tokio")]
+impl<'a, S, Request> fmt::Debug for WatchLoadPermit<'a, S, Request>
+where
+    S: Service<Request> + Load + fmt::Debug + 'a,
+    S::Metric: fmt::Debug,
+    S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WatchLoadPermit")
+            .field("inner", &self.inner)
+            .field("service", &self.service)
+            .finish()
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<Request, S> Service<Request> for WatchLoad<S, S::Metric>
+where
+    S: Service<Request> + Load,
+{
+    type Response = S::Response;
+    type Permit<'a> = WatchLoadPermit<'a, S, Request>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        WatchLoadPermit {
+            inner: self.inner.acquire().await,
+            service: self,
+        }
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        let WatchLoadPermit { inner, service } = permit;
+        let _update = Update(service);
+        let mut call = pin!(S::call(inner, request));
+        // Poll once so that the inner service has registered the call before measuring.
+        if let Poll::Ready(response) = poll!(call.as_mut()) {
+            return response;
+        }
+        service.update();
+        call.await
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<S, M> Load for WatchLoad<S, M>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<S, M> Describe for WatchLoad<S, M>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("WatchLoad").with_child(self.inner.describe())
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use std::{
+        pin::pin,
+        time::{Duration, Instant},
+    };
+
+    use futures_util::{poll, FutureExt};
+
+    use crate::{service_fn, Service, ServiceExt};
+
+    use super::{Load, RttEstimate};
+
+    #[tokio::test]
+    async fn queued_acquires() {
+        let svc = service_fn(|x: u32| async move { x })
+            .concurrency_limit(1)
+            .pending_requests();
+        let permit = svc.acquire().await;
+        assert_eq!(svc.load(), 1);
+
+        // Waiting behind the concurrency limit counts as pending.
+        {
+            let mut queued = pin!(svc.acquire());
+            assert!(poll!(queued.as_mut()).is_pending());
+            assert_eq!(svc.load(), 2);
+        }
+        assert_eq!(svc.load(), 1);
+
+        drop(permit);
+        assert_eq!(svc.load(), 0);
+        assert!(svc.oneshot(1).now_or_never().is_some());
+        assert_eq!(svc.load(), 0);
+    }
+
+    #[test]
+    fn peak_then_decay() {
+        let decay_ns = Duration::from_secs(1).as_nanos() as f64;
+        let start = Instant::now();
+        let mut estimate = RttEstimate {
+            rtt_ns: 100.0,
+            updated_at: start,
+        };
+
+        // Larger observations are adopted immediately.
+        assert_eq!(
+            estimate.update(Duration::from_nanos(1_000), start, decay_ns),
+            1_000.0
+        );
+
+        // Smaller observations are blended in according to the elapsed time.
+        let later = start + Duration::from_secs(1);
+        let next = estimate.update(Duration::from_nanos(0), later, decay_ns);
+        assert!(next < 1_000.0 && next > 0.0);
+
+        // Without observations the estimate decays towards zero.
+        let much_later = later + Duration::from_secs(100);
+        assert!(estimate.decayed(much_later, decay_ns) < 1.0);
+    }
+
+    #[tokio::test]
+    async fn success_rate_window() {
+        let svc =
+            service_fn(|x: u32| async move { (x != 0).then_some(x).ok_or(x) }).success_rate(2, 2.0);
+        let _ = svc.oneshot(0).await;
+        assert_eq!(svc.load(), 3.0);
+
+        // Pending requests are inflated by the failures.
+        let permit = svc.acquire().await;
+        assert_eq!(svc.load(), 6.0);
+        drop(permit);
+
+        // Failures are forgotten once they leave the window.
+        let _ = svc.oneshot(1).await;
+        assert_eq!(svc.success_ratio(), 0.5);
+        let _ = svc.oneshot(1).await;
+        assert_eq!(svc.success_ratio(), 1.0);
+        assert_eq!(svc.load(), 1.0);
     }
 }
@@ -8,6 +8,16 @@
 //! This is a relative of [`ServiceExt::depressurize`](crate::ServiceExt::depressurize), which
 //! immediately accepts all work.
 //!
+//! The [`ServiceExt::load_shed_after`](crate::ServiceExt::load_shed_after) combinator returns
+//! [`LoadShedAfter`], a variant which only sheds once a threshold number of callers are already
+//! waiting for the inner permit. Below the threshold, [`Service::acquire`] waits as usual. A
+//! threshold of zero is equivalent to [`LoadShed`].
+//!
+//! The [`ServiceExt::load_shed_with`](crate::ServiceExt::load_shed_with) combinator returns
+//! [`LoadShedWith`], which sheds like [`LoadShed`] but responds to shed requests using a closure.
+//! This keeps the response type unchanged, rather than wrapping it in a [`Result`], for example
+//! when a rejection can be expressed as an HTTP 503 response.
+//!
 //! # Example
 //!
 //! ```rust
@@ -15,6 +25,7 @@
 //! # use tokio::time::sleep;
 //! # use std::time::Duration;
 //!
+//! # #[cfg(feature = "tokio")]
 //! # #[tokio::main]
 //! async fn main() {
 //! let svc = service_fn(|x| async move {
@@ -30,15 +41,56 @@
 //! assert_eq!(a, Ok(37));
 //! assert_eq!(b, Err(31));
 //! # }
+//! # #[cfg(not(feature = "tokio"))]
+//! # fn main() {}
+//! ```
+//!
+//! Synthesizing a rejection:
+//!
+//! ```rust
+//! use burger::*;
+//! # use tokio::time::sleep;
+//! # use std::time::Duration;
+//!
+//! # #[cfg(feature = "tokio")]
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc = service_fn(|x: u32| async move {
+//!     sleep(Duration::from_secs(1)).await;
+//!     format!("processed {x}")
+//! })
+//! .concurrency_limit(1)
+//! .load_shed_with(|x| format!("rejected {x}"));
+//! let (a, b) = tokio::join! {
+//!     svc.oneshot(1),
+//!     svc.oneshot(2)
+//! };
+//! assert_eq!(a, "processed 1");
+//! assert_eq!(b, "rejected 2");
+//! # }
+//! # #[cfg(not(feature = "tokio"))]
+//! # fn main() {}
 //! ```
 //!
 //! # Load
 //!
-//! The [`Load::load`] on [LoadShed] defers to the inner service.
+//! The [`Load::load`] on [LoadShed] and [LoadShedWith] defers to the inner service.
+//!
+//! The [`Load::load`] on [LoadShedAfter] is the number of callers waiting for the inner permit.
+
+use core::{
+    any, fmt,
+    pin::pin,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 use futures_util::FutureExt;
 
-use crate::{load::Load, Middleware, Service};
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Middleware, Service,
+};
 
 /// A wrapper [`Service`] for the [`ServiceExt::load_shed`](crate::ServiceExt::load_shed)
 /// combinator.
@@ -88,6 +140,15 @@ where
     }
 }
 
+impl<S> Describe for LoadShed<S>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("LoadShed").with_child(self.inner.describe())
+    }
+}
+
 impl<S, T> Middleware<S> for LoadShed<T>
 where
     T: Middleware<S>,
@@ -101,3 +162,243 @@ where
         }
     }
 }
+
+/// A wrapper [`Service`] for the
+/// [`ServiceExt::load_shed_with`](crate::ServiceExt::load_shed_with) combinator.
+///
+/// See the [module](crate::load_shed) for more information.
+#[derive(Clone)]
+pub struct LoadShedWith<S, F> {
+    inner: S,
+    closure: F,
+}
+
+impl<S, F> fmt::Debug for LoadShedWith<S, F>
+where
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoadShedWith")
+            .field("inner", &self.inner)
+            .field("closure", &format_args!("{}", any::type_name::<F>()))
+            .finish()
+    }
+}
+
+impl<S, F> LoadShedWith<S, F> {
+    pub(crate) fn new(inner: S, closure: F) -> Self {
+        LoadShedWith { inner, closure }
+    }
+}
+
+/// The [`Service::Permit`] type for [`LoadShedWith`].
+pub struct LoadShedWithPermit<'a, S, F, Request>
+where
+    S: Service<Request> + 'a,
+{
+    inner: Option<S::Permit<'a>>,
+    closure: &'a F,
+}
+
+impl<'a, S, F, Request> fmt::Debug for LoadShedWithPermit<'a, S, F, Request>
+where
+    S: Service<Request>,
+    S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoadShedWithPermit")
+            .field("inner", &self.inner)
+            .field("closure", &format_args!("{}", any::type_name::<F>()))
+            .finish()
+    }
+}
+
+impl<Request, S, F> Service<Request> for LoadShedWith<S, F>
+where
+    S: Service<Request>,
+    F: Fn(Request) -> S::Response,
+{
+    type Response = S::Response;
+    type Permit<'a> = LoadShedWithPermit<'a, S, F, Request>
+    where
+        S: 'a, F: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        LoadShedWithPermit {
+            inner: self.inner.acquire().now_or_never(),
+            closure: &self.closure,
+        }
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        if let Some(inner) = permit.inner {
+            S::call(inner, request).await
+        } else {
+            (permit.closure)(request)
+        }
+    }
+}
+
+impl<S, F> Load for LoadShedWith<S, F>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S, F> Describe for LoadShedWith<S, F>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("LoadShedWith").with_child(self.inner.describe())
+    }
+}
+
+impl<S, T, F> Middleware<S> for LoadShedWith<T, F>
+where
+    T: Middleware<S>,
+{
+    type Service = LoadShedWith<T::Service, F>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self { inner, closure } = self;
+        LoadShedWith {
+            inner: inner.apply(svc),
+            closure,
+        }
+    }
+}
+
+/// A wrapper [`Service`] for the
+/// [`ServiceExt::load_shed_after`](crate::ServiceExt::load_shed_after) combinator.
+///
+/// See the [module](crate::load_shed) for more information.
+#[derive(Debug)]
+pub struct LoadShedAfter<S> {
+    inner: S,
+    waiting: AtomicUsize,
+    threshold: usize,
+}
+
+impl<S> LoadShedAfter<S> {
+    pub(crate) fn new(inner: S, threshold: usize) -> Self {
+        LoadShedAfter {
+            inner,
+            waiting: AtomicUsize::new(0),
+            threshold,
+        }
+    }
+}
+
+/// Decrements the number of waiting callers on drop.
+struct Waiting<'a>(&'a AtomicUsize);
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+impl<Request, S> Service<Request> for LoadShedAfter<S>
+where
+    S: Service<Request>,
+{
+    type Response = Result<S::Response, Request>;
+    type Permit<'a> = Option<S::Permit<'a>>
+    where
+        S: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        let mut acquire = pin!(self.inner.acquire());
+        if let Some(permit) = acquire.as_mut().now_or_never() {
+            return Some(permit);
+        }
+        self.waiting
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |waiting| {
+                (waiting < self.threshold).then_some(waiting + 1)
+            })
+            .ok()?;
+        let _waiting = Waiting(&self.waiting);
+        Some(acquire.await)
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        if let Some(permit) = permit {
+            Ok(S::call(permit, request).await)
+        } else {
+            Err(request)
+        }
+    }
+}
+
+impl<S> Load for LoadShedAfter<S> {
+    type Metric = usize;
+
+    fn load(&self) -> Self::Metric {
+        self.waiting.load(Ordering::Acquire)
+    }
+}
+
+impl<S> Describe for LoadShedAfter<S>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("LoadShedAfter")
+            .with_config("threshold", self.threshold)
+            .with_child(self.inner.describe())
+    }
+}
+
+impl<S, T> Middleware<S> for LoadShedAfter<T>
+where
+    T: Middleware<S>,
+{
+    type Service = LoadShedAfter<T::Service>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self {
+            inner,
+            waiting,
+            threshold,
+        } = self;
+        LoadShedAfter {
+            inner: inner.apply(svc),
+            waiting,
+            threshold,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::sleep;
+
+    use crate::{load::Load, service_fn, ServiceExt};
+
+    #[tokio::test]
+    async fn threshold() {
+        let svc = service_fn(|x: u32| async move {
+            sleep(Duration::from_millis(50)).await;
+            x
+        })
+        .concurrency_limit(1)
+        .load_shed_after(1);
+
+        // The first is permitted, the second waits and the third is shed.
+        let (a, b, c, load) = tokio::join!(svc.oneshot(1), svc.oneshot(2), svc.oneshot(3), async {
+            sleep(Duration::from_millis(10)).await;
+            svc.load()
+        });
+        assert_eq!((a, b, c), (Ok(1), Ok(2), Err(3)));
+        assert_eq!(load, 1);
+        assert_eq!(svc.load(), 0);
+    }
+}
@@ -0,0 +1,640 @@
+//! A [`MakeService`] is a [`Service`] whose requests are targets, such as addresses, and whose
+//! responses are fallibly constructed [services](Service), such as connections to those targets.
+//! The trait is implemented for every [`Service`] with a [`Result`] response, so a make service
+//! can be built from any constructor, for example [`service_fn`](fn@crate::service_fn).
+//!
+//! Two constructors manage the services made for a single target:
+//!
+//! - [`reconnect`] returns [`Reconnect`], which holds a single service shared by all calls. The
+//!   service is made by the first [`Service::acquire`], and made again once a closure classifies a
+//!   response as indicating that it's broken.
+//! - [`pool`] returns [`Pool`], which holds up to a maximum number of services, each used by one
+//!   call at a time. Services are made on demand and, once a call completes, kept for reuse unless
+//!   a closure classifies the response as indicating that the service is broken.
+//!
+//! If making a service fails, [`Service::call`] returns the error rather than calling. The call
+//! isn't retried by these constructors, but may be by
+//! [`ServiceExt::retry`](crate::ServiceExt::retry), and [`Reconnect`] can delay the next attempt
+//! to make the service.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::{
+//!     convert::Infallible,
+//!     sync::atomic::{AtomicUsize, Ordering},
+//! };
+//!
+//! use burger::*;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let connections = AtomicUsize::new(0);
+//! let connect = service_fn(|addr: &'static str| {
+//!     let id = connections.fetch_add(1, Ordering::SeqCst);
+//!     // Stands in for a connection which fails its second request.
+//!     let connection = service_fn(move |x: u32| async move {
+//!         if x == 2 {
+//!             Err(format!("{addr} reset"))
+//!         } else {
+//!             Ok(id)
+//!         }
+//!     });
+//!     async move { Ok::<_, Infallible>(connection) }
+//! });
+//!
+//! let svc = make::reconnect(connect, "10.0.0.1:6379", |response: &Result<_, _>| {
+//!     response.is_err()
+//! });
+//! assert_eq!(svc.oneshot(1).await, Ok(Ok(0)));
+//! assert!(svc.oneshot(2).await.unwrap().is_err());
+//! // The broken connection was replaced.
+//! assert_eq!(svc.oneshot(3).await, Ok(Ok(1)));
+//! # }
+//! ```
+//!
+//! # Backoff
+//!
+//! [`Reconnect::with_backoff`] sets a [`Backoff`], from the
+//! [`retry::backoff`](crate::retry::backoff) module, which delays each attempt to make the service
+//! following a failed one. The failure is returned to the caller whose attempt failed, while
+//! subsequent callers wait in [`Service::acquire`] for the delay and the next attempt, so a
+//! long-lived client rides over transient failures without further supervision. The schedule is
+//! reset once a service is made.
+//!
+//! ```rust
+//! use std::{
+//!     sync::atomic::{AtomicUsize, Ordering},
+//!     time::Duration,
+//! };
+//!
+//! use burger::{retry::backoff::Exponential, *};
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let attempts = AtomicUsize::new(0);
+//! let connect = service_fn(|addr: &'static str| {
+//!     let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+//!     async move {
+//!         if attempt == 0 {
+//!             return Err("connection refused");
+//!         }
+//!         Ok(service_fn(move |x: u32| async move { Ok::<_, ()>((addr, x)) }))
+//!     }
+//! });
+//! let backoff = Exponential::new(Duration::from_millis(10), 2.0, Duration::from_secs(1));
+//! let svc = make::reconnect(connect, "10.0.0.1:6379", |response: &Result<_, _>| {
+//!     response.is_err()
+//! })
+//! .with_backoff(backoff);
+//! assert_eq!(svc.oneshot(1).await, Err("connection refused"));
+//! assert_eq!(svc.oneshot(2).await, Ok(Ok(("10.0.0.1:6379", 2))));
+//! # }
+//! ```
+//!
+//! # Connection pools
+//!
+//! A [`Pool`] can be made for each target produced by service discovery, and the pools then
+//! balanced, using the [`balance`](crate::balance) module.
+//!
+//! ```rust
+//! use std::{convert::Infallible, future::ready};
+//!
+//! use burger::*;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let connect = |addr: &'static str| {
+//!     let connection = service_fn(move |x: u32| ready((addr, x)));
+//!     ready(Ok::<_, Infallible>(connection))
+//! };
+//! let targets = ["10.0.0.1:6379", "10.0.0.2:6379"];
+//! let pools = targets.map(|addr| (addr, make::pool(service_fn(connect), addr, 4, |_: &_| false)));
+//! let (svc, worker) = balance::p2c(discover::fixed(pools));
+//! tokio::spawn(worker);
+//! let (addr, response) = svc.oneshot(5).await.unwrap();
+//! assert!(targets.contains(&addr));
+//! assert_eq!(response, 5);
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`Reconnect`] defers to the current service, or is the default metric
+//! while there is none. The [`Load::load`] on [`Pool`] is the number of services in use, as
+//! returned by [`Pool::in_use`].
+
+use std::{
+    any, fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use tokio::sync::{Mutex as AsyncMutex, SemaphorePermit};
+
+use crate::{
+    describe::{Describe, StackNode},
+    leak::OwnedPermit,
+    limit::{FixedLimit, Permits},
+    load::Load,
+    retry::backoff::{Backoff, Fixed},
+    rt, Service, ServiceExt,
+};
+
+/// A [`Service`] which makes services for a target.
+///
+/// See the [module](crate::make) for more information.
+pub trait MakeService<Target>:
+    Service<Target, Response = Result<Self::Service, Self::Error>>
+{
+    /// The [`Service`] made.
+    type Service;
+    /// The error returned if a [`Service`] can't be made.
+    type Error;
+}
+
+impl<Target, S, E, M> MakeService<Target> for M
+where
+    M: Service<Target, Response = Result<S, E>>,
+{
+    type Service = S;
+    type Error = E;
+}
+
+/// The [`Service`] returned by the [`reconnect`] constructor.
+///
+/// See the [module](crate::make) for more information.
+pub struct Reconnect<M, Target, S, F, B = Fixed> {
+    make: M,
+    target: Target,
+    is_broken: F,
+    backoff: B,
+    current: Mutex<Option<Arc<S>>>,
+    connecting: AsyncMutex<Attempts>,
+}
+
+/// The failed attempts to make a service since one was last made.
+#[derive(Debug)]
+struct Attempts {
+    failures: usize,
+    last_failure: Option<Instant>,
+}
+
+impl<M, Target, S, F, B> fmt::Debug for Reconnect<M, Target, S, F, B>
+where
+    M: fmt::Debug,
+    Target: fmt::Debug,
+    S: fmt::Debug,
+    B: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Reconnect")
+            .field("make", &self.make)
+            .field("target", &self.target)
+            .field("is_broken", &format_args!("{}", any::type_name::<F>()))
+            .field("backoff", &self.backoff)
+            .field("current", &self.current)
+            .field("connecting", &self.connecting)
+            .finish()
+    }
+}
+
+impl<M, Target, S, F, B> Reconnect<M, Target, S, F, B> {
+    /// Sets the [`Backoff`] between failed attempts to make the service.
+    ///
+    /// See the [module](crate::make#backoff) for more information.
+    pub fn with_backoff<B2>(self, backoff: B2) -> Reconnect<M, Target, S, F, B2> {
+        let Self {
+            make,
+            target,
+            is_broken,
+            backoff: _,
+            current,
+            connecting,
+        } = self;
+        Reconnect {
+            make,
+            target,
+            is_broken,
+            backoff,
+            current,
+            connecting,
+        }
+    }
+
+    /// Returns whether a service is currently held.
+    pub fn is_connected(&self) -> bool {
+        self.current.lock().unwrap().is_some()
+    }
+
+    fn current(&self) -> Option<Arc<S>> {
+        self.current.lock().unwrap().clone()
+    }
+
+    /// Drops the service, unless it has already been replaced.
+    fn disconnect(&self, service: &Arc<S>) {
+        let mut current = self.current.lock().unwrap();
+        if current
+            .as_ref()
+            .is_some_and(|current| Arc::ptr_eq(current, service))
+        {
+            *current = None;
+        }
+    }
+
+    async fn connect(&self) -> Result<Arc<S>, M::Error>
+    where
+        M: MakeService<Target, Service = S>,
+        Target: Clone,
+        B: Backoff,
+    {
+        if let Some(service) = self.current() {
+            return Ok(service);
+        }
+        let mut attempts = self.connecting.lock().await;
+        // Another caller may have connected while this one waited.
+        if let Some(service) = self.current() {
+            return Ok(service);
+        }
+        if let Some(last_failure) = attempts.last_failure {
+            let delay = self.backoff.delay(attempts.failures - 1);
+            if delay > Duration::ZERO {
+                rt::sleep_until(last_failure + delay).await;
+            }
+        }
+        tracing::trace!(failures = attempts.failures, "making service");
+        match self.make.oneshot(self.target.clone()).await {
+            Ok(service) => {
+                *attempts = Attempts {
+                    failures: 0,
+                    last_failure: None,
+                };
+                let service = Arc::new(service);
+                *self.current.lock().unwrap() = Some(service.clone());
+                Ok(service)
+            }
+            Err(error) => {
+                attempts.failures += 1;
+                attempts.last_failure = Some(Instant::now());
+                Err(error)
+            }
+        }
+    }
+}
+
+/// The current service, alongside a permit acquired from it.
+type Connected<S, Request> = (Arc<S>, OwnedPermit<S, Request>);
+
+/// The [`Service::Permit`] type for [`Reconnect`].
+pub struct ReconnectPermit<'a, M, Target, S, F, Request, B = Fixed>
+where
+    M: MakeService<Target>,
+    S: Service<Request> + 'static,
+{
+    inner: Result<Connected<S, Request>, M::Error>,
+    service: &'a Reconnect<M, Target, S, F, B>,
+}
+
+impl<M, Target, S, F, Request, B> fmt::Debug for ReconnectPermit<'_, M, Target, S, F, Request, B>
+where
+    M: MakeService<Target> + fmt::Debug,
+    M::Error: fmt::Debug,
+    Target: fmt::Debug,
+    S: Service<Request> + fmt::Debug + 'static,
+    for<'a> S::Permit<'a>: fmt::Debug,
+    B: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReconnectPermit")
+            .field("inner", &self.inner)
+            .field("service", &self.service)
+            .finish()
+    }
+}
+
+impl<Request, M, Target, S, F, B> Service<Request> for Reconnect<M, Target, S, F, B>
+where
+    M: MakeService<Target, Service = S>,
+    Target: Clone,
+    S: Service<Request> + 'static,
+    F: Fn(&S::Response) -> bool,
+    B: Backoff,
+{
+    type Response = Result<S::Response, M::Error>;
+    type Permit<'a> = ReconnectPermit<'a, M, Target, S, F, Request, B>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        let inner = match self.connect().await {
+            Ok(service) => Ok((service.clone(), service.acquire_owned().await)),
+            Err(error) => Err(error),
+        };
+        ReconnectPermit {
+            inner,
+            service: self,
+        }
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        let ReconnectPermit { inner, service } = permit;
+        let (connection, permit) = inner?;
+        let response = permit.call(request).await;
+        if (service.is_broken)(&response) {
+            tracing::trace!("service broken");
+            service.disconnect(&connection);
+        }
+        Ok(response)
+    }
+}
+
+impl<M, Target, S, F, B> Load for Reconnect<M, Target, S, F, B>
+where
+    S: Load,
+    S::Metric: Default,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.current()
+            .map(|service| service.load())
+            .unwrap_or_default()
+    }
+}
+
+impl<M, Target, S, F, B> Describe for Reconnect<M, Target, S, F, B>
+where
+    M: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("Reconnect").with_child(self.make.describe())
+    }
+}
+
+/// Constructs a [`Reconnect`], which makes a single service for the target, and makes it again
+/// once `is_broken` returns `true` for a response. Failed attempts to make the service aren't
+/// delayed until a [`Backoff`] is set using [`Reconnect::with_backoff`].
+///
+/// See the [module](crate::make) for more information.
+pub fn reconnect<M, Target, F>(
+    make: M,
+    target: Target,
+    is_broken: F,
+) -> Reconnect<M, Target, M::Service, F>
+where
+    M: MakeService<Target>,
+{
+    Reconnect {
+        make,
+        target,
+        is_broken,
+        backoff: Fixed::new(Duration::ZERO),
+        current: Mutex::new(None),
+        connecting: AsyncMutex::new(Attempts {
+            failures: 0,
+            last_failure: None,
+        }),
+    }
+}
+
+/// The [`Service`] returned by the [`pool`] constructor.
+///
+/// See the [module](crate::make) for more information.
+pub struct Pool<M, Target, S, F> {
+    make: M,
+    target: Target,
+    is_broken: F,
+    idle: Mutex<Vec<S>>,
+    semaphore: FixedLimit,
+    max_size: usize,
+}
+
+impl<M, Target, S, F> fmt::Debug for Pool<M, Target, S, F>
+where
+    M: fmt::Debug,
+    Target: fmt::Debug,
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pool")
+            .field("make", &self.make)
+            .field("target", &self.target)
+            .field("is_broken", &format_args!("{}", any::type_name::<F>()))
+            .field("idle", &self.idle)
+            .field("semaphore", &self.semaphore)
+            .field("max_size", &self.max_size)
+            .finish()
+    }
+}
+
+impl<M, Target, S, F> Pool<M, Target, S, F> {
+    /// Returns the number of services in use by a permit.
+    pub fn in_use(&self) -> usize {
+        self.max_size - self.semaphore.available_permits()
+    }
+
+    /// Returns the number of services made and awaiting reuse.
+    pub fn idle(&self) -> usize {
+        self.idle.lock().unwrap().len()
+    }
+}
+
+/// A service taken from a [`Pool`], returned to it on drop unless discarded.
+struct Checkout<'a, S> {
+    service: Option<S>,
+    idle: &'a Mutex<Vec<S>>,
+}
+
+impl<S> Drop for Checkout<'_, S> {
+    fn drop(&mut self) {
+        if let Some(service) = self.service.take() {
+            self.idle.lock().unwrap().push(service);
+        }
+    }
+}
+
+/// The [`Service::Permit`] type for [`Pool`].
+pub struct PoolPermit<'a, M, Target, S, F>
+where
+    M: MakeService<Target>,
+{
+    // Returns the service before releasing the slot, so the next caller can reuse it.
+    inner: Result<Checkout<'a, S>, M::Error>,
+    pool: &'a Pool<M, Target, S, F>,
+    _slot: SemaphorePermit<'a>,
+}
+
+impl<M, Target, S, F> fmt::Debug for PoolPermit<'_, M, Target, S, F>
+where
+    M: MakeService<Target> + fmt::Debug,
+    M::Error: fmt::Debug,
+    Target: fmt::Debug,
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let service = self
+            .inner
+            .as_ref()
+            .map(|checkout| checkout.service.as_ref());
+        f.debug_struct("PoolPermit")
+            .field("inner", &service)
+            .field("pool", &self.pool)
+            .field("_slot", &self._slot)
+            .finish()
+    }
+}
+
+impl<Request, M, Target, S, F> Service<Request> for Pool<M, Target, S, F>
+where
+    M: MakeService<Target, Service = S>,
+    Target: Clone,
+    S: Service<Request>,
+    F: Fn(&S::Response) -> bool,
+{
+    type Response = Result<S::Response, M::Error>;
+    type Permit<'a> = PoolPermit<'a, M, Target, S, F>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        let slot = self.semaphore.acquire().await;
+        let idle = self.idle.lock().unwrap().pop();
+        let service = match idle {
+            Some(service) => Ok(service),
+            None => {
+                tracing::trace!("making service");
+                self.make.oneshot(self.target.clone()).await
+            }
+        };
+        PoolPermit {
+            inner: service.map(|service| Checkout {
+                service: Some(service),
+                idle: &self.idle,
+            }),
+            pool: self,
+            _slot: slot,
+        }
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        let PoolPermit { inner, pool, _slot } = permit;
+        let mut checkout = inner?;
+        let service = checkout.service.as_ref().expect("present until dropped");
+        let response = service.oneshot(request).await;
+        if (pool.is_broken)(&response) {
+            tracing::trace!("service broken");
+            checkout.service = None;
+        }
+        Ok(response)
+    }
+}
+
+impl<M, Target, S, F> Load for Pool<M, Target, S, F> {
+    type Metric = usize;
+
+    fn load(&self) -> Self::Metric {
+        self.in_use()
+    }
+}
+
+impl<M, Target, S, F> Describe for Pool<M, Target, S, F>
+where
+    M: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("Pool")
+            .with_config("max_size", self.max_size)
+            .with_child(self.make.describe())
+    }
+}
+
+/// Constructs a [`Pool`], which makes up to `max_size` services for the target, each used by one
+/// call at a time, and discards a service once `is_broken` returns `true` for a response.
+///
+/// See the [module](crate::make) for more information.
+pub fn pool<M, Target, F>(
+    make: M,
+    target: Target,
+    max_size: usize,
+    is_broken: F,
+) -> Pool<M, Target, M::Service, F>
+where
+    M: MakeService<Target>,
+{
+    Pool {
+        make,
+        target,
+        is_broken,
+        idle: Mutex::new(Vec::new()),
+        semaphore: FixedLimit::new(max_size),
+        max_size,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        future::ready,
+        sync::atomic::{AtomicUsize, Ordering},
+        time::{Duration, Instant},
+    };
+
+    use crate::{retry::backoff::Fixed, service_fn, Service, ServiceExt};
+
+    use super::{pool, reconnect, Pool};
+
+    #[tokio::test]
+    async fn reconnect_backs_off() {
+        let attempts = AtomicUsize::new(0);
+        let make = service_fn(|()| {
+            let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+            ready(match attempt {
+                0 | 1 => Err(attempt),
+                _ => Ok(service_fn(|x: u32| ready(x))),
+            })
+        });
+        let svc = reconnect(make, (), |_: &u32| false)
+            .with_backoff(Fixed::new(Duration::from_millis(20)));
+
+        let start = Instant::now();
+        assert_eq!(svc.oneshot(1).await, Err(0));
+        assert_eq!(svc.oneshot(1).await, Err(1));
+        assert_eq!(svc.oneshot(1).await, Ok(1));
+        assert!(start.elapsed() >= Duration::from_millis(40));
+        assert!(svc.is_connected());
+    }
+
+    #[tokio::test]
+    async fn pool_reuses_services() {
+        let made = AtomicUsize::new(0);
+        let make = service_fn(|()| {
+            let id = made.fetch_add(1, Ordering::SeqCst);
+            ready(Ok::<_, Infallible>(service_fn(move |x: u32| {
+                ready((id, x))
+            })))
+        });
+        let svc = pool(make, (), 2, |(_, x): &(usize, u32)| *x == 0);
+
+        let first = svc.acquire().await;
+        let second = svc.acquire().await;
+        assert_eq!(svc.in_use(), 2);
+        drop(first);
+        assert_eq!(svc.idle(), 1);
+        assert_eq!(Pool::call(second, 1).await, Ok((1, 1)));
+
+        // Both services are reused, until one is broken.
+        assert_eq!(svc.idle(), 2);
+        assert_eq!(svc.oneshot(0).await, Ok((1, 0)));
+        assert_eq!(svc.idle(), 1);
+        assert_eq!(svc.oneshot(2).await, Ok((0, 2)));
+        assert_eq!(made.load(Ordering::SeqCst), 2);
+        assert_eq!(svc.in_use(), 0);
+    }
+}
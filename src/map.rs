@@ -18,11 +18,15 @@
 //!
 //! # Load
 //!
-//! [`Load`](crate::load::Load) measurements defer to the inner service.
+//! The [`Load::load`] on [`Map`] defers to the inner service.
 
-use std::{any, fmt};
+use core::{any, fmt};
 
-use crate::{Middleware, Service};
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Middleware, Service,
+};
 
 /// A wrapper [`Service`] for the [`ServiceExt::map`](crate::ServiceExt::map) combinator.
 ///
@@ -81,6 +85,26 @@ where
     }
 }
 
+impl<S, F> Load for Map<S, F>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S, F> Describe for Map<S, F>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("Map").with_child(self.inner.describe())
+    }
+}
+
 impl<S, T, F> Middleware<S> for Map<T, F>
 where
     T: Middleware<S>,
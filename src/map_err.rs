@@ -0,0 +1,126 @@
+//! The [`ServiceExt::map_err`](crate::ServiceExt::map_err) combinator returns [`MapErr`], which
+//! extends a [fallible service](crate::TryService) with a closure modifying the [`Err`] variant of
+//! the [`Service::Response`]. The [`Ok`] variant is passed through unchanged.
+//!
+//! For an asynchronous version see the [or_else](mod@crate::or_else) module.
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::*;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc = service_fn(|x: u32| async move { x.checked_sub(3).ok_or(x) })
+//!     .map_err(|x| format!("{x} is too small"));
+//! assert_eq!(svc.oneshot(5).await, Ok(2));
+//! assert_eq!(svc.oneshot(2).await, Err("2 is too small".to_string()));
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`MapErr`] defers to the inner service.
+
+use core::{any, fmt};
+
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Middleware, Service, TryService,
+};
+
+/// A wrapper [`Service`] for the [`ServiceExt::map_err`](crate::ServiceExt::map_err) combinator.
+///
+/// See the [module](crate::map_err) for more information.
+#[derive(Clone, Debug)]
+pub struct MapErr<S, F> {
+    inner: S,
+    closure: F,
+}
+
+impl<S, F> MapErr<S, F> {
+    pub(crate) fn new(inner: S, closure: F) -> Self {
+        Self { inner, closure }
+    }
+}
+
+/// The [`Service::Permit`] type for [`MapErr`].
+pub struct MapErrPermit<'a, S, F, Request>
+where
+    S: Service<Request> + 'a,
+{
+    inner: S::Permit<'a>,
+    closure: &'a F,
+}
+
+impl<'a, S, F, Request> fmt::Debug for MapErrPermit<'a, S, F, Request>
+where
+    S: Service<Request>,
+    S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MapErrPermit")
+            .field("inner", &self.inner)
+            .field("closure", &format_args!("{}", any::type_name::<F>()))
+            .finish()
+    }
+}
+
+impl<Request, S, F, Error> Service<Request> for MapErr<S, F>
+where
+    S: TryService<Request>,
+    F: Fn(S::Error) -> Error,
+{
+    type Response = Result<S::Ok, Error>;
+    type Permit<'a> = MapErrPermit<'a, S, F, Request>
+    where
+        S: 'a,
+        F: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        MapErrPermit {
+            inner: self.inner.acquire().await,
+            closure: &self.closure,
+        }
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        S::call(permit.inner, request).await.map_err(permit.closure)
+    }
+}
+
+impl<S, F> Load for MapErr<S, F>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S, F> Describe for MapErr<S, F>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("MapErr").with_child(self.inner.describe())
+    }
+}
+
+impl<S, T, F> Middleware<S> for MapErr<T, F>
+where
+    T: Middleware<S>,
+{
+    type Service = MapErr<T::Service, F>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self { inner, closure } = self;
+        MapErr {
+            inner: inner.apply(svc),
+            closure,
+        }
+    }
+}
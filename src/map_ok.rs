@@ -0,0 +1,126 @@
+//! The [`ServiceExt::map_ok`](crate::ServiceExt::map_ok) combinator returns [`MapOk`], which
+//! extends a [fallible service](crate::TryService) with a closure modifying the [`Ok`] variant of
+//! the [`Service::Response`]. The [`Err`] variant is passed through unchanged.
+//!
+//! For an asynchronous version see the [and_then](mod@crate::and_then) module.
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::*;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc = service_fn(|x: u32| async move { x.checked_sub(3).ok_or("underflow") })
+//!     .map_ok(|x: u32| x.to_string());
+//! assert_eq!(svc.oneshot(5).await, Ok("2".to_string()));
+//! assert_eq!(svc.oneshot(2).await, Err("underflow"));
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`MapOk`] defers to the inner service.
+
+use core::{any, fmt};
+
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Middleware, Service, TryService,
+};
+
+/// A wrapper [`Service`] for the [`ServiceExt::map_ok`](crate::ServiceExt::map_ok) combinator.
+///
+/// See the [module](crate::map_ok) for more information.
+#[derive(Clone, Debug)]
+pub struct MapOk<S, F> {
+    inner: S,
+    closure: F,
+}
+
+impl<S, F> MapOk<S, F> {
+    pub(crate) fn new(inner: S, closure: F) -> Self {
+        Self { inner, closure }
+    }
+}
+
+/// The [`Service::Permit`] type for [`MapOk`].
+pub struct MapOkPermit<'a, S, F, Request>
+where
+    S: Service<Request> + 'a,
+{
+    inner: S::Permit<'a>,
+    closure: &'a F,
+}
+
+impl<'a, S, F, Request> fmt::Debug for MapOkPermit<'a, S, F, Request>
+where
+    S: Service<Request>,
+    S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MapOkPermit")
+            .field("inner", &self.inner)
+            .field("closure", &format_args!("{}", any::type_name::<F>()))
+            .finish()
+    }
+}
+
+impl<Request, S, F, Output> Service<Request> for MapOk<S, F>
+where
+    S: TryService<Request>,
+    F: Fn(S::Ok) -> Output,
+{
+    type Response = Result<Output, S::Error>;
+    type Permit<'a> = MapOkPermit<'a, S, F, Request>
+    where
+        S: 'a,
+        F: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        MapOkPermit {
+            inner: self.inner.acquire().await,
+            closure: &self.closure,
+        }
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        S::call(permit.inner, request).await.map(permit.closure)
+    }
+}
+
+impl<S, F> Load for MapOk<S, F>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S, F> Describe for MapOk<S, F>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("MapOk").with_child(self.inner.describe())
+    }
+}
+
+impl<S, T, F> Middleware<S> for MapOk<T, F>
+where
+    T: Middleware<S>,
+{
+    type Service = MapOk<T::Service, F>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self { inner, closure } = self;
+        MapOk {
+            inner: inner.apply(svc),
+            closure,
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! The [`ServiceExt::map_request`](crate::ServiceExt::map_request) combinator returns
+//! [`MapRequest`], which extends a service with a closure modifying the request before it is passed
+//! to the inner [`Service::call`].
+//!
+//! For an asynchronous version of this combinator see the [then_request](mod@crate::then_request)
+//! module.
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::*;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc = service_fn(|x: u32| async move { x + 1 }).map_request(|x: &str| x.len() as u32);
+//! let response = svc.oneshot("hello").await;
+//! assert_eq!(response, 6);
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`MapRequest`] defers to the inner service.
+
+use core::{any, fmt};
+
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Middleware, Service,
+};
+
+/// A wrapper [`Service`] for the [`ServiceExt::map_request`](crate::ServiceExt::map_request) combinator.
+///
+/// See the [module](crate::map_request) for more information.
+#[derive(Clone, Debug)]
+pub struct MapRequest<S, F> {
+    inner: S,
+    closure: F,
+}
+
+impl<S, F> MapRequest<S, F> {
+    pub(crate) fn new(inner: S, closure: F) -> Self {
+        Self { inner, closure }
+    }
+}
+
+/// The [`Service::Permit`] type for [`MapRequest`].
+pub struct MapRequestPermit<'a, S, F, Request>
+where
+    S: Service<Request> + 'a,
+{
+    inner: S::Permit<'a>,
+    closure: &'a F,
+}
+
+impl<'a, S, F, Request> fmt::Debug for MapRequestPermit<'a, S, F, Request>
+where
+    S: Service<Request>,
+    S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MapRequestPermit")
+            .field("inner", &self.inner)
+            .field("closure", &format_args!("{}", any::type_name::<F>()))
+            .finish()
+    }
+}
+
+impl<Request, S, F, Inner> Service<Request> for MapRequest<S, F>
+where
+    S: Service<Inner>,
+    F: Fn(Request) -> Inner,
+{
+    type Response = S::Response;
+    type Permit<'a> = MapRequestPermit<'a, S, F, Inner>
+    where
+        S: 'a,
+        F: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        MapRequestPermit {
+            inner: self.inner.acquire().await,
+            closure: &self.closure,
+        }
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        S::call(permit.inner, (permit.closure)(request)).await
+    }
+}
+
+impl<S, F> Load for MapRequest<S, F>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S, F> Describe for MapRequest<S, F>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("MapRequest").with_child(self.inner.describe())
+    }
+}
+
+impl<S, T, F> Middleware<S> for MapRequest<T, F>
+where
+    T: Middleware<S>,
+{
+    type Service = MapRequest<T::Service, F>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self { inner, closure } = self;
+        MapRequest {
+            inner: inner.apply(svc),
+            closure,
+        }
+    }
+}
@@ -0,0 +1,276 @@
+//! The [`ServiceExt::metrics`](crate::ServiceExt::metrics) combinator returns [`Metrics`], which
+//! reports the [calls](Service::call) to a service through a [`MetricsRecorder`].
+//!
+//! Each call is reported to [`MetricsRecorder::call_started`] when it begins and to
+//! [`MetricsRecorder::call_finished`] when it either completes or is cancelled, along with its
+//! latency. From these a recorder can derive a request count, a latency histogram and an in-flight
+//! gauge. Permits which are acquired but never used are not reported.
+//!
+//! With the `metrics` feature enabled, [`Facade`] records to the [`metrics`](::metrics) crate.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::{
+//!     sync::atomic::{AtomicUsize, Ordering},
+//!     time::Duration,
+//! };
+//!
+//! use burger::{metrics::MetricsRecorder, *};
+//!
+//! #[derive(Default)]
+//! struct Counts {
+//!     in_flight: AtomicUsize,
+//!     completed: AtomicUsize,
+//! }
+//!
+//! impl MetricsRecorder for Counts {
+//!     fn call_started(&self) {
+//!         self.in_flight.fetch_add(1, Ordering::Relaxed);
+//!     }
+//!
+//!     fn call_finished(&self, _latency: Duration, completed: bool) {
+//!         self.in_flight.fetch_sub(1, Ordering::Relaxed);
+//!         if completed {
+//!             self.completed.fetch_add(1, Ordering::Relaxed);
+//!         }
+//!     }
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let counts = Counts::default();
+//! let svc = service_fn(|x: u32| async move { x + 1 }).metrics(&counts);
+//! svc.oneshot(1).await;
+//! svc.oneshot(2).await;
+//! assert_eq!(counts.completed.load(Ordering::Relaxed), 2);
+//! assert_eq!(counts.in_flight.load(Ordering::Relaxed), 0);
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`Metrics`] defers to the inner service.
+
+use std::{
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Middleware, Service,
+};
+
+/// Receives the calls reported by [`Metrics`].
+///
+/// See the [module](crate::metrics) for more information.
+pub trait MetricsRecorder {
+    /// Records that a call has started.
+    fn call_started(&self);
+
+    /// Records that a call has finished after the specified latency, where `completed` is `false`
+    /// if it was cancelled.
+    fn call_finished(&self, latency: Duration, completed: bool);
+}
+
+impl<R> MetricsRecorder for &R
+where
+    R: MetricsRecorder,
+{
+    fn call_started(&self) {
+        R::call_started(self)
+    }
+
+    fn call_finished(&self, latency: Duration, completed: bool) {
+        R::call_finished(self, latency, completed)
+    }
+}
+
+impl<R> MetricsRecorder for Arc<R>
+where
+    R: MetricsRecorder,
+{
+    fn call_started(&self) {
+        R::call_started(self)
+    }
+
+    fn call_finished(&self, latency: Duration, completed: bool) {
+        R::call_finished(self, latency, completed)
+    }
+}
+
+/// A [`MetricsRecorder`] which records to the [`metrics`](::metrics) crate.
+///
+/// For a specified name, the following are recorded:
+///
+/// - `{name}_requests_total`, a counter labelled by `outcome`, either `completed` or `cancelled`.
+/// - `{name}_request_duration_seconds`, a histogram labelled by `outcome`.
+/// - `{name}_in_flight`, a gauge of the calls in progress.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone)]
+pub struct Facade {
+    requests: String,
+    duration: String,
+    in_flight: String,
+}
+
+#[cfg(feature = "metrics")]
+impl Facade {
+    /// Constructs a [`Facade`], prefixing each metric with the specified name.
+    pub fn new(name: &str) -> Self {
+        Self {
+            requests: format!("{name}_requests_total"),
+            duration: format!("{name}_request_duration_seconds"),
+            in_flight: format!("{name}_in_flight"),
+        }
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl MetricsRecorder for Facade {
+    fn call_started(&self) {
+        ::metrics::gauge!(self.in_flight.clone()).increment(1.0);
+    }
+
+    fn call_finished(&self, latency: Duration, completed: bool) {
+        let outcome = if completed { "completed" } else { "cancelled" };
+        ::metrics::gauge!(self.in_flight.clone()).decrement(1.0);
+        ::metrics::counter!(self.requests.clone(), "outcome" => outcome).increment(1);
+        ::metrics::histogram!(self.duration.clone(), "outcome" => outcome).record(latency);
+    }
+}
+
+/// A wrapper [`Service`] for the [`ServiceExt::metrics`](crate::ServiceExt::metrics) combinator.
+///
+/// See the [module](crate::metrics) for more information.
+#[derive(Clone, Debug)]
+pub struct Metrics<S, R> {
+    inner: S,
+    recorder: R,
+}
+
+impl<S, R> Metrics<S, R> {
+    pub(crate) fn new(inner: S, recorder: R) -> Self {
+        Self { inner, recorder }
+    }
+
+    /// Returns a reference to the [`MetricsRecorder`].
+    pub fn recorder(&self) -> &R {
+        &self.recorder
+    }
+}
+
+/// The [`Service::Permit`] type for [`Metrics`].
+pub struct MetricsPermit<'a, S, R, Request>
+where
+    S: Service<Request> + 'a,
+{
+    inner: S::Permit<'a>,
+    recorder: &'a R,
+}
+
+impl<'a, S, R, Request> fmt::Debug for MetricsPermit<'a, S, R, Request>
+where
+    S: Service<Request>,
+    S::Permit<'a>: fmt::Debug,
+    R: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MetricsPermit")
+            .field("inner", &self.inner)
+            .field("recorder", &self.recorder)
+            .finish()
+    }
+}
+
+/// Reports a call as finished on drop, as cancelled unless marked completed.
+struct Call<'a, R>
+where
+    R: MetricsRecorder,
+{
+    recorder: &'a R,
+    start: Instant,
+    completed: bool,
+}
+
+impl<R> Drop for Call<'_, R>
+where
+    R: MetricsRecorder,
+{
+    fn drop(&mut self) {
+        self.recorder
+            .call_finished(self.start.elapsed(), self.completed);
+    }
+}
+
+impl<Request, S, R> Service<Request> for Metrics<S, R>
+where
+    S: Service<Request>,
+    R: MetricsRecorder,
+{
+    type Response = S::Response;
+    type Permit<'a> = MetricsPermit<'a, S, R, Request>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        MetricsPermit {
+            inner: self.inner.acquire().await,
+            recorder: &self.recorder,
+        }
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        let MetricsPermit { inner, recorder } = permit;
+        recorder.call_started();
+        let mut call = Call {
+            recorder,
+            start: Instant::now(),
+            completed: false,
+        };
+        let response = S::call(inner, request).await;
+        call.completed = true;
+        response
+    }
+}
+
+impl<S, R> Load for Metrics<S, R>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S, R> Describe for Metrics<S, R>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("Metrics").with_child(self.inner.describe())
+    }
+}
+
+impl<S, T, R> Middleware<S> for Metrics<T, R>
+where
+    T: Middleware<S>,
+{
+    type Service = Metrics<T::Service, R>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self { inner, recorder } = self;
+        Metrics {
+            inner: inner.apply(svc),
+            recorder,
+        }
+    }
+}
@@ -0,0 +1,200 @@
+//! The [`ServiceExt::mirror`](crate::ServiceExt::mirror) combinator returns [`Mirror`], which sends
+//! a clone of a sample of requests to a secondary service, for example to dark launch a new
+//! backend with real traffic.
+//!
+//! The [`Service::acquire`] on [`Mirror`] acquires only the primary permit. The [`Service::call`]
+//! then, for a fraction `sample_rate` of requests, spawns a task, using [`spawn_local`], which
+//! calls the secondary service with a clone of the request. The primary call proceeds without
+//! waiting for the secondary, and the secondary response is dropped.
+//!
+//! The mirrored tasks aren't bounded, so a slow secondary service accumulates them. Applying
+//! [`ServiceExt::load_shed`](crate::ServiceExt::load_shed) to the secondary service drops mirrored
+//! requests which can't be permitted immediately.
+//!
+//! As with [`ServiceExt::spawned`](crate::ServiceExt::spawned), the task is spawned onto the
+//! current [`LocalSet`](tokio::task::LocalSet). So that a missing
+//! [`LocalSet`](tokio::task::LocalSet) isn't only found by the sampled calls, constructing a
+//! [`Mirror`] outside of one panics.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::{cell::Cell, rc::Rc};
+//!
+//! use burger::*;
+//! use tokio::task::LocalSet;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let mirrored = Rc::new(Cell::new(0));
+//! let secondary = service_fn({
+//!     let mirrored = mirrored.clone();
+//!     move |x: u32| {
+//!         mirrored.set(mirrored.get() + x);
+//!         async {}
+//!     }
+//! })
+//! .load_shed();
+//! LocalSet::new()
+//!     .run_until(async {
+//!         let svc = service_fn(|x: u32| async move { x + 1 }).mirror(secondary, 1.0);
+//!         assert_eq!(svc.oneshot(3).await, 4);
+//!         tokio::task::yield_now().await;
+//!     })
+//!     .await;
+//! assert_eq!(mirrored.get(), 3);
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`Mirror`] defers to the primary service.
+
+use std::{fmt, sync::Arc};
+
+use tokio::task::spawn_local;
+
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    random, Middleware, Service, ServiceExt,
+};
+
+/// A wrapper [`Service`] for the [`ServiceExt::mirror`](crate::ServiceExt::mirror) combinator.
+///
+/// See the [module](crate::mirror) for more information.
+#[derive(Debug)]
+pub struct Mirror<S, T> {
+    inner: S,
+    secondary: Arc<T>,
+    sample_rate: f64,
+}
+
+impl<S, T> Clone for Mirror<S, T>
+where
+    S: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            secondary: self.secondary.clone(),
+            sample_rate: self.sample_rate,
+        }
+    }
+}
+
+impl<S, T> Mirror<S, T> {
+    pub(crate) fn new(inner: S, secondary: T, sample_rate: f64) -> Self {
+        // Panics outside of a `LocalSet`, as a sampled call would.
+        drop(spawn_local(async {}));
+        Self {
+            inner,
+            secondary: Arc::new(secondary),
+            sample_rate: sample_rate.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Returns whether to mirror the next request.
+    fn sample(&self) -> bool {
+        random::next_f64() < self.sample_rate
+    }
+}
+
+/// The [`Service::Permit`] type for [`Mirror`].
+pub struct MirrorPermit<'a, S, T, Request>
+where
+    S: Service<Request> + 'a,
+{
+    inner: S::Permit<'a>,
+    secondary: Option<&'a Arc<T>>,
+}
+
+impl<'a, S, T, Request> fmt::Debug for MirrorPermit<'a, S, T, Request>
+where
+    S: Service<Request>,
+    S::Permit<'a>: fmt::Debug,
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MirrorPermit")
+            .field("inner", &self.inner)
+            .field("secondary", &self.secondary)
+            .finish()
+    }
+}
+
+impl<Request, S, T> Service<Request> for Mirror<S, T>
+where
+    S: Service<Request>,
+    T: Service<Request> + 'static,
+    Request: Clone + 'static,
+{
+    type Response = S::Response;
+    type Permit<'a> = MirrorPermit<'a, S, T, Request>
+    where
+        S: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        MirrorPermit {
+            inner: self.inner.acquire().await,
+            secondary: self.sample().then_some(&self.secondary),
+        }
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        let MirrorPermit { inner, secondary } = permit;
+        if let Some(secondary) = secondary {
+            let secondary = secondary.clone();
+            let request = request.clone();
+            spawn_local(async move {
+                secondary.oneshot(request).await;
+                tracing::trace!("mirrored call completed");
+            });
+        }
+        S::call(inner, request).await
+    }
+}
+
+impl<S, T> Load for Mirror<S, T>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S, T> Describe for Mirror<S, T>
+where
+    S: Describe,
+    T: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("Mirror")
+            .with_config("sample_rate", self.sample_rate)
+            .with_child(self.inner.describe())
+            .with_child(self.secondary.describe())
+    }
+}
+
+impl<S, U, T> Middleware<S> for Mirror<U, T>
+where
+    U: Middleware<S>,
+{
+    type Service = Mirror<U::Service, T>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self {
+            inner,
+            secondary,
+            sample_rate,
+        } = self;
+        Mirror {
+            inner: inner.apply(svc),
+            secondary,
+            sample_rate,
+        }
+    }
+}
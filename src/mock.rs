@@ -0,0 +1,295 @@
+//! The [`pair`] function returns a [`Mock`] [`Service`] and a [`Handle`], which together allow a
+//! test to play the role of an inner service, for example when testing a custom combinator or
+//! [`Middleware`](crate::Middleware).
+//!
+//! Each [call](Service::call) on the [`Mock`] sends its request to the [`Handle`] and waits. The
+//! test receives the request using [`Handle::next_request`], or asserts on it using
+//! [`Handle::assert_request_eq`], and then replies using [`SendResponse::send`].
+//!
+//! The [`Service::acquire`] on [`Mock`] is unrestricted by default. [`Handle::allow`] restricts the
+//! number of further permits which may be acquired, causing [`Service::acquire`] to wait once they
+//! are exhausted, and [`Handle::allow_unlimited`] lifts the restriction.
+//!
+//! A [call](Service::call) panics if the [`Handle`] is dropped, or if the [`SendResponse`] is
+//! dropped without a response being sent.
+//!
+//! This module is enabled by the `test-util` feature.
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::*;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let (svc, mut handle) = mock::pair::<u32, String>();
+//! let svc = svc.map_request(|x: u32| x + 1);
+//!
+//! let test = async {
+//!     handle.assert_request_eq(4).await.send("four".to_string());
+//! };
+//! let (response, ()) = tokio::join!(svc.oneshot(3), test);
+//! assert_eq!(response, "four");
+//!
+//! handle.allow(0);
+//! assert!(futures::FutureExt::now_or_never(svc.acquire()).is_none());
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`Mock`] is the number of [calls](Service::call) waiting for a response.
+
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use tokio::sync::{mpsc, oneshot, Notify};
+
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Service,
+};
+
+type Message<Request, Response> = (Request, oneshot::Sender<Response>);
+
+/// The state shared between [`Mock`] and its [`Handle`].
+#[derive(Debug)]
+struct Shared {
+    /// The number of permits which may be acquired, or [`None`] if unrestricted.
+    remaining: Mutex<Option<usize>>,
+    allowed: Notify,
+    pending: AtomicUsize,
+}
+
+/// The [`Service`] returned by the [`pair`] constructor.
+///
+/// See the [module](crate::mock) for more information.
+pub struct Mock<Request, Response> {
+    sender: mpsc::UnboundedSender<Message<Request, Response>>,
+    shared: Arc<Shared>,
+}
+
+impl<Request, Response> fmt::Debug for Mock<Request, Response> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mock")
+            .field("sender", &self.sender)
+            .field("shared", &self.shared)
+            .finish()
+    }
+}
+
+impl<Request, Response> Clone for Mock<Request, Response> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+/// The [`Service::Permit`] type for [`Mock`].
+pub struct MockPermit<'a, Request, Response> {
+    mock: &'a Mock<Request, Response>,
+}
+
+impl<Request, Response> fmt::Debug for MockPermit<'_, Request, Response> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockPermit")
+            .field("mock", &self.mock)
+            .finish()
+    }
+}
+
+/// Decrements the pending count on drop, including on cancellation.
+struct Pending<'a>(&'a AtomicUsize);
+
+impl Drop for Pending<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Release);
+    }
+}
+
+impl<Request, Response> Service<Request> for Mock<Request, Response> {
+    type Response = Response;
+    type Permit<'a> = MockPermit<'a, Request, Response>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        loop {
+            // Register for wakeups before checking, so that an allowance in between isn't missed.
+            let allowed = self.shared.allowed.notified();
+            {
+                let mut remaining = self.shared.remaining.lock().unwrap();
+                match remaining.as_mut() {
+                    None => break,
+                    Some(0) => {}
+                    Some(remaining) => {
+                        *remaining -= 1;
+                        break;
+                    }
+                }
+            }
+            allowed.await;
+        }
+        MockPermit { mock: self }
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        let mock = permit.mock;
+        mock.shared.pending.fetch_add(1, Ordering::Release);
+        let _pending = Pending(&mock.shared.pending);
+        let (sender, receiver) = oneshot::channel();
+        if mock.sender.send((request, sender)).is_err() {
+            panic!("mock handle dropped");
+        }
+        receiver.await.expect("response dropped without being sent")
+    }
+}
+
+impl<Request, Response> Load for Mock<Request, Response> {
+    type Metric = usize;
+
+    fn load(&self) -> Self::Metric {
+        self.shared.pending.load(Ordering::Acquire)
+    }
+}
+
+impl<Request, Response> Describe for Mock<Request, Response> {
+    fn describe(&self) -> StackNode {
+        StackNode::new("Mock")
+    }
+}
+
+/// A handle to the requests received by a [`Mock`], returned by [`pair`].
+///
+/// See the [module](crate::mock) for more information.
+pub struct Handle<Request, Response> {
+    receiver: mpsc::UnboundedReceiver<Message<Request, Response>>,
+    shared: Arc<Shared>,
+}
+
+impl<Request, Response> fmt::Debug for Handle<Request, Response> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Handle")
+            .field("receiver", &self.receiver)
+            .field("shared", &self.shared)
+            .finish()
+    }
+}
+
+impl<Request, Response> Handle<Request, Response> {
+    /// Allows a further `n` permits to be acquired from the [`Mock`], replacing any previous
+    /// allowance.
+    pub fn allow(&self, n: usize) {
+        *self.shared.remaining.lock().unwrap() = Some(n);
+        self.shared.allowed.notify_waiters();
+    }
+
+    /// Allows an unlimited number of permits to be acquired from the [`Mock`].
+    pub fn allow_unlimited(&self) {
+        *self.shared.remaining.lock().unwrap() = None;
+        self.shared.allowed.notify_waiters();
+    }
+
+    /// Waits for the next request, returning it alongside a [`SendResponse`] used to reply.
+    ///
+    /// Returns [`None`] once every [`Mock`] has been dropped and all requests have been received.
+    pub async fn next_request(&mut self) -> Option<(Request, SendResponse<Response>)> {
+        let (request, sender) = self.receiver.recv().await?;
+        Some((request, SendResponse { sender }))
+    }
+
+    /// Waits for the next request and asserts that it equals `expected`, returning a
+    /// [`SendResponse`] used to reply.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the request differs or if every [`Mock`] has been dropped.
+    pub async fn assert_request_eq(&mut self, expected: Request) -> SendResponse<Response>
+    where
+        Request: PartialEq + fmt::Debug,
+    {
+        let (request, send_response) = self
+            .next_request()
+            .await
+            .expect("every mock has been dropped");
+        assert_eq!(request, expected);
+        send_response
+    }
+}
+
+/// Sends the response to a single [call](Service::call) on a [`Mock`].
+///
+/// See the [module](crate::mock) for more information.
+pub struct SendResponse<Response> {
+    sender: oneshot::Sender<Response>,
+}
+
+impl<Response> fmt::Debug for SendResponse<Response> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SendResponse")
+            .field("closed", &self.sender.is_closed())
+            .finish()
+    }
+}
+
+impl<Response> SendResponse<Response> {
+    /// Sends the response. This does nothing if the call has been cancelled.
+    pub fn send(self, response: Response) {
+        let _ = self.sender.send(response);
+    }
+}
+
+/// Constructs a [`Mock`] [`Service`] and the [`Handle`] used to control it.
+///
+/// See the [module](crate::mock) for more information.
+pub fn pair<Request, Response>() -> (Mock<Request, Response>, Handle<Request, Response>) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let shared = Arc::new(Shared {
+        remaining: Mutex::new(None),
+        allowed: Notify::new(),
+        pending: AtomicUsize::new(0),
+    });
+    let mock = Mock {
+        sender,
+        shared: shared.clone(),
+    };
+    let handle = Handle { receiver, shared };
+    (mock, handle)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::FutureExt;
+
+    use crate::{load::Load, Service};
+
+    use super::{pair, Mock};
+
+    #[tokio::test]
+    async fn allow() {
+        let (svc, mut handle) = pair::<u32, u32>();
+        handle.allow(1);
+        let permit = svc.acquire().await;
+        assert!(svc.acquire().now_or_never().is_none());
+
+        let call = Mock::call(permit, 2);
+        let (response, ()) = tokio::join!(call, async {
+            let (request, send_response) = handle.next_request().await.unwrap();
+            assert_eq!(svc.load(), 1);
+            send_response.send(request * 2);
+        });
+        assert_eq!(response, 4);
+        assert_eq!(svc.load(), 0);
+
+        // Waiting callers are woken once permits are allowed.
+        let (_permit, ()) = tokio::join!(svc.acquire(), async { handle.allow_unlimited() });
+    }
+}
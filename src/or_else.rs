@@ -0,0 +1,132 @@
+//! The [`ServiceExt::or_else`](crate::ServiceExt::or_else) combinator returns [`OrElse`], which
+//! extends a [fallible service](crate::TryService) with a closure accepting the [`Err`] variant of
+//! the [`Service::Response`] and returning a [`Future`]. The [`Ok`] variant is passed through
+//! unchanged.
+//!
+//! For a synchronous version see the [map_err](mod@crate::map_err) module.
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::*;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc = service_fn(|x: u32| async move { x.checked_sub(3).ok_or(x) })
+//!     .or_else(|x| async move { if x == 0 { Err("zero") } else { Ok(0) } });
+//! assert_eq!(svc.oneshot(5).await, Ok(2));
+//! assert_eq!(svc.oneshot(2).await, Ok(0));
+//! assert_eq!(svc.oneshot(0).await, Err("zero"));
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`OrElse`] defers to the inner service.
+
+use core::{any, fmt, future::Future};
+
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Middleware, Service, TryService,
+};
+
+/// A wrapper [`Service`] for the [`ServiceExt::or_else`](crate::ServiceExt::or_else) combinator.
+///
+/// See the [module](crate::or_else) for more information.
+#[derive(Clone, Debug)]
+pub struct OrElse<S, F> {
+    inner: S,
+    closure: F,
+}
+
+impl<S, F> OrElse<S, F> {
+    pub(crate) fn new(inner: S, closure: F) -> Self {
+        Self { inner, closure }
+    }
+}
+
+/// The [`Service::Permit`] type for [`OrElse`].
+pub struct OrElsePermit<'a, S, F, Request>
+where
+    S: Service<Request> + 'a,
+{
+    inner: S::Permit<'a>,
+    closure: &'a F,
+}
+
+impl<'a, S, F, Request> fmt::Debug for OrElsePermit<'a, S, F, Request>
+where
+    S: Service<Request>,
+    S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OrElsePermit")
+            .field("inner", &self.inner)
+            .field("closure", &format_args!("{}", any::type_name::<F>()))
+            .finish()
+    }
+}
+
+impl<Request, S, F, Fut, Error> Service<Request> for OrElse<S, F>
+where
+    S: TryService<Request>,
+    F: Fn(S::Error) -> Fut,
+    Fut: Future<Output = Result<S::Ok, Error>>,
+{
+    type Response = Result<S::Ok, Error>;
+    type Permit<'a> = OrElsePermit<'a, S, F, Request>
+    where
+        S: 'a,
+        F: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        OrElsePermit {
+            inner: self.inner.acquire().await,
+            closure: &self.closure,
+        }
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        match S::call(permit.inner, request).await {
+            Ok(ok) => Ok(ok),
+            Err(err) => (permit.closure)(err).await,
+        }
+    }
+}
+
+impl<S, F> Load for OrElse<S, F>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S, F> Describe for OrElse<S, F>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("OrElse").with_child(self.inner.describe())
+    }
+}
+
+impl<S, T, F> Middleware<S> for OrElse<T, F>
+where
+    T: Middleware<S>,
+{
+    type Service = OrElse<T::Service, F>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self { inner, closure } = self;
+        OrElse {
+            inner: inner.apply(svc),
+            closure,
+        }
+    }
+}
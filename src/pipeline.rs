@@ -0,0 +1,285 @@
+//! The [`pipeline`] function takes a [`Sink`] of requests and a [`Stream`] of responses, such as
+//! the halves of a connection, and returns a [`Pipeline`] and a worker [`Future`]. The [`Pipeline`]
+//! is a cloneable [`Service`] which sends each request, over a channel, to the worker [`Future`]
+//! which writes it to the [`Sink`]. The connection is assumed to respond to requests in the order
+//! they were written, as with Redis or HTTP/1.1 pipelining, so the worker [`Future`] matches each
+//! response from the [`Stream`] to the earliest request still awaiting one.
+//!
+//! Requests are written without waiting for earlier responses, so many may be inflight on a single
+//! connection. A framed transport implementing both [`Sink`] and [`Stream`] can be divided into the
+//! two halves using [`StreamExt::split`](futures_util::StreamExt::split).
+//!
+//! The worker [`Future`] must be spawned, or otherwise polled, for requests to progress. It
+//! receives requests only while the [`Sink`] is ready, so the [`Service::acquire`] on [`Pipeline`]
+//! waits for capacity on the channel, which holds at most the specified number of requests, once
+//! the [`Sink`] applies backpressure. The worker [`Future`] completes, closing the [`Sink`], once
+//! every [`Pipeline`] has been dropped and all responses have been received. It also completes
+//! once the [`Stream`] ends, or with an error if the [`Sink`] fails.
+//!
+//! If the worker [`Future`] completes, or is dropped, before a response is received then the
+//! [`Pipeline`] returns [`Closed`]. A caller which stops waiting doesn't disturb the order, as its
+//! response is still received and then discarded. Responses received while no request is awaiting
+//! one are discarded.
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::*;
+//! use futures::{channel::mpsc, StreamExt};
+//! # use tokio::join;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! // Stands in for a connection to a server which doubles each request.
+//! let (sink, requests) = mpsc::channel::<u32>(4);
+//! let responses = requests.map(|x| 2 * x);
+//!
+//! let (svc, worker) = pipeline(sink, responses, 8);
+//! let worker = tokio::spawn(worker);
+//! let (a, b) = join!(svc.oneshot(1), svc.oneshot(2));
+//! assert_eq!((a, b), (Ok(2), Ok(4)));
+//!
+//! drop(svc);
+//! assert!(worker.await.unwrap().is_ok());
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`Pipeline`] is the number of requests awaiting a response, as returned
+//! by [`Pipeline::pending`].
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    future::{poll_fn, Future},
+    pin::pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::Poll,
+};
+
+use futures_util::{Sink, Stream};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Service,
+};
+
+type Message<Request, Response> = (Request, oneshot::Sender<Response>);
+
+/// The worker [`Future`] has completed, or been dropped, before a response was received.
+#[derive(Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Closed;
+
+impl fmt::Display for Closed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("pipeline closed")
+    }
+}
+
+impl std::error::Error for Closed {}
+
+/// A handle [`Service`] for the [`pipeline`] constructor.
+///
+/// See the [module](mod@crate::pipeline) for more information.
+pub struct Pipeline<Request, Response> {
+    sender: mpsc::Sender<Message<Request, Response>>,
+    pending: Arc<AtomicUsize>,
+}
+
+impl<Request, Response> fmt::Debug for Pipeline<Request, Response> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pipeline")
+            .field("sender", &self.sender)
+            .field("pending", &self.pending)
+            .finish()
+    }
+}
+
+impl<Request, Response> Clone for Pipeline<Request, Response> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            pending: self.pending.clone(),
+        }
+    }
+}
+
+impl<Request, Response> Pipeline<Request, Response> {
+    /// Returns the number of requests awaiting a response, including those still in the channel.
+    pub fn pending(&self) -> usize {
+        self.pending.load(Ordering::Acquire)
+    }
+}
+
+/// Decrements the pending count on drop, including on cancellation.
+struct Pending<'a>(&'a AtomicUsize);
+
+impl Drop for Pending<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Release);
+    }
+}
+
+/// The [`Service::Permit`] type for [`Pipeline`].
+pub struct PipelinePermit<'a, Request, Response> {
+    inner: Option<mpsc::Permit<'a, Message<Request, Response>>>,
+    pending: &'a AtomicUsize,
+}
+
+impl<Request, Response> fmt::Debug for PipelinePermit<'_, Request, Response> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PipelinePermit")
+            .field("inner", &self.inner)
+            .field("pending", &self.pending)
+            .finish()
+    }
+}
+
+impl<Request, Response> Service<Request> for Pipeline<Request, Response> {
+    type Response = Result<Response, Closed>;
+    type Permit<'a> = PipelinePermit<'a, Request, Response>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        PipelinePermit {
+            inner: self.sender.reserve().await.ok(),
+            pending: &self.pending,
+        }
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        let PipelinePermit { inner, pending } = permit;
+        let permit = inner.ok_or(Closed)?;
+        pending.fetch_add(1, Ordering::Release);
+        let _pending = Pending(pending);
+        let (sender, receiver) = oneshot::channel();
+        permit.send((request, sender));
+        receiver.await.map_err(|_| Closed)
+    }
+}
+
+impl<Request, Response> Load for Pipeline<Request, Response> {
+    type Metric = usize;
+
+    fn load(&self) -> Self::Metric {
+        self.pending()
+    }
+}
+
+impl<Request, Response> Describe for Pipeline<Request, Response> {
+    fn describe(&self) -> StackNode {
+        StackNode::new("Pipeline")
+    }
+}
+
+/// Constructs a [`Pipeline`] and a worker [`Future`] from a [`Sink`] of requests and a [`Stream`]
+/// of their responses, where the channel between them holds at most `capacity` requests.
+///
+/// See the [module](mod@crate::pipeline) for more information.
+///
+/// # Panics
+///
+/// Panics if `capacity` is zero.
+pub fn pipeline<Si, St, Request>(
+    sink: Si,
+    stream: St,
+    capacity: usize,
+) -> (
+    Pipeline<Request, St::Item>,
+    impl Future<Output = Result<(), Si::Error>>,
+)
+where
+    Si: Sink<Request>,
+    St: Stream,
+{
+    let (sender, mut receiver) = mpsc::channel::<Message<Request, St::Item>>(capacity);
+    let worker = async move {
+        let mut sink = pin!(sink);
+        let mut stream = pin!(stream);
+        let mut inflight = VecDeque::<oneshot::Sender<St::Item>>::new();
+        let mut receiving = true;
+        let mut flushed = true;
+        poll_fn(|cx| {
+            while let Poll::Ready(response) = stream.as_mut().poll_next(cx) {
+                let Some(response) = response else {
+                    return Poll::Ready(Ok(()));
+                };
+                match inflight.pop_front() {
+                    Some(sender) => {
+                        let _ = sender.send(response);
+                    }
+                    None => tracing::trace!("discarding unsolicited response"),
+                }
+            }
+            // Requests are only received while the sink is ready, so backpressure reaches callers.
+            while receiving && sink.as_mut().poll_ready(cx)?.is_ready() {
+                match receiver.poll_recv(cx) {
+                    Poll::Ready(Some((request, sender))) => {
+                        sink.as_mut().start_send(request)?;
+                        inflight.push_back(sender);
+                        flushed = false;
+                    }
+                    Poll::Ready(None) => receiving = false,
+                    Poll::Pending => break,
+                }
+            }
+            if !flushed {
+                flushed = sink.as_mut().poll_flush(cx)?.is_ready();
+            }
+            if !receiving && flushed && inflight.is_empty() {
+                return sink.as_mut().poll_close(cx);
+            }
+            Poll::Pending
+        })
+        .await
+    };
+    let pipeline = Pipeline {
+        sender,
+        pending: Arc::new(AtomicUsize::new(0)),
+    };
+    (pipeline, worker)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{channel::mpsc, FutureExt, StreamExt};
+
+    use crate::{Service, ServiceExt};
+
+    use super::{pipeline, Closed, Pipeline};
+
+    #[tokio::test]
+    async fn cancelled_caller_keeps_order() {
+        let (sink, requests) = mpsc::channel::<u32>(4);
+        let (svc, worker) = pipeline(sink, requests.map(|x| x + 1), 4);
+        let worker = tokio::spawn(worker);
+
+        // The first caller stops waiting once its request has been sent.
+        let permit = svc.acquire().await;
+        let mut call = Box::pin(Pipeline::call(permit, 1));
+        assert!((&mut call).now_or_never().is_none());
+        drop(call);
+        assert_eq!(svc.oneshot(2).await, Ok(3));
+        assert_eq!(svc.pending(), 0);
+
+        drop(svc);
+        assert!(worker.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn closed_when_stream_ends() {
+        let (sink, _requests) = mpsc::channel::<u32>(4);
+        let (svc, worker) = pipeline(sink, futures::stream::empty::<u32>(), 4);
+        let (response, result) = tokio::join!(svc.oneshot(1), worker);
+        assert_eq!(response, Err(Closed));
+        assert!(result.is_ok());
+    }
+}
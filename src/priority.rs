@@ -0,0 +1,276 @@
+//! The [`ServiceExt::priority`](crate::ServiceExt::priority) combinator returns [`PriorityBuffer`],
+//! which hands the inner service's permits to the highest priority waiting caller, rather than in
+//! the order callers arrived.
+//!
+//! The priority is extracted from the request by a closure. As the request isn't known until
+//! [`Service::call`], the [`Service::acquire`] on [`PriorityBuffer`] resolves immediately and
+//! [`Service::call`] waits in the queue for the inner permit. Callers of equal priority are served
+//! in first-in, first-out order.
+//!
+//! Only the highest priority caller acquires from the inner service at any one time. If a higher
+//! priority caller arrives in the meantime, it takes over.
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::*;
+//!
+//! #[derive(PartialEq)]
+//! enum Class {
+//!     Bulk,
+//!     Admin,
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc = service_fn(|(_, x): (Class, u32)| async move { x + 1 })
+//!     .concurrency_limit(1)
+//!     .priority(|(class, _): &(Class, u32)| *class == Class::Admin);
+//! let response = svc.oneshot((Class::Bulk, 2)).await;
+//! assert_eq!(response, 3);
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`PriorityBuffer`] defers to the inner service.
+
+use std::{any, cmp::Reverse, collections::BTreeSet, fmt, pin::pin, sync::Mutex};
+
+use tokio::{select, sync::Notify};
+
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Middleware, Service,
+};
+
+#[derive(Debug)]
+struct Queue<P> {
+    /// Ordered such that the last is the highest priority and earliest arrival.
+    waiting: BTreeSet<(P, Reverse<u64>)>,
+    next: u64,
+}
+
+/// A wrapper [`Service`] for the [`ServiceExt::priority`](crate::ServiceExt::priority)
+/// combinator.
+///
+/// See the [module](crate::priority) for more information.
+pub struct PriorityBuffer<S, F, P> {
+    inner: S,
+    closure: F,
+    queue: Mutex<Queue<P>>,
+    notify: Notify,
+}
+
+impl<S, F, P> fmt::Debug for PriorityBuffer<S, F, P>
+where
+    S: fmt::Debug,
+    P: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PriorityBuffer")
+            .field("inner", &self.inner)
+            .field("closure", &format_args!("{}", any::type_name::<F>()))
+            .field("queue", &self.queue)
+            .field("notify", &self.notify)
+            .finish()
+    }
+}
+
+impl<S, F, P> PriorityBuffer<S, F, P> {
+    pub(crate) fn new(inner: S, closure: F) -> Self {
+        Self {
+            inner,
+            closure,
+            queue: Mutex::new(Queue {
+                waiting: BTreeSet::new(),
+                next: 0,
+            }),
+            notify: Notify::new(),
+        }
+    }
+}
+
+/// A place in the queue, removed on drop.
+struct Waiter<'a, P>
+where
+    P: Ord,
+{
+    queue: &'a Mutex<Queue<P>>,
+    notify: &'a Notify,
+    key: Option<(P, Reverse<u64>)>,
+}
+
+impl<'a, P> Waiter<'a, P>
+where
+    P: Ord + Clone,
+{
+    fn new(queue: &'a Mutex<Queue<P>>, notify: &'a Notify, priority: P) -> Self {
+        let mut guard = queue.lock().unwrap();
+        let key = (priority, Reverse(guard.next));
+        guard.next += 1;
+        guard.waiting.insert(key.clone());
+        drop(guard);
+        // The caller currently acquiring may no longer be first.
+        notify.notify_waiters();
+        Self {
+            queue,
+            notify,
+            key: Some(key),
+        }
+    }
+
+    fn is_first(&self) -> bool {
+        self.queue.lock().unwrap().waiting.last() == self.key.as_ref()
+    }
+}
+
+impl<P> Drop for Waiter<'_, P>
+where
+    P: Ord,
+{
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.queue.lock().unwrap().waiting.remove(&key);
+            self.notify.notify_waiters();
+        }
+    }
+}
+
+/// The [`Service::Permit`] type for [`PriorityBuffer`].
+pub struct PriorityBufferPermit<'a, S, F, P> {
+    service: &'a PriorityBuffer<S, F, P>,
+}
+
+impl<S, F, P> fmt::Debug for PriorityBufferPermit<'_, S, F, P>
+where
+    S: fmt::Debug,
+    P: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PriorityBufferPermit")
+            .field("service", &self.service)
+            .finish()
+    }
+}
+
+impl<Request, S, F, P> Service<Request> for PriorityBuffer<S, F, P>
+where
+    S: Service<Request>,
+    F: Fn(&Request) -> P,
+    P: Ord + Clone,
+{
+    type Response = S::Response;
+    type Permit<'a> = PriorityBufferPermit<'a, S, F, P>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        PriorityBufferPermit { service: self }
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        let service = permit.service;
+        let priority = (service.closure)(&request);
+        let waiter = Waiter::new(&service.queue, &service.notify, priority);
+        let permit = loop {
+            let mut notified = pin!(service.notify.notified());
+            notified.as_mut().enable();
+            if !waiter.is_first() {
+                notified.await;
+                continue;
+            }
+            // Yield to any higher priority caller which arrives while acquiring.
+            select! {
+                permit = service.inner.acquire() => break permit,
+                _ = notified => {}
+            }
+        };
+        drop(waiter);
+        S::call(permit, request).await
+    }
+}
+
+impl<S, F, P> Load for PriorityBuffer<S, F, P>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S, F, P> Describe for PriorityBuffer<S, F, P>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("PriorityBuffer").with_child(self.inner.describe())
+    }
+}
+
+impl<S, T, F, P> Middleware<S> for PriorityBuffer<T, F, P>
+where
+    T: Middleware<S>,
+{
+    type Service = PriorityBuffer<T::Service, F, P>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self {
+            inner,
+            closure,
+            queue,
+            notify,
+        } = self;
+        PriorityBuffer {
+            inner: inner.apply(svc),
+            closure,
+            queue,
+            notify,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Mutex, time::Duration};
+
+    use tokio::time::sleep;
+
+    use crate::{service_fn, ServiceExt};
+
+    #[tokio::test]
+    async fn preempt() {
+        let order = Mutex::new(Vec::new());
+        let svc = service_fn(|x: u32| {
+            order.lock().unwrap().push(x);
+            sleep(Duration::from_millis(20))
+        })
+        .concurrency_limit(1)
+        .priority(|x: &u32| *x);
+
+        // The first occupies the permit, the remainder queue and are served by priority.
+        tokio::join!(
+            svc.oneshot(1),
+            async {
+                sleep(Duration::from_millis(5)).await;
+                svc.oneshot(2).await
+            },
+            async {
+                sleep(Duration::from_millis(5)).await;
+                svc.oneshot(5).await
+            },
+            async {
+                sleep(Duration::from_millis(5)).await;
+                svc.oneshot(3).await
+            },
+        );
+        assert_eq!(*order.lock().unwrap(), [1, 5, 3, 2]);
+    }
+}
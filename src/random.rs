@@ -0,0 +1,17 @@
+//! Random sampling, without depending on a random number generator.
+
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+};
+
+/// Returns a random [`u64`].
+pub(crate) fn next_u64() -> u64 {
+    // Each `RandomState` is keyed differently.
+    RandomState::new().build_hasher().finish()
+}
+
+/// Returns a random [`f64`] between zero and one, inclusive.
+pub(crate) fn next_f64() -> f64 {
+    next_u64() as f64 / u64::MAX as f64
+}
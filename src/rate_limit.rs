@@ -1,18 +1,32 @@
 //! The [`ServiceExt::rate_limit`](crate::ServiceExt::rate_limit) combinator returns [`RateLimit`],
 //! which limits the number of [`Service::call`]s invoked per period of time.
 //!
-//! This implementation requires the number of permits and the interval is specified, each
-//! [`Service::acquire`] acquires a permit, when [`Service::call`] is invoked the permit is
-//! forgotten. The number of available permits is refreshed when the period has elapsed.
+//! Time is divided into fixed windows of the specified interval, starting when the [`RateLimit`] is
+//! constructed. Each [`Service::acquire`] takes one of the window's permits and, when
+//! [`Service::call`] is invoked, the permit is used. Dropping a permit without calling returns it
+//! to the window, if the window hasn't since ended. Once a window's permits are exhausted,
+//! [`Service::acquire`] waits for the next window.
+//!
+//! The window and the number of permits taken from it are stored in a single atomic, so
+//! [`Service::acquire`] doesn't lock in the common case. Callers waiting for the next window are
+//! not served in any particular order. As the count shares the atomic, at most 2^24 - 1 permits
+//! are granted per window.
 //!
 //! Note that this does _not_ garauntee that a remote server will receive requests under these
 //! restrictions. Network conditions, other middleware, etc can cause requests to arrive in bursts
 //! exceeding the rate limit specified here.
 //!
+//! Cloning a [`RateLimit`] clones the inner service, while the limit is shared between the clones.
+//! Other limiters, such as [`ServiceExt::token_bucket`](crate::ServiceExt::token_bucket), are
+//! listed in the [`limit`](crate::limit) module.
+//!
+//! [`RateLimit::available_permits`] returns the number of permits remaining in the current window,
+//! and [`RateLimit::time_until_refill`] returns the time until the next window starts.
+//!
 //! # Example
 //!
 //! If 5 permits and a interval of 2 second is specified then the first 5 [`Service::acquire`]s will
-//! immediately resolve and the 6th will resolve after the 2 second interval has elapsed.
+//! immediately resolve and the 6th will resolve once the 2 second window has elapsed.
 //!
 //! ```rust
 //! use std::time::Duration;
@@ -27,37 +41,281 @@
 //! # let _ = response;
 //! # }
 //! ```
-use std::time::{Duration, Instant};
+//!
+//! # Adjusting the rate
+//!
+//! [`RateLimit::handle`] returns a [`RateLimitHandle`], whose [`RateLimitHandle::set_rate`] changes
+//! the interval and permits of the [`RateLimit`] and its clones, for example to back off after an
+//! upstream responds with `429 Too Many Requests`. The change takes effect immediately, starting a
+//! new window of the new interval, in which no permits have been taken. Callers waiting for the
+//! next window wake at the time they computed before the change and then retry.
+//!
+//! ```rust
+//! use std::time::Duration;
+//!
+//! use burger::*;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc =
+//!     service_fn(|x: u32| async move { x.to_string() }).rate_limit(Duration::from_secs(1), 5);
+//! let handle = svc.handle();
+//! handle.set_rate(Duration::from_secs(10), 1);
+//! assert_eq!(svc.available_permits(), 1);
+//! let response = svc.oneshot(1).await;
+//! assert_eq!(svc.available_permits(), 0);
+//! # let _ = response;
+//! # }
+//! ```
+//!
+//! # Per key
+//!
+//! The [`ServiceExt::rate_limit_per_key`](crate::ServiceExt::rate_limit_per_key) combinator returns
+//! [`RateLimitPerKey`], which maintains a separate limit for each key extracted from the request by
+//! a closure. As the key isn't known until the request is provided, [`Service::acquire`] resolves
+//! immediately and [`Service::call`] waits for the key's limit before acquiring the inner permit.
+//!
+//! A key's window starts at its first call. Keys whose window has elapsed are idle and are
+//! periodically expired, so the number of keys tracked is bounded by those seen within an interval.
+//!
+//! ```rust
+//! use std::time::Duration;
+//!
+//! use burger::*;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let user = |(user, _): &(&str, u32)| user.to_string();
+//! let svc = service_fn(|(_, x): (&str, u32)| async move { x.to_string() })
+//!     .rate_limit_per_key(Duration::from_secs(1), 5, user);
+//! let response = svc.oneshot(("alice", 1)).await;
+//! # let _ = response;
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`RateLimit`] and [`RateLimitPerKey`] defers to the inner service.
 
-use tokio::{
-    select,
-    sync::{Mutex, Semaphore, SemaphorePermit},
+use std::{
+    any,
+    collections::HashMap,
+    fmt,
+    hash::Hash,
+    sync::{
+        atomic::{AtomicU32, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
 };
 
-use crate::Service;
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    rt, Middleware, Service, ServiceExt,
+};
+
+/// The fixed windows shared between clones of a [`RateLimit`].
+#[derive(Debug)]
+struct FixedWindows {
+    start: Instant,
+    /// The interval in nanoseconds.
+    interval: AtomicU64,
+    permits: AtomicU32,
+    /// The permits taken from the current window in the lower [`TAKEN_BITS`], and the current
+    /// window, truncated, in the remaining upper bits.
+    state: AtomicU64,
+    /// Incremented when the rate is set, as window numbers may then repeat.
+    epoch: AtomicU32,
+}
+
+impl FixedWindows {
+    fn new(interval: Duration, permits: usize) -> Self {
+        Self {
+            start: Instant::now(),
+            interval: AtomicU64::new(nanos(interval)),
+            permits: AtomicU32::new(saturate(permits)),
+            state: AtomicU64::new(0),
+            epoch: AtomicU32::new(0),
+        }
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_nanos(self.interval.load(Ordering::Acquire))
+    }
+
+    fn permits(&self) -> u32 {
+        self.permits.load(Ordering::Acquire)
+    }
+
+    fn window(&self, now: Instant) -> u64 {
+        let elapsed = now.saturating_duration_since(self.start).as_nanos();
+        let window = elapsed / u128::from(self.interval.load(Ordering::Acquire).max(1));
+        window.try_into().unwrap_or(u64::MAX)
+    }
+
+    fn end(&self, window: u64) -> Instant {
+        let nanos = u128::from(self.interval.load(Ordering::Acquire)) * (u128::from(window) + 1);
+        let offset = Duration::from_nanos(nanos.try_into().unwrap_or(u64::MAX));
+        // Beyond the range of `Instant`, wait for a long time instead.
+        self.start
+            .checked_add(offset)
+            .unwrap_or_else(|| Instant::now() + FAR_FUTURE)
+    }
+
+    /// Sets the rate, starting a new window aligned to the new interval.
+    fn set(&self, interval: Duration, permits: usize) {
+        self.epoch.fetch_add(1, Ordering::AcqRel);
+        self.interval.store(nanos(interval), Ordering::Release);
+        self.permits.store(saturate(permits), Ordering::Release);
+        let window = self.window(Instant::now());
+        self.state.store(pack(window, 0), Ordering::Release);
+    }
+
+    /// Returns the permits taken from the current window.
+    fn taken(&self, window: u64) -> u32 {
+        taken(self.state.load(Ordering::Acquire), window)
+    }
+
+    /// Takes a permit from the current window, returning the epoch and window or when the caller
+    /// must wait until otherwise.
+    fn take(&self, now: Instant) -> Result<(u32, u64), Instant> {
+        // Loaded first, so that a concurrent `set` can only cause the permit to not be returned.
+        let epoch = self.epoch.load(Ordering::Acquire);
+        let window = self.window(now);
+        self.state
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |state| {
+                let taken = taken(state, window);
+                (taken < self.permits()).then(|| pack(window, taken + 1))
+            })
+            .map(|_| (epoch, window))
+            .map_err(|_| self.end(window))
+    }
+
+    /// Returns an unused permit to the window, if it's still current and the rate hasn't been set
+    /// since it was taken.
+    fn release(&self, epoch: u32, window: u64) {
+        let _ = self
+            .state
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |state| {
+                let current = self.epoch.load(Ordering::Acquire) == epoch;
+                (current && taken(state, window) > 0).then(|| state - 1)
+            });
+    }
+}
+
+/// The number of bits of [`FixedWindows::state`] counting the permits taken.
+const TAKEN_BITS: u32 = 24;
+
+/// The maximum number of permits per window.
+const MAX_PERMITS: u32 = (1 << TAKEN_BITS) - 1;
+
+/// Roughly 30 years, as used by `tokio` for an unbounded deadline.
+const FAR_FUTURE: Duration = Duration::from_secs(86400 * 365 * 30);
+
+/// Packs the window, discarding its upper bits, and the permits taken from it.
+fn pack(window: u64, taken: u32) -> u64 {
+    (window << TAKEN_BITS) | u64::from(taken)
+}
+
+/// Returns the permits taken from the window, or zero if the state is of another window.
+fn taken(state: u64, window: u64) -> u32 {
+    if state >> TAKEN_BITS == pack(window, 0) >> TAKEN_BITS {
+        (state & u64::from(MAX_PERMITS)) as u32
+    } else {
+        0
+    }
+}
+
+fn nanos(interval: Duration) -> u64 {
+    interval.as_nanos().try_into().unwrap_or(u64::MAX)
+}
+
+fn saturate(permits: usize) -> u32 {
+    permits.try_into().unwrap_or(u32::MAX).min(MAX_PERMITS)
+}
+
+/// A permit taken from a window, returned on drop unless used.
+#[derive(Debug)]
+struct Taken<'a> {
+    windows: &'a FixedWindows,
+    epoch: u32,
+    window: u64,
+    used: bool,
+}
+
+impl Drop for Taken<'_> {
+    fn drop(&mut self) {
+        if !self.used {
+            self.windows.release(self.epoch, self.window);
+        }
+    }
+}
 
 /// A wrapper for the [`ServiceExt::rate_limit`](crate::ServiceExt::rate_limit) combinator.
 ///
 /// See the [module](crate::rate_limit) for more information.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct RateLimit<S> {
     inner: S,
-    semaphore: Semaphore,
-    last_update: Mutex<Instant>,
-    interval: Duration,
-    permits: usize,
+    windows: Arc<FixedWindows>,
 }
 
 impl<S> RateLimit<S> {
     pub(crate) fn new(inner: S, interval: Duration, permits: usize) -> Self {
         Self {
             inner,
-            semaphore: Semaphore::new(permits),
-            last_update: Mutex::new(Instant::now()),
-            interval,
-            permits,
+            windows: Arc::new(FixedWindows::new(interval, permits)),
         }
     }
+
+    /// Returns the number of permits remaining in the current window.
+    pub fn available_permits(&self) -> usize {
+        let window = self.windows.window(Instant::now());
+        self.windows
+            .permits()
+            .saturating_sub(self.windows.taken(window)) as usize
+    }
+
+    /// Returns the time remaining until the next window starts.
+    pub fn time_until_refill(&self) -> Duration {
+        let now = Instant::now();
+        self.windows
+            .end(self.windows.window(now))
+            .saturating_duration_since(now)
+    }
+
+    /// Returns a [`RateLimitHandle`], used to adjust the rate of this and its clones.
+    pub fn handle(&self) -> RateLimitHandle {
+        RateLimitHandle {
+            windows: self.windows.clone(),
+        }
+    }
+}
+
+/// A handle, returned by [`RateLimit::handle`], used to adjust the rate of a [`RateLimit`].
+///
+/// See the [module](crate::rate_limit#adjusting-the-rate) for more information.
+#[derive(Clone, Debug)]
+pub struct RateLimitHandle {
+    windows: Arc<FixedWindows>,
+}
+
+impl RateLimitHandle {
+    /// Sets the number of permits per interval, starting a new window.
+    pub fn set_rate(&self, interval: Duration, permits: usize) {
+        self.windows.set(interval, permits);
+    }
+
+    /// Returns the current interval.
+    pub fn interval(&self) -> Duration {
+        self.windows.interval()
+    }
+
+    /// Returns the current number of permits per interval.
+    pub fn permits(&self) -> usize {
+        self.windows.permits() as usize
+    }
 }
 
 /// The [`Service::Permit`] type for [`RateLimit`].
@@ -67,7 +325,7 @@ where
     S: Service<Request> + 'a,
 {
     inner: S::Permit<'a>,
-    _permit: SemaphorePermit<'a>,
+    taken: Taken<'a>,
 }
 
 impl<Request, S> Service<Request> for RateLimit<S>
@@ -81,25 +339,21 @@ where
         Self: 'a;
 
     async fn acquire(&self) -> Self::Permit<'_> {
-        let fut = async move {
-            let mut guard = self.last_update.lock().await;
-            loop {
-                let now = Instant::now();
-                let end = *guard + self.interval;
-                tokio::time::sleep_until(end.into()).await;
-
-                // Remove all permits, then add new ones
-                self.semaphore.forget_permits(usize::MAX);
-                self.semaphore.add_permits(self.permits);
-                *guard = now;
+        let (epoch, window) = loop {
+            match self.windows.take(Instant::now()) {
+                Ok(taken) => break taken,
+                Err(until) => rt::sleep_until(until).await,
             }
         };
-        let acquire = self.semaphore.acquire();
-        let permit = select! { permit = acquire => { permit }, never = fut => { never } };
-
+        let taken = Taken {
+            windows: &self.windows,
+            epoch,
+            window,
+            used: false,
+        };
         RateLimitPermit {
-            _permit: permit.unwrap(),
             inner: self.inner.acquire().await,
+            taken,
         }
     }
 
@@ -107,17 +361,231 @@ where
     where
         Self: 'a,
     {
-        let RateLimitPermit { inner, _permit } = permit;
-        _permit.forget();
+        let RateLimitPermit { inner, mut taken } = permit;
+        taken.used = true;
         S::call(inner, request).await
     }
 }
 
+impl<S> Load for RateLimit<S>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S> Describe for RateLimit<S>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("RateLimit")
+            .with_config("permits", self.windows.permits())
+            .with_config("interval", format_args!("{:?}", self.windows.interval()))
+            .with_child(self.inner.describe())
+    }
+}
+
+impl<S, T> Middleware<S> for RateLimit<T>
+where
+    T: Middleware<S>,
+{
+    type Service = RateLimit<T::Service>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self { inner, windows } = self;
+        RateLimit::new(
+            inner.apply(svc),
+            windows.interval(),
+            windows.permits() as usize,
+        )
+    }
+}
+
+#[derive(Debug)]
+struct Window {
+    start: Instant,
+    used: usize,
+}
+
+#[derive(Debug)]
+struct Windows<K> {
+    windows: HashMap<K, Window>,
+    next_expiry: Instant,
+}
+
+/// A wrapper for the [`ServiceExt::rate_limit_per_key`](crate::ServiceExt::rate_limit_per_key)
+/// combinator.
+///
+/// See the [module](crate::rate_limit) for more information.
+pub struct RateLimitPerKey<S, F, K> {
+    inner: S,
+    closure: F,
+    windows: Mutex<Windows<K>>,
+    interval: Duration,
+    permits: usize,
+}
+
+impl<S, F, K> fmt::Debug for RateLimitPerKey<S, F, K>
+where
+    S: fmt::Debug,
+    K: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RateLimitPerKey")
+            .field("inner", &self.inner)
+            .field("closure", &format_args!("{}", any::type_name::<F>()))
+            .field("windows", &self.windows)
+            .field("interval", &self.interval)
+            .field("permits", &self.permits)
+            .finish()
+    }
+}
+
+impl<S, F, K> RateLimitPerKey<S, F, K> {
+    pub(crate) fn new(inner: S, interval: Duration, permits: usize, closure: F) -> Self {
+        Self {
+            inner,
+            closure,
+            windows: Mutex::new(Windows {
+                windows: HashMap::new(),
+                next_expiry: Instant::now() + interval,
+            }),
+            interval,
+            permits,
+        }
+    }
+}
+
+impl<S, F, K> RateLimitPerKey<S, F, K>
+where
+    K: Hash + Eq,
+{
+    /// Returns the number of keys currently tracked.
+    pub fn keys(&self) -> usize {
+        self.windows.lock().unwrap().windows.len()
+    }
+
+    /// Takes a call from the key's window, returning when the caller must wait until otherwise.
+    fn take(&self, key: K, now: Instant) -> Result<(), Instant> {
+        let mut windows = self.windows.lock().unwrap();
+        if now >= windows.next_expiry {
+            windows
+                .windows
+                .retain(|_, window| now < window.start + self.interval);
+            windows.next_expiry = now + self.interval;
+        }
+        let window = windows.windows.entry(key).or_insert(Window {
+            start: now,
+            used: 0,
+        });
+        if now >= window.start + self.interval {
+            window.start = now;
+            window.used = 0;
+        }
+        if window.used < self.permits {
+            window.used += 1;
+            Ok(())
+        } else {
+            Err(window.start + self.interval)
+        }
+    }
+}
+
+/// The [`Service::Permit`] type for [`RateLimitPerKey`].
+pub struct RateLimitPerKeyPermit<'a, S, F, K> {
+    service: &'a RateLimitPerKey<S, F, K>,
+}
+
+impl<S, F, K> fmt::Debug for RateLimitPerKeyPermit<'_, S, F, K>
+where
+    S: fmt::Debug,
+    K: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RateLimitPerKeyPermit")
+            .field("service", &self.service)
+            .finish()
+    }
+}
+
+impl<Request, S, F, K> Service<Request> for RateLimitPerKey<S, F, K>
+where
+    S: Service<Request>,
+    F: Fn(&Request) -> K,
+    K: Hash + Eq,
+{
+    type Response = S::Response;
+    type Permit<'a> = RateLimitPerKeyPermit<'a, S, F, K>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        RateLimitPerKeyPermit { service: self }
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        let service = permit.service;
+        while let Err(until) = service.take((service.closure)(&request), Instant::now()) {
+            rt::sleep_until(until).await;
+        }
+        service.inner.oneshot(request).await
+    }
+}
+
+impl<S, F, K> Load for RateLimitPerKey<S, F, K>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S, F, K> Describe for RateLimitPerKey<S, F, K>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("RateLimitPerKey")
+            .with_config("permits", self.permits)
+            .with_config("interval", format_args!("{:?}", self.interval))
+            .with_child(self.inner.describe())
+    }
+}
+
+impl<S, T, F, K> Middleware<S> for RateLimitPerKey<T, F, K>
+where
+    T: Middleware<S>,
+{
+    type Service = RateLimitPerKey<T::Service, F, K>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self {
+            inner,
+            closure,
+            interval,
+            permits,
+            ..
+        } = self;
+        RateLimitPerKey::new(inner.apply(svc), interval, permits, closure)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::time::{Duration, Instant};
 
-    use crate::{service_fn, ServiceExt};
+    use crate::{service_fn, Middleware, MiddlewareBuilder, Service, ServiceExt};
 
     #[tokio::test]
     async fn limit() {
@@ -140,4 +608,100 @@ mod tests {
         println!("{elapsed:?}");
         assert!(elapsed > Duration::from_millis(200));
     }
+
+    #[tokio::test]
+    async fn inspection() {
+        let interval = Duration::from_millis(100);
+        let svc = service_fn(|x: u32| async move { x }).rate_limit(interval, 2);
+        assert_eq!(svc.available_permits(), 2);
+
+        svc.oneshot(1).await;
+        svc.oneshot(2).await;
+        assert_eq!(svc.available_permits(), 0);
+        let remaining = svc.time_until_refill();
+        assert!(remaining > Duration::ZERO && remaining <= interval);
+    }
+
+    #[tokio::test]
+    async fn unused_permit() {
+        let svc = service_fn(|x: u32| async move { x }).rate_limit(Duration::from_secs(10), 1);
+
+        // Dropping a permit without calling returns it to the window.
+        let permit = svc.acquire().await;
+        assert_eq!(svc.available_permits(), 0);
+        drop(permit);
+        assert_eq!(svc.available_permits(), 1);
+        assert_eq!(svc.oneshot(1).await, 1);
+        assert_eq!(svc.available_permits(), 0);
+    }
+
+    #[tokio::test]
+    async fn applied_independently() {
+        let middleware = MiddlewareBuilder.rate_limit(Duration::from_secs(10), 1);
+        let a = middleware
+            .clone()
+            .apply(service_fn(|x: u32| async move { x }));
+        let b = middleware.apply(service_fn(|x: u32| async move { x }));
+        assert_eq!(a.oneshot(1).await, 1);
+        assert_eq!(a.available_permits(), 0);
+        assert_eq!(b.available_permits(), 1);
+    }
+
+    #[tokio::test]
+    async fn unused_permit_after_set() {
+        let svc = service_fn(|x: u32| async move { x }).rate_limit(Duration::from_secs(10), 1);
+        let permit = svc.acquire().await;
+
+        // The new window is numbered the same, so the old permit mustn't be returned to it.
+        svc.handle().set_rate(Duration::from_secs(10), 1);
+        assert_eq!(svc.oneshot(1).await, 1);
+        drop(permit);
+        assert_eq!(svc.available_permits(), 0);
+    }
+
+    #[test]
+    fn window_wrap() {
+        let svc = service_fn(|x: u32| async move { x }).rate_limit(Duration::from_nanos(1), 1);
+        let start = svc.windows.start;
+
+        // Windows beyond those that fit in 32 and 40 bits are each limited, and end in the future.
+        for window in [
+            u32::MAX.into(),
+            1 << 32,
+            (1 << 40) - 1,
+            1 << 40,
+            (1 << 40) + 1,
+        ] {
+            let now = start + Duration::from_nanos(window);
+            assert_eq!(svc.windows.window(now), window);
+            assert!(svc.windows.take(now).is_ok());
+            let until = svc.windows.take(now).unwrap_err();
+            assert_eq!(until, now + Duration::from_nanos(1));
+        }
+
+        // The end of the last window saturates.
+        assert!(svc.windows.end(u64::MAX) > Instant::now());
+    }
+
+    #[tokio::test]
+    async fn per_key() {
+        let svc = service_fn(|x: u32| async move { x })
+            .rate_limit_per_key(Duration::from_millis(100), 2, |x: &u32| *x);
+        let now = Instant::now();
+
+        // Each key has its own limit.
+        for x in 0..4 {
+            svc.oneshot(x).await;
+            svc.oneshot(x).await;
+        }
+        assert!(now.elapsed() < Duration::from_millis(100));
+        assert_eq!(svc.keys(), 4);
+
+        // The third call for a key waits for its window.
+        svc.oneshot(0).await;
+        assert!(now.elapsed() >= Duration::from_millis(100));
+
+        // Idle keys are expired.
+        assert_eq!(svc.keys(), 1);
+    }
 }
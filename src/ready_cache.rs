@@ -0,0 +1,140 @@
+//! The [`ready_cache`] function accepts a [`Service`] and returns a [`ReadyCache`] and a worker
+//! [`Future`]. The worker [`Future`] keeps up to a specified number of permits pre-acquired from
+//! the inner service, re-filling as they are taken, such that [`Service::acquire`] latency is
+//! hidden from callers.
+//!
+//! The [`Service::acquire`] on [`ReadyCache`] takes a pre-acquired permit, waiting in first-in,
+//! first-out order if none are available. The permits are [`OwnedPermit`]s, holding the inner
+//! service alive. If the worker [`Future`] has been dropped, permits are instead acquired from the
+//! inner service directly.
+//!
+//! Note that pre-acquired permits are held even while unused. This is unsuitable where permits
+//! represent scarce capacity shared with other callers, or expire.
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::*;
+//! # use std::time::Duration;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc = service_fn(|x: u32| async move { x + 1 }).rate_limit(Duration::from_millis(100), 4);
+//! let (svc, worker) = ready_cache(svc, 2);
+//! tokio::spawn(worker);
+//! let response = svc.oneshot(5).await;
+//! assert_eq!(response, 6);
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`ReadyCache`] defers to the inner service.
+
+use std::{fmt, future::Future, sync::Arc};
+
+use tokio::sync::{mpsc, Mutex};
+
+use crate::{
+    describe::{Describe, StackNode},
+    leak::OwnedPermit,
+    load::Load,
+    Service, ServiceExt,
+};
+
+/// A wrapper [`Service`] for the [`ready_cache`] constructor.
+///
+/// See the [module](mod@crate::ready_cache) for more information.
+pub struct ReadyCache<S, Request>
+where
+    S: Service<Request> + 'static,
+{
+    inner: Arc<S>,
+    permits: Mutex<mpsc::Receiver<OwnedPermit<S, Request>>>,
+}
+
+impl<S, Request> fmt::Debug for ReadyCache<S, Request>
+where
+    S: Service<Request> + fmt::Debug + 'static,
+    for<'a> S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReadyCache")
+            .field("inner", &self.inner)
+            .field("permits", &self.permits)
+            .finish()
+    }
+}
+
+impl<Request, S> Service<Request> for ReadyCache<S, Request>
+where
+    S: Service<Request> + 'static,
+{
+    type Response = S::Response;
+    type Permit<'a> = OwnedPermit<S, Request>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        if let Some(permit) = self.permits.lock().await.recv().await {
+            return permit;
+        }
+        self.inner.clone().acquire_owned().await
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        permit.call(request).await
+    }
+}
+
+impl<S, Request> Load for ReadyCache<S, Request>
+where
+    S: Service<Request> + Load + 'static,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S, Request> Describe for ReadyCache<S, Request>
+where
+    S: Service<Request> + Describe + 'static,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("ReadyCache").with_child(self.inner.describe())
+    }
+}
+
+/// Constructs a [`ReadyCache`] and a worker [`Future`], which keeps at most `size` permits
+/// pre-acquired.
+///
+/// See the [module](mod@crate::ready_cache) for more information.
+pub fn ready_cache<S, Request>(
+    service: S,
+    size: usize,
+) -> (ReadyCache<S, Request>, impl Future<Output = ()>)
+where
+    S: Service<Request> + 'static,
+{
+    let inner = Arc::new(service);
+    let (sender, receiver) = mpsc::channel(size.max(1));
+    let worker = {
+        let inner = inner.clone();
+        async move {
+            // Reserve a slot before acquiring, so at most `size` permits are held.
+            while let Ok(slot) = sender.reserve().await {
+                slot.send(inner.clone().acquire_owned().await);
+            }
+        }
+    };
+    let ready_cache = ReadyCache {
+        inner,
+        permits: Mutex::new(receiver),
+    };
+    (ready_cache, worker)
+}
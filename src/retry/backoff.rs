@@ -0,0 +1,216 @@
+//! A [`Backoff`] determines the delay before each retry attempt. Backoffs are independent of the
+//! classification performed by a [`Policy`], and are combined with one using [`WithBackoff`] or
+//! [`ServiceExt::retry_with_backoff`](crate::ServiceExt::retry_with_backoff).
+//!
+//! The following are provided:
+//!
+//! - [`Fixed`] waits the same duration before each attempt.
+//! - [`Exponential`] multiplies the duration by a factor after each attempt, up to a maximum.
+//! - [`Jitter`] randomizes the duration of another [`Backoff`].
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::time::Duration;
+//!
+//! use burger::retry::backoff::{Backoff, Exponential, Jitter};
+//!
+//! let backoff = Exponential::new(Duration::from_millis(100), 2.0, Duration::from_secs(1));
+//! assert_eq!(backoff.delay(0), Duration::from_millis(100));
+//! assert_eq!(backoff.delay(1), Duration::from_millis(200));
+//! assert_eq!(backoff.delay(5), Duration::from_secs(1));
+//!
+//! let jittered = Jitter::new(backoff);
+//! assert!(jittered.delay(1) <= Duration::from_millis(200));
+//! ```
+
+use std::{
+    collections::hash_map::RandomState,
+    fmt,
+    hash::{BuildHasher, Hasher},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{rt, Service};
+
+use super::Policy;
+
+/// A schedule of delays between retry attempts.
+///
+/// See the [module](crate::retry::backoff) for more information.
+pub trait Backoff {
+    /// Returns the delay before the retry attempt with the specified index, starting at zero.
+    fn delay(&self, attempt: usize) -> Duration;
+}
+
+impl<B> Backoff for &B
+where
+    B: Backoff,
+{
+    fn delay(&self, attempt: usize) -> Duration {
+        B::delay(self, attempt)
+    }
+}
+
+/// A [`Backoff`] waiting a fixed duration before each attempt.
+///
+/// See the [module](crate::retry::backoff) for more information.
+#[derive(Debug, Clone, Copy)]
+pub struct Fixed {
+    delay: Duration,
+}
+
+impl Fixed {
+    /// Constructs a [`Fixed`] backoff.
+    pub fn new(delay: Duration) -> Self {
+        Self { delay }
+    }
+}
+
+impl Backoff for Fixed {
+    fn delay(&self, _attempt: usize) -> Duration {
+        self.delay
+    }
+}
+
+/// A [`Backoff`] whose delay is multiplied by a factor after each attempt, up to a maximum.
+///
+/// See the [module](crate::retry::backoff) for more information.
+#[derive(Debug, Clone, Copy)]
+pub struct Exponential {
+    initial: Duration,
+    factor: f64,
+    max: Duration,
+}
+
+impl Exponential {
+    /// Constructs an [`Exponential`] backoff.
+    pub fn new(initial: Duration, factor: f64, max: Duration) -> Self {
+        Self {
+            initial,
+            factor,
+            max,
+        }
+    }
+}
+
+impl Backoff for Exponential {
+    fn delay(&self, attempt: usize) -> Duration {
+        let exponent = i32::try_from(attempt).unwrap_or(i32::MAX);
+        let secs = self.initial.as_secs_f64() * self.factor.powi(exponent);
+        Duration::try_from_secs_f64(secs)
+            .unwrap_or(self.max)
+            .min(self.max)
+    }
+}
+
+/// A [`Backoff`] which uniformly randomizes the delay of another between zero and its value, also
+/// known as "full jitter".
+///
+/// See the [module](crate::retry::backoff) for more information.
+#[derive(Debug, Clone)]
+pub struct Jitter<B> {
+    inner: B,
+    state: RandomState,
+}
+
+impl<B> Jitter<B> {
+    /// Constructs a [`Jitter`] backoff.
+    pub fn new(inner: B) -> Self {
+        Self {
+            inner,
+            state: RandomState::new(),
+        }
+    }
+}
+
+impl<B> Backoff for Jitter<B>
+where
+    B: Backoff,
+{
+    fn delay(&self, attempt: usize) -> Duration {
+        let mut hasher = self.state.build_hasher();
+        hasher.write_usize(attempt);
+        let now = SystemTime::now().duration_since(UNIX_EPOCH);
+        hasher.write_u128(now.unwrap_or_default().as_nanos());
+        let ratio = (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64;
+        self.inner.delay(attempt).mul_f64(ratio)
+    }
+}
+
+/// A [`Policy`] which waits for a [`Backoff`] before each retry of an inner [`Policy`].
+///
+/// See the [module](crate::retry::backoff) for more information.
+#[derive(Debug, Clone)]
+pub struct WithBackoff<P, B> {
+    policy: P,
+    backoff: B,
+}
+
+impl<P, B> WithBackoff<P, B> {
+    /// Constructs a [`Policy`] from an inner [`Policy`] and a [`Backoff`].
+    pub fn new(policy: P, backoff: B) -> Self {
+        Self { policy, backoff }
+    }
+}
+
+/// The [`Policy::RequestState`] for [`WithBackoff`].
+pub struct WithBackoffState<'a, S, P, Request>
+where
+    S: Service<Request>,
+    P: Policy<S, Request>,
+{
+    inner: P::RequestState<'a>,
+    attempt: usize,
+}
+
+impl<'a, S, P, Request> fmt::Debug for WithBackoffState<'a, S, P, Request>
+where
+    S: Service<Request>,
+    P: Policy<S, Request>,
+    P::RequestState<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WithBackoffState")
+            .field("inner", &self.inner)
+            .field("attempt", &self.attempt)
+            .finish()
+    }
+}
+
+impl<S, P, B, Request> Policy<S, Request> for WithBackoff<P, B>
+where
+    S: Service<Request>,
+    P: Policy<S, Request>,
+    B: Backoff,
+{
+    type RequestState<'a> = WithBackoffState<'a, S, P, Request>;
+
+    fn create(&self, request: &Request) -> Self::RequestState<'_> {
+        WithBackoffState {
+            inner: self.policy.create(request),
+            attempt: 0,
+        }
+    }
+
+    async fn classify<'a>(
+        &self,
+        state: Self::RequestState<'a>,
+        response: S::Response,
+    ) -> Result<S::Response, (Request, Self::RequestState<'a>)> {
+        let WithBackoffState { inner, attempt } = state;
+        match self.policy.classify(inner, response).await {
+            Ok(response) => Ok(response),
+            Err((request, inner)) => {
+                rt::sleep(self.backoff.delay(attempt)).await;
+                Err((
+                    request,
+                    WithBackoffState {
+                        inner,
+                        attempt: attempt + 1,
+                    },
+                ))
+            }
+        }
+    }
+}
@@ -0,0 +1,155 @@
+//! A [`Budget`] limits the number of retries relative to the number of requests, preventing retry
+//! storms when a service is overloaded. It's attached to a [`Retry`] using [`Retry::with_budget`],
+//! and may be shared between many [`Retry`]s.
+//!
+//! Each request deposits a fraction of a token into the budget, and each retry withdraws a whole
+//! token. The balance is capped at a reserve, which is also the initial balance. When the balance
+//! is insufficient, the [`Policy`](super::Policy) is not consulted and the response is returned
+//! without retrying.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::sync::Arc;
+//!
+//! use burger::retry::budget::Budget;
+//!
+//! // Permit one retry per ten requests, with a reserve of five retries.
+//! let budget = Arc::new(Budget::new(0.1, 5));
+//! assert_eq!(budget.balance(), 5.0);
+//! ```
+
+use std::sync::{Arc, Mutex};
+
+use super::Retry;
+
+/// A budget of retries, replenished by requests.
+///
+/// See the [module](crate::retry::budget) for more information.
+#[derive(Debug)]
+pub struct Budget {
+    balance: Mutex<f64>,
+    ratio: f64,
+    reserve: f64,
+}
+
+impl Budget {
+    /// Constructs a [`Budget`], where each request deposits `ratio` tokens and the balance is
+    /// capped at `reserve` tokens.
+    pub fn new(ratio: f64, reserve: usize) -> Self {
+        let reserve = reserve as f64;
+        Self {
+            balance: Mutex::new(reserve),
+            ratio: ratio.max(0.0),
+            reserve,
+        }
+    }
+
+    /// Returns the current balance.
+    pub fn balance(&self) -> f64 {
+        *self.balance.lock().unwrap()
+    }
+
+    /// Deposits the tokens for a single request.
+    pub fn deposit(&self) {
+        let mut balance = self.balance.lock().unwrap();
+        *balance = (*balance + self.ratio).min(self.reserve);
+    }
+
+    /// Returns whether a withdrawal would currently succeed.
+    pub fn can_withdraw(&self) -> bool {
+        self.balance() >= 1.0
+    }
+
+    /// Withdraws a token for a single retry, returning whether the balance was sufficient.
+    pub fn withdraw(&self) -> bool {
+        let mut balance = self.balance.lock().unwrap();
+        if *balance >= 1.0 {
+            *balance -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns a token withdrawn for a retry which didn't take place.
+    pub(crate) fn refund(&self) {
+        let mut balance = self.balance.lock().unwrap();
+        *balance = (*balance + 1.0).min(self.reserve);
+    }
+}
+
+impl<S, P> Retry<S, P> {
+    /// Limits the retries using a [`Budget`].
+    ///
+    /// See the [module](crate::retry::budget) for more information.
+    pub fn with_budget(mut self, budget: Arc<Budget>) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use super::Budget;
+    use crate::{
+        retry::{CloneRequest, Policy},
+        service_fn, Service, ServiceExt,
+    };
+
+    struct Always;
+
+    impl<S> Policy<S, ()> for Always
+    where
+        S: Service<()>,
+    {
+        type RequestState<'a> = ();
+
+        fn create(&self, _request: &()) {}
+
+        async fn classify<'a>(
+            &self,
+            _state: (),
+            _response: S::Response,
+        ) -> Result<S::Response, ((), ())> {
+            Err(((), ()))
+        }
+    }
+
+    #[tokio::test]
+    async fn exhausted() {
+        let calls = AtomicUsize::new(0);
+        let budget = Arc::new(Budget::new(0.5, 2));
+        let svc = service_fn(|()| async {
+            calls.fetch_add(1, Ordering::SeqCst);
+        })
+        .retry(Always)
+        .with_budget(budget.clone());
+
+        // The reserve permits two retries.
+        svc.oneshot(()).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        // Two further requests deposit one token.
+        svc.oneshot(()).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+        svc.oneshot(()).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 6);
+        assert_eq!(budget.balance(), 0.0);
+    }
+
+    #[tokio::test]
+    async fn refunded_without_retry() {
+        let budget = Arc::new(Budget::new(0.0, 1));
+        let svc = service_fn(|x: u32| async move { x })
+            .retry(CloneRequest::new(3, |_: &u32| false))
+            .with_budget(budget.clone());
+        assert_eq!(svc.oneshot(1).await, 1);
+        assert_eq!(budget.balance(), 1.0);
+    }
+}
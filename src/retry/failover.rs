@@ -0,0 +1,248 @@
+//! The [`ServiceExt::retry_with`](crate::ServiceExt::retry_with) combinator returns [`RetryWith`],
+//! which retries following a [`Policy`], as [`Retry`](super::Retry) does, but directs each attempt
+//! to a service of its own and may bound each attempt with a timeout.
+//!
+//! The first attempt is made against the service the combinator was applied to, and the retries
+//! are made against the fallbacks, in order. Once the fallbacks are exhausted, the remaining
+//! retries are made against the last of them. For example, with a single fallback in a secondary
+//! region, every retry fails over to that region. Services of differing types can be combined
+//! using [`ServiceExt::left`](crate::ServiceExt::left) and
+//! [`ServiceExt::right`](crate::ServiceExt::right), or
+//! [`ServiceExt::boxed`](crate::ServiceExt::boxed).
+//!
+//! [`RetryWith::with_timeout`] bounds the [call](Service::call) of each attempt, including those
+//! whose permits are acquired during retries. An attempt which times out responds with
+//! [`TimedOut`], converted using [`From`] into the [`Err`] variant of the
+//! [fallible service](crate::TryService), which is then classified by the [`Policy`] like any other
+//! response. [`TimedOut`] converts into [`Error::TimedOut`](crate::Error::TimedOut).
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::time::Duration;
+//!
+//! use burger::{retry::CloneRequest, *};
+//! use tokio::time::sleep;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let primary = service_fn(|x: u32| async move {
+//!     sleep(Duration::from_secs(10)).await;
+//!     Ok::<_, Error>(x)
+//! })
+//! .boxed();
+//! let secondary = service_fn(|x: u32| async move { Ok(x + 1) }).boxed();
+//! let svc = primary
+//!     .retry_with([secondary], CloneRequest::new(2, |response: &Result<_, _>| response.is_err()))
+//!     .with_timeout(Duration::from_millis(10));
+//! assert_eq!(svc.oneshot(1).await, Ok(2));
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`RetryWith`] defers to the primary service.
+
+use std::{fmt, pin::pin, time::Duration};
+
+use futures_util::future::{self, Either};
+
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    rt, Service, TryService,
+};
+
+use super::Policy;
+
+/// An attempt of a [`RetryWith`] exceeded its timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimedOut;
+
+impl fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("attempt timed out")
+    }
+}
+
+impl std::error::Error for TimedOut {}
+
+/// A wrapper for the [`ServiceExt::retry_with`](crate::ServiceExt::retry_with) combinator.
+///
+/// See the [module](crate::retry::failover) for more information.
+#[derive(Clone, Debug)]
+pub struct RetryWith<S, P> {
+    services: Box<[S]>,
+    policy: P,
+    timeout: Option<Duration>,
+}
+
+impl<S, P> RetryWith<S, P> {
+    pub(crate) fn new<I>(primary: S, fallbacks: I, policy: P) -> Self
+    where
+        I: IntoIterator<Item = S>,
+    {
+        Self {
+            services: std::iter::once(primary).chain(fallbacks).collect(),
+            policy,
+            timeout: None,
+        }
+    }
+
+    /// Bounds the [call](Service::call) of each attempt by a timeout.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Returns the service to which the specified attempt, starting at zero, is directed.
+    fn service(&self, attempt: usize) -> &S {
+        // There is always a primary service.
+        &self.services[attempt.min(self.services.len() - 1)]
+    }
+}
+
+/// Makes a single attempt, bounded by the timeout.
+async fn attempt<S, Request>(
+    permit: S::Permit<'_>,
+    request: Request,
+    timeout: Option<Duration>,
+) -> S::Response
+where
+    S: TryService<Request>,
+    S::Error: From<TimedOut>,
+{
+    let call = S::call(permit, request);
+    let Some(timeout) = timeout else {
+        return call.await;
+    };
+    match future::select(pin!(call), rt::sleep(timeout)).await {
+        Either::Left((response, _)) => response,
+        Either::Right(((), _)) => {
+            tracing::trace!("attempt timed out");
+            Err(TimedOut.into())
+        }
+    }
+}
+
+/// The [`Service::Permit`] type for [`RetryWith`].
+pub struct RetryWithPermit<'a, S, P, Request>
+where
+    S: Service<Request>,
+{
+    service: &'a RetryWith<S, P>,
+    inner: S::Permit<'a>,
+}
+
+impl<'a, S, P, Request> fmt::Debug for RetryWithPermit<'a, S, P, Request>
+where
+    S: Service<Request> + fmt::Debug,
+    P: fmt::Debug,
+    S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryWithPermit")
+            .field("service", &self.service)
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<Request, S, P> Service<Request> for RetryWith<S, P>
+where
+    S: TryService<Request>,
+    S::Error: From<TimedOut>,
+    P: Policy<S, Request>,
+{
+    type Response = S::Response;
+    type Permit<'a> = RetryWithPermit<'a, S, P, Request>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        RetryWithPermit {
+            service: self,
+            inner: self.service(0).acquire().await,
+        }
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        let RetryWithPermit { service, inner } = permit;
+        let mut state = service.policy.create(&request);
+        let mut response = attempt::<S, _>(inner, request, service.timeout).await;
+        let mut attempts = 1;
+        loop {
+            match service.policy.classify(state, response).await {
+                Ok(response) => return response,
+                Err((request, new_state)) => {
+                    state = new_state;
+                    let next = service.service(attempts);
+                    attempts += 1;
+                    let permit = next.acquire().await;
+                    response = attempt::<S, _>(permit, request, service.timeout).await;
+                }
+            }
+        }
+    }
+}
+
+impl<S, P> Load for RetryWith<S, P>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.service(0).load()
+    }
+}
+
+impl<S, P> Describe for RetryWith<S, P>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        let node = StackNode::new("RetryWith");
+        let node = match self.timeout {
+            Some(timeout) => node.with_config("timeout", format_args!("{timeout:?}")),
+            None => node,
+        };
+        node.with_children(self.services.iter().map(Describe::describe))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    use tokio::time::sleep;
+
+    use crate::{retry::CloneRequest, service_fn, Error, ServiceExt};
+
+    #[tokio::test]
+    async fn attempts_follow_fallbacks() {
+        let calls: [AtomicUsize; 3] = Default::default();
+        let svc = |index: usize| {
+            let calls = &calls;
+            service_fn(move |_: ()| async move {
+                calls[index].fetch_add(1, Ordering::SeqCst);
+                sleep(Duration::from_millis(50)).await;
+                Ok::<_, Error>(index)
+            })
+        };
+        let svc = svc(0)
+            .retry_with(
+                [svc(1), svc(2)],
+                CloneRequest::new(3, |r: &Result<_, _>| r.is_err()),
+            )
+            .with_timeout(Duration::from_millis(5));
+
+        // Every attempt times out, and the last fallback is reused once the others are exhausted.
+        assert_eq!(svc.oneshot(()).await, Err(Error::TimedOut));
+        let calls = calls.map(|calls| calls.into_inner());
+        assert_eq!(calls, [1, 1, 2]);
+    }
+}
@@ -0,0 +1,587 @@
+//! The [`ServiceExt::retry`] combinator returns [`Retry`], which retries following a specified
+//! [`Policy`].
+//!
+//! A [`Policy`] is used to instantiate the per request state and classify whether a request was
+//! successful or not.
+//!
+//! The [`Service::acquire`] on [`Retry`] waits to acquire the inner [`Service::Permit`]. The
+//! [`Service::call`] then:
+//!
+//! 1. Calls [`Policy::create`] to produce [`Policy::RequestState`].
+//! 2. Uses the inner permit to [`Service::call`] the inner [`Service`].
+//! 3. Calls [`Policy::classify`], with the [`Policy::RequestState`] from (1).
+//! 4. If [`Ok`] then returns the [`Service::Response`], if [`Err`] then returns retries using
+//!    [`ServiceExt::oneshot`] to obtain the next permit.
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::{retry, Service};
+//! use http::{Request, Response};
+//!
+//! struct RetryServiceUnavailable;
+//!
+//! struct State<BReq>(Request<BReq>);
+//!
+//! impl<S, BReq, BResp> retry::Policy<S, Request<BReq>> for RetryServiceUnavailable
+//! where
+//!     S: Service<Request<BReq>, Response = Response<BResp>> + 'static,
+//!     BReq: Clone,
+//! {
+//!     type RequestState<'a> = State<BReq>;
+//!
+//!     fn create(&self, request: &Request<BReq>) -> State<BReq> {
+//!         State(request.clone())
+//!     }
+//!
+//!     async fn classify<'a>(
+//!         &self,
+//!         mut state: State<BReq>,
+//!         response: Response<BResp>,
+//!     ) -> Result<Response<BResp>, (Request<BReq>, State<BReq>)> {
+//!         if response.status() != http::StatusCode::SERVICE_UNAVAILABLE {
+//!             return Ok(response);
+//!         }
+//!         if let Some(some) = response.headers().get("retry-after") {
+//!             Err((state.0.clone(), state))
+//!         } else {
+//!             Ok(response)
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! # Cloning requests
+//!
+//! Where the request is [`Clone`], [`CloneRequest`] provides a [`Policy`] which retries a clone of
+//! the original request while a closure classifies the response as retryable, up to a maximum
+//! number of retries.
+//!
+//! ```rust
+//! use burger::{retry::CloneRequest, *};
+//! # use std::sync::atomic::{AtomicUsize, Ordering};
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let attempts = AtomicUsize::new(0);
+//! let svc = service_fn(|x: u32| {
+//!     let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+//!     async move {
+//!         if attempt < 2 {
+//!             Err("unavailable")
+//!         } else {
+//!             Ok(x)
+//!         }
+//!     }
+//! })
+//! .retry(CloneRequest::new(3, |response: &Result<u32, _>| response.is_err()));
+//! let response = svc.oneshot(7).await;
+//! assert_eq!(response, Ok(7));
+//! assert_eq!(attempts.load(Ordering::SeqCst), 3);
+//! # }
+//! ```
+//!
+//! # Rebuilding requests
+//!
+//! Where cloning the request is expensive, or impossible, such as a request with a large or
+//! streaming body, [`RebuildRequest`] provides a [`Policy`] which instead reconstructs each retry
+//! using an implementation of [`Rebuild`]. [`Rebuild::capture`] captures cheaper state from the
+//! original request, such as its method, URI and a factory for its body, from which
+//! [`Rebuild::rebuild`] asynchronously produces the next attempt. Returning [`None`] ends the
+//! retries, returning the last response.
+//!
+//! ```rust
+//! use burger::{
+//!     retry::{Rebuild, RebuildRequest},
+//!     *,
+//! };
+//! # use std::sync::atomic::{AtomicUsize, Ordering};
+//!
+//! struct Upload {
+//!     id: u32,
+//!     body: Vec<u8>,
+//! }
+//!
+//! // Rebuilds the upload from its identifier, rather than cloning the body.
+//! struct Reload;
+//!
+//! impl Rebuild<Upload> for Reload {
+//!     type State = u32;
+//!
+//!     fn capture(&self, request: &Upload) -> u32 {
+//!         request.id
+//!     }
+//!
+//!     async fn rebuild(&self, id: &mut u32) -> Option<Upload> {
+//!         let body = vec![0; 1024];
+//!         Some(Upload { id: *id, body })
+//!     }
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let attempts = AtomicUsize::new(0);
+//! let svc = service_fn(|upload: Upload| {
+//!     let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+//!     async move {
+//!         if attempt < 1 {
+//!             Err("unavailable")
+//!         } else {
+//!             Ok(upload.body.len())
+//!         }
+//!     }
+//! })
+//! .retry(RebuildRequest::new(3, Reload, |response: &Result<usize, _>| {
+//!     response.is_err()
+//! }));
+//! let response = svc.oneshot(Upload { id: 1, body: vec![0; 1024] }).await;
+//! assert_eq!(response, Ok(1024));
+//! assert_eq!(attempts.load(Ordering::SeqCst), 2);
+//! # }
+//! ```
+//!
+//! # Backoff
+//!
+//! Delays between attempts are configured separately from classification, using the
+//! [`backoff`] module.
+//!
+//! # Failover
+//!
+//! Retries are made against the same inner service. The
+//! [`ServiceExt::retry_with`](crate::ServiceExt::retry_with) combinator instead directs each
+//! attempt to a service of its own, such as a secondary region, and can bound each attempt with a
+//! timeout. See the [`failover`] module for more information.
+//!
+//! # Budget
+//!
+//! The number of retries, relative to the number of requests, can be limited using the [`budget`]
+//! module.
+//!
+//! Retries can also be subject to an [`Admission`] shared with other layers, such as
+//! [`ServiceExt::admit`], using [`Retry::with_admission`]. Each retry must then be admitted, and its
+//! outcome, as classified by the [`Policy`], is released to the [`Admission`]. See the
+//! [`admission`](crate::admission) module for more information.
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`Retry`] defers to the inner service.
+
+#[cfg(feature = "tokio")]
+pub mod backoff;
+pub mod budget;
+#[cfg(feature = "tokio")]
+pub mod failover;
+
+use std::{any, fmt, sync::Arc};
+
+use budget::Budget;
+
+use crate::{
+    admission::{Admission, Admitted, Attempt, Outcome},
+    describe::{Describe, StackNode},
+    load::Load,
+    Middleware, Service, ServiceExt,
+};
+
+/// A retry policy allows for customization of [Retry].
+///
+/// # Example
+///
+/// ```rust
+/// use burger::{retry, Service};
+/// use http::{Request, Response};
+///
+/// struct FiniteRetries(usize);
+///
+/// struct Attempts<'a, BReq> {
+///     max: &'a usize,
+///     request: Request<BReq>,
+///     attempted: usize,
+/// }
+///
+/// impl<S, BReq, BResp> retry::Policy<S, Request<BReq>> for FiniteRetries
+/// where
+///     S: Service<Request<BReq>, Response = http::Response<BResp>>,
+///     BReq: Clone,
+/// {
+///     type RequestState<'a> = Attempts<'a, BReq>;
+///
+///     fn create(&self, request: &Request<BReq>) -> Self::RequestState<'_> {
+///         Attempts {
+///             max: &self.0,
+///             request: request.clone(),
+///             attempted: 0,
+///         }
+///     }
+///
+///     async fn classify<'a>(
+///         &self,
+///         mut state: Self::RequestState<'a>,
+///         response: Response<BResp>,
+///     ) -> Result<Response<BResp>, (Request<BReq>, Self::RequestState<'a>)> {
+///         if response.status() == http::status::StatusCode::OK {
+///             return Ok(response);
+///         }
+///
+///         state.attempted += 1;
+///         if state.attempted >= *state.max {
+///             return Ok(response);
+///         }
+///
+///         Err((state.request.clone(), state))
+///     }
+/// }
+/// ```
+pub trait Policy<S, Request>
+where
+    S: Service<Request>,
+{
+    /// The type of the request state.
+    type RequestState<'a>;
+
+    /// Creates a new [RequestState](Policy::RequestState).
+    fn create(&self, request: &Request) -> Self::RequestState<'_>;
+
+    /// Classifies the response, determining whether it was successful. On success returns [Ok]
+    /// [`Service::Response`], on failure returns the next request and the updated
+    /// [`Policy::RequestState`].
+    async fn classify<'a>(
+        &self,
+        state: Self::RequestState<'a>,
+        response: S::Response,
+    ) -> Result<S::Response, (Request, Self::RequestState<'a>)>;
+}
+
+/// A [`Policy`] which retries a clone of the request while a closure returns `true` for the
+/// response, up to a maximum number of retries.
+///
+/// See the [module](crate::retry#cloning-requests) for more information.
+#[derive(Clone)]
+pub struct CloneRequest<F> {
+    max_retries: usize,
+    closure: F,
+}
+
+impl<F> CloneRequest<F> {
+    /// Constructs a [`CloneRequest`] policy.
+    pub fn new(max_retries: usize, closure: F) -> Self {
+        Self {
+            max_retries,
+            closure,
+        }
+    }
+}
+
+impl<F> fmt::Debug for CloneRequest<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CloneRequest")
+            .field("max_retries", &self.max_retries)
+            .field("closure", &format_args!("{}", any::type_name::<F>()))
+            .finish()
+    }
+}
+
+/// The [`Policy::RequestState`] for [`CloneRequest`].
+#[derive(Debug)]
+pub struct CloneRequestState<Request> {
+    request: Request,
+    retries: usize,
+}
+
+impl<S, F, Request> Policy<S, Request> for CloneRequest<F>
+where
+    S: Service<Request>,
+    F: Fn(&S::Response) -> bool,
+    Request: Clone,
+{
+    type RequestState<'a> = CloneRequestState<Request>;
+
+    fn create(&self, request: &Request) -> Self::RequestState<'_> {
+        CloneRequestState {
+            request: request.clone(),
+            retries: 0,
+        }
+    }
+
+    async fn classify<'a>(
+        &self,
+        mut state: Self::RequestState<'a>,
+        response: S::Response,
+    ) -> Result<S::Response, (Request, Self::RequestState<'a>)> {
+        if state.retries >= self.max_retries || !(self.closure)(&response) {
+            return Ok(response);
+        }
+        state.retries += 1;
+        Err((state.request.clone(), state))
+    }
+}
+
+/// Reconstructs requests for [`RebuildRequest`] from state captured from the original request.
+///
+/// See the [module](crate::retry#rebuilding-requests) for more information.
+pub trait Rebuild<Request> {
+    /// The state captured from the original request.
+    type State;
+
+    /// Captures the state from which retries are rebuilt.
+    fn capture(&self, request: &Request) -> Self::State;
+
+    /// Rebuilds the next request, or returns [`None`] if it can't be.
+    async fn rebuild(&self, state: &mut Self::State) -> Option<Request>;
+}
+
+/// A [`Policy`] which retries a request rebuilt by a [`Rebuild`] while a closure returns `true`
+/// for the response, up to a maximum number of retries.
+///
+/// See the [module](crate::retry#rebuilding-requests) for more information.
+#[derive(Clone)]
+pub struct RebuildRequest<R, F> {
+    max_retries: usize,
+    rebuild: R,
+    closure: F,
+}
+
+impl<R, F> RebuildRequest<R, F> {
+    /// Constructs a [`RebuildRequest`] policy.
+    pub fn new(max_retries: usize, rebuild: R, closure: F) -> Self {
+        Self {
+            max_retries,
+            rebuild,
+            closure,
+        }
+    }
+}
+
+impl<R, F> fmt::Debug for RebuildRequest<R, F>
+where
+    R: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RebuildRequest")
+            .field("max_retries", &self.max_retries)
+            .field("rebuild", &self.rebuild)
+            .field("closure", &format_args!("{}", any::type_name::<F>()))
+            .finish()
+    }
+}
+
+/// The [`Policy::RequestState`] for [`RebuildRequest`].
+#[derive(Debug)]
+pub struct RebuildRequestState<T> {
+    state: T,
+    retries: usize,
+}
+
+impl<S, R, F, Request> Policy<S, Request> for RebuildRequest<R, F>
+where
+    S: Service<Request>,
+    R: Rebuild<Request>,
+    F: Fn(&S::Response) -> bool,
+{
+    type RequestState<'a> = RebuildRequestState<R::State>;
+
+    fn create(&self, request: &Request) -> Self::RequestState<'_> {
+        RebuildRequestState {
+            state: self.rebuild.capture(request),
+            retries: 0,
+        }
+    }
+
+    async fn classify<'a>(
+        &self,
+        mut state: Self::RequestState<'a>,
+        response: S::Response,
+    ) -> Result<S::Response, (Request, Self::RequestState<'a>)> {
+        if state.retries >= self.max_retries || !(self.closure)(&response) {
+            return Ok(response);
+        }
+        let Some(request) = self.rebuild.rebuild(&mut state.state).await else {
+            return Ok(response);
+        };
+        state.retries += 1;
+        Err((request, state))
+    }
+}
+
+/// A wrapper for the [`ServiceExt::retry`] combinator.
+///
+/// See the [module](crate::retry) for more information.
+#[derive(Clone, Debug)]
+pub struct Retry<S, P> {
+    inner: S,
+    policy: P,
+    budget: Option<Arc<Budget>>,
+    admission: Option<Arc<dyn Admission>>,
+}
+
+impl<S, P> Retry<S, P> {
+    pub(crate) fn new(inner: S, policy: P) -> Self {
+        Self {
+            inner,
+            policy,
+            budget: None,
+            admission: None,
+        }
+    }
+
+    /// Subjects each retry to an [`Admission`], which may be shared with other layers.
+    ///
+    /// See the [admission](crate::admission) module for more information.
+    pub fn with_admission<A>(mut self, admission: A) -> Self
+    where
+        A: Admission + 'static,
+    {
+        self.admission = Some(Arc::new(admission));
+        self
+    }
+}
+
+/// The [`Service::Permit`] type for [`Retry`].
+pub struct RetryPermit<'a, S, P, Request>
+where
+    S: Service<Request>,
+{
+    service: &'a S,
+    policy: &'a P,
+    budget: Option<&'a Budget>,
+    admission: Option<&'a dyn Admission>,
+    inner: S::Permit<'a>,
+}
+
+impl<'a, S, P, Request> fmt::Debug for RetryPermit<'a, S, P, Request>
+where
+    S: Service<Request> + fmt::Debug,
+    P: fmt::Debug,
+    S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPermit")
+            .field("service", &self.service)
+            .field("policy", &self.policy)
+            .field("budget", &self.budget)
+            .field("admission", &self.admission)
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<Request, S, P> Service<Request> for Retry<S, P>
+where
+    S: Service<Request>,
+    P: Policy<S, Request>,
+{
+    type Response = S::Response;
+    type Permit<'a> = RetryPermit<'a, S, P, Request>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        RetryPermit {
+            service: &self.inner,
+            policy: &self.policy,
+            budget: self.budget.as_deref(),
+            admission: self.admission.as_deref(),
+            inner: self.inner.acquire().await,
+        }
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        let RetryPermit {
+            service,
+            policy,
+            budget,
+            admission,
+            inner,
+        } = permit;
+        if let Some(budget) = budget {
+            budget.deposit();
+        }
+        let mut state = policy.create(&request);
+        let mut response = S::call(inner, request).await;
+        // The admitted retry whose response is being classified.
+        let mut attempt: Option<Admitted<dyn Admission>> = None;
+
+        loop {
+            // Withdraw and admit the next retry before classifying, so the response can still be
+            // returned. The token is refunded if no retry follows.
+            if let Some(budget) = budget {
+                if !budget.withdraw() {
+                    tracing::trace!("retry budget exhausted");
+                    return response;
+                }
+            }
+            let next = match admission.map(|admission| Admitted::new(admission, Attempt::Retry)) {
+                Some(None) => {
+                    tracing::trace!("retry not admitted");
+                    if let Some(budget) = budget {
+                        budget.refund();
+                    }
+                    return response;
+                }
+                Some(Some(next)) => Some(next),
+                None => None,
+            };
+            match policy.classify(state, response).await {
+                Ok(response) => {
+                    if let Some(attempt) = attempt {
+                        attempt.finish(Outcome::Success);
+                    }
+                    if let Some(budget) = budget {
+                        budget.refund();
+                    }
+                    return response;
+                }
+                Err((request, new_state)) => {
+                    if let Some(attempt) = attempt.take() {
+                        attempt.finish(Outcome::Failure);
+                    }
+                    attempt = next;
+                    state = new_state;
+                    response = service.oneshot(request).await;
+                }
+            }
+        }
+    }
+}
+
+impl<S, P> Load for Retry<S, P>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S, P> Describe for Retry<S, P>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("Retry").with_child(self.inner.describe())
+    }
+}
+
+impl<S, T, P> Middleware<S> for Retry<T, P>
+where
+    T: Middleware<S>,
+{
+    type Service = Retry<T::Service, P>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self {
+            inner,
+            policy,
+            budget,
+            admission,
+        } = self;
+        Retry {
+            inner: inner.apply(svc),
+            policy,
+            budget,
+            admission,
+        }
+    }
+}
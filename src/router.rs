@@ -0,0 +1,174 @@
+//! Given a closure extracting a key from the request, the [`router`] function constructs a
+//! [`Router`] [`Service`], which routes each request to the [`Service`] registered under its key.
+//!
+//! Routes are registered using [`Router::route`] and a default route using [`Router::fallback`].
+//! Routes may also be added and removed while the [`Router`] is in use, via [`Router::insert`] and
+//! [`Router::remove`].
+//!
+//! The [`Service::acquire`] on [`Router`] resolves immediately, as the route isn't known until the
+//! request is provided. The [`Service::call`] then acquires the permit of the routed [`Service`]
+//! and calls it. If there's no matching route and no fallback, the request is returned as [`Err`].
+//!
+//! Unlike [`steer`](mod@crate::steer), routes are named by key rather than picked by index.
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::*;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let multiply = |n: u32| service_fn(move |x: u32| async move { n * x });
+//! let svc = router(|x: &u32| x % 2 == 0)
+//!     .route(true, multiply(2))
+//!     .route(false, multiply(3));
+//! assert_eq!(svc.oneshot(2).await, Ok(4));
+//! assert_eq!(svc.oneshot(3).await, Ok(9));
+//!
+//! svc.remove(&false);
+//! assert_eq!(svc.oneshot(3).await, Err(3));
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! This has _no_ [`Load`](crate::load::Load) implementation, as the route is only known once the
+//! request is provided.
+
+use std::{
+    any,
+    collections::HashMap,
+    fmt,
+    hash::Hash,
+    sync::{Arc, RwLock},
+};
+
+use crate::{
+    describe::{Describe, StackNode},
+    Service, ServiceExt,
+};
+
+/// A wrapper [`Service`] for the [`router`] constructor.
+///
+/// See the [module](mod@crate::router) for more information.
+pub struct Router<K, S, F> {
+    routes: RwLock<HashMap<K, Arc<S>>>,
+    fallback: Option<Arc<S>>,
+    closure: F,
+}
+
+impl<K, S, F> fmt::Debug for Router<K, S, F>
+where
+    K: fmt::Debug,
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Router")
+            .field("routes", &self.routes)
+            .field("fallback", &self.fallback)
+            .field("closure", &format_args!("{}", any::type_name::<F>()))
+            .finish()
+    }
+}
+
+/// Constructs a [`Router`] with no routes, using a closure which extracts the key from the request.
+///
+/// See the [module](mod@crate::router) for more information.
+pub fn router<K, S, F>(closure: F) -> Router<K, S, F> {
+    Router {
+        routes: RwLock::new(HashMap::new()),
+        fallback: None,
+        closure,
+    }
+}
+
+impl<K, S, F> Router<K, S, F>
+where
+    K: Hash + Eq,
+{
+    /// Adds a route, replacing any existing route with the same key.
+    pub fn route(self, key: K, service: S) -> Self {
+        self.insert(key, service);
+        self
+    }
+
+    /// Sets the route used when no other route matches.
+    pub fn fallback(mut self, service: S) -> Self {
+        self.fallback = Some(Arc::new(service));
+        self
+    }
+
+    /// Inserts a route, returning the existing route with the same key.
+    ///
+    /// Calls already routed to the existing route are unaffected.
+    pub fn insert(&self, key: K, service: S) -> Option<Arc<S>> {
+        self.routes.write().unwrap().insert(key, Arc::new(service))
+    }
+
+    /// Removes a route, returning it.
+    ///
+    /// Calls already routed to it are unaffected.
+    pub fn remove(&self, key: &K) -> Option<Arc<S>> {
+        self.routes.write().unwrap().remove(key)
+    }
+}
+
+/// The [`Service::Permit`] type for [`Router`].
+pub struct RouterPermit<'a, K, S, F> {
+    router: &'a Router<K, S, F>,
+}
+
+impl<K, S, F> fmt::Debug for RouterPermit<'_, K, S, F>
+where
+    K: fmt::Debug,
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RouterPermit")
+            .field("router", &self.router)
+            .finish()
+    }
+}
+
+impl<Request, K, S, F> Service<Request> for Router<K, S, F>
+where
+    K: Hash + Eq,
+    S: Service<Request>,
+    F: Fn(&Request) -> K,
+{
+    type Response = Result<S::Response, Request>;
+    type Permit<'a> = RouterPermit<'a, K, S, F>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        RouterPermit { router: self }
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        let RouterPermit { router } = permit;
+        let key = (router.closure)(&request);
+        let service = router.routes.read().unwrap().get(&key).cloned();
+        let Some(service) = service.or_else(|| router.fallback.clone()) else {
+            return Err(request);
+        };
+        Ok(service.oneshot(request).await)
+    }
+}
+
+impl<K, S, F> Describe for Router<K, S, F>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        let routes = self.routes.read().unwrap();
+        let node = StackNode::new("Router")
+            .with_config("routes", routes.len())
+            .with_config("fallback", self.fallback.is_some())
+            .with_children(routes.values().map(|service| service.describe()));
+        match &self.fallback {
+            Some(fallback) => node.with_child(fallback.describe()),
+            None => node,
+        }
+    }
+}
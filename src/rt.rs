@@ -0,0 +1,58 @@
+//! The timers used by this crate.
+//!
+//! By default, timers are provided by [`tokio::time`] and require a Tokio runtime with the time
+//! driver enabled. With the `futures-timer` feature, they're provided by [`futures_timer`] instead,
+//! which drives timers from a background thread and so works with any executor.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+/// A [`Future`] which resolves at a deadline.
+#[derive(Debug)]
+pub(crate) struct Sleep {
+    #[cfg(not(feature = "futures-timer"))]
+    inner: Pin<Box<tokio::time::Sleep>>,
+    #[cfg(feature = "futures-timer")]
+    inner: futures_timer::Delay,
+}
+
+impl Sleep {
+    /// Resets the deadline.
+    pub(crate) fn reset(&mut self, deadline: Instant) {
+        #[cfg(not(feature = "futures-timer"))]
+        self.inner.as_mut().reset(deadline.into());
+        #[cfg(feature = "futures-timer")]
+        self.inner
+            .reset(deadline.saturating_duration_since(Instant::now()));
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.inner).poll(cx)
+    }
+}
+
+/// Returns a [`Sleep`] which resolves after `duration`.
+pub(crate) fn sleep(duration: Duration) -> Sleep {
+    #[cfg(not(feature = "futures-timer"))]
+    let inner = Box::pin(tokio::time::sleep(duration));
+    #[cfg(feature = "futures-timer")]
+    let inner = futures_timer::Delay::new(duration);
+    Sleep { inner }
+}
+
+/// Returns a [`Sleep`] which resolves at `deadline`.
+pub(crate) fn sleep_until(deadline: Instant) -> Sleep {
+    #[cfg(not(feature = "futures-timer"))]
+    let inner = Box::pin(tokio::time::sleep_until(deadline.into()));
+    #[cfg(feature = "futures-timer")]
+    let inner = futures_timer::Delay::new(deadline.saturating_duration_since(Instant::now()));
+    Sleep { inner }
+}
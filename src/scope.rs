@@ -0,0 +1,188 @@
+//! The [`scope`] function runs an asynchronous closure alongside tasks spawned onto a [`Scope`].
+//! Spawned tasks may borrow from outside of the [`scope`], so services used by several tasks need
+//! not be placed within an [`Arc`](std::sync::Arc) to satisfy the `'static` bound of
+//! [`tokio::spawn`](https://docs.rs/tokio/latest/tokio/fn.spawn.html).
+//!
+//! Tasks are driven concurrently with the closure, as part of the [`Future`] returned by
+//! [`scope`], rather than being spawned onto the runtime. Hence, they run on the same thread as
+//! the closure and don't run in parallel. This suits tasks which spend most of their time waiting,
+//! such as calling services.
+//!
+//! - [`Scope::spawn`] spawns a task which is awaited before [`scope`] completes.
+//! - [`Scope::spawn_background`] spawns a task, such as a [balance](crate::balance) worker, which
+//!   is dropped once the closure and the other tasks have completed.
+//!
+//! Tasks need not be [`Send`], and so neither is the [`Future`] returned by [`scope`]. If it's
+//! dropped then all of its tasks are dropped.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::{cell::RefCell, future::ready};
+//!
+//! use burger::{scope::Scope, *};
+//! # use futures::{stream::{iter, pending}, StreamExt};
+//!
+//! # #[cfg(feature = "tokio")]
+//! # #[tokio::main]
+//! # async fn main() {
+//! let double: fn(_) -> _ = |x: u32| ready(2 * x);
+//! let changes = iter([balance::Change::Insert(0, service_fn(double).pending_requests())]);
+//! let (svc, worker) = balance::p2c(changes.chain(pending()));
+//! let responses = RefCell::new(Vec::new());
+//!
+//! let total = scope(async |s: &Scope<'_>| {
+//!     s.spawn_background(async {
+//!         let _ = worker.await;
+//!     });
+//!     for x in 0..4 {
+//!         let (svc, responses) = (&svc, &responses);
+//!         s.spawn(async move {
+//!             let response = svc.oneshot(x).await;
+//!             responses.borrow_mut().push(response);
+//!         });
+//!     }
+//!     svc.oneshot(10).await
+//! })
+//! .await;
+//!
+//! assert_eq!(total, 20);
+//! let mut responses = responses.into_inner();
+//! responses.sort();
+//! assert_eq!(responses, [0, 2, 4, 6]);
+//! # }
+//! # #[cfg(not(feature = "tokio"))]
+//! # fn main() {}
+//! ```
+
+use std::{
+    cell::RefCell,
+    fmt,
+    future::{poll_fn, Future},
+    pin::pin,
+    task::{Context, Poll},
+};
+
+use futures_util::{future::LocalBoxFuture, stream::FuturesUnordered, FutureExt, StreamExt};
+
+type Tasks<'env> = RefCell<FuturesUnordered<LocalBoxFuture<'env, ()>>>;
+
+/// Tasks spawned within a [`scope`].
+///
+/// See the [module](crate::scope) for more information.
+pub struct Scope<'env> {
+    tasks: Tasks<'env>,
+    background: Tasks<'env>,
+}
+
+impl fmt::Debug for Scope<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scope")
+            .field("tasks", &self.tasks.borrow().len())
+            .field("background", &self.background.borrow().len())
+            .finish()
+    }
+}
+
+impl<'env> Scope<'env> {
+    /// Spawns a task, which is awaited before the [`scope`] completes.
+    pub fn spawn<F>(&self, task: F)
+    where
+        F: Future<Output = ()> + 'env,
+    {
+        self.tasks.borrow_mut().push(task.boxed_local());
+    }
+
+    /// Spawns a task, which is dropped once the [`scope`]'s closure and its other tasks have
+    /// completed.
+    pub fn spawn_background<F>(&self, task: F)
+    where
+        F: Future<Output = ()> + 'env,
+    {
+        self.background.borrow_mut().push(task.boxed_local());
+    }
+}
+
+/// Drives the tasks to completion, or until they're all pending. Returns whether none remain.
+fn drive(tasks: &Tasks<'_>, cx: &mut Context<'_>) -> bool {
+    let mut tasks = tasks.borrow_mut();
+    while let Poll::Ready(Some(())) = tasks.poll_next_unpin(cx) {}
+    tasks.is_empty()
+}
+
+/// Runs the closure alongside the tasks it spawns onto the [`Scope`], returning the closure's
+/// output once it and the [spawned](Scope::spawn) tasks have completed.
+///
+/// See the [module](crate::scope) for more information.
+pub async fn scope<'env, F, R>(closure: F) -> R
+where
+    F: for<'scope> AsyncFnOnce(&'scope Scope<'env>) -> R,
+{
+    let scope = Scope {
+        tasks: RefCell::new(FuturesUnordered::new()),
+        background: RefCell::new(FuturesUnordered::new()),
+    };
+    let mut body = pin!(closure(&scope));
+    let mut output = None;
+    poll_fn(|cx| {
+        if output.is_none() {
+            if let Poll::Ready(value) = body.as_mut().poll(cx) {
+                output = Some(value);
+            }
+        }
+        // Tasks can't borrow the `Scope`, so can't spawn while they're borrowed.
+        let finished = drive(&scope.tasks, cx);
+        drive(&scope.background, cx);
+        match output.take() {
+            Some(output) if finished => Poll::Ready(output),
+            taken => {
+                output = taken;
+                Poll::Pending
+            }
+        }
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, future::pending};
+
+    use futures_util::FutureExt;
+
+    use super::{scope, Scope};
+
+    struct OnDrop<'a>(&'a Cell<bool>);
+
+    impl Drop for OnDrop<'_> {
+        fn drop(&mut self) {
+            self.0.set(true);
+        }
+    }
+
+    #[test]
+    fn awaits_tasks_and_drops_background() {
+        let completed = Cell::new(0);
+        let dropped = Cell::new(false);
+        let output = scope(async |s: &Scope<'_>| {
+            let guard = OnDrop(&dropped);
+            s.spawn_background(async move {
+                let _guard = guard;
+                pending::<()>().await;
+            });
+            for _ in 0..3 {
+                s.spawn(async {
+                    tokio::task::yield_now().await;
+                    completed.set(completed.get() + 1);
+                });
+            }
+            "done"
+        });
+        let mut output = Box::pin(output);
+        // The closure completes immediately, but the tasks yield once.
+        assert!((&mut output).now_or_never().is_none());
+        assert_eq!(output.now_or_never(), Some("done"));
+        assert_eq!(completed.get(), 3);
+        assert!(dropped.get());
+    }
+}
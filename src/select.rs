@@ -1,5 +1,10 @@
 //! Given a collection of some [services](Service), [`select`] constructs a [`Service`] which uses the
-//! first permit available.
+//! first permit available. If the collection is empty then no permit ever becomes available, and
+//! [`Service::acquire`] waits indefinitely.
+//!
+//! The collection must be homogeneous. To select between differently typed services, which share a
+//! [`Service::Response`], [`select_tuple`] accepts a tuple of up to four services. Its permit is a
+//! nested [`Either`] of the permits.
 //!
 //! # Example
 //!
@@ -36,14 +41,85 @@
 //! # }
 //! ```
 //!
+//! Differently typed services are selected between using [`select_tuple`]:
+//!
+//! ```rust
+//! use burger::*;
+//!
+//! # #[cfg(feature = "tokio")]
+//! # #[tokio::main]
+//! # async fn main() {
+//! let local = service_fn(|x: u64| async move { x + 1 });
+//! let remote = service_fn(|x: u64| async move { x + 2 }).concurrency_limit(1);
+//! let svc = select_tuple((local, remote));
+//! let response = svc.oneshot(7).await;
+//! assert_eq!(8, response);
+//! # }
+//! # #[cfg(not(feature = "tokio"))]
+//! # fn main() {}
+//! ```
+//!
+//! # Fairness
+//!
+//! By default, when several permits are available at once, the earliest service in the collection
+//! is preferred. [`Select::rotating`] and [`SelectTuple::rotating`] instead start from the next
+//! service on each [`Service::acquire`], so that each is preferred in turn.
+//!
+//! ```rust
+//! use burger::*;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svcs: Vec<_> = (0..3)
+//!     .map(|index| service_fn(move |x: u32| async move { index + x }))
+//!     .collect();
+//! let svc = select(svcs).rotating();
+//! let mut responses = Vec::new();
+//! for _ in 0..3 {
+//!     responses.push(svc.oneshot(7).await);
+//! }
+//! assert_eq!(responses, [7, 8, 9]);
+//! # }
+//! ```
+//!
 //! # Load
 //!
-//! This has _no_ [`Load`](crate::Load) implementation.
-use std::{fmt, marker::PhantomData};
+//! This has _no_ [`Load`](crate::load::Load) implementation, as which service is called isn't known
+//! until a permit is acquired. One can be added using
+//! [`ServiceExt::constant_load`](crate::ServiceExt::constant_load).
 
-use futures_util::future::select_all;
+use core::{
+    fmt,
+    future::{self, poll_fn, Future},
+    marker::PhantomData,
+    pin::pin,
+    sync::atomic::{AtomicUsize, Ordering},
+    task::Poll,
+};
 
-use crate::Service;
+use futures_util::{stream::FuturesUnordered, StreamExt};
+
+use crate::{
+    describe::{Describe, StackNode},
+    either::Either,
+    Service,
+};
+
+/// The order in which to poll `len` futures, starting from `start`.
+fn order(start: usize, len: usize) -> impl Iterator<Item = usize> {
+    (start..len).chain(0..start)
+}
+
+/// Returns the index to start polling from, advancing it if rotating.
+fn start(next: &Option<AtomicUsize>, len: usize) -> usize {
+    next.as_ref()
+        .map_or(0, |next| next.fetch_add(1, Ordering::Relaxed) % len)
+}
+
+fn clone_next(next: &Option<AtomicUsize>) -> Option<AtomicUsize> {
+    next.as_ref()
+        .map(|next| AtomicUsize::new(next.load(Ordering::Relaxed)))
+}
 
 /// A wrapper [`Service`] for the [`select`] constructor.
 ///
@@ -51,6 +127,20 @@ use crate::Service;
 pub struct Select<S, I> {
     _inner: PhantomData<S>,
     services: I,
+    next: Option<AtomicUsize>,
+}
+
+impl<S, I> Select<S, I> {
+    /// Prefers each service in turn, rather than the earliest, when several permits are available
+    /// at once.
+    ///
+    /// See the [module](mod@crate::select#fairness) for more information.
+    pub fn rotating(self) -> Self {
+        Self {
+            next: Some(AtomicUsize::new(0)),
+            ..self
+        }
+    }
 }
 
 impl<S, I> fmt::Debug for Select<S, I>
@@ -61,6 +151,7 @@ where
         f.debug_struct("Select")
             .field("_inner", &self._inner)
             .field("services", &self.services)
+            .field("next", &self.next)
             .finish()
     }
 }
@@ -73,6 +164,7 @@ where
         Self {
             _inner: self._inner,
             services: self.services.clone(),
+            next: clone_next(&self.next),
         }
     }
 }
@@ -88,10 +180,21 @@ where
         S: 'a, I: 'a;
 
     async fn acquire(&self) -> Self::Permit<'_> {
-        // This `Box::pin` could be removed with `return_type_notation`.
-        let iter = self.services.into_iter().map(|s| s.acquire()).map(Box::pin);
-        let (permit, _, _) = select_all(iter).await;
-        permit
+        let len = self.services.into_iter().count();
+        // Nothing can be acquired from no services.
+        if len == 0 {
+            return future::pending().await;
+        }
+        let start = start(&self.next, len);
+
+        // Acquisitions are first polled in the order pushed, so the earliest is preferred.
+        let services = self.services.into_iter();
+        let mut acquires: FuturesUnordered<_> = services
+            .skip(start)
+            .chain(self.services.into_iter().take(start))
+            .map(|s| s.acquire())
+            .collect();
+        acquires.next().await.expect("not empty")
     }
 
     async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
@@ -102,6 +205,16 @@ where
     }
 }
 
+impl<S, I> Describe for Select<S, I>
+where
+    for<'a> &'a I: IntoIterator<Item = &'a S>,
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("Select").with_children(self.services.into_iter().map(S::describe))
+    }
+}
+
 /// Constructs a [`Service`] from a collection ([`IntoIterator`] must be implemented for its
 /// reference) of services whose [`Service::call`] is the by the first available child.
 ///
@@ -110,5 +223,257 @@ pub fn select<S, I>(services: I) -> Select<S, I> {
     Select {
         _inner: PhantomData,
         services,
+        next: None,
+    }
+}
+
+/// A wrapper [`Service`] for the [`select_tuple`] constructor.
+///
+/// See the [module](mod@crate::select) for more information.
+#[derive(Debug)]
+pub struct SelectTuple<T> {
+    services: T,
+    next: Option<AtomicUsize>,
+}
+
+impl<T> SelectTuple<T> {
+    /// Prefers each service in turn, rather than the earliest, when several permits are available
+    /// at once.
+    ///
+    /// See the [module](mod@crate::select#fairness) for more information.
+    pub fn rotating(self) -> Self {
+        Self {
+            next: Some(AtomicUsize::new(0)),
+            ..self
+        }
+    }
+}
+
+impl<T> Clone for SelectTuple<T>
+where
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            services: self.services.clone(),
+            next: clone_next(&self.next),
+        }
+    }
+}
+
+/// Constructs a [`Service`] from a tuple of up to four services whose [`Service::call`] is the by
+/// the first available child.
+///
+/// See [module](mod@crate::select) for more information.
+pub fn select_tuple<T>(services: T) -> SelectTuple<T> {
+    SelectTuple {
+        services,
+        next: None,
+    }
+}
+
+impl<Request, A, B> Service<Request> for SelectTuple<(A, B)>
+where
+    A: Service<Request>,
+    B: Service<Request, Response = A::Response>,
+{
+    type Response = A::Response;
+    type Permit<'a> = Either<A::Permit<'a>, B::Permit<'a>>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        let (a, b) = &self.services;
+        let (mut a, mut b) = (pin!(a.acquire()), pin!(b.acquire()));
+        let start = start(&self.next, 2);
+        poll_fn(|cx| {
+            for index in order(start, 2) {
+                let poll = match index {
+                    0 => a.as_mut().poll(cx).map(Either::Left),
+                    _ => b.as_mut().poll(cx).map(Either::Right),
+                };
+                if poll.is_ready() {
+                    return poll;
+                }
+            }
+            Poll::Pending
+        })
+        .await
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        Either::<A, B>::call(permit, request).await
+    }
+}
+
+impl<A, B> Describe for SelectTuple<(A, B)>
+where
+    A: Describe,
+    B: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("SelectTuple")
+            .with_children([self.services.0.describe(), self.services.1.describe()])
+    }
+}
+
+impl<Request, A, B, C> Service<Request> for SelectTuple<(A, B, C)>
+where
+    A: Service<Request>,
+    B: Service<Request, Response = A::Response>,
+    C: Service<Request, Response = A::Response>,
+{
+    type Response = A::Response;
+    type Permit<'a> = Either<A::Permit<'a>, Either<B::Permit<'a>, C::Permit<'a>>>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        let (a, b, c) = &self.services;
+        let (mut a, mut b, mut c) = (pin!(a.acquire()), pin!(b.acquire()), pin!(c.acquire()));
+        let start = start(&self.next, 3);
+        poll_fn(|cx| {
+            for index in order(start, 3) {
+                let poll = match index {
+                    0 => a.as_mut().poll(cx).map(Either::Left),
+                    1 => b.as_mut().poll(cx).map(|b| Either::Right(Either::Left(b))),
+                    _ => c.as_mut().poll(cx).map(|c| Either::Right(Either::Right(c))),
+                };
+                if poll.is_ready() {
+                    return poll;
+                }
+            }
+            Poll::Pending
+        })
+        .await
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        Either::<A, Either<B, C>>::call(permit, request).await
+    }
+}
+
+impl<A, B, C> Describe for SelectTuple<(A, B, C)>
+where
+    A: Describe,
+    B: Describe,
+    C: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("SelectTuple").with_children([
+            self.services.0.describe(),
+            self.services.1.describe(),
+            self.services.2.describe(),
+        ])
+    }
+}
+
+impl<Request, A, B, C, D> Service<Request> for SelectTuple<(A, B, C, D)>
+where
+    A: Service<Request>,
+    B: Service<Request, Response = A::Response>,
+    C: Service<Request, Response = A::Response>,
+    D: Service<Request, Response = A::Response>,
+{
+    type Response = A::Response;
+    type Permit<'a> =
+        Either<A::Permit<'a>, Either<B::Permit<'a>, Either<C::Permit<'a>, D::Permit<'a>>>>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        let (a, b, c, d) = &self.services;
+        let (mut a, mut b) = (pin!(a.acquire()), pin!(b.acquire()));
+        let (mut c, mut d) = (pin!(c.acquire()), pin!(d.acquire()));
+        let start = start(&self.next, 4);
+        poll_fn(|cx| {
+            for index in order(start, 4) {
+                let poll = match index {
+                    0 => a.as_mut().poll(cx).map(Either::Left),
+                    1 => b.as_mut().poll(cx).map(|b| Either::Right(Either::Left(b))),
+                    2 => c
+                        .as_mut()
+                        .poll(cx)
+                        .map(|c| Either::Right(Either::Right(Either::Left(c)))),
+                    _ => d
+                        .as_mut()
+                        .poll(cx)
+                        .map(|d| Either::Right(Either::Right(Either::Right(d)))),
+                };
+                if poll.is_ready() {
+                    return poll;
+                }
+            }
+            Poll::Pending
+        })
+        .await
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        Either::<A, Either<B, Either<C, D>>>::call(permit, request).await
+    }
+}
+
+impl<A, B, C, D> Describe for SelectTuple<(A, B, C, D)>
+where
+    A: Describe,
+    B: Describe,
+    C: Describe,
+    D: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("SelectTuple").with_children([
+            self.services.0.describe(),
+            self.services.1.describe(),
+            self.services.2.describe(),
+            self.services.3.describe(),
+        ])
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::timeout;
+
+    use crate::{service_fn, Service, ServiceExt};
+
+    use super::{select, select_tuple};
+
+    #[tokio::test]
+    async fn empty() {
+        let mut svcs = vec![service_fn(|x: u32| async move { x })];
+        svcs.clear();
+        let svc = select(svcs);
+        let acquire = timeout(Duration::from_millis(10), svc.acquire()).await;
+        assert!(acquire.is_err());
+    }
+
+    #[tokio::test]
+    async fn rotating_tuple() {
+        let a = service_fn(|x: u32| async move { x });
+        let b = service_fn(|x: u32| async move { x + 1 });
+        let c = service_fn(|x: u32| async move { x + 2 });
+
+        let svc = select_tuple((a, b, c));
+        assert_eq!(svc.oneshot(7).await, 7);
+        assert_eq!(svc.oneshot(7).await, 7);
+
+        let svc = svc.rotating();
+        let mut responses = Vec::new();
+        for _ in 0..4 {
+            responses.push(svc.oneshot(7).await);
+        }
+        assert_eq!(responses, [7, 8, 9, 7]);
     }
 }
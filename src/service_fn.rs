@@ -14,13 +14,50 @@
 //! # }
 //! ```
 //!
+//! # Borrowed requests
+//!
+//! The [`service_ref_fn`] function accepts an [async closure](AsyncFn) taking a reference to the
+//! request and returns [`ServiceRefFn`], a [`Service`] accepting `&Request`. The response mustn't
+//! borrow the request. As a reference is cheap to copy, middleware which reissues a request, such as
+//! [`Retry`](crate::retry::Retry) with [`CloneRequest`](crate::retry::CloneRequest), does so
+//! without cloning the underlying request.
+//!
+//! ```rust
+//! # #[cfg(feature = "tokio")]
+//! use burger::{retry::CloneRequest, *};
+//! # use std::sync::atomic::{AtomicUsize, Ordering};
+//!
+//! # #[cfg(feature = "tokio")]
+//! # #[tokio::main]
+//! # async fn main() {
+//! let attempts = AtomicUsize::new(0);
+//! let svc = service_ref_fn(async |body: &Vec<u8>| {
+//!     if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+//!         Err("unavailable")
+//!     } else {
+//!         Ok(body.len())
+//!     }
+//! })
+//! .retry(CloneRequest::new(1, |response: &Result<_, _>| response.is_err()));
+//! let body = vec![0; 1024];
+//! let response = svc.oneshot(&body).await;
+//! assert_eq!(response, Ok(1024));
+//! # }
+//! # #[cfg(not(feature = "tokio"))]
+//! # fn main() {}
+//! ```
+//!
 //! # Load
 //!
-//! This has _no_ [`Load`](crate::load::Load) implementation.
+//! This has _no_ [`Load`](crate::load::Load) implementation, one can be added using
+//! [`ServiceExt::constant_load`](crate::ServiceExt::constant_load).
 
-use std::{any, fmt, future::Future};
+use core::{any, fmt, future::Future};
 
-use crate::Service;
+use crate::{
+    describe::{Describe, StackNode},
+    Service,
+};
 
 /// The [`Service`] returned by the [`service_fn`] constructor.
 ///
@@ -55,9 +92,61 @@ where
     }
 }
 
+impl<F> Describe for ServiceFn<F> {
+    fn describe(&self) -> StackNode {
+        StackNode::new("ServiceFn")
+    }
+}
+
 /// Constructs a [`Service`] from a closure.
 ///
 /// See the [module](mod@crate::service_fn) for more details.
 pub fn service_fn<F>(closure: F) -> ServiceFn<F> {
     ServiceFn { closure }
 }
+
+/// The [`Service`] returned by the [`service_ref_fn`] constructor.
+///
+/// See the [module](mod@crate::service_fn#borrowed-requests) for more information.
+#[derive(Clone)]
+pub struct ServiceRefFn<F> {
+    closure: F,
+}
+
+impl<F> fmt::Debug for ServiceRefFn<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServiceRefFn")
+            .field("closure", &format_args!("{}", any::type_name::<F>()))
+            .finish()
+    }
+}
+
+impl<'r, Request, F, Response> Service<&'r Request> for ServiceRefFn<F>
+where
+    F: AsyncFn(&Request) -> Response,
+{
+    type Response = Response;
+    type Permit<'a> = &'a F where F: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        &self.closure
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: &'r Request) -> Self::Response {
+        permit(request).await
+    }
+}
+
+impl<F> Describe for ServiceRefFn<F> {
+    fn describe(&self) -> StackNode {
+        StackNode::new("ServiceRefFn")
+    }
+}
+
+/// Constructs a [`Service`] accepting a reference to the request from an
+/// [async closure](AsyncFn).
+///
+/// See the [module](mod@crate::service_fn#borrowed-requests) for more details.
+pub fn service_ref_fn<F>(closure: F) -> ServiceRefFn<F> {
+    ServiceRefFn { closure }
+}
@@ -0,0 +1,101 @@
+//! The [`shared_mut`] function accepts a [`ServiceMut`], a service requiring exclusive access to
+//! its state during each call, and returns [`SharedMut`], a [`Service`] which shares it behind a
+//! [`Mutex`].
+//!
+//! The [`Service::acquire`] on [`SharedMut`] waits to lock the [`Mutex`], in first-in, first-out
+//! order, and the guard is used as the [`Service::Permit`]. The lock is therefore held for the
+//! duration of the [`Service::call`], and calls are executed one at a time.
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::{shared_mut::ServiceMut, *};
+//!
+//! struct Sequence(u64);
+//!
+//! impl ServiceMut<&'static str> for Sequence {
+//!     type Response = String;
+//!
+//!     async fn call(&mut self, request: &'static str) -> String {
+//!         self.0 += 1;
+//!         format!("{request}-{}", self.0)
+//!     }
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc = shared_mut(Sequence(0)).concurrency_limit(4);
+//! assert_eq!(svc.oneshot("a").await, "a-1");
+//! assert_eq!(svc.oneshot("b").await, "b-2");
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! This has _no_ [`Load`](crate::load::Load) implementation.
+
+use tokio::sync::{Mutex, MutexGuard};
+
+use crate::{
+    describe::{Describe, StackNode},
+    Service,
+};
+
+/// An asynchronous function call requiring exclusive access to its state.
+///
+/// See the [module](mod@crate::shared_mut) for more information.
+pub trait ServiceMut<Request> {
+    /// The type produced by the service call.
+    type Response;
+
+    /// Calls the service.
+    async fn call(&mut self, request: Request) -> Self::Response;
+}
+
+/// The [`Service`] returned by the [`shared_mut`] constructor.
+///
+/// See the [module](mod@crate::shared_mut) for more information.
+#[derive(Debug)]
+pub struct SharedMut<S> {
+    inner: Mutex<S>,
+}
+
+impl<S> SharedMut<S> {
+    /// Consumes the [`SharedMut`], returning the inner [`ServiceMut`].
+    pub fn into_inner(self) -> S {
+        self.inner.into_inner()
+    }
+}
+
+impl<Request, S> Service<Request> for SharedMut<S>
+where
+    S: ServiceMut<Request>,
+{
+    type Response = S::Response;
+    type Permit<'a> = MutexGuard<'a, S>
+    where
+        S: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        self.inner.lock().await
+    }
+
+    async fn call(mut permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        permit.call(request).await
+    }
+}
+
+impl<S> Describe for SharedMut<S> {
+    fn describe(&self) -> StackNode {
+        StackNode::new("SharedMut")
+    }
+}
+
+/// Constructs a [`Service`] from a [`ServiceMut`].
+///
+/// See the [module](mod@crate::shared_mut) for more details.
+pub fn shared_mut<S>(service: S) -> SharedMut<S> {
+    SharedMut {
+        inner: Mutex::new(service),
+    }
+}
@@ -0,0 +1,206 @@
+//! The [`ServiceExt::singleflight`](crate::ServiceExt::singleflight) combinator returns
+//! [`Singleflight`], which de-duplicates concurrent [calls](Service::call) with equal requests.
+//!
+//! The first call with a given request is forwarded to the inner service, while subsequent calls
+//! with an equal request wait for, and receive a clone of, its response. If the first call is
+//! cancelled then a waiting call takes its place. Unlike the [cache](mod@crate::cache) module,
+//! nothing is retained once the call completes.
+//!
+//! The [`Service::acquire`] on [`Singleflight`] waits to acquire the inner [`Service::Permit`], as
+//! the request isn't known until [`Service::call`]. If an equal call is then found to be in flight,
+//! the inner permit is released without being used.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::{
+//!     sync::atomic::{AtomicUsize, Ordering},
+//!     time::Duration,
+//! };
+//!
+//! use burger::*;
+//! use tokio::{join, time::sleep};
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let calls = AtomicUsize::new(0);
+//! let svc = service_fn(|x: u32| {
+//!     calls.fetch_add(1, Ordering::SeqCst);
+//!     async move {
+//!         sleep(Duration::from_millis(10)).await;
+//!         x.to_string()
+//!     }
+//! })
+//! .singleflight();
+//!
+//! let (a, b) = join!(svc.oneshot(3), svc.oneshot(3));
+//! assert_eq!((a.as_str(), b.as_str()), ("3", "3"));
+//! assert_eq!(calls.load(Ordering::SeqCst), 1);
+//!
+//! svc.oneshot(3).await;
+//! assert_eq!(calls.load(Ordering::SeqCst), 2);
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`Singleflight`] defers to the inner service.
+
+use std::{collections::HashMap, fmt, hash::Hash, sync::Mutex};
+
+use crate::{
+    describe::{Describe, StackNode},
+    inflight::{self, Inflight, Lookup},
+    load::Load,
+    Middleware, Service,
+};
+
+/// A wrapper for the [`ServiceExt::singleflight`](crate::ServiceExt::singleflight) combinator.
+///
+/// See the [module](crate::singleflight) for more information.
+#[derive(Debug)]
+pub struct Singleflight<S, Request, Response> {
+    inner: S,
+    inflight: Mutex<Inflight<Request, Response>>,
+}
+
+impl<S, Request, Response> Singleflight<S, Request, Response> {
+    pub(crate) fn new(inner: S) -> Self {
+        Self {
+            inner,
+            inflight: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<S, Request, Response> Singleflight<S, Request, Response>
+where
+    Request: Hash + Eq + Clone,
+{
+    fn join(
+        &self,
+        request: &Request,
+    ) -> Lookup<'_, Inflight<Request, Response>, Request, Response> {
+        let mut inflight = self.inflight.lock().unwrap();
+        inflight::join(&self.inflight, &mut inflight, request)
+    }
+}
+
+/// The [`Service::Permit`] type for [`Singleflight`].
+pub struct SingleflightPermit<'a, S, Request>
+where
+    S: Service<Request>,
+{
+    service: &'a Singleflight<S, Request, S::Response>,
+    inner: S::Permit<'a>,
+}
+
+impl<'a, S, Request> fmt::Debug for SingleflightPermit<'a, S, Request>
+where
+    S: Service<Request> + fmt::Debug,
+    Request: fmt::Debug,
+    S::Response: fmt::Debug,
+    S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SingleflightPermit")
+            .field("service", &self.service)
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<Request, S> Service<Request> for Singleflight<S, Request, S::Response>
+where
+    S: Service<Request>,
+    Request: Hash + Eq + Clone,
+    S::Response: Clone,
+{
+    type Response = S::Response;
+    type Permit<'a> = SingleflightPermit<'a, S, Request>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        SingleflightPermit {
+            service: self,
+            inner: self.inner.acquire().await,
+        }
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        let SingleflightPermit { service, inner } = permit;
+        let join = |request: &Request| service.join(request);
+        inflight::call(&service.inner, inner, request, join, |_, _| {}).await
+    }
+}
+
+impl<S, Request, Response> Load for Singleflight<S, Request, Response>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S, Request, Response> Describe for Singleflight<S, Request, Response>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("Singleflight").with_child(self.inner.describe())
+    }
+}
+
+impl<S, T, Request, Response> Middleware<S> for Singleflight<T, Request, Response>
+where
+    T: Middleware<S>,
+{
+    type Service = Singleflight<T::Service, Request, Response>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self { inner, inflight } = self;
+        Singleflight {
+            inner: inner.apply(svc),
+            inflight,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    use tokio::time::{sleep, timeout};
+
+    use crate::{service_fn, ServiceExt};
+
+    #[tokio::test]
+    async fn cancelled_leader() {
+        let calls = AtomicUsize::new(0);
+        let svc = service_fn(|x: u32| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async move {
+                sleep(Duration::from_millis(20)).await;
+                x
+            }
+        })
+        .singleflight();
+
+        // The leader is cancelled, so the waiting call takes its place.
+        let leader = timeout(Duration::from_millis(5), svc.oneshot(1));
+        let (leader, waiter) = tokio::join!(leader, svc.oneshot(1));
+        assert!(leader.is_err());
+        assert_eq!(waiter, 1);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,123 @@
+//! The [`ServiceExt::spawned`](crate::ServiceExt::spawned) combinator returns [`Spawned`], which
+//! executes each [`Service::call`] of the inner service on a spawned task.
+//!
+//! The [`Service::acquire`] on [`Spawned`] waits to acquire an [`OwnedPermit`] from the inner
+//! service. The [`Service::call`] then spawns a task, using [`spawn_local`], which uses the permit
+//! to call the inner service, and immediately returns its [`JoinHandle`].
+//!
+//! The [`JoinHandle`] may be awaited to obtain the response, dropped to detach the call, or used
+//! to abort it.
+//!
+//! Spawning with [`tokio::spawn`] requires a [`Send`] future, and the [`Service`] trait can't
+//! require that of the future returned by [`Service::call`]. The task is therefore spawned onto
+//! the current [`LocalSet`](tokio::task::LocalSet), rather than onto any worker thread of the
+//! runtime. [`Service::call`] on [`Spawned`] must be run within a
+//! [`LocalSet`](tokio::task::LocalSet), for example using
+//! [`LocalSet::run_until`](tokio::task::LocalSet::run_until), and panics otherwise.
+//!
+//! When used as a [`Middleware`], [`Middleware::apply`] on [`Spawned`] panics if an
+//! [`OwnedPermit`] acquired from it is outstanding, as the permit shares the inner middleware.
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::*;
+//! use tokio::task::LocalSet;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! LocalSet::new()
+//!     .run_until(async {
+//!         let svc = service_fn(|x: u32| async move { x + 1 }).spawned();
+//!         let handle = svc.oneshot(3).await;
+//!         assert_eq!(handle.await.unwrap(), 4);
+//!
+//!         // Fire-and-forget, aborting if still running.
+//!         let handle = svc.oneshot(3).await;
+//!         handle.abort();
+//!     })
+//!     .await;
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`Spawned`] defers to the inner service.
+
+use std::sync::Arc;
+
+use tokio::task::{spawn_local, JoinHandle};
+
+use crate::{
+    describe::{Describe, StackNode},
+    leak::OwnedPermit,
+    load::Load,
+    Middleware, Service, ServiceExt,
+};
+
+/// A wrapper [`Service`] for the [`ServiceExt::spawned`](crate::ServiceExt::spawned) combinator.
+///
+/// See the [module](crate::spawn) for more information.
+#[derive(Debug)]
+pub struct Spawned<S> {
+    inner: Arc<S>,
+}
+
+impl<S> Spawned<S> {
+    pub(crate) fn new(inner: S) -> Self {
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+}
+
+impl<Request, S> Service<Request> for Spawned<S>
+where
+    S: Service<Request> + 'static,
+    S::Response: 'static,
+    Request: 'static,
+{
+    type Response = JoinHandle<S::Response>;
+    type Permit<'a> = OwnedPermit<S, Request>;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        self.inner.clone().acquire_owned().await
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        spawn_local(permit.call(request))
+    }
+}
+
+impl<S> Load for Spawned<S>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S> Describe for Spawned<S>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("Spawned").with_child(self.inner.describe())
+    }
+}
+
+impl<S, T> Middleware<S> for Spawned<T>
+where
+    T: Middleware<S>,
+{
+    type Service = Spawned<T::Service>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self { inner } = self;
+        let inner = Arc::into_inner(inner).expect("middleware is not shared");
+        Spawned::new(inner.apply(svc))
+    }
+}
@@ -4,6 +4,11 @@
 //! The [`Service::acquire`] on [`Steer`] acquires _all_ [permits](Service::Permit) from the
 //! collection, the [`Picker`] then selects which permit and [`Service`] to [`Service::call`].
 //!
+//! Alternatively, the [`steer_lazy`] function constructs a [`SteerLazy`] [`Service`]. Its
+//! [`Service::acquire`] resolves immediately and, during [`Service::call`], the [`Picker`] selects
+//! a [`Service`] and only that permit is acquired. This avoids occupying a permit on every service
+//! for each request, at the cost of acquiring after the request has been provided.
+//!
 //! # Example
 //!
 //! ```rust
@@ -27,15 +32,198 @@
 //! # }
 //! ```
 //!
+//! The same [`Picker`] can be used with [`steer_lazy`]:
+//!
+//! ```rust
+//! use burger::*;
+//!
+//! # #[cfg(feature = "tokio")]
+//! # #[tokio::main]
+//! # async fn main() {
+//! # struct AlwaysFirst;
+//! # impl<S, Request> steer::Picker<S, Request> for AlwaysFirst {
+//! #     fn pick(&self, services: &[S], _request: &Request) -> usize {
+//! #         0
+//! #     }
+//! # }
+//! let svcs = (0..10)
+//!     .map(|index| service_fn(move |x| async move { index * x }).concurrency_limit(1));
+//! let svc = steer_lazy(svcs, AlwaysFirst);
+//! let response = svc.oneshot(7).await;
+//! assert_eq!(0, response);
+//! # }
+//! # #[cfg(not(feature = "tokio"))]
+//! # fn main() {}
+//! ```
+//!
+//! The collection must be homogeneous. Differently typed services, which share a
+//! [`Service::Response`], can be reconciled using [`ServiceExt::left`] and [`ServiceExt::right`],
+//! nesting for more than two:
+//!
+//! ```rust
+//! use burger::*;
+//!
+//! # #[cfg(feature = "tokio")]
+//! # #[tokio::main]
+//! # async fn main() {
+//! # struct AlwaysLast;
+//! # impl<S, Request> steer::Picker<S, Request> for AlwaysLast {
+//! #     fn pick(&self, services: &[S], _request: &Request) -> usize {
+//! #         services.len() - 1
+//! #     }
+//! # }
+//! let a = service_fn(|x: u32| async move { x + 1 });
+//! let b = service_fn(|x: u32| async move { x + 2 }).concurrency_limit(1);
+//! let c = service_fn(|x: u32| async move { x + 3 }).buffer(1);
+//! let svcs = [a.left(), b.left().right(), c.right().right()];
+//! let svc = steer_lazy(svcs, AlwaysLast);
+//! let response = svc.oneshot(7).await;
+//! assert_eq!(10, response);
+//! # }
+//! # #[cfg(not(feature = "tokio"))]
+//! # fn main() {}
+//! ```
+//!
+//! # Pickers
+//!
+//! The following [`Picker`]s are provided:
+//!
+//! - [`RoundRobin`] picks each service in turn.
+//! - [`Hashed`] picks by the hash of a key extracted from the request, so that equal keys are
+//!   steered to the same service.
+//! - [`Weighted`] picks at random, in proportion to a weight for each service.
+//! - [`WeightedPicker`] picks as [`Weighted`] does, but its weights may be updated.
+//!
+//! ```rust
+//! use burger::{steer::RoundRobin, *};
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svcs = (0..3).map(|index| service_fn(move |x: u32| async move { index + x }));
+//! let svc = steer_lazy(svcs, RoundRobin::new());
+//! let mut responses = Vec::new();
+//! for _ in 0..4 {
+//!     responses.push(svc.oneshot(7).await);
+//! }
+//! assert_eq!(responses, [7, 8, 9, 7]);
+//! # }
+//! ```
+//!
+//! # Shifting traffic
+//!
+//! The weights of a [`WeightedPicker`] are updated at runtime using its [`WeightsHandle`], for
+//! example to gradually shift traffic from a stable service to a canary. A service with a weight of
+//! zero is never picked. As a [`Picker`] must always pick a service, weights which sum to zero, or
+//! which don't match the number of services, are rejected by [`WeightsHandle::set`]. So are weights
+//! whose sum overflows a [`u64`].
+//!
+//! ```rust
+//! # #[cfg(feature = "tokio")]
+//! use burger::{steer::WeightedPicker, *};
+//!
+//! # #[cfg(feature = "tokio")]
+//! # #[tokio::main]
+//! # async fn main() {
+//! let named = |name| service_fn(move |x: u32| async move { (name, x) });
+//! let (picker, handle) = WeightedPicker::new([1, 0]);
+//! let svc = steer_lazy([named("stable"), named("canary")], picker);
+//! assert_eq!(svc.oneshot(7).await, ("stable", 7));
+//!
+//! // Dial up the canary, before shifting all traffic to it.
+//! handle.set([99, 1]).unwrap();
+//! handle.set([0, 1]).unwrap();
+//! assert_eq!(svc.oneshot(7).await, ("canary", 7));
+//! assert!(handle.set([0, 0]).is_err());
+//! # }
+//! # #[cfg(not(feature = "tokio"))]
+//! # fn main() {}
+//! ```
+//!
+//! # Fallible picking
+//!
+//! A [`Picker`] must always return a valid index. Where some requests can't be routed, a
+//! [`TryPicker`] may instead return an error. The [`try_steer`] and [`try_steer_lazy`] functions
+//! construct [`TrySteer`] and [`TrySteerLazy`], which behave as [`Steer`] and [`SteerLazy`] but
+//! respond with [`Err`] when the [`TryPicker`] fails, without calling any service.
+//!
+//! ```rust
+//! use burger::{steer::TryPicker, *};
+//!
+//! struct ByRegion;
+//!
+//! impl<S> TryPicker<S, (&str, u32)> for ByRegion {
+//!     type Error = String;
+//!
+//!     fn try_pick(&self, _services: &[S], (region, _): &(&str, u32)) -> Result<usize, String> {
+//!         match *region {
+//!             "eu" => Ok(0),
+//!             "us" => Ok(1),
+//!             region => Err(format!("unknown region {region}")),
+//!         }
+//!     }
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svcs = (0..2).map(|index| service_fn(move |(_, x): (&str, u32)| async move { index + x }));
+//! let svc = try_steer_lazy(svcs, ByRegion);
+//! assert_eq!(svc.oneshot(("us", 7)).await, Ok(8));
+//! assert!(svc.oneshot(("ap", 7)).await.is_err());
+//! # }
+//! ```
+//!
+//! # Fixed-size collections
+//!
+//! The [`Service::acquire`] on [`Steer`] collects the permits into a [`Vec`] for every request.
+//! Where the number of services is known at compile time, the [`steer_array`] function constructs
+//! a [`SteerArray`] from an array of services, whose [`SteerArrayPermit`] holds the permits in an
+//! array instead, so that steering doesn't allocate. [`steer_lazy`] also avoids allocating, as it
+//! never collects permits.
+//!
+//! ```rust
+//! use burger::{steer::RoundRobin, *};
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svcs = [0, 1, 2].map(|index| service_fn(move |x: u32| async move { index + x }));
+//! let svc = steer_array(svcs, RoundRobin::new());
+//! assert_eq!(svc.oneshot(7).await, 7);
+//! assert_eq!(svc.oneshot(7).await, 8);
+//! # }
+//! ```
+//!
 //! # Load
 //!
-//! This has _no_ [`Load`](crate::load::Load) implementation.
+//! As the [`Picker`] chooses the service per request, the [`Load::load`] on each of these services
+//! is the sum of the loads of the services in the collection, where their [`Load::Metric`]
+//! implements [`Sum`], for example the total number of
+//! [pending requests](crate::ServiceExt::pending_requests).
 
-use std::fmt;
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    array, fmt,
+    future::{poll_fn, Future},
+    iter::Sum,
+    pin::pin,
+    sync::atomic::{AtomicUsize, Ordering},
+    task::Poll,
+};
+#[cfg(feature = "std")]
+use std::{
+    any,
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::{Arc, RwLock},
+};
 
-use futures_util::future::join_all;
+use futures_util::future::{join_all, maybe_done};
 
-use crate::Service;
+#[cfg(feature = "std")]
+use crate::random;
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Service, ServiceExt,
+};
 
 /// A wrapper [`Service`] for the [`steer`] constructor.
 ///
@@ -54,7 +242,231 @@ pub trait Picker<S, Request> {
     fn pick(&self, services: &[S], request: &Request) -> usize;
 }
 
-/// The [`Service::Permit`] type for [`Steer`].
+/// Picks a service from an underlying collection of services to steer requests, or fails if the
+/// request can't be routed.
+///
+/// See the [module](mod@crate::steer#fallible-picking) for more information.
+pub trait TryPicker<S, Request> {
+    /// The error returned when a request can't be routed.
+    type Error;
+
+    /// Returns the index of the picked service, or an error if there's none.
+    ///
+    /// A returned index MUST be valid.
+    fn try_pick(&self, services: &[S], request: &Request) -> Result<usize, Self::Error>;
+}
+
+/// A [`Picker`] which picks each service in turn.
+///
+/// See the [module](mod@crate::steer#pickers) for more information.
+#[derive(Debug, Default)]
+pub struct RoundRobin {
+    next: AtomicUsize,
+}
+
+impl RoundRobin {
+    /// Constructs a [`RoundRobin`] picker, starting from the first service.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S, Request> Picker<S, Request> for RoundRobin {
+    fn pick(&self, services: &[S], _request: &Request) -> usize {
+        self.next.fetch_add(1, Ordering::Relaxed) % services.len()
+    }
+}
+
+/// A [`Picker`] which picks by the hash of a key extracted from the request using a closure.
+///
+/// The same key is steered to the same service, provided the number of services is unchanged.
+///
+/// See the [module](mod@crate::steer#pickers) for more information.
+#[cfg(feature = "std")]
+#[derive(Clone)]
+pub struct Hashed<F> {
+    closure: F,
+}
+
+#[cfg(feature = "std")]
+impl<F> Hashed<F> {
+    /// Constructs a [`Hashed`] picker.
+    pub fn new(closure: F) -> Self {
+        Self { closure }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<F> fmt::Debug for Hashed<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hashed")
+            .field("closure", &format_args!("{}", any::type_name::<F>()))
+            .finish()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S, Request, F, K> Picker<S, Request> for Hashed<F>
+where
+    F: Fn(&Request) -> K,
+    K: Hash,
+{
+    fn pick(&self, services: &[S], request: &Request) -> usize {
+        let mut hasher = DefaultHasher::new();
+        (self.closure)(request).hash(&mut hasher);
+        (hasher.finish() % services.len() as u64) as usize
+    }
+}
+
+/// A [`Picker`] which picks at random, in proportion to a weight for each service.
+///
+/// The weights correspond to the services by position, and there MUST be one for each service.
+///
+/// See the [module](mod@crate::steer#pickers) for more information.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct Weighted {
+    /// The running total of the weights.
+    cumulative: Box<[u64]>,
+}
+
+#[cfg(feature = "std")]
+impl Weighted {
+    /// Constructs a [`Weighted`] picker, panicking if the weights sum to zero or overflow a
+    /// [`u64`].
+    pub fn new(weights: impl IntoIterator<Item = u64>) -> Self {
+        Self::try_new(weights).unwrap_or_else(|error| panic!("invalid weights: {error}"))
+    }
+
+    fn try_new(weights: impl IntoIterator<Item = u64>) -> Result<Self, InvalidWeights> {
+        let mut total = 0u64;
+        let cumulative = weights
+            .into_iter()
+            .map(|weight| {
+                total = total.checked_add(weight).ok_or(InvalidWeights::Overflow)?;
+                Ok(total)
+            })
+            .collect::<Result<_, _>>()?;
+        if total == 0 {
+            return Err(InvalidWeights::Zero);
+        }
+        Ok(Self { cumulative })
+    }
+
+    /// Returns the weights, from their running total.
+    fn weights(&self) -> Vec<u64> {
+        let mut previous = 0;
+        self.cumulative
+            .iter()
+            .map(|total| {
+                let weight = total - previous;
+                previous = *total;
+                weight
+            })
+            .collect()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S, Request> Picker<S, Request> for Weighted {
+    fn pick(&self, services: &[S], _request: &Request) -> usize {
+        debug_assert_eq!(self.cumulative.len(), services.len());
+        // Services with zero weight are never picked.
+        let point = random::next_u64() % self.cumulative[self.cumulative.len() - 1];
+        self.cumulative.partition_point(|total| *total <= point)
+    }
+}
+
+/// A [`Picker`] which picks at random, in proportion to weights which may be updated using a
+/// [`WeightsHandle`].
+///
+/// The weights correspond to the services by position, and there MUST be one for each service.
+///
+/// See the [module](mod@crate::steer#shifting-traffic) for more information.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct WeightedPicker {
+    weighted: Arc<RwLock<Weighted>>,
+}
+
+#[cfg(feature = "std")]
+impl WeightedPicker {
+    /// Constructs a [`WeightedPicker`] and a [`WeightsHandle`] to update its weights, panicking if
+    /// the weights sum to zero or overflow a [`u64`].
+    pub fn new(weights: impl IntoIterator<Item = u64>) -> (Self, WeightsHandle) {
+        let weighted = Arc::new(RwLock::new(Weighted::new(weights)));
+        let picker = Self {
+            weighted: weighted.clone(),
+        };
+        (picker, WeightsHandle { weighted })
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S, Request> Picker<S, Request> for WeightedPicker {
+    fn pick(&self, services: &[S], request: &Request) -> usize {
+        Picker::<S, Request>::pick(&*self.weighted.read().unwrap(), services, request)
+    }
+}
+
+/// Updates the weights of a [`WeightedPicker`].
+///
+/// See the [module](mod@crate::steer#shifting-traffic) for more information.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct WeightsHandle {
+    weighted: Arc<RwLock<Weighted>>,
+}
+
+/// The weights passed to [`WeightsHandle::set`] were rejected.
+#[cfg(feature = "std")]
+#[derive(Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InvalidWeights {
+    /// The number of weights differs from the number of services.
+    Length,
+    /// The weights sum to zero, so no service could be picked.
+    Zero,
+    /// The weights sum to more than [`u64::MAX`].
+    Overflow,
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for InvalidWeights {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidWeights::Length => f.write_str("weights do not match the services"),
+            InvalidWeights::Zero => f.write_str("weights sum to zero"),
+            InvalidWeights::Overflow => f.write_str("weights overflow"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidWeights {}
+
+#[cfg(feature = "std")]
+impl WeightsHandle {
+    /// Replaces the weights, taking effect from the next pick.
+    ///
+    /// If the weights are rejected, the previous weights are retained.
+    pub fn set(&self, weights: impl IntoIterator<Item = u64>) -> Result<(), InvalidWeights> {
+        let new = Weighted::try_new(weights)?;
+        let mut weighted = self.weighted.write().unwrap();
+        if new.cumulative.len() != weighted.cumulative.len() {
+            return Err(InvalidWeights::Length);
+        }
+        *weighted = new;
+        Ok(())
+    }
+
+    /// Returns the current weights.
+    pub fn weights(&self) -> Vec<u64> {
+        self.weighted.read().unwrap().weights()
+    }
+}
+
+/// The [`Service::Permit`] type for [`Steer`] and [`TrySteer`].
 pub struct SteerPermit<'a, S, P, Request>
 where
     S: Service<Request>,
@@ -110,6 +522,27 @@ where
     }
 }
 
+impl<S, P> Describe for Steer<S, P>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("Steer").with_children(self.services.iter().map(S::describe))
+    }
+}
+
+impl<S, P> Load for Steer<S, P>
+where
+    S: Load,
+    S::Metric: Sum,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.services.iter().map(S::load).sum()
+    }
+}
+
 /// Constructs a [`Service`] from a [collection](IntoIterator) of services whose [`Service::call`] is
 /// steered via a [`Picker`].
 ///
@@ -120,3 +553,417 @@ pub fn steer<S, P>(services: impl IntoIterator<Item = S>, picker: P) -> Steer<S,
         picker,
     }
 }
+
+/// A wrapper [`Service`] for the [`try_steer`] constructor.
+///
+/// See the [module](mod@crate::steer#fallible-picking) for more information.
+#[derive(Debug)]
+pub struct TrySteer<S, P> {
+    services: Box<[S]>,
+    picker: P,
+}
+
+impl<Request, S, P> Service<Request> for TrySteer<S, P>
+where
+    S: Service<Request>,
+    P: TryPicker<S, Request>,
+{
+    type Response = Result<S::Response, P::Error>;
+    type Permit<'a> = SteerPermit<'a, S, P, Request>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        SteerPermit {
+            services: &self.services,
+            picker: &self.picker,
+            permits: join_all(self.services.iter().map(|x| x.acquire())).await,
+        }
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        let SteerPermit {
+            services,
+            mut permits,
+            picker,
+        } = permit;
+        let index = picker.try_pick(services, &request)?;
+        let permit = permits.swap_remove(index);
+        drop(permits);
+        Ok(S::call(permit, request).await)
+    }
+}
+
+impl<S, P> Describe for TrySteer<S, P>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("TrySteer").with_children(self.services.iter().map(S::describe))
+    }
+}
+
+impl<S, P> Load for TrySteer<S, P>
+where
+    S: Load,
+    S::Metric: Sum,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.services.iter().map(S::load).sum()
+    }
+}
+
+/// Constructs a [`Service`] from a [collection](IntoIterator) of services whose [`Service::call`] is
+/// steered via a [`TryPicker`].
+///
+/// See [module](mod@crate::steer#fallible-picking) for more information.
+pub fn try_steer<S, P>(services: impl IntoIterator<Item = S>, picker: P) -> TrySteer<S, P> {
+    TrySteer {
+        services: services.into_iter().collect(),
+        picker,
+    }
+}
+
+/// A wrapper [`Service`] for the [`steer_lazy`] constructor.
+///
+/// See the [module](mod@crate::steer) for more information.
+#[derive(Debug)]
+pub struct SteerLazy<S, P> {
+    services: Box<[S]>,
+    picker: P,
+}
+
+/// The [`Service::Permit`] type for [`SteerLazy`] and [`TrySteerLazy`].
+#[derive(Debug)]
+pub struct SteerLazyPermit<'a, S, P> {
+    services: &'a [S],
+    picker: &'a P,
+}
+
+impl<Request, S, P> Service<Request> for SteerLazy<S, P>
+where
+    S: Service<Request>,
+    P: Picker<S, Request>,
+{
+    type Response = S::Response;
+    type Permit<'a> = SteerLazyPermit<'a, S, P>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        SteerLazyPermit {
+            services: &self.services,
+            picker: &self.picker,
+        }
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        let SteerLazyPermit { services, picker } = permit;
+        let index = picker.pick(services, &request);
+        services[index].oneshot(request).await
+    }
+}
+
+impl<S, P> Describe for SteerLazy<S, P>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("SteerLazy").with_children(self.services.iter().map(S::describe))
+    }
+}
+
+impl<S, P> Load for SteerLazy<S, P>
+where
+    S: Load,
+    S::Metric: Sum,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.services.iter().map(S::load).sum()
+    }
+}
+
+/// Constructs a [`Service`] from a [collection](IntoIterator) of services whose [`Service::call`] is
+/// steered via a [`Picker`], acquiring only the permit of the picked service.
+///
+/// See [module](mod@crate::steer) for more information.
+pub fn steer_lazy<S, P>(services: impl IntoIterator<Item = S>, picker: P) -> SteerLazy<S, P> {
+    SteerLazy {
+        services: services.into_iter().collect(),
+        picker,
+    }
+}
+
+/// A wrapper [`Service`] for the [`try_steer_lazy`] constructor.
+///
+/// See the [module](mod@crate::steer#fallible-picking) for more information.
+#[derive(Debug)]
+pub struct TrySteerLazy<S, P> {
+    services: Box<[S]>,
+    picker: P,
+}
+
+impl<Request, S, P> Service<Request> for TrySteerLazy<S, P>
+where
+    S: Service<Request>,
+    P: TryPicker<S, Request>,
+{
+    type Response = Result<S::Response, P::Error>;
+    type Permit<'a> = SteerLazyPermit<'a, S, P>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        SteerLazyPermit {
+            services: &self.services,
+            picker: &self.picker,
+        }
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        let SteerLazyPermit { services, picker } = permit;
+        let index = picker.try_pick(services, &request)?;
+        Ok(services[index].oneshot(request).await)
+    }
+}
+
+impl<S, P> Describe for TrySteerLazy<S, P>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("TrySteerLazy").with_children(self.services.iter().map(S::describe))
+    }
+}
+
+impl<S, P> Load for TrySteerLazy<S, P>
+where
+    S: Load,
+    S::Metric: Sum,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.services.iter().map(S::load).sum()
+    }
+}
+
+/// Constructs a [`Service`] from a [collection](IntoIterator) of services whose [`Service::call`] is
+/// steered via a [`TryPicker`], acquiring only the permit of the picked service.
+///
+/// See [module](mod@crate::steer#fallible-picking) for more information.
+pub fn try_steer_lazy<S, P>(
+    services: impl IntoIterator<Item = S>,
+    picker: P,
+) -> TrySteerLazy<S, P> {
+    TrySteerLazy {
+        services: services.into_iter().collect(),
+        picker,
+    }
+}
+
+/// Joins an array of futures, without allocating, returning their outputs in order.
+async fn join_array<F, const N: usize>(futures: [F; N]) -> [F::Output; N]
+where
+    F: Future,
+{
+    let mut futures = pin!(futures.map(maybe_done));
+    poll_fn(|cx| {
+        let mut ready = true;
+        for index in 0..N {
+            // SAFETY: The elements of a pinned array are never moved.
+            let future = unsafe {
+                futures
+                    .as_mut()
+                    .map_unchecked_mut(|futures| &mut futures[index])
+            };
+            ready &= future.poll(cx).is_ready();
+        }
+        if ready {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    })
+    .await;
+    array::from_fn(|index| {
+        // SAFETY: The elements of a pinned array are never moved.
+        let future = unsafe {
+            futures
+                .as_mut()
+                .map_unchecked_mut(|futures| &mut futures[index])
+        };
+        future.take_output().expect("every future is ready")
+    })
+}
+
+/// A wrapper [`Service`] for the [`steer_array`] constructor.
+///
+/// See the [module](mod@crate::steer#fixed-size-collections) for more information.
+#[derive(Debug)]
+pub struct SteerArray<S, P, const N: usize> {
+    services: [S; N],
+    picker: P,
+}
+
+/// The [`Service::Permit`] type for [`SteerArray`].
+pub struct SteerArrayPermit<'a, S, P, Request, const N: usize>
+where
+    S: Service<Request>,
+{
+    services: &'a [S; N],
+    permits: [S::Permit<'a>; N],
+    picker: &'a P,
+}
+
+impl<'a, S, P, Request, const N: usize> fmt::Debug for SteerArrayPermit<'a, S, P, Request, N>
+where
+    S: Service<Request> + fmt::Debug,
+    S::Permit<'a>: fmt::Debug,
+    P: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SteerArrayPermit")
+            .field("services", &self.services)
+            .field("permits", &self.permits)
+            .field("picker", &self.picker)
+            .finish()
+    }
+}
+
+impl<Request, S, P, const N: usize> Service<Request> for SteerArray<S, P, N>
+where
+    S: Service<Request>,
+    P: Picker<S, Request>,
+{
+    type Response = S::Response;
+    type Permit<'a> = SteerArrayPermit<'a, S, P, Request, N>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        SteerArrayPermit {
+            services: &self.services,
+            picker: &self.picker,
+            permits: join_array(self.services.each_ref().map(|x| x.acquire())).await,
+        }
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        let SteerArrayPermit {
+            services,
+            permits,
+            picker,
+        } = permit;
+        let index = picker.pick(services, &request);
+        let permit = permits
+            .into_iter()
+            .nth(index)
+            .expect("picker returned an index within the collection");
+        S::call(permit, request).await
+    }
+}
+
+impl<S, P, const N: usize> Load for SteerArray<S, P, N>
+where
+    S: Load,
+    S::Metric: Sum,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.services.iter().map(S::load).sum()
+    }
+}
+
+impl<S, P, const N: usize> Describe for SteerArray<S, P, N>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("SteerArray").with_children(self.services.iter().map(S::describe))
+    }
+}
+
+/// Constructs a [`Service`] from an array of services whose [`Service::call`] is steered via a
+/// [`Picker`], without allocating per request.
+///
+/// See [module](mod@crate::steer#fixed-size-collections) for more information.
+pub fn steer_array<S, P, const N: usize>(services: [S; N], picker: P) -> SteerArray<S, P, N> {
+    SteerArray { services, picker }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::{load::Load, service_fn, ServiceExt};
+
+    use super::{
+        steer_array, Hashed, InvalidWeights, Picker, RoundRobin, Weighted, WeightedPicker,
+    };
+
+    #[test]
+    fn weighted() {
+        let services = [(), (), ()];
+        let picker = Weighted::new([0, 1, 0]);
+        for _ in 0..10 {
+            assert_eq!(picker.pick(&services, &()), 1);
+        }
+    }
+
+    #[test]
+    fn weights_handle() {
+        let services = [(), ()];
+        let (picker, handle) = WeightedPicker::new([1, 0]);
+        assert_eq!(picker.pick(&services, &()), 0);
+
+        assert_eq!(handle.set([0, 0]), Err(InvalidWeights::Zero));
+        assert_eq!(handle.set([1, 1, 1]), Err(InvalidWeights::Length));
+        assert_eq!(handle.set([u64::MAX, 1]), Err(InvalidWeights::Overflow));
+        assert_eq!(handle.weights(), [1, 0]);
+
+        handle.set([0, 3]).unwrap();
+        assert_eq!(handle.weights(), [0, 3]);
+        assert_eq!(picker.pick(&services, &()), 1);
+    }
+
+    #[test]
+    fn hashed() {
+        let services = [(), (), ()];
+        let picker = Hashed::new(|(user, _): &(&str, u32)| user.to_string());
+        let index = picker.pick(&services, &("alice", 1));
+        for x in 0..10 {
+            assert_eq!(picker.pick(&services, &("alice", x)), index);
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn steer_array_releases_unpicked() {
+        use futures_util::FutureExt;
+
+        use crate::Service;
+
+        use super::SteerArray;
+
+        let svcs =
+            [1, 2].map(|n| service_fn(move |x: u32| async move { n * x }).concurrency_limit(1));
+        let svc = steer_array(svcs, RoundRobin::new());
+        let permit = svc.acquire().now_or_never().unwrap();
+        assert!(svc.acquire().now_or_never().is_none());
+
+        // Only the picked permit is used, and the others are released before the call.
+        assert_eq!(SteerArray::call(permit, 3).now_or_never(), Some(3));
+        assert_eq!(svc.oneshot(3).now_or_never(), Some(6));
+    }
+
+    #[test]
+    fn steer_sums_loads() {
+        let svcs = [1, 2].map(|n| service_fn(move |x: u32| async move { n * x }).constant_load(n));
+        let svc = steer_array(svcs, RoundRobin::new());
+        assert_eq!(svc.load(), 3);
+    }
+}
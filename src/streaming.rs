@@ -0,0 +1,531 @@
+//! Some services respond with a [`Stream`], such as server-sent events or chunked downloads. These
+//! are described by the [`StreamService`](crate::StreamService) trait, which is implemented for
+//! every [`Service`] whose [`Service::Response`] is a [`Stream`].
+//!
+//! The work of such a service continues after [`Service::call`] has returned, until the stream
+//! has ended. The following combinators therefore operate on each item, or hold their accounting
+//! until the stream has ended or been dropped:
+//!
+//! - [`ServiceExt::map_items`](crate::ServiceExt::map_items) modifies each item using a closure,
+//!   returning [`MapItems`].
+//! - [`ServiceExt::then_items`](crate::ServiceExt::then_items) modifies each item using a closure
+//!   returning a [`Future`](std::future::Future), returning [`ThenItems`].
+//! - [`ServiceExt::pending_streams`](crate::ServiceExt::pending_streams) records [`Load`] as the
+//!   number of streams yet to end, returning [`PendingStreams`].
+//! - [`ServiceExt::stream_concurrency_limit`](crate::ServiceExt::stream_concurrency_limit) limits
+//!   the number of streams yet to end, returning [`StreamConcurrencyLimit`].
+//!
+//! In contrast, [`ServiceExt::pending_requests`](crate::ServiceExt::pending_requests) and
+//! [`ServiceExt::concurrency_limit`](crate::ServiceExt::concurrency_limit) stop accounting for a
+//! call once [`Service::call`] has returned the stream.
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::{load::Load, *};
+//! use futures::stream::{self, StreamExt};
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc = service_fn(|x: u32| async move { stream::iter(0..x) })
+//!     .map_items(|x| x * 2)
+//!     .pending_streams()
+//!     .stream_concurrency_limit(4);
+//! let response = svc.oneshot(3).await;
+//! assert_eq!(svc.load(), 1);
+//! assert_eq!(response.collect::<Vec<_>>().await, [0, 2, 4]);
+//! assert_eq!(svc.load(), 0);
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`MapItems`], [`ThenItems`] and [`StreamConcurrencyLimit`] defers to the
+//! inner service.
+
+use std::{
+    any, fmt,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use futures_util::{stream, Stream, StreamExt};
+use pin_project_lite::pin_project;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Middleware, Service, StreamService,
+};
+
+/// A wrapper [`Service`] for the [`ServiceExt::map_items`](crate::ServiceExt::map_items)
+/// combinator.
+///
+/// See the [module](crate::streaming) for more information.
+#[derive(Clone, Debug)]
+pub struct MapItems<S, F> {
+    inner: S,
+    closure: F,
+}
+
+impl<S, F> MapItems<S, F> {
+    pub(crate) fn new(inner: S, closure: F) -> Self {
+        Self { inner, closure }
+    }
+}
+
+/// A wrapper [`Service`] for the [`ServiceExt::then_items`](crate::ServiceExt::then_items)
+/// combinator.
+///
+/// See the [module](crate::streaming) for more information.
+#[derive(Clone, Debug)]
+pub struct ThenItems<S, F> {
+    inner: S,
+    closure: F,
+}
+
+impl<S, F> ThenItems<S, F> {
+    pub(crate) fn new(inner: S, closure: F) -> Self {
+        Self { inner, closure }
+    }
+}
+
+/// The [`Service::Permit`] type for [`MapItems`] and [`ThenItems`].
+pub struct ItemsPermit<'a, S, F, Request>
+where
+    S: Service<Request> + 'a,
+{
+    inner: S::Permit<'a>,
+    closure: &'a F,
+}
+
+impl<'a, S, F, Request> fmt::Debug for ItemsPermit<'a, S, F, Request>
+where
+    S: Service<Request>,
+    S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ItemsPermit")
+            .field("inner", &self.inner)
+            .field("closure", &format_args!("{}", any::type_name::<F>()))
+            .finish()
+    }
+}
+
+impl<Request, S, F, Output> Service<Request> for MapItems<S, F>
+where
+    S: StreamService<Request>,
+    F: FnMut(S::Item) -> Output + Clone,
+{
+    type Response = stream::Map<S::Stream, F>;
+    type Permit<'a> = ItemsPermit<'a, S, F, Request>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        ItemsPermit {
+            inner: self.inner.acquire().await,
+            closure: &self.closure,
+        }
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        let ItemsPermit { inner, closure } = permit;
+        S::call(inner, request).await.map(closure.clone())
+    }
+}
+
+impl<Request, S, F, Fut> Service<Request> for ThenItems<S, F>
+where
+    S: StreamService<Request>,
+    F: FnMut(S::Item) -> Fut + Clone,
+    Fut: Future,
+{
+    type Response = stream::Then<S::Stream, Fut, F>;
+    type Permit<'a> = ItemsPermit<'a, S, F, Request>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        ItemsPermit {
+            inner: self.inner.acquire().await,
+            closure: &self.closure,
+        }
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        let ItemsPermit { inner, closure } = permit;
+        S::call(inner, request).await.then(closure.clone())
+    }
+}
+
+pin_project! {
+    /// A [`Stream`] holding a guard until it has ended, returned by [`PendingStreams`] and
+    /// [`StreamConcurrencyLimit`].
+    ///
+    /// See the [module](crate::streaming) for more information.
+    #[derive(Debug)]
+    pub struct Guarded<St, G> {
+        #[pin]
+        inner: St,
+        guard: Option<G>,
+    }
+}
+
+impl<St, G> Stream for Guarded<St, G>
+where
+    St: Stream,
+{
+    type Item = St::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let poll = this.inner.poll_next(cx);
+        if let Poll::Ready(None) = poll {
+            this.guard.take();
+        }
+        poll
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// The guard held by the [`Stream`] returned by [`PendingStreams`], decrementing the count of
+/// pending streams on drop.
+#[derive(Debug)]
+pub struct PendingGuard {
+    count: Arc<AtomicUsize>,
+}
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        self.count.fetch_sub(1, Ordering::Release);
+    }
+}
+
+/// A wrapper [`Service`] for the
+/// [`ServiceExt::pending_streams`](crate::ServiceExt::pending_streams) combinator.
+///
+/// Clones share the count of pending streams.
+///
+/// See the [module](crate::streaming) for more information.
+#[derive(Clone, Debug)]
+pub struct PendingStreams<S> {
+    inner: S,
+    count: Arc<AtomicUsize>,
+}
+
+impl<S> PendingStreams<S> {
+    pub(crate) fn new(inner: S) -> Self {
+        Self {
+            inner,
+            count: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+}
+
+/// The [`Service::Permit`] type for [`PendingStreams`].
+pub struct PendingStreamsPermit<'a, S, Request>
+where
+    S: Service<Request> + 'a,
+{
+    inner: S::Permit<'a>,
+    count: &'a Arc<AtomicUsize>,
+}
+
+impl<'a, S, Request> fmt::Debug for PendingStreamsPermit<'a, S, Request>
+where
+    S: Service<Request>,
+    S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PendingStreamsPermit")
+            .field("inner", &self.inner)
+            .field("count", &self.count)
+            .finish()
+    }
+}
+
+impl<Request, S> Service<Request> for PendingStreams<S>
+where
+    S: StreamService<Request>,
+{
+    type Response = Guarded<S::Stream, PendingGuard>;
+    type Permit<'a> = PendingStreamsPermit<'a, S, Request>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        PendingStreamsPermit {
+            inner: self.inner.acquire().await,
+            count: &self.count,
+        }
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        let PendingStreamsPermit { inner, count } = permit;
+        count.fetch_add(1, Ordering::Release);
+        // Decrements if the call is cancelled, or once the stream has ended.
+        let guard = PendingGuard {
+            count: count.clone(),
+        };
+        Guarded {
+            inner: S::call(inner, request).await,
+            guard: Some(guard),
+        }
+    }
+}
+
+impl<S> Load for PendingStreams<S> {
+    type Metric = usize;
+
+    fn load(&self) -> Self::Metric {
+        self.count.load(Ordering::Acquire)
+    }
+}
+
+impl<S> Describe for PendingStreams<S>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("PendingStreams").with_child(self.inner.describe())
+    }
+}
+
+/// A wrapper [`Service`] for the
+/// [`ServiceExt::stream_concurrency_limit`](crate::ServiceExt::stream_concurrency_limit)
+/// combinator.
+///
+/// Clones share the limit.
+///
+/// See the [module](crate::streaming) for more information.
+#[derive(Clone, Debug)]
+pub struct StreamConcurrencyLimit<S> {
+    inner: S,
+    semaphore: Arc<Semaphore>,
+}
+
+impl<S> StreamConcurrencyLimit<S> {
+    pub(crate) fn new(inner: S, n_permits: usize) -> Self {
+        Self {
+            inner,
+            semaphore: Arc::new(Semaphore::new(n_permits)),
+        }
+    }
+}
+
+/// The [`Service::Permit`] type for [`StreamConcurrencyLimit`].
+pub struct StreamConcurrencyLimitPermit<'a, S, Request>
+where
+    S: Service<Request> + 'a,
+{
+    inner: S::Permit<'a>,
+    semaphore_permit: OwnedSemaphorePermit,
+}
+
+impl<'a, S, Request> fmt::Debug for StreamConcurrencyLimitPermit<'a, S, Request>
+where
+    S: Service<Request>,
+    S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StreamConcurrencyLimitPermit")
+            .field("inner", &self.inner)
+            .field("semaphore_permit", &self.semaphore_permit)
+            .finish()
+    }
+}
+
+impl<Request, S> Service<Request> for StreamConcurrencyLimit<S>
+where
+    S: StreamService<Request>,
+{
+    type Response = Guarded<S::Stream, OwnedSemaphorePermit>;
+    type Permit<'a> = StreamConcurrencyLimitPermit<'a, S, Request>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        StreamConcurrencyLimitPermit {
+            // The semaphore is private and never closed.
+            semaphore_permit: self
+                .semaphore
+                .clone()
+                .acquire_owned()
+                .await
+                .expect("never closed"),
+            inner: self.inner.acquire().await,
+        }
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        let StreamConcurrencyLimitPermit {
+            inner,
+            semaphore_permit,
+        } = permit;
+        Guarded {
+            inner: S::call(inner, request).await,
+            guard: Some(semaphore_permit),
+        }
+    }
+}
+
+impl<S, F> Load for MapItems<S, F>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S, F> Describe for MapItems<S, F>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("MapItems").with_child(self.inner.describe())
+    }
+}
+
+impl<S, F> Load for ThenItems<S, F>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S, F> Describe for ThenItems<S, F>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("ThenItems").with_child(self.inner.describe())
+    }
+}
+
+impl<S> Load for StreamConcurrencyLimit<S>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S> Describe for StreamConcurrencyLimit<S>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("StreamConcurrencyLimit").with_child(self.inner.describe())
+    }
+}
+
+impl<S, T, F> Middleware<S> for MapItems<T, F>
+where
+    T: Middleware<S>,
+{
+    type Service = MapItems<T::Service, F>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self { inner, closure } = self;
+        MapItems {
+            inner: inner.apply(svc),
+            closure,
+        }
+    }
+}
+
+impl<S, T, F> Middleware<S> for ThenItems<T, F>
+where
+    T: Middleware<S>,
+{
+    type Service = ThenItems<T::Service, F>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self { inner, closure } = self;
+        ThenItems {
+            inner: inner.apply(svc),
+            closure,
+        }
+    }
+}
+
+impl<S, T> Middleware<S> for PendingStreams<T>
+where
+    T: Middleware<S>,
+{
+    type Service = PendingStreams<T::Service>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self { inner, count } = self;
+        PendingStreams {
+            inner: inner.apply(svc),
+            count,
+        }
+    }
+}
+
+impl<S, T> Middleware<S> for StreamConcurrencyLimit<T>
+where
+    T: Middleware<S>,
+{
+    type Service = StreamConcurrencyLimit<T::Service>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self { inner, semaphore } = self;
+        StreamConcurrencyLimit {
+            inner: inner.apply(svc),
+            semaphore,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::{stream, FutureExt, StreamExt};
+
+    use crate::{service_fn, Service, ServiceExt};
+
+    #[tokio::test]
+    async fn limit_until_end() {
+        let svc =
+            service_fn(|x: u32| async move { stream::iter(0..x) }).stream_concurrency_limit(1);
+        let mut response = svc.oneshot(2).await;
+
+        // The permit is held until the stream has ended.
+        assert!(svc.acquire().now_or_never().is_none());
+        while response.next().await.is_some() {}
+        assert!(svc.acquire().now_or_never().is_some());
+    }
+}
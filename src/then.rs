@@ -20,9 +20,13 @@
 //!
 //! The [`Load::load`] on [Then] defers to the inner service.
 
-use std::{any, fmt, future::Future};
+use core::{any, fmt, future::Future};
 
-use crate::{load::Load, Middleware, Service};
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Middleware, Service,
+};
 
 /// A wrapper for the [`ServiceExt::then`](crate::ServiceExt::then) combinator.
 ///
@@ -93,6 +97,15 @@ where
     }
 }
 
+impl<S, F> Describe for Then<S, F>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("Then").with_child(self.inner.describe())
+    }
+}
+
 impl<S, T, F> Middleware<S> for Then<T, F>
 where
     T: Middleware<S>,
@@ -0,0 +1,130 @@
+//! The [`ServiceExt::then_request`](crate::ServiceExt::then_request) combinator returns
+//! [`ThenRequest`], which extends a service with a closure modifying the request asynchronously
+//! before it is passed to the inner [`Service::call`].
+//!
+//! Note that the inner [`Service::Permit`] is held while the closure executes.
+//!
+//! For a synchronous version see the [map_request](mod@crate::map_request) module.
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::*;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc = service_fn(|x: u32| async move { x + 1 })
+//!     .then_request(|x: String| async move { x.len() as u32 });
+//! let response = svc.oneshot("hello".to_string()).await;
+//! assert_eq!(response, 6);
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`ThenRequest`] defers to the inner service.
+
+use core::{any, fmt, future::Future};
+
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Middleware, Service,
+};
+
+/// A wrapper [`Service`] for the [`ServiceExt::then_request`](crate::ServiceExt::then_request) combinator.
+///
+/// See the [module](crate::then_request) for more information.
+#[derive(Clone, Debug)]
+pub struct ThenRequest<S, F> {
+    inner: S,
+    closure: F,
+}
+
+impl<S, F> ThenRequest<S, F> {
+    pub(crate) fn new(inner: S, closure: F) -> Self {
+        Self { inner, closure }
+    }
+}
+
+/// The [`Service::Permit`] type for [`ThenRequest`].
+pub struct ThenRequestPermit<'a, S, F, Request>
+where
+    S: Service<Request> + 'a,
+{
+    inner: S::Permit<'a>,
+    closure: &'a F,
+}
+
+impl<'a, S, F, Request> fmt::Debug for ThenRequestPermit<'a, S, F, Request>
+where
+    S: Service<Request>,
+    S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ThenRequestPermit")
+            .field("inner", &self.inner)
+            .field("closure", &format_args!("{}", any::type_name::<F>()))
+            .finish()
+    }
+}
+
+impl<Request, S, F, Fut> Service<Request> for ThenRequest<S, F>
+where
+    S: Service<Fut::Output>,
+    F: Fn(Request) -> Fut,
+    Fut: Future,
+{
+    type Response = S::Response;
+    type Permit<'a> = ThenRequestPermit<'a, S, F, Fut::Output>
+    where
+        S: 'a,
+        F: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        ThenRequestPermit {
+            inner: self.inner.acquire().await,
+            closure: &self.closure,
+        }
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        let request = (permit.closure)(request).await;
+        S::call(permit.inner, request).await
+    }
+}
+
+impl<S, F> Load for ThenRequest<S, F>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S, F> Describe for ThenRequest<S, F>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("ThenRequest").with_child(self.inner.describe())
+    }
+}
+
+impl<S, T, F> Middleware<S> for ThenRequest<T, F>
+where
+    T: Middleware<S>,
+{
+    type Service = ThenRequest<T::Service, F>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self { inner, closure } = self;
+        ThenRequest {
+            inner: inner.apply(svc),
+            closure,
+        }
+    }
+}
@@ -0,0 +1,276 @@
+//! The [`ServiceExt::token_bucket`](crate::ServiceExt::token_bucket) combinator returns
+//! [`TokenBucket`], which limits the rate of [`Service::call`]s using the token bucket algorithm.
+//!
+//! The bucket holds at most `burst` tokens and is continuously replenished, gaining a token every
+//! `interval`. Each [`Service::acquire`] waits for and takes a token, which is consumed when
+//! [`Service::call`] is invoked. If the permit is dropped without calling, the token is returned to
+//! the bucket. Waiting [`Service::acquire`]s are served in first-in, first-out order.
+//!
+//! Unlike [`ServiceExt::rate_limit`](crate::ServiceExt::rate_limit), which replenishes all permits
+//! at the end of a fixed window, there are no window boundaries allowing twice the burst size. The
+//! [`limit`](crate::limit) module compares the available limiters.
+//!
+//! # Example
+//!
+//! If a burst of 5 and an interval of 200 milliseconds is specified then the first 5
+//! [`Service::acquire`]s will immediately resolve and each subsequent one will resolve 200
+//! milliseconds after the previous.
+//!
+//! ```rust
+//! use std::time::Duration;
+//!
+//! use burger::*;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc = service_fn(|x: u32| async move { x.to_string() })
+//!     .token_bucket(Duration::from_millis(200), 5);
+//! assert_eq!(svc.burst(), 5);
+//! let response = svc.oneshot(1).await;
+//! # let _ = response;
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`TokenBucket`] defers to the inner service.
+
+use std::{
+    fmt,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use tokio::sync::Mutex as AsyncMutex;
+
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    rt, Middleware, Service,
+};
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: usize,
+    last_refill: Instant,
+}
+
+impl Bucket {
+    /// Adds the tokens accrued since the last refill.
+    fn refill(&mut self, now: Instant, interval: Duration, burst: usize) {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        let accrued = elapsed.as_nanos() / interval.as_nanos().max(1);
+        let accrued = usize::try_from(accrued).unwrap_or(usize::MAX);
+        self.tokens = self.tokens.saturating_add(accrued).min(burst);
+        if self.tokens == burst {
+            self.last_refill = now;
+        } else {
+            // `accrued` is bounded by `burst` here.
+            self.last_refill += interval * accrued as u32;
+        }
+    }
+}
+
+/// A wrapper for the [`ServiceExt::token_bucket`](crate::ServiceExt::token_bucket) combinator.
+///
+/// See the [module](crate::token_bucket) for more information.
+#[derive(Debug)]
+pub struct TokenBucket<S> {
+    inner: S,
+    bucket: Mutex<Bucket>,
+    queue: AsyncMutex<()>,
+    interval: Duration,
+    burst: usize,
+}
+
+impl<S> TokenBucket<S> {
+    pub(crate) fn new(inner: S, interval: Duration, burst: usize) -> Self {
+        Self {
+            inner,
+            bucket: Mutex::new(Bucket {
+                tokens: burst,
+                last_refill: Instant::now(),
+            }),
+            queue: AsyncMutex::new(()),
+            interval,
+            burst,
+        }
+    }
+
+    /// Returns the maximum number of tokens the bucket may hold.
+    pub fn burst(&self) -> usize {
+        self.burst
+    }
+
+    /// Returns the interval at which a token is replenished.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    async fn take(&self) -> Token<'_> {
+        // Serve waiters in order.
+        let _guard = self.queue.lock().await;
+        loop {
+            let wait = {
+                let now = Instant::now();
+                let mut bucket = self.bucket.lock().unwrap();
+                bucket.refill(now, self.interval, self.burst);
+                if bucket.tokens > 0 {
+                    bucket.tokens -= 1;
+                    return Token {
+                        bucket: &self.bucket,
+                        burst: self.burst,
+                        consumed: false,
+                    };
+                }
+                (bucket.last_refill + self.interval).saturating_duration_since(now)
+            };
+            rt::sleep(wait).await;
+        }
+    }
+}
+
+/// A token taken from the bucket, returned on drop unless consumed.
+#[derive(Debug)]
+struct Token<'a> {
+    bucket: &'a Mutex<Bucket>,
+    burst: usize,
+    consumed: bool,
+}
+
+impl Drop for Token<'_> {
+    fn drop(&mut self) {
+        if !self.consumed {
+            let mut bucket = self.bucket.lock().unwrap();
+            bucket.tokens = (bucket.tokens + 1).min(self.burst);
+        }
+    }
+}
+
+/// The [`Service::Permit`] type for [`TokenBucket`].
+pub struct TokenBucketPermit<'a, S, Request>
+where
+    S: Service<Request> + 'a,
+{
+    inner: S::Permit<'a>,
+    token: Token<'a>,
+}
+
+impl<'a, S, Request> fmt::Debug for TokenBucketPermit<'a, S, Request>
+where
+    S: Service<Request>,
+    S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenBucketPermit")
+            .field("inner", &self.inner)
+            .field("token", &self.token)
+            .finish()
+    }
+}
+
+impl<Request, S> Service<Request> for TokenBucket<S>
+where
+    S: Service<Request>,
+{
+    type Response = S::Response;
+    type Permit<'a> = TokenBucketPermit<'a, S, Request>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        TokenBucketPermit {
+            token: self.take().await,
+            inner: self.inner.acquire().await,
+        }
+    }
+
+    async fn call<'a>(permit: Self::Permit<'a>, request: Request) -> Self::Response
+    where
+        Self: 'a,
+    {
+        let TokenBucketPermit { inner, mut token } = permit;
+        token.consumed = true;
+        drop(token);
+        S::call(inner, request).await
+    }
+}
+
+impl<S> Load for TokenBucket<S>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S> Describe for TokenBucket<S>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("TokenBucket")
+            .with_config("interval", format_args!("{:?}", self.interval))
+            .with_config("burst", self.burst)
+            .with_child(self.inner.describe())
+    }
+}
+
+impl<S, T> Middleware<S> for TokenBucket<T>
+where
+    T: Middleware<S>,
+{
+    type Service = TokenBucket<T::Service>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self {
+            inner,
+            interval,
+            burst,
+            ..
+        } = self;
+        TokenBucket::new(inner.apply(svc), interval, burst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use futures_util::FutureExt;
+
+    use crate::{service_fn, Service, ServiceExt};
+
+    #[tokio::test]
+    async fn limit() {
+        let svc = service_fn(|x: u32| async move { x.to_string() })
+            .token_bucket(Duration::from_millis(50), 2);
+        let now = Instant::now();
+
+        // 0, 1 happen instantly
+        // 2, 3, 4 each wait 50ms
+        for _ in 0..5 {
+            svc.oneshot(1).await;
+        }
+        let elapsed = now.elapsed();
+        assert!(elapsed >= Duration::from_millis(150));
+        assert!(elapsed < Duration::from_millis(250));
+    }
+
+    #[tokio::test]
+    async fn disarm() {
+        let svc = service_fn(|x: u32| async move { x.to_string() })
+            .token_bucket(Duration::from_secs(60), 1);
+
+        // Dropping the permit returns the token.
+        let permit = svc.acquire().now_or_never().unwrap();
+        drop(permit);
+        assert_eq!(svc.oneshot(1).now_or_never(), Some("1".to_string()));
+
+        // The token has been consumed.
+        assert!(svc.acquire().now_or_never().is_none());
+    }
+}
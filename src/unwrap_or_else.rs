@@ -0,0 +1,128 @@
+//! The [`ServiceExt::unwrap_or_else`](crate::ServiceExt::unwrap_or_else) combinator returns
+//! [`UnwrapOrElse`], which extends a [fallible service](crate::TryService) with a closure
+//! converting the [`Err`] variant of the [`Service::Response`] into the [`Ok`] type. The resulting
+//! [`Service::Response`] is no longer a [`Result`].
+//!
+//! # Example
+//!
+//! ```rust
+//! use burger::*;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc = service_fn(|x: u32| async move { x.checked_sub(3).ok_or(x) })
+//!     .unwrap_or_else(|_| 0);
+//! assert_eq!(svc.oneshot(5).await, 2);
+//! assert_eq!(svc.oneshot(2).await, 0);
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`UnwrapOrElse`] defers to the inner service.
+
+use core::{any, fmt};
+
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Middleware, Service, TryService,
+};
+
+/// A wrapper [`Service`] for the [`ServiceExt::unwrap_or_else`](crate::ServiceExt::unwrap_or_else)
+/// combinator.
+///
+/// See the [module](crate::unwrap_or_else) for more information.
+#[derive(Clone, Debug)]
+pub struct UnwrapOrElse<S, F> {
+    inner: S,
+    closure: F,
+}
+
+impl<S, F> UnwrapOrElse<S, F> {
+    pub(crate) fn new(inner: S, closure: F) -> Self {
+        Self { inner, closure }
+    }
+}
+
+/// The [`Service::Permit`] type for [`UnwrapOrElse`].
+pub struct UnwrapOrElsePermit<'a, S, F, Request>
+where
+    S: Service<Request> + 'a,
+{
+    inner: S::Permit<'a>,
+    closure: &'a F,
+}
+
+impl<'a, S, F, Request> fmt::Debug for UnwrapOrElsePermit<'a, S, F, Request>
+where
+    S: Service<Request>,
+    S::Permit<'a>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UnwrapOrElsePermit")
+            .field("inner", &self.inner)
+            .field("closure", &format_args!("{}", any::type_name::<F>()))
+            .finish()
+    }
+}
+
+impl<Request, S, F> Service<Request> for UnwrapOrElse<S, F>
+where
+    S: TryService<Request>,
+    F: Fn(S::Error) -> S::Ok,
+{
+    type Response = S::Ok;
+    type Permit<'a> = UnwrapOrElsePermit<'a, S, F, Request>
+    where
+        S: 'a,
+        F: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        UnwrapOrElsePermit {
+            inner: self.inner.acquire().await,
+            closure: &self.closure,
+        }
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        S::call(permit.inner, request)
+            .await
+            .unwrap_or_else(permit.closure)
+    }
+}
+
+impl<S, F> Load for UnwrapOrElse<S, F>
+where
+    S: Load,
+{
+    type Metric = S::Metric;
+
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S, F> Describe for UnwrapOrElse<S, F>
+where
+    S: Describe,
+{
+    fn describe(&self) -> StackNode {
+        StackNode::new("UnwrapOrElse").with_child(self.inner.describe())
+    }
+}
+
+impl<S, T, F> Middleware<S> for UnwrapOrElse<T, F>
+where
+    T: Middleware<S>,
+{
+    type Service = UnwrapOrElse<T::Service, F>;
+
+    fn apply(self, svc: S) -> Self::Service {
+        let Self { inner, closure } = self;
+        UnwrapOrElse {
+            inner: inner.apply(svc),
+            closure,
+        }
+    }
+}
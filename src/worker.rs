@@ -0,0 +1,221 @@
+//! The [`worker`] function takes ownership of a [`Service`] and returns a [`Worker`] and a worker
+//! [`Future`]. The [`Worker`] is a cloneable [`Service`] which sends each request, over a channel,
+//! to the worker [`Future`] which calls the inner service and sends back the response.
+//!
+//! This allows a [`Service`] which isn't [`Sync`], for example one owning a connection, to be
+//! shared. The worker [`Future`] must be spawned, or otherwise polled, for requests to progress. It
+//! completes once every [`Worker`] has been dropped and all calls have completed.
+//!
+//! The worker [`Future`] acquires a permit from the inner service before receiving each request,
+//! and drives calls concurrently. The [`Service::acquire`] on [`Worker`] waits for capacity on the
+//! channel, which holds at most the specified number of requests. Hence, backpressure from the
+//! inner service propagates to the [`Worker`] once the channel is full.
+//!
+//! If the worker [`Future`] is dropped then the [`Worker`] returns [`Closed`].
+//!
+//! This is the closest equivalent to `tower`'s `Buffer`, queueing requests rather than callers as
+//! the [`buffer`](crate::buffer) module does.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::{cell::Cell, rc::Rc};
+//!
+//! use burger::*;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! // A service which isn't `Sync`.
+//! let count = Rc::new(Cell::new(0));
+//! let svc = service_fn(move |x: u32| {
+//!     count.set(count.get() + 1);
+//!     let count = count.get();
+//!     async move { x + count }
+//! });
+//! let (svc, worker) = worker(svc, 8);
+//! let local = tokio::task::LocalSet::new();
+//! local.spawn_local(worker);
+//! local
+//!     .run_until(async move {
+//!         let other = svc.clone();
+//!         assert_eq!(svc.oneshot(1).await, Ok(2));
+//!         assert_eq!(other.oneshot(1).await, Ok(3));
+//!     })
+//!     .await;
+//! # }
+//! ```
+//!
+//! # Queue depth
+//!
+//! [`Worker::queued`] returns the number of requests in the channel, including those whose
+//! [`Service::Permit`] has been acquired but not yet called. Requests are rejected, rather than
+//! waiting, when the channel is full using [`ServiceExt::load_shed`](crate::ServiceExt::load_shed).
+//!
+//! ```rust
+//! use burger::{load::Load, *};
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let svc = service_fn(|x: u32| async move { x }).concurrency_limit(1);
+//! let (svc, worker) = worker(svc, 1);
+//! let svc = svc.load_shed();
+//! // The worker isn't spawned, so the first request remains queued.
+//! let _queued = svc.acquire().await;
+//! assert_eq!(svc.load(), 1);
+//! assert_eq!(svc.oneshot(2).await, Err(2));
+//! # drop(worker);
+//! # }
+//! ```
+//!
+//! # Load
+//!
+//! The [`Load::load`] on [`Worker`] is the number of queued requests, as returned by
+//! [`Worker::queued`].
+
+use std::{fmt, future::Future};
+
+use futures_util::{stream::FuturesUnordered, StreamExt};
+use tokio::{
+    select,
+    sync::{mpsc, oneshot},
+};
+
+use crate::{
+    describe::{Describe, StackNode},
+    load::Load,
+    Service,
+};
+
+type Message<Request, Response> = (Request, oneshot::Sender<Response>);
+
+/// The worker [`Future`] has been dropped.
+#[derive(Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Closed;
+
+impl fmt::Display for Closed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("worker closed")
+    }
+}
+
+impl std::error::Error for Closed {}
+
+/// A handle [`Service`] for the [`worker`] constructor.
+///
+/// See the [module](mod@crate::worker) for more information.
+pub struct Worker<Request, Response> {
+    sender: mpsc::Sender<Message<Request, Response>>,
+}
+
+impl<Request, Response> fmt::Debug for Worker<Request, Response> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Worker")
+            .field("sender", &self.sender)
+            .finish()
+    }
+}
+
+impl<Request, Response> Clone for Worker<Request, Response> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+        }
+    }
+}
+
+impl<Request, Response> Worker<Request, Response> {
+    /// Returns the number of requests in the channel, including those reserved by a permit.
+    pub fn queued(&self) -> usize {
+        self.sender.max_capacity() - self.sender.capacity()
+    }
+}
+
+/// The [`Service::Permit`] type for [`Worker`].
+pub struct WorkerPermit<'a, Request, Response> {
+    inner: Option<mpsc::Permit<'a, Message<Request, Response>>>,
+}
+
+impl<Request, Response> fmt::Debug for WorkerPermit<'_, Request, Response> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WorkerPermit")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<Request, Response> Service<Request> for Worker<Request, Response> {
+    type Response = Result<Response, Closed>;
+    type Permit<'a> = WorkerPermit<'a, Request, Response>
+    where
+        Self: 'a;
+
+    async fn acquire(&self) -> Self::Permit<'_> {
+        WorkerPermit {
+            inner: self.sender.reserve().await.ok(),
+        }
+    }
+
+    async fn call(permit: Self::Permit<'_>, request: Request) -> Self::Response {
+        let permit = permit.inner.ok_or(Closed)?;
+        let (sender, receiver) = oneshot::channel();
+        permit.send((request, sender));
+        receiver.await.map_err(|_| Closed)
+    }
+}
+
+impl<Request, Response> Load for Worker<Request, Response> {
+    type Metric = usize;
+
+    fn load(&self) -> Self::Metric {
+        self.queued()
+    }
+}
+
+impl<Request, Response> Describe for Worker<Request, Response> {
+    fn describe(&self) -> StackNode {
+        StackNode::new("Worker")
+    }
+}
+
+/// Constructs a [`Worker`] and a worker [`Future`] from a [`Service`], where the channel between
+/// them holds at most `capacity` requests.
+///
+/// See the [module](mod@crate::worker) for more information.
+///
+/// # Panics
+///
+/// Panics if `capacity` is zero.
+pub fn worker<S, Request>(
+    service: S,
+    capacity: usize,
+) -> (Worker<Request, S::Response>, impl Future<Output = ()>)
+where
+    S: Service<Request>,
+{
+    let (sender, mut receiver) = mpsc::channel::<Message<Request, S::Response>>(capacity);
+    let worker = async move {
+        let mut calls = FuturesUnordered::new();
+        let mut permit = None;
+        loop {
+            select! {
+                acquired = service.acquire(), if permit.is_none() => {
+                    permit = Some(acquired);
+                }
+                message = receiver.recv(), if permit.is_some() => {
+                    let Some((request, sender)) = message else {
+                        break;
+                    };
+                    let permit = permit.take().expect("checked above");
+                    calls.push(async move {
+                        let _ = sender.send(S::call(permit, request).await);
+                    });
+                }
+                Some(()) = calls.next() => {}
+            }
+        }
+        drop(permit);
+        while calls.next().await.is_some() {}
+    };
+    (Worker { sender }, worker)
+}